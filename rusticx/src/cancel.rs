@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Cancels the statements of the handles made with
+/// `Connection::with_cancellation`, for example when the request that
+/// started them is aborted.
+///
+/// ```ignore
+/// let token = CancellationToken::new();
+/// let report = conn.with_cancellation(&token);
+/// let worker = std::thread::spawn(move || report.query_positional("SELECT build_report()", &[]));
+///
+/// token.cancel(); // the statement fails with RusticxError::Cancelled
+/// ```
+///
+/// Cancelling stops the running statement (with a PostgreSQL cancel
+/// request, MySQL `KILL QUERY` or a SQLite interrupt) and makes later
+/// statements on the handles fail without running. Clones share the state.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<CancelState>,
+}
+
+/// A registered canceller, taken by whichever of `cancel`, `register` or
+/// the guard's drop gets to it first.
+type Canceller = Arc<Mutex<Option<Box<dyn FnOnce() + Send>>>>;
+
+#[derive(Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    next: AtomicU64,
+    cancellers: Mutex<HashMap<u64, Canceller>>,
+}
+
+impl CancellationToken {
+    /// Creates a token that has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the statements running under the token, and those started
+    /// under it from now on.
+    ///
+    /// Returns once each running statement has been sent its cancellation.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        // Cancellers may block (MySQL connects to send `KILL QUERY`), so they
+        // run after the lock is released
+        let cancellers: Vec<Canceller> = self
+            .inner
+            .cancellers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .drain()
+            .map(|(_, canceller)| canceller)
+            .collect();
+        for canceller in &cancellers {
+            run(canceller);
+        }
+    }
+
+    /// Whether `cancel` has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Calls `cancel` if the token is cancelled before the returned guard
+    /// is dropped, right away if it already is.
+    pub(crate) fn register(&self, cancel: impl FnOnce() + Send + 'static) -> CancelGuard {
+        let id = self.inner.next.fetch_add(1, Ordering::Relaxed);
+        let canceller: Canceller = Arc::new(Mutex::new(Some(Box::new(cancel))));
+        // `cancel` takes the lock after setting the flag, so a canceller
+        // registered before then is run there and any later one here
+        let cancelled = {
+            let mut cancellers = self.inner.cancellers.lock().unwrap_or_else(|e| e.into_inner());
+            let cancelled = self.is_cancelled();
+            if !cancelled {
+                cancellers.insert(id, Arc::clone(&canceller));
+            }
+            cancelled
+        };
+        if cancelled {
+            run(&canceller);
+        }
+        CancelGuard { token: self.clone(), id, canceller }
+    }
+}
+
+impl std::fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancellationToken").field("cancelled", &self.is_cancelled()).finish()
+    }
+}
+
+/// Runs a canceller unless it already ran or was unregistered, holding its
+/// slot so that `CancelGuard::drop` waits for it.
+fn run(canceller: &Canceller) {
+    let mut slot = canceller.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(cancel) = slot.take() {
+        cancel();
+    }
+}
+
+/// Unregisters a statement's canceller when the statement has finished.
+///
+/// Dropping it waits for a canceller that is already running, so the
+/// cancellation cannot reach the next statement on the same connection.
+pub(crate) struct CancelGuard {
+    token: CancellationToken,
+    id: u64,
+    canceller: Canceller,
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        self.token.inner.cancellers.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.id);
+        self.canceller.lock().unwrap_or_else(|e| e.into_inner()).take();
+    }
+}
@@ -0,0 +1,123 @@
+use crate::connection::DatabaseType;
+
+/// The version of the database server a `Connection` talks to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerVersion {
+    /// The major version, e.g. `16` for PostgreSQL 16.2.
+    pub major: u32,
+    /// The minor version.
+    pub minor: u32,
+    /// The patch version, `0` where the server reports none.
+    pub patch: u32,
+    /// The version as the server reports it, e.g. `8.0.36-0ubuntu0.22.04.1`
+    /// or `10.11.6-MariaDB`.
+    pub text: String,
+}
+
+impl ServerVersion {
+    /// Parses the leading `major.minor.patch` of a server's version string.
+    pub(crate) fn parse(text: &str) -> Self {
+        let numeric = text.split(|c: char| !c.is_ascii_digit() && c != '.').next().unwrap_or_default();
+        let mut parts = numeric.split('.').map(|part| part.parse::<u32>().unwrap_or(0));
+        ServerVersion {
+            major: parts.next().unwrap_or(0),
+            minor: parts.next().unwrap_or(0),
+            patch: parts.next().unwrap_or(0),
+            text: text.to_string(),
+        }
+    }
+
+    /// Whether the server is at least version `major.minor`.
+    pub fn at_least(&self, major: u32, minor: u32) -> bool {
+        (self.major, self.minor) >= (major, minor)
+    }
+
+    /// Whether a MySQL server is MariaDB, whose versions and features
+    /// differ from MySQL's.
+    pub fn is_mariadb(&self) -> bool {
+        self.text.contains("MariaDB")
+    }
+}
+
+/// The SQL features the connected server supports, from its type and
+/// version; see `Connection::capabilities`.
+///
+/// ```ignore
+/// let sql = if conn.capabilities()?.ilike {
+///     r#"SELECT * FROM "users" WHERE "email" ILIKE $1"#
+/// } else {
+///     r#"SELECT * FROM "users" WHERE LOWER("email") LIKE LOWER(?)"#
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Writes can return rows with `RETURNING` (PostgreSQL, SQLite 3.35+,
+    /// MariaDB 10.5+).
+    pub returning: bool,
+    /// Case-insensitive matching with `ILIKE` (PostgreSQL).
+    pub ilike: bool,
+    /// A native JSON column type (PostgreSQL 9.4+ `jsonb`, MySQL 5.7.8+,
+    /// MariaDB 10.2+; SQLite stores JSON as text).
+    pub json_type: bool,
+    /// Inserts that update on a key conflict (`ON CONFLICT` on PostgreSQL
+    /// 9.5+ and SQLite 3.24+, `ON DUPLICATE KEY UPDATE` on MySQL).
+    pub upsert: bool,
+    /// Window functions such as `ROW_NUMBER() OVER (...)`.
+    pub window_functions: bool,
+    /// `WITH` queries, including `WITH RECURSIVE`.
+    pub common_table_expressions: bool,
+    /// Locking reads that skip locked rows with `FOR UPDATE SKIP LOCKED`.
+    pub skip_locked: bool,
+    /// Schema changes (`CREATE TABLE`, `ALTER TABLE`) that roll back with
+    /// their transaction.
+    pub transactional_ddl: bool,
+}
+
+impl Capabilities {
+    /// The capabilities of a `db_type` server at `version`.
+    pub(crate) fn detect(db_type: &DatabaseType, version: &ServerVersion) -> Self {
+        let v = |major, minor| version.at_least(major, minor);
+        match db_type {
+            DatabaseType::PostgreSQL => Capabilities {
+                returning: true,
+                ilike: true,
+                json_type: v(9, 4),
+                upsert: v(9, 5),
+                window_functions: true,
+                common_table_expressions: true,
+                skip_locked: v(9, 5),
+                transactional_ddl: true,
+            },
+            DatabaseType::MySQL if version.is_mariadb() => Capabilities {
+                returning: v(10, 5),
+                ilike: false,
+                json_type: v(10, 2),
+                upsert: true,
+                window_functions: v(10, 2),
+                common_table_expressions: v(10, 2),
+                skip_locked: v(10, 6),
+                transactional_ddl: false,
+            },
+            DatabaseType::MySQL => Capabilities {
+                returning: false,
+                ilike: false,
+                json_type: (version.major, version.minor, version.patch) >= (5, 7, 8),
+                upsert: true,
+                window_functions: v(8, 0),
+                common_table_expressions: v(8, 0),
+                skip_locked: v(8, 0),
+                transactional_ddl: false,
+            },
+            DatabaseType::SQLite => Capabilities {
+                returning: v(3, 35),
+                ilike: false,
+                json_type: false,
+                upsert: v(3, 24),
+                window_functions: v(3, 25),
+                common_table_expressions: v(3, 8),
+                skip_locked: false,
+                transactional_ddl: true,
+            },
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt;
+use std::sync::Arc;
+use serde_json::Value;
+use crate::model::SQLModel;
+use crate::sql_types::SqlType;
+
+/// A built-in conversion for reading values stored with a different type
+/// than the model declares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coercion {
+    /// Parses text such as `"42"` into `Integer` and `BigInt` fields.
+    StringToInt,
+    /// Reads integers into `Boolean` fields: 0 is `false`, anything else `true`.
+    IntToBool,
+    /// Normalizes UUID text (any case, with or without hyphens, braces or a
+    /// `urn:uuid:` prefix) for `Uuid` fields.
+    StringToUuid,
+    /// Reads Unix timestamps in seconds into `DateTime` fields as UTC.
+    EpochToDateTime,
+}
+
+impl Coercion {
+    /// Every built-in coercion.
+    pub const ALL: [Coercion; 4] = [
+        Coercion::StringToInt,
+        Coercion::IntToBool,
+        Coercion::StringToUuid,
+        Coercion::EpochToDateTime,
+    ];
+
+    fn applies_to(self, target: &SqlType) -> bool {
+        match self {
+            Coercion::StringToInt => matches!(target, SqlType::Integer | SqlType::BigInt),
+            Coercion::IntToBool => *target == SqlType::Boolean,
+            Coercion::StringToUuid => *target == SqlType::Uuid,
+            Coercion::EpochToDateTime => *target == SqlType::DateTime,
+        }
+    }
+
+    fn convert(self, value: &Value) -> Option<Value> {
+        match (self, value) {
+            (Coercion::StringToInt, Value::String(s)) => s.trim().parse::<i64>().ok().map(Value::from),
+            (Coercion::IntToBool, Value::Number(n)) => n.as_i64().map(|i| Value::Bool(i != 0)),
+            (Coercion::StringToUuid, Value::String(s)) => normalize_uuid(s).map(Value::String),
+            (Coercion::EpochToDateTime, Value::Number(n)) => n
+                .as_i64()
+                .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+                .map(|dt| Value::String(dt.naive_utc().format("%Y-%m-%dT%H:%M:%S%.6f").to_string())),
+            _ => None,
+        }
+    }
+}
+
+type CustomRule = Arc<dyn Fn(&Value) -> Option<Value> + Send + Sync>;
+
+/// The coercions a `Connection` applies while decoding rows into models.
+///
+/// Each rule targets the `SqlType` a field is declared with and rewrites
+/// column values the model could not otherwise read, such as `"42"` stored in
+/// a text column behind an `i32` field. Values that already have the right
+/// shape are left alone. Install with `Connection::with_coercions`:
+///
+/// ```ignore
+/// let conn = Connection::new(url)?.with_coercions(
+///     Coercions::new()
+///         .with(Coercion::StringToInt)
+///         .with(Coercion::IntToBool)
+///         .with_rule(SqlType::Float, |v| v.as_str()?.replace(',', ".").parse::<f64>().ok().map(Into::into)),
+/// );
+/// ```
+///
+/// Coercions apply to the `SQLModel` finders, which decode rows through the
+/// model's declared field types; `Connection::query_raw` into arbitrary types
+/// is unaffected. Rules are tried in the order added and the first one that
+/// returns a value wins.
+#[derive(Clone, Default)]
+pub struct Coercions {
+    builtin: Vec<Coercion>,
+    custom: Vec<(SqlType, CustomRule)>,
+}
+
+impl Coercions {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a registry with every built-in coercion.
+    pub fn all() -> Self {
+        Coercion::ALL.into_iter().fold(Self::new(), Self::with)
+    }
+
+    /// Adds a built-in coercion.
+    pub fn with(mut self, coercion: Coercion) -> Self {
+        if !self.builtin.contains(&coercion) {
+            self.builtin.push(coercion);
+        }
+        self
+    }
+
+    /// Adds a custom rule for fields declared as `target`. The rule returns
+    /// the replacement value, or `None` to leave the value to later rules.
+    pub fn with_rule<F>(mut self, target: SqlType, rule: F) -> Self
+    where
+        F: Fn(&Value) -> Option<Value> + Send + Sync + 'static,
+    {
+        self.custom.push((target, Arc::new(rule)));
+        self
+    }
+
+    fn coerce(&self, target: &SqlType, value: &Value) -> Option<Value> {
+        self.builtin
+            .iter()
+            .filter(|coercion| coercion.applies_to(target))
+            .find_map(|coercion| coercion.convert(value))
+            .or_else(|| {
+                self.custom
+                    .iter()
+                    .filter(|(rule_target, _)| rule_target == target)
+                    .find_map(|(_, rule)| rule(value))
+            })
+    }
+
+    /// Rewrites the columns of `row` that `T` declares, in place.
+    pub(crate) fn apply<T: SQLModel>(&self, row: &mut serde_json::Map<String, Value>) {
+        for (column, sql_type) in T::field_names().into_iter().zip(T::field_sql_types()) {
+            if let Some(value) = row.get_mut(column) {
+                if let Some(coerced) = self.coerce(&sql_type, value) {
+                    *value = coerced;
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Coercions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coercions")
+            .field("builtin", &self.builtin)
+            .field("custom", &self.custom.iter().map(|(target, _)| target).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Formats UUID text as lowercase and hyphenated, if it is one.
+fn normalize_uuid(text: &str) -> Option<String> {
+    let text = text.trim();
+    let text = text.strip_prefix("urn:uuid:").unwrap_or(text);
+    let text = text.strip_prefix('{').and_then(|t| t.strip_suffix('}')).unwrap_or(text);
+    let hex: String = text.chars().filter(|c| *c != '-').collect();
+    if hex.len() != 32 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let hex = hex.to_ascii_lowercase();
+    Some(format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..]))
+}
@@ -1,24 +1,55 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+#[cfg(any(feature = "postgres", feature = "rusqlite"))]
+use std::sync::Mutex;
+use std::time::Duration;
+#[cfg(any(feature = "mysql", feature = "rusqlite"))]
+use crate::cancel::CancelGuard;
+use crate::cancel::CancellationToken;
+use crate::capabilities::{Capabilities, ServerVersion};
+use crate::coercion::Coercions;
 use crate::error::RusticxError;
-use crate::model::SQLModel;
-use crate::transaction_manager::TransactionExecutor;
+use crate::interceptor::{Interceptors, Next, QueryInfo};
+use crate::limiter::{Limiter, Permit};
+use crate::manager::DEFAULT_DATABASE;
+use crate::metrics::{InFlight, PoolMetrics, PoolStats};
+use crate::model::{expired_condition, SQLModel, ToSql};
+use crate::partition::{Lease, Partition, PartitionConfig};
+use crate::sessions::{SessionLease, TxSessions};
+use crate::options::{apply_url_params, session_settings, url_from_env, ConnectionOptions};
+#[cfg(feature = "mysql")]
+use crate::options::mysql_session_statements;
+#[cfg(feature = "rusqlite")]
+use crate::options::sqlite_pragmas;
+use crate::stream::RowIter;
+use crate::tls::MySqlTls;
+use crate::transaction_manager::{
+    prepared_transaction, validate_gid, DistributedTx, Transaction, TransactionExecutor, TxOptions,
+};
 
 // Conditional includes based on feature flags
 #[cfg(feature = "mysql")]
 use crate::transaction_manager::{run_mysql_transaction, mysql};
+#[cfg(feature = "mysql")]
+use crate::options::{split_hosts, split_mysql_session};
 #[cfg(feature = "rusqlite")]
 use crate::transaction_manager::{run_sqlite_transaction, rusqlite};
 #[cfg(feature = "postgres")]
 use crate::transaction_manager::{run_postgres_transaction, tokio_postgres};
+#[cfg(any(feature = "postgres", feature = "rusqlite"))]
+use crate::options::statement_cache_size;
+#[cfg(any(feature = "postgres", feature = "mysql"))]
+use crate::options::take_url_param;
 #[cfg(feature = "postgres")]
-use postgres::types::ToSql;
-use tokio::runtime::Runtime;
+use crate::prepared::StatementCache;
+use tokio::runtime::{Handle, Runtime};
 #[cfg(feature = "mysql")]
 use mysql::prelude::Queryable;
 
 /// Represents the type of database being used.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DatabaseType {
     /// PostgreSQL database type.
     PostgreSQL,
@@ -28,6 +59,74 @@ pub enum DatabaseType {
     SQLite,
 }
 
+impl DatabaseType {
+    /// The kind of database a connection URL's scheme names.
+    pub(crate) fn from_url(url: &str) -> Result<Self, RusticxError> {
+        if url.starts_with("postgresql://") {
+            Ok(DatabaseType::PostgreSQL)
+        } else if url.starts_with("mysql://") {
+            Ok(DatabaseType::MySQL)
+        } else if url.starts_with("sqlite:") {
+            Ok(DatabaseType::SQLite)
+        } else {
+            Err(RusticxError::ConnectionError(
+                "Invalid database URL scheme. Must start with postgresql://, mysql://, or sqlite:"
+                    .to_string(),
+            ))
+        }
+    }
+
+    /// Returns the bind placeholder for the 1-based parameter `index`.
+    ///
+    /// PostgreSQL uses numbered placeholders (`$1`, `$2`, ...) while MySQL and
+    /// SQLite use positional `?` markers.
+    pub(crate) fn placeholder(&self, index: usize) -> String {
+        match self {
+            DatabaseType::PostgreSQL => format!("${}", index),
+            _ => "?".to_string(),
+        }
+    }
+
+    /// The most parameters one statement can bind: 65535 on PostgreSQL and
+    /// MySQL, whose protocols count them in 16 bits, and SQLite's default
+    /// `SQLITE_MAX_VARIABLE_NUMBER` of 32766.
+    pub(crate) fn max_bind_params(&self) -> usize {
+        match self {
+            DatabaseType::PostgreSQL | DatabaseType::MySQL => 65535,
+            DatabaseType::SQLite => 32766,
+        }
+    }
+
+    /// Quotes an identifier (table or column name) for this dialect.
+    ///
+    /// MySQL uses backticks while PostgreSQL and SQLite use double quotes, so
+    /// generated SQL works without MySQL's `ANSI_QUOTES` mode. Schema-qualified
+    /// names (`schema.table`) are quoted part by part, and quote characters
+    /// inside a name are doubled.
+    pub fn quote_identifier(&self, ident: &str) -> String {
+        let quote = match self {
+            DatabaseType::MySQL => '`',
+            DatabaseType::PostgreSQL | DatabaseType::SQLite => '"',
+        };
+        ident
+            .split('.')
+            .map(|part| {
+                let escaped = part.replace(quote, &format!("{quote}{quote}"));
+                format!("{quote}{escaped}{quote}")
+            })
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+}
+
+/// A statement prepared by `Connection::prepare`.
+pub(crate) enum Prepared {
+    #[cfg(feature = "postgres")]
+    Postgres(tokio_postgres::Statement, String),
+    #[cfg_attr(not(any(feature = "mysql", feature = "rusqlite")), allow(dead_code))]
+    Text(String),
+}
+
 /// Represents a connection pool for different database types.
 ///
 /// This enum holds the specific connection pool or client instance
@@ -36,14 +135,16 @@ pub enum DatabaseType {
 #[derive(Clone)]
 pub enum ConnectionPool {
     /// Connection pool/client for PostgreSQL.
-    /// Holds an `Arc<Mutex<tokio_postgres::Client>>` for thread-safe access
-    /// and an `Arc<Runtime>` for managing async operations.
+    /// Holds an `Arc<Mutex<tokio_postgres::Client>>` for thread-safe access,
+    /// a `Handle` to the runtime its session runs on and the session's
+    /// prepared statement cache.
     #[cfg(feature = "postgres")]
-    PostgreSQL(Arc<Mutex<tokio_postgres::Client>>, Arc<Runtime>),
+    PostgreSQL(Arc<Mutex<tokio_postgres::Client>>, Handle, Arc<StatementCache>),
     /// Connection pool for MySQL.
-    /// Holds an `Arc<mysql::Pool>`.
+    /// Holds an `Arc<mysql::Pool>` and the options its connections are
+    /// opened with, used to open a separate connection for `KILL QUERY`.
     #[cfg(feature = "mysql")]
-    MySQL(Arc<mysql::Pool>),
+    MySQL(Arc<mysql::Pool>, Arc<mysql::Opts>),
     /// Connection for SQLite.
     /// Holds an `Arc<Mutex<rusqlite::Connection>>` for thread-safe access.
     #[cfg(feature = "rusqlite")]
@@ -52,6 +153,232 @@ pub enum ConnectionPool {
     None,
 }
 
+impl ConnectionPool {
+    /// Whether another handle — a clone, a partition checkout or an open
+    /// row stream — still uses this pool.
+    pub(crate) fn is_shared(&self) -> bool {
+        match self {
+            #[cfg(feature = "postgres")]
+            ConnectionPool::PostgreSQL(client, ..) => Arc::strong_count(client) > 1,
+            #[cfg(feature = "mysql")]
+            ConnectionPool::MySQL(pool, _) => Arc::strong_count(pool) > 1,
+            #[cfg(feature = "rusqlite")]
+            ConnectionPool::SQLite(conn) => Arc::strong_count(conn) > 1,
+            ConnectionPool::None => false,
+        }
+    }
+
+    /// Readies a transaction session for another transaction, returning
+    /// whether it can run one: it is still open, not left inside a
+    /// transaction and, on MySQL, reset to a fresh session.
+    pub(crate) fn reset_for_reuse(&self) -> bool {
+        match self {
+            #[cfg(feature = "postgres")]
+            ConnectionPool::PostgreSQL(client, _, statements) => {
+                !statements.in_transaction() && !client.lock().unwrap_or_else(|e| e.into_inner()).is_closed()
+            }
+            // The session pool does not reset its connection between
+            // statements, so it is reset once here as it goes back to the
+            // pool, clearing whatever the transaction left behind. A
+            // connection that fails the reset is closed and replaced.
+            #[cfg(feature = "mysql")]
+            ConnectionPool::MySQL(pool, _) => match pool.get_conn() {
+                Ok(mut conn) => {
+                    conn.reset_connection(true);
+                    true
+                }
+                Err(_) => false,
+            },
+            #[cfg(feature = "rusqlite")]
+            ConnectionPool::SQLite(_) => false,
+            ConnectionPool::None => false,
+        }
+    }
+
+    /// Closes the sessions of a pool no other handle uses.
+    fn close(self) -> Result<(), RusticxError> {
+        match self {
+            #[cfg(feature = "postgres")]
+            ConnectionPool::PostgreSQL(client, ..) => {
+                // Dropping the client ends its background task, which tells
+                // the server goodbye
+                drop(client);
+                Ok(())
+            }
+
+            #[cfg(feature = "mysql")]
+            ConnectionPool::MySQL(pool, _) => {
+                drop(pool);
+                Ok(())
+            }
+
+            #[cfg(feature = "rusqlite")]
+            ConnectionPool::SQLite(conn) => {
+                let Ok(conn) = Arc::try_unwrap(conn) else { return Ok(()) };
+                conn.into_inner()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .close()
+                    .map_err(|(_, e)| RusticxError::ConnectionError(format!("Failed to close SQLite database: {}", e)))
+            }
+
+            ConnectionPool::None => Ok(()),
+        }
+    }
+}
+
+/// Progress of a SQLite backup or restore, reported after every step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackupProgress {
+    /// Pages that still need to be copied.
+    pub remaining: u32,
+    /// Total pages in the source database.
+    pub total: u32,
+}
+
+/// The Tokio runtime PostgreSQL sessions run on unless given one with
+/// `Connection::new_with_runtime`, started on first use.
+pub(crate) fn shared_runtime() -> Result<Handle, RusticxError> {
+    static RUNTIME: std::sync::OnceLock<Result<Runtime, String>> = std::sync::OnceLock::new();
+    RUNTIME
+        .get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .thread_name("rusticx-runtime")
+                .build()
+                .map_err(|e| e.to_string())
+        })
+        .as_ref()
+        .map(|rt| rt.handle().clone())
+        .map_err(|e| RusticxError::ConnectionError(format!("Failed to create Tokio runtime: {}", e)))
+}
+
+/// Runs `future` on `rt` from synchronous code.
+///
+/// Blocking a worker thread of a Tokio runtime directly panics, so when
+/// called from one (a `Connection` method inside an `async fn` on a
+/// multi-threaded runtime) the worker's other tasks are first handed to
+/// another thread with `block_in_place`. A current-thread runtime has no
+/// other thread to hand them to, so there the call still panics.
+#[cfg(feature = "postgres")]
+pub(crate) fn block_on<F: std::future::Future>(rt: &Handle, future: F) -> F::Output {
+    match Handle::try_current() {
+        Ok(current) if current.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| rt.block_on(future))
+        }
+        _ => rt.block_on(future),
+    }
+}
+
+/// Opens a PostgreSQL session whose background task runs on `rt`, and
+/// applies the `session` settings to it.
+#[cfg(feature = "postgres")]
+fn open_postgres(url: &str, rt: &Handle, session: &[(String, String)]) -> Result<tokio_postgres::Client, RusticxError> {
+    // The driver does not know the statement cache parameter
+    let (url, _) = take_url_param(url, "statement_cache_size");
+    let (client, connection) = block_on(rt, async { tokio_postgres::connect(&url, tokio_postgres::NoTls).await })
+        .map_err(|e| {
+            RusticxError::ConnectionError(format!("Failed to connect to PostgreSQL: {}", crate::error::pg_error_message(&e)))
+        })?;
+
+    // Spawn a task to handle the connection errors asynchronously
+    rt.spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("PostgreSQL connection error: {}", e);
+        }
+    });
+
+    // `set_config` takes the value as a parameter, so lists such as a
+    // search path need no quoting
+    for (name, value) in session {
+        block_on(rt, client.execute("SELECT set_config($1, $2, false)", &[name, value]))
+            .map_err(|e| RusticxError::ConnectionError(format!("Failed to set {}: {}", name, crate::error::pg_error_message(&e))))?;
+    }
+    Ok(client)
+}
+
+/// Opens the SQLite database a `sqlite:` URL names.
+///
+/// Accepts `sqlite::memory:` and `sqlite://:memory:` for a private in-memory
+/// database, `sqlite://path/to/file.db`, and either form followed by SQLite
+/// URI parameters such as `?mode=ro&cache=shared`, plus `busy_timeout` in
+/// milliseconds and `statement_cache_size`. Missing parent directories of a
+/// file are created unless it is opened read-only.
+#[cfg(feature = "rusqlite")]
+fn open_sqlite(url: &str) -> Result<rusqlite::Connection, RusticxError> {
+    let target = url.strip_prefix("sqlite://").or_else(|| url.strip_prefix("sqlite:")).unwrap_or(url);
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let path = path.strip_prefix("file:").unwrap_or(path);
+
+    // `busy_timeout` and `statement_cache_size` are applied here; SQLite
+    // does not know them as URI parameters
+    let mut busy_timeout = None;
+    let statement_cache = statement_cache_size(url)?;
+    let mut params = Vec::new();
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        match pair.strip_prefix("busy_timeout=") {
+            Some(ms) => {
+                busy_timeout = Some(ms.parse::<u64>().map_err(|_| {
+                    RusticxError::ValidationError(format!("Invalid busy_timeout '{}': expected milliseconds", ms))
+                })?)
+            }
+            None if pair.starts_with("statement_cache_size=") => {}
+            None => params.push(pair),
+        }
+    }
+    let query = (!params.is_empty()).then(|| params.join("&"));
+
+    let in_memory = path == ":memory:";
+    let read_only = params.contains(&"mode=ro");
+    if !in_memory && !read_only {
+        if let Some(parent) = std::path::Path::new(path).parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                RusticxError::ConnectionError(format!(
+                    "Failed to create the directory {} for SQLite: {}",
+                    parent.display(),
+                    e
+                ))
+            })?;
+        }
+    }
+
+    let failed = |e: rusqlite::Error| RusticxError::ConnectionError(format!("Failed to connect to SQLite: {}", e));
+    let conn = match query {
+        // rusqlite opens with SQLITE_OPEN_URI, so SQLite applies the parameters itself
+        Some(query) => rusqlite::Connection::open(format!("file:{}?{}", if in_memory { ":memory:" } else { path }, query)),
+        None if in_memory => rusqlite::Connection::open_in_memory(),
+        None => rusqlite::Connection::open(path),
+    }
+    .map_err(failed)?;
+    if let Some(ms) = busy_timeout {
+        conn.busy_timeout(Duration::from_millis(ms)).map_err(failed)?;
+    }
+    if let Some(capacity) = statement_cache {
+        conn.set_prepared_statement_cache_capacity(capacity);
+    }
+    Ok(conn)
+}
+
+/// Steps a SQLite backup to completion, reporting progress after each step
+/// and pausing while the source is busy.
+#[cfg(feature = "rusqlite")]
+fn run_backup(backup: &rusqlite::backup::Backup<'_, '_>, progress: &mut dyn FnMut(BackupProgress)) -> Result<(), RusticxError> {
+    use rusqlite::backup::StepResult;
+
+    loop {
+        let step = backup.step(100)?;
+        let current = backup.progress();
+        progress(BackupProgress {
+            remaining: current.remaining.max(0) as u32,
+            total: current.pagecount.max(0) as u32,
+        });
+        match step {
+            StepResult::Done => return Ok(()),
+            StepResult::More => {}
+            _ => std::thread::sleep(std::time::Duration::from_millis(250)),
+        }
+    }
+}
+
 /// Represents a database connection with its URL, type, and connection pool.
 ///
 /// This struct provides a unified interface for interacting with different
@@ -64,6 +391,91 @@ pub struct Connection {
     db_type: DatabaseType,
     /// The underlying connection pool or client.
     pool: ConnectionPool,
+    /// Named partitions of dedicated connections, shared by all clones.
+    partitions: HashMap<String, Arc<Partition>>,
+    /// Keeps a partition connection checked out while this handle is alive.
+    lease: Option<Arc<Lease>>,
+    /// The sessions `begin` runs transactions on, shared by all clones.
+    tx_sessions: Arc<TxSessions>,
+    /// Gauges of the main connection, shared by all clones.
+    stats: Arc<PoolStats>,
+    /// Caps concurrent statements on the main connection, shared by all clones.
+    limiter: Option<Arc<Limiter>>,
+    /// Conversions applied when decoding rows into models.
+    coercions: Option<Arc<Coercions>>,
+    /// TLS settings for MySQL connections, overriding those in the URL.
+    mysql_tls: Option<MySqlTls>,
+    /// Read replicas that serve plain `SELECT`s, shared by all clones.
+    replicas: Option<Arc<Replicas>>,
+    /// How long each statement run through this handle may take.
+    timeout: Option<Duration>,
+    /// The schema generated SQL qualifies table names with, if any.
+    schema: Option<String>,
+    /// The runtime PostgreSQL sessions run on, instead of the shared one.
+    #[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+    runtime: Option<Handle>,
+    /// The server's version, read once and shared by all clones.
+    server_version: Arc<OnceLock<ServerVersion>>,
+    /// Settings applied to every session opened, from `set_session`.
+    #[cfg_attr(not(any(feature = "postgres", feature = "mysql", feature = "rusqlite")), allow(dead_code))]
+    session: Arc<Vec<(String, String)>>,
+    /// Hooks and interceptors statements run through, shared by all clones.
+    interceptors: Arc<Interceptors>,
+    /// Cancels this handle's statements when cancelled.
+    cancellation: Option<CancellationToken>,
+}
+
+/// Read replicas of a `Connection`, used in turn.
+struct Replicas {
+    connections: Vec<Connection>,
+    next: AtomicUsize,
+}
+
+/// Removes the progress handler installed by `Connection::sqlite_deadline`.
+#[cfg(feature = "rusqlite")]
+struct SqliteDeadline<'c>(&'c rusqlite::Connection);
+
+#[cfg(feature = "rusqlite")]
+impl Drop for SqliteDeadline<'_> {
+    fn drop(&mut self) {
+        self.0.progress_handler(0, None::<fn() -> bool>);
+    }
+}
+
+/// Converts a SQLite error, reporting statements interrupted by
+/// `Connection::sqlite_deadline` as timeouts.
+#[cfg(feature = "rusqlite")]
+pub(crate) fn sqlite_error(err: rusqlite::Error) -> RusticxError {
+    match err.sqlite_error_code() {
+        Some(rusqlite::ErrorCode::OperationInterrupted) => {
+            RusticxError::Timeout("The statement was interrupted after its timeout".to_string())
+        }
+        _ => RusticxError::QueryError(err.to_string()),
+    }
+}
+
+/// Whether `sql` only reads and so may run on a replica: a `SELECT` that
+/// takes no row locks, writes nothing and reads no per-session state such
+/// as the last inserted id.
+fn is_read_only(sql: &str) -> bool {
+    const WRITES: [&str; 14] = [
+        "FOR UPDATE",
+        "FOR NO KEY UPDATE",
+        "FOR SHARE",
+        "FOR KEY SHARE",
+        "LOCK IN SHARE MODE",
+        " INTO ",
+        "NEXTVAL",
+        "SETVAL",
+        "LASTVAL",
+        "CURRVAL",
+        "LAST_INSERT_ID",
+        "LAST_INSERT_ROWID",
+        "PG_ADVISORY",
+        "SET_CONFIG",
+    ];
+    let sql = sql.trim_start().to_ascii_uppercase();
+    sql.starts_with("SELECT") && !WRITES.iter().any(|write| sql.contains(write))
 }
 
 impl Connection {
@@ -74,36 +486,180 @@ impl Connection {
     ///
     /// # Arguments
     ///
-    /// * `url`: The database connection string (e.g., "postgres://...", "mysql://...", "sqlite://...").
+    /// * `url`: The database connection string (e.g., "postgres://...", "mysql://...", "sqlite://...",
+    ///   "sqlite::memory:").
+    ///
+    /// PostgreSQL and MySQL URLs may list several hosts for a cluster, as in
+    /// `postgresql://app@db1:5432,db2:5432/shop?target_session_attrs=read-write`.
+    /// They are tried in order until one accepts the connection and, with
+    /// `target_session_attrs=read-write`, is not read-only. A PostgreSQL
+    /// session that drops is reopened by `ping` the same way, so it fails
+    /// over to the new primary; a MySQL pool stays on the host it was opened on.
     ///
     /// # Returns
     ///
     /// Returns a `Result` containing the initialized `Connection` on success,
     /// or a `RusticxError` if the URL is invalid or connection fails.
     pub fn new(url: &str) -> Result<Self, RusticxError> {
-        let db_type = if url.starts_with("postgresql://") {
-            DatabaseType::PostgreSQL
-        } else if url.starts_with("mysql://") {
-            DatabaseType::MySQL
-        } else if url.starts_with("sqlite://") {
-            DatabaseType::SQLite
-        } else {
-            return Err(RusticxError::ConnectionError(
-                "Invalid database URL scheme. Must start with postgresql://, mysql://, or sqlite://"
-                    .to_string(),
-            ));
-        };
+        let db_type = DatabaseType::from_url(url)?;
 
         let connection = Connection {
             url: url.to_string(),
             db_type,
             pool: ConnectionPool::None, // Initialize with None, connect() will populate
+            partitions: HashMap::new(),
+            lease: None,
+            tx_sessions: Arc::new(TxSessions::default()),
+            stats: Arc::new(PoolStats::new(DEFAULT_DATABASE)),
+            limiter: None,
+            coercions: None,
+            mysql_tls: None,
+            replicas: None,
+            timeout: None,
+            schema: None,
+            runtime: None,
+            server_version: Arc::new(OnceLock::new()),
+            session: Arc::new(Vec::new()),
+            interceptors: Arc::new(Interceptors::default()),
+            cancellation: None,
         };
 
         // Immediately attempt to connect after determining the type
         connection.connect()
     }
 
+    /// Starts building a connection to a `db_type` database from individual
+    /// settings; see `ConnectionOptions`.
+    pub fn builder(db_type: DatabaseType) -> ConnectionOptions {
+        ConnectionOptions::new(db_type)
+    }
+
+    /// Creates a `Connection` from the process environment, for deployments
+    /// configured through it.
+    ///
+    /// Connects to `DATABASE_URL`. These optional variables set the standard
+    /// URL parameter of the same meaning, replacing any in the URL:
+    ///
+    /// * `DATABASE_POOL_SIZE`: the most connections to keep open. Only MySQL
+    ///   connections are pooled; other databases ignore it and log that they did.
+    /// * `DATABASE_CONNECT_TIMEOUT`: seconds to wait for the server.
+    /// * `DATABASE_SSLMODE`: `disable`, `prefer`, `require`, ...
+    /// * `DATABASE_APPLICATION_NAME`: the name the server lists the client under.
+    /// * `DATABASE_STATEMENT_CACHE_SIZE`: prepared statements kept per session.
+    ///
+    /// Empty variables count as unset.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::ValidationError` if `DATABASE_URL` is not set
+    /// or a variable's value is invalid for the database, and otherwise
+    /// fails as `Connection::new` does.
+    pub fn from_env() -> Result<Self, RusticxError> {
+        Connection::from_env_with(|name| std::env::var(name).ok())
+    }
+
+    /// Creates a `Connection` as `from_env` does, reading each `DATABASE_*`
+    /// variable from `lookup` instead of the process environment.
+    ///
+    /// ```ignore
+    /// let settings: HashMap<String, String> = load_settings()?;
+    /// let conn = Connection::from_env_with(|name| settings.get(name).cloned())?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails as `from_env` does.
+    pub fn from_env_with<F>(lookup: F) -> Result<Self, RusticxError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        Connection::new(&url_from_env(lookup)?)
+    }
+
+    /// Creates a PostgreSQL `Connection` whose session runs on the
+    /// multi-threaded Tokio runtime behind `runtime` instead of the runtime
+    /// connections share by default.
+    ///
+    /// ```ignore
+    /// let rt = tokio::runtime::Runtime::new()?;
+    /// let conn = Connection::new_with_runtime("postgresql://app@db/shop", rt.handle().clone())?;
+    /// ```
+    ///
+    /// Its partitions and replicas use the runtime as well. Statements still
+    /// block the calling thread, which must not be one of the runtime's.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::ValidationError` if `url` is not a
+    /// `postgresql://` URL, and otherwise fails as `Connection::new` does.
+    pub fn new_with_runtime(url: &str, runtime: Handle) -> Result<Self, RusticxError> {
+        if !url.starts_with("postgresql://") {
+            return Err(RusticxError::ValidationError(
+                "A Tokio runtime can only be given for a postgresql:// URL".to_string(),
+            ));
+        }
+
+        let connection = Connection {
+            url: url.to_string(),
+            db_type: DatabaseType::PostgreSQL,
+            pool: ConnectionPool::None,
+            partitions: HashMap::new(),
+            lease: None,
+            tx_sessions: Arc::new(TxSessions::default()),
+            stats: Arc::new(PoolStats::new(DEFAULT_DATABASE)),
+            limiter: None,
+            coercions: None,
+            mysql_tls: None,
+            replicas: None,
+            timeout: None,
+            schema: None,
+            runtime: Some(runtime),
+            server_version: Arc::new(OnceLock::new()),
+            session: Arc::new(Vec::new()),
+            interceptors: Arc::new(Interceptors::default()),
+            cancellation: None,
+        };
+        connection.connect()
+    }
+
+    /// Creates a MySQL `Connection` that uses the TLS settings `tls`.
+    ///
+    /// Settings given here replace any `ssl-*` parameters in `url`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::ValidationError` if `url` is not a `mysql://`
+    /// URL, and otherwise fails as `Connection::new` does.
+    pub fn new_with_tls(url: &str, tls: MySqlTls) -> Result<Self, RusticxError> {
+        if !url.starts_with("mysql://") {
+            return Err(RusticxError::ValidationError(
+                "MySQL TLS settings can only be used with a mysql:// URL".to_string(),
+            ));
+        }
+
+        let connection = Connection {
+            url: url.to_string(),
+            db_type: DatabaseType::MySQL,
+            pool: ConnectionPool::None,
+            partitions: HashMap::new(),
+            lease: None,
+            tx_sessions: Arc::new(TxSessions::default()),
+            stats: Arc::new(PoolStats::new(DEFAULT_DATABASE)),
+            limiter: None,
+            coercions: None,
+            mysql_tls: Some(tls),
+            replicas: None,
+            timeout: None,
+            schema: None,
+            runtime: None,
+            server_version: Arc::new(OnceLock::new()),
+            session: Arc::new(Vec::new()),
+            interceptors: Arc::new(Interceptors::default()),
+            cancellation: None,
+        };
+        connection.connect()
+    }
+
     /// Establishes a connection to the database and returns the updated `Connection`.
     ///
     /// This internal helper function performs the actual database connection
@@ -113,50 +669,58 @@ impl Connection {
     ///
     /// Returns a `Result` containing the `Connection` with an active pool on success,
     /// or a `RusticxError` if the connection fails or the database feature is not enabled.
-    fn connect(self) -> Result<Self, RusticxError> {
+    fn connect(mut self) -> Result<Self, RusticxError> {
+        self.url = apply_url_params(&self.db_type, &self.url)?;
         let pool = match self.db_type {
             #[cfg(feature = "postgres")]
             DatabaseType::PostgreSQL => {
-                use tokio_postgres::NoTls;
-
-                // Create a dedicated Tokio runtime for blocking async operations
-                let rt = Runtime::new().map_err(|e| {
-                    RusticxError::ConnectionError(format!("Failed to create Tokio runtime: {}", e))
-                })?;
+                let rt = match &self.runtime {
+                    Some(handle) => handle.clone(),
+                    None => shared_runtime()?,
+                };
+                let capacity = statement_cache_size(&self.url)?.unwrap_or(0);
+                let client = open_postgres(&self.url, &rt, &self.session)?;
 
-                let (client, connection) = rt
-                    .block_on(async { tokio_postgres::connect(&self.url, NoTls).await })
-                    .map_err(|e| {
-                        RusticxError::ConnectionError(format!("Failed to connect to PostgreSQL: {}", e))
-                    })?;
-
-                // Spawn a task to handle the connection errors asynchronously
-                rt.spawn(async move {
-                    if let Err(e) = connection.await {
-                        eprintln!("PostgreSQL connection error: {}", e);
-                    }
-                });
-
-                ConnectionPool::PostgreSQL(Arc::new(Mutex::new(client)), Arc::new(rt))
+                self.stats.set_size(1);
+                ConnectionPool::PostgreSQL(Arc::new(Mutex::new(client)), rt, Arc::new(StatementCache::new(capacity)))
             }
 
             #[cfg(feature = "mysql")]
             DatabaseType::MySQL => {
-                let opts = mysql::OptsBuilder::from_opts(
-                    mysql::Opts::from_url(&self.url)
-                        .map_err(|e| RusticxError::ConnectionError(format!("Invalid MySQL URL: {}", e)))?,
-                );
-                let pool = mysql::Pool::new(opts)
-                    .map_err(|e| RusticxError::ConnectionError(format!("Failed to connect to MySQL: {}", e)))?;
-                ConnectionPool::MySQL(Arc::new(pool))
+                let (url, target) = take_url_param(&self.url, "target_session_attrs");
+                let read_write = target.as_deref() == Some("read-write");
+
+                // Hosts are tried in order, as PostgreSQL's driver does
+                let mut failures = Vec::new();
+                let mut opened = None;
+                for (host, host_url) in split_hosts(&url) {
+                    match self.open_mysql(&host_url, read_write) {
+                        Ok((pool, opts)) => {
+                            opened = Some((pool, opts, host_url));
+                            break;
+                        }
+                        Err(e) => failures.push(format!("{}: {}", host, e)),
+                    }
+                }
+                let Some((pool, opts, url)) = opened else {
+                    return Err(RusticxError::ConnectionError(format!(
+                        "Failed to connect to MySQL: {}",
+                        failures.join("; ")
+                    )));
+                };
+                // The driver allows 100 connections unless `pool_max` says otherwise
+                let (_, max) = take_url_param(&url, "pool_max");
+                self.stats.set_size(max.and_then(|max| max.parse().ok()).unwrap_or(100));
+                ConnectionPool::MySQL(Arc::new(pool), Arc::new(opts))
             }
 
             #[cfg(feature = "rusqlite")]
             DatabaseType::SQLite => {
-                let path = self.url.trim_start_matches("sqlite://");
-                let conn = rusqlite::Connection::open(path).map_err(|e| {
-                    RusticxError::ConnectionError(format!("Failed to connect to SQLite: {}", e))
+                let conn = open_sqlite(&self.url)?;
+                conn.execute_batch(&sqlite_pragmas(&self.session)).map_err(|e| {
+                    RusticxError::ConnectionError(format!("Failed to apply session settings: {}", e))
                 })?;
+                self.stats.set_size(1);
                 ConnectionPool::SQLite(Arc::new(Mutex::new(conn)))
             }
 
@@ -173,96 +737,901 @@ impl Connection {
         };
 
         Ok(Connection {
-            url: self.url.clone(),
-            db_type: self.db_type.clone(),
+            url: self.url,
+            db_type: self.db_type,
             pool,
+            partitions: self.partitions,
+            lease: self.lease,
+            tx_sessions: self.tx_sessions,
+            stats: self.stats,
+            limiter: self.limiter,
+            coercions: self.coercions,
+            mysql_tls: self.mysql_tls,
+            replicas: self.replicas,
+            timeout: self.timeout,
+            schema: self.schema,
+            runtime: self.runtime,
+            server_version: self.server_version,
+            session: self.session,
+            interceptors: self.interceptors,
+            cancellation: self.cancellation,
         })
     }
 
-    /// Creates a table in the database based on the provided SQL model definition.
+    /// Opens a MySQL pool to the single host of `url`, checking that it
+    /// accepts writes if `read_write` is set.
+    #[cfg(feature = "mysql")]
+    fn open_mysql(&self, url: &str, read_write: bool) -> Result<(mysql::Pool, mysql::Opts), RusticxError> {
+        let (url, url_tls) = MySqlTls::from_url(url)?;
+        let (url, statement_timeout, application_name) = split_mysql_session(&url)?;
+        let mut opts = mysql::OptsBuilder::from_opts(
+            mysql::Opts::from_url(&url)
+                .map_err(|e| RusticxError::ConnectionError(format!("Invalid MySQL URL: {}", e)))?,
+        );
+        if let Some(tls) = self.mysql_tls.as_ref().or(url_tls.as_ref()) {
+            opts = opts.ssl_opts(tls.ssl_opts()?);
+        }
+        // Run on every connection the pool opens
+        let mut init = mysql_session_statements(&self.session);
+        if let Some(ms) = statement_timeout {
+            init.push(format!("SET SESSION max_execution_time = {}", ms));
+        }
+        if !init.is_empty() {
+            opts = opts.init(init);
+        }
+        if let Some(name) = application_name {
+            opts = opts.connect_attrs(Some(HashMap::from([("program_name".to_string(), name)])));
+        }
+        let opts = mysql::Opts::from(opts);
+        let pool = mysql::Pool::new(opts.clone()).map_err(|e| RusticxError::ConnectionError(e.to_string()))?;
+
+        if read_write {
+            let read_only: Option<u8> = pool
+                .get_conn()
+                .and_then(|mut conn| conn.query_first("SELECT @@global.read_only"))
+                .map_err(|e| RusticxError::ConnectionError(e.to_string()))?;
+            if read_only != Some(0) {
+                return Err(RusticxError::ConnectionError("the server is read-only".to_string()));
+            }
+        }
+        Ok((pool, opts))
+    }
+
+    /// Adds a named partition of dedicated connections.
     ///
-    /// This function uses the `SQLModel` trait to generate the appropriate
-    /// `CREATE TABLE` SQL statement for the current database type and
-    /// executes it.
+    /// Opens `config.size` additional connections to the same database that
+    /// are only used by handles obtained from `partition(name)`. Separate
+    /// partitions keep workloads apart, so for example a batch export cannot
+    /// occupy the connections serving interactive requests.
     ///
-    /// # Type Parameters
+    /// # Errors
     ///
-    /// * `T`: The type representing the SQL model, which must implement `SQLModel`.
+    /// Returns `RusticxError::ValidationError` if `config.size` is zero, or a
+    /// `RusticxError::ConnectionError` if a connection cannot be opened.
+    pub fn with_partition(mut self, name: &str, config: PartitionConfig) -> Result<Self, RusticxError> {
+        if config.size == 0 {
+            return Err(RusticxError::ValidationError(format!(
+                "Partition '{}' must have at least one connection", name
+            )));
+        }
+
+        let mut slots = Vec::with_capacity(config.size);
+        for _ in 0..config.size {
+            let slot = Connection {
+                url: self.url.clone(),
+                db_type: self.db_type.clone(),
+                pool: ConnectionPool::None,
+                partitions: HashMap::new(),
+                lease: None,
+                tx_sessions: Arc::new(TxSessions::default()),
+                stats: Arc::clone(&self.stats),
+                limiter: None,
+                coercions: None,
+                mysql_tls: self.mysql_tls.clone(),
+                replicas: None,
+                timeout: None,
+                schema: None,
+                runtime: self.runtime.clone(),
+                server_version: Arc::clone(&self.server_version),
+                session: Arc::clone(&self.session),
+                interceptors: Arc::clone(&self.interceptors),
+                cancellation: None,
+            }
+            .connect()?;
+            slots.push(slot.pool);
+        }
+
+        let stats = PoolStats::new(&format!("{}/{}", self.stats.name(), name));
+        self.partitions.insert(name.to_string(), Arc::new(Partition::new(name, config, slots, stats)));
+        Ok(self)
+    }
+
+    /// Sets how many sessions `begin` keeps for PostgreSQL and MySQL
+    /// transactions, and how long it waits for one when all are in use.
     ///
-    /// # Returns
+    /// Each transaction needs a session of its own. Sessions are opened as
+    /// transactions need them, up to `config.size` (10 by default), and kept
+    /// open for the next transaction once one ends. The setting applies to
+    /// this handle and the clones made from it afterwards.
     ///
-    /// Returns `Ok(())` on successful table creation, or a `RusticxError`
-    /// if the SQL generation or execution fails.
-    pub fn create_table<T: SQLModel>(&self) -> Result<(), RusticxError> {
-        // The table name is not directly used here, but could be for logging or validation
-        let _table_name = T::table_name();
-        let sql = T::create_table_sql(&self.db_type);
-        self.execute(&sql, &[])?;
-        Ok(())
+    /// # Errors
+    ///
+    /// Returns `RusticxError::ValidationError` if `config.size` is zero.
+    pub fn with_transaction_sessions(mut self, config: PartitionConfig) -> Result<Self, RusticxError> {
+        if config.size == 0 {
+            return Err(RusticxError::ValidationError(
+                "Transactions need at least one session".to_string(),
+            ));
+        }
+        self.tx_sessions = Arc::new(TxSessions::new(config.size, config.acquire_timeout));
+        Ok(self)
     }
 
-    /// Executes a SQL command (INSERT, UPDATE, DELETE, CREATE, DROP, etc.)
-    /// with the provided parameters.
+    /// Checks out a connection from the named partition.
     ///
-    /// This function is typically used for commands that do not return a result set.
-    /// The number of affected rows (where applicable) is returned.
-    ///
-    /// # Arguments
+    /// The returned `Connection` can be passed to any query or model method and
+    /// keeps its partition connection reserved until it (and every clone of it)
+    /// is dropped. If all connections of the partition are in use, this waits
+    /// up to the partition's acquire timeout.
     ///
-    /// * `sql`: The SQL query string to execute.
-    /// * `params`: A slice of references to values to be used as query parameters.
-    ///             The specific type required depends on the database driver
-    ///             (e.g., `&(dyn ToSql + Sync + 'static)` for postgres).
+    /// # Errors
     ///
-    /// # Returns
+    /// Returns `RusticxError::ConnectionError` if no partition named `name` was
+    /// registered or no connection became free in time.
+    pub fn partition(&self, name: &str) -> Result<Connection, RusticxError> {
+        let partition = self.partitions.get(name).ok_or_else(|| {
+            RusticxError::ConnectionError(format!("No connection partition named '{}' is configured", name))
+        })?;
+        let (pool, lease) = partition.acquire()?;
+        #[cfg(feature = "postgres")]
+        if let ConnectionPool::PostgreSQL(client, rt, statements) = &pool {
+            // Nothing on a session that closed while free belongs to the new lease
+            if client.lock().is_ok_and(|client| client.is_closed()) {
+                statements.reset();
+            }
+            drop(self.reopen_postgres(client, rt, statements)?);
+        }
+
+        Ok(Connection {
+            url: self.url.clone(),
+            db_type: self.db_type.clone(),
+            pool,
+            partitions: self.partitions.clone(),
+            lease: Some(Arc::new(lease)),
+            tx_sessions: Arc::clone(&self.tx_sessions),
+            stats: Arc::clone(&self.stats),
+            limiter: None,
+            coercions: self.coercions.clone(),
+            mysql_tls: self.mysql_tls.clone(),
+            replicas: None,
+            timeout: self.timeout,
+            schema: self.schema.clone(),
+            runtime: self.runtime.clone(),
+            server_version: Arc::clone(&self.server_version),
+            session: Arc::clone(&self.session),
+            interceptors: Arc::clone(&self.interceptors),
+            cancellation: self.cancellation.clone(),
+        })
+    }
+
+    /// Adds a read replica at `url`.
     ///
-    /// Returns a `Result` containing the number of rows affected on success,
-    /// or a `RusticxError` if the execution fails. Note that the meaning
-    /// and availability of "rows affected" can vary between database drivers.
+    /// Once replicas are added, plain `SELECT`s run through `query_raw`,
+    /// `query_positional` and `query_stream` (and so the model finders) are
+    /// spread over the replicas in turn; writes, locking reads and everything
+    /// else go to this connection, the primary. Read-only transactions (see
+    /// `TxOptions::read_only`) take turns on the replicas too. Use `primary`
+    /// for reads that must see the latest writes.
     ///
     /// # Errors
     ///
-    /// Returns a `RusticxError::QueryError` on database query execution failure
-    /// or `RusticxError::ConnectionError` if the connection pool is not initialized.
-    pub fn execute(
-        &self,
-        sql: &str,
-        params: &[&(dyn ToSql + Sync + 'static)],
-    ) -> Result<u64, RusticxError> {
-        match &self.pool {
-            #[cfg(feature = "postgres")]
-            ConnectionPool::PostgreSQL(client, rt) => {
-                let client_guard = client.lock().map_err(|e| {
-                    RusticxError::TransactionError(format!("Failed to acquire lock on connection: {}", e))
-                })?;
-                
-                let result = rt
-                    .block_on(async { client_guard.execute(sql, params).await })
-                    .map_err(|e| RusticxError::QueryError(e.to_string()))?;
-                Ok(result)
-            }
+    /// Returns `RusticxError::ValidationError` if the replica is a different
+    /// kind of database, or a `RusticxError::ConnectionError` if it cannot be
+    /// opened.
+    pub fn with_replica(mut self, url: &str) -> Result<Self, RusticxError> {
+        // Checked before connecting, so a mismatched URL is never dialled
+        let db_type = DatabaseType::from_url(url)?;
+        if db_type != self.db_type {
+            return Err(RusticxError::ValidationError(format!(
+                "A {:?} replica cannot serve a {:?} primary",
+                db_type, self.db_type
+            )));
+        }
 
-            #[cfg(feature = "mysql")]
-            ConnectionPool::MySQL(pool) => {
-                let mut conn = pool
-                    .get_conn()
-                    .map_err(|e| RusticxError::QueryError(e.to_string()))?;
-                // MySQL's `exec_drop` does not reliably return rows affected, returning 1 is a common workaround
-                conn.exec_drop(sql, ())
-                    .map_err(|e| RusticxError::QueryError(e.to_string()))?;
-                Ok(1) // Indicate at least one operation was attempted
+        let replica = match (&self.mysql_tls, &self.runtime) {
+            (Some(tls), _) => Connection::new_with_tls(url, tls.clone())?,
+            (None, Some(runtime)) if url.starts_with("postgresql://") => {
+                Connection::new_with_runtime(url, runtime.clone())?
             }
+            _ => Connection::new(url)?,
+        };
+        let replica = if self.session.is_empty() { replica } else { replica.with_session(Arc::clone(&self.session))? };
 
-            #[cfg(feature = "rusqlite")]
-            ConnectionPool::SQLite(conn) => {
-                let conn_guard = conn.lock().map_err(|e| {
-                    RusticxError::ConnectionError(format!("Failed to acquire lock on SQLite connection: {}", e))
-                })?;
-                let result = conn_guard
-                    .execute(sql, []) // rusqlite requires params as a slice of ToSql, converting &[&dyn ToSql] to &[&dyn ToSql] is complex. Assuming no params for simplicity in this example or adjust signature.
-                    .map_err(|e| RusticxError::QueryError(e.to_string()))?;
-                Ok(result as u64)
-            }
+        let mut connections = self.replicas.as_ref().map(|r| r.connections.clone()).unwrap_or_default();
+        let name = format!("{}/replica-{}", self.stats.name(), connections.len() + 1);
+        connections.push(replica.with_pool_name(&name));
+        self.replicas = Some(Arc::new(Replicas { connections, next: AtomicUsize::new(0) }));
+        Ok(self)
+    }
+
+    /// Returns a handle that runs everything, reads included, on the primary.
+    pub fn primary(&self) -> Connection {
+        Connection { replicas: None, ..self.clone() }
+    }
+
+    /// The replica to run `sql` on, if it is a plain read and replicas exist.
+    fn reader(&self, sql: &str) -> Option<Connection> {
+        if is_read_only(sql) { self.next_replica() } else { None }
+    }
+
+    /// The next replica in turn, if replicas exist.
+    fn next_replica(&self) -> Option<Connection> {
+        let replicas = self.replicas.as_ref()?;
+        let turn = replicas.next.fetch_add(1, Ordering::Relaxed);
+        let replica = replicas.connections.get(turn % replicas.connections.len())?;
+        Some(Connection {
+            timeout: self.timeout,
+            interceptors: Arc::clone(&self.interceptors),
+            cancellation: self.cancellation.clone(),
+            ..replica.clone()
+        })
+    }
+
+    /// Returns a handle whose statements are each cancelled if they run
+    /// longer than `timeout`, failing with `RusticxError::Timeout`.
+    ///
+    /// ```ignore
+    /// let report = Order::find_all(&conn.with_timeout(Duration::from_secs(2)))?;
+    /// ```
+    ///
+    /// PostgreSQL statements are cancelled on the server. MySQL `SELECT`s get
+    /// a `MAX_EXECUTION_TIME` hint; other MySQL statements are not limited.
+    /// SQLite statements are interrupted between steps. For a default on
+    /// every statement of a session, see `ConnectionOptions::statement_timeout`.
+    pub fn with_timeout(&self, timeout: Duration) -> Connection {
+        Connection { timeout: Some(timeout), ..self.clone() }
+    }
+
+    /// Returns a handle whose statements are cancelled when `token` is,
+    /// failing with `RusticxError::Cancelled`; see `CancellationToken`.
+    ///
+    /// PostgreSQL statements are cancelled on the server, MySQL statements
+    /// with `KILL QUERY` sent over a separate connection, and SQLite
+    /// statements are interrupted. Statements run inside `transaction` and
+    /// `copy_in`/`copy_out` transfers are not cancelled.
+    pub fn with_cancellation(&self, token: &CancellationToken) -> Connection {
+        Connection { cancellation: Some(token.clone()), ..self.clone() }
+    }
+
+    /// Runs a statement future of the PostgreSQL client, cancelling it on
+    /// the server once the handle's timeout passes or its cancellation
+    /// token is cancelled.
+    #[cfg(feature = "postgres")]
+    fn run_postgres<T>(
+        &self,
+        rt: &Handle,
+        client: &tokio_postgres::Client,
+        statement: impl std::future::Future<Output = Result<T, tokio_postgres::Error>>,
+    ) -> Result<T, RusticxError> {
+        if self.timeout.is_none() && self.cancellation.is_none() {
+            return block_on(rt, statement).map_err(RusticxError::from);
+        }
+        let cancelled = Arc::new(tokio::sync::Notify::new());
+        let _cancel = self.cancellation.as_ref().map(|token| {
+            let cancelled = Arc::clone(&cancelled);
+            token.register(move || cancelled.notify_one())
+        });
+        let cancel = client.cancel_token();
+        block_on(rt, async move {
+            let mut statement = std::pin::pin!(statement);
+            let deadline = async {
+                match self.timeout {
+                    Some(timeout) => tokio::time::sleep(timeout).await,
+                    None => std::future::pending().await,
+                }
+            };
+            let error = tokio::select! {
+                result = &mut statement => return result.map_err(RusticxError::from),
+                _ = deadline => RusticxError::Timeout(format!(
+                    "The statement did not finish within {:?}",
+                    self.timeout.unwrap_or_default()
+                )),
+                _ = cancelled.notified() => RusticxError::Cancelled("The statement was cancelled".to_string()),
+            };
+            // Wait for the cancelled statement to end, so the cancel request
+            // cannot hit the next statement on this session. A request that
+            // overtakes the statement is ignored by the server, so it is sent
+            // again until the statement ends.
+            loop {
+                let _ = cancel.cancel_query(tokio_postgres::NoTls).await;
+                let resend = tokio::time::sleep(std::time::Duration::from_millis(50));
+                tokio::select! {
+                    _ = &mut statement => return Err(error),
+                    _ = resend => {}
+                }
+            }
+        })
+    }
+
+    /// Interrupts the statement running on a locked SQLite connection if
+    /// the handle's cancellation token is cancelled, until the returned
+    /// guard is dropped.
+    #[cfg(feature = "rusqlite")]
+    fn sqlite_cancel(&self, conn: &rusqlite::Connection) -> Option<CancelGuard> {
+        let token = self.cancellation.as_ref()?;
+        let interrupt = conn.get_interrupt_handle();
+        Some(token.register(move || interrupt.interrupt()))
+    }
+
+    /// Kills the statement running on a pooled MySQL connection if the
+    /// handle's cancellation token is cancelled, until the returned guard is
+    /// dropped.
+    ///
+    /// `KILL QUERY` is sent on a connection of its own rather than one from
+    /// the pool, which may be exhausted (a transaction's pool holds one).
+    #[cfg(feature = "mysql")]
+    fn mysql_cancel(&self, opts: &Arc<mysql::Opts>, conn: &mysql::PooledConn) -> Option<CancelGuard> {
+        let token = self.cancellation.as_ref()?;
+        let (opts, id) = (Arc::clone(opts), conn.connection_id());
+        Some(token.register(move || {
+            // The running statement blocks its own connection
+            match mysql::Conn::new((*opts).clone()) {
+                Ok(mut killer) => {
+                    if let Err(e) = killer.query_drop(format!("KILL QUERY {}", id)) {
+                        log::warn!("Failed to cancel MySQL statement on connection {}: {}", id, e);
+                    }
+                }
+                Err(e) => log::warn!("Failed to cancel MySQL statement on connection {}: {}", id, e),
+            }
+        }))
+    }
+
+    /// Adds a `MAX_EXECUTION_TIME` hint for the handle's timeout to a MySQL
+    /// `SELECT`.
+    #[cfg(feature = "mysql")]
+    fn mysql_sql<'s>(&self, sql: &'s str) -> std::borrow::Cow<'s, str> {
+        let trimmed = sql.trim_start();
+        match self.timeout {
+            Some(timeout) if trimmed.get(..6).is_some_and(|k| k.eq_ignore_ascii_case("SELECT")) => {
+                format!("SELECT /*+ MAX_EXECUTION_TIME({}) */{}", timeout.as_millis(), &trimmed[6..]).into()
+            }
+            _ => sql.into(),
+        }
+    }
+
+    /// Interrupts statements on a locked SQLite connection once the handle's
+    /// timeout passes or its cancellation token is cancelled, until the
+    /// returned guard is dropped.
+    ///
+    /// SQLite forgets an interrupt from `sqlite_cancel` that arrives before
+    /// the statement starts, so the token is checked here as well.
+    #[cfg(feature = "rusqlite")]
+    fn sqlite_deadline<'c>(&self, conn: &'c rusqlite::Connection) -> Option<SqliteDeadline<'c>> {
+        if self.timeout.is_none() && self.cancellation.is_none() {
+            return None;
+        }
+        let deadline = self.timeout.map(|timeout| std::time::Instant::now() + timeout);
+        let token = self.cancellation.clone();
+        conn.progress_handler(
+            1000,
+            Some(move || {
+                deadline.is_some_and(|deadline| std::time::Instant::now() > deadline)
+                    || token.as_ref().is_some_and(|token| token.is_cancelled())
+            }),
+        );
+        Some(SqliteDeadline(conn))
+    }
+
+    /// Names this connection's pool in `PoolMetrics` (`"default"` otherwise).
+    ///
+    /// Partitions are reported as `<name>/<partition>`, so call this before
+    /// `with_partition`.
+    pub fn with_pool_name(mut self, name: &str) -> Self {
+        let stats = PoolStats::new(name);
+        stats.set_size(self.stats.size());
+        self.stats = Arc::new(stats);
+        self
+    }
+
+    /// Applies session settings, such as the time zone or search path, to
+    /// every session this connection opens, so they all start out alike.
+    ///
+    /// ```ignore
+    /// let conn = Connection::new(url)?.set_session(&[("timezone", "UTC"), ("search_path", "app")])?;
+    /// ```
+    ///
+    /// On PostgreSQL each setting is applied with `set_config`, so any
+    /// run-time parameter can be set, custom ones such as `app.tenant`
+    /// included. On MySQL `names` becomes `SET NAMES`, `timezone` sets
+    /// `time_zone`, `search_path` selects the database with `USE`, and any
+    /// other setting is a `SET SESSION` variable; the pool runs them on every
+    /// connection it opens. On SQLite each setting is a `PRAGMA`.
+    ///
+    /// The current sessions are replaced by new ones with the settings
+    /// applied, which also replace those of an earlier call. Partitions and
+    /// replicas added afterwards use them as well.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::ValidationError` for an invalid setting name, a
+    /// setting the database does not have, or if partitions or replicas were
+    /// already added, and `RusticxError::ConnectionError` if the server
+    /// rejects a setting.
+    pub fn set_session(self, settings: &[(&str, &str)]) -> Result<Self, RusticxError> {
+        if !self.partitions.is_empty() || self.replicas.is_some() {
+            return Err(RusticxError::ValidationError(
+                "set_session must be called before with_partition and with_replica".to_string(),
+            ));
+        }
+        let session = session_settings(&self.db_type, settings)?;
+        self.with_session(Arc::new(session))
+    }
+
+    /// Reopens the connection with the `session` settings applied.
+    fn with_session(mut self, session: Arc<Vec<(String, String)>>) -> Result<Self, RusticxError> {
+        self.session = session;
+        self.connect()
+    }
+
+    /// Calls `hook` after every statement this connection and its clones
+    /// run, with its SQL, duration and outcome.
+    ///
+    /// ```ignore
+    /// let conn = conn.on_query(|q| {
+    ///     if q.duration > Duration::from_millis(500) {
+    ///         log::warn!("slow query on {} ({:?}): {}", q.pool, q.duration, q.sql);
+    ///     }
+    /// });
+    /// ```
+    ///
+    /// Hooks run synchronously on the calling thread, in the order added.
+    /// Statements run inside `transaction` are not reported.
+    pub fn on_query(mut self, hook: impl Fn(&QueryInfo) + Send + Sync + 'static) -> Self {
+        Arc::make_mut(&mut self.interceptors).add_hook(hook);
+        self
+    }
+
+    /// Wraps every statement this connection and its clones run in
+    /// `interceptor`, which is given the SQL and must call `next` to run it.
+    ///
+    /// An interceptor can pass `next` different SQL to rewrite the
+    /// statement, or return an error instead of calling it to reject the
+    /// statement:
+    ///
+    /// ```ignore
+    /// let conn = conn.around_query(|sql, next| {
+    ///     if sql.trim_start().to_ascii_uppercase().starts_with("DELETE") && !sql.contains("tenant_id") {
+    ///         return Err(RusticxError::ValidationError("DELETE without a tenant filter".to_string()));
+    ///     }
+    ///     next(&format!("/* app=checkout */ {}", sql))
+    /// });
+    /// ```
+    ///
+    /// `next` returns the statement's outcome (rows are passed on to the
+    /// caller directly) and may only be called once. An error the
+    /// interceptor returns replaces the statement's. Interceptors added
+    /// first run outermost. Statements prepared with `QueryBuilder::prepare`
+    /// cannot be rewritten, and statements inside `transaction` are not
+    /// intercepted.
+    pub fn around_query(
+        mut self,
+        interceptor: impl Fn(&str, &mut Next<'_>) -> Result<(), RusticxError> + Send + Sync + 'static,
+    ) -> Self {
+        Arc::make_mut(&mut self.interceptors).add_around(interceptor);
+        self
+    }
+
+    /// Limits how many statements this connection and its clones run at once.
+    ///
+    /// Statements beyond `max_concurrent` queue in arrival order and start
+    /// first come, first served. One that cannot start within `max_wait` fails
+    /// with `RusticxError::Busy` without running, so a burst of writers (which
+    /// SQLite serializes anyway) backs off instead of contending for the
+    /// database lock. `max_concurrent` is at least 1.
+    ///
+    /// Handles from `partition` are not limited; their partition size already
+    /// bounds them.
+    pub fn with_concurrency_limit(mut self, max_concurrent: usize, max_wait: std::time::Duration) -> Self {
+        self.limiter = Some(Arc::new(Limiter::new(max_concurrent, max_wait)));
+        self
+    }
+
+    /// Sets the conversions applied when decoding rows into models, so legacy
+    /// columns stored with a different type than the model declares can still
+    /// be read. See `Coercions`.
+    ///
+    /// Clones and partition handles share the registry.
+    pub fn with_coercions(mut self, coercions: Coercions) -> Self {
+        self.coercions = Some(Arc::new(coercions));
+        self
+    }
+
+    /// The conversions set with `with_coercions`, if any.
+    pub(crate) fn coercions(&self) -> Option<&Coercions> {
+        self.coercions.as_deref()
+    }
+
+    /// Returns the current gauges of the pool this handle runs statements
+    /// on: the main connection's, or its partition's for a handle from
+    /// `partition`.
+    ///
+    /// ```ignore
+    /// let metrics = conn.metrics();
+    /// if metrics.waiting > 0 && metrics.idle == 0 {
+    ///     log::warn!("pool {} exhausted ({} connections)", metrics.pool, metrics.size);
+    /// }
+    /// ```
+    pub fn metrics(&self) -> PoolMetrics {
+        self.lease.as_ref().map_or(&*self.stats, |lease| lease.stats()).snapshot()
+    }
+
+    /// Returns the current gauges of the main connection followed by those of
+    /// each partition, ordered by partition name, and of each replica.
+    ///
+    /// The same snapshots are pushed to the hook installed with
+    /// `rusticx::metrics::set_hook` whenever they change.
+    pub fn pool_metrics(&self) -> Vec<PoolMetrics> {
+        let mut partitions: Vec<_> = self.partitions.iter().collect();
+        partitions.sort_by_key(|(name, _)| name.as_str());
+
+        std::iter::once(self.stats.snapshot())
+            .chain(partitions.into_iter().map(|(_, partition)| partition.stats().snapshot()))
+            .chain(self.replicas.iter().flat_map(|r| r.connections.iter().map(|replica| replica.stats.snapshot())))
+            .collect()
+    }
+
+    /// Checks that the database answers.
+    ///
+    /// A PostgreSQL session that was closed (the server restarted, the
+    /// network dropped it, or it was terminated) is replaced by this, as it
+    /// is by the next statement, so callers recover without reopening the
+    /// `Connection`; a partition connection is also replaced when it is
+    /// checked out. Settings made with a plain `SET` are gone with the old
+    /// session, while those from `set_session` are applied to the new one.
+    /// A session that closed during a transaction is not replaced until the
+    /// transaction is ended with `ROLLBACK` (or `COMMIT`, which fails), since
+    /// statements after the lost `BEGIN` would autocommit on a new session;
+    /// until then they fail with `RusticxError::ConnectionError`. Statements
+    /// prepared with `QueryBuilder::prepare` must be prepared again. MySQL's
+    /// pool replaces broken connections (for example after `wait_timeout`) on
+    /// checkout.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::ConnectionError` if the database cannot be
+    /// reached, or a `RusticxError::QueryError` if it does not answer.
+    pub fn ping(&self) -> Result<(), RusticxError> {
+        match &self.pool {
+            #[cfg(feature = "postgres")]
+            ConnectionPool::PostgreSQL(client, rt, statements) => {
+                let (client_guard, _in_flight) = self.track(|| self.reopen_postgres(client, rt, statements))?;
+                block_on(rt, async { client_guard.simple_query("SELECT 1").await }).map_err(RusticxError::from)?;
+                Ok(())
+            }
+
+            #[cfg(feature = "mysql")]
+            ConnectionPool::MySQL(pool, opts) => {
+                let (mut conn, _in_flight) = self
+                    .track(|| pool.get_conn())
+                    .map_err(|e| RusticxError::ConnectionError(e.to_string()))?;
+                let _cancel = self.mysql_cancel(opts, &conn);
+                conn.query_drop("SELECT 1").map_err(|e| RusticxError::QueryError(e.to_string()))
+            }
+
+            #[cfg(feature = "rusqlite")]
+            ConnectionPool::SQLite(conn) => {
+                let (conn_guard, _in_flight) = self.track(|| conn.lock()).map_err(|e| {
+                    RusticxError::ConnectionError(format!("Failed to acquire lock on SQLite connection: {}", e))
+                })?;
+                conn_guard
+                    .query_row("SELECT 1", [], |_| Ok(()))
+                    .map_err(|e| RusticxError::QueryError(e.to_string()))
+            }
+
+            ConnectionPool::None => {
+                Err(RusticxError::ConnectionError(
+                    "No active database connection pool initialized".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Closes the connection: its sessions, those of its partitions and
+    /// replicas, and any SQLite file handles.
+    ///
+    /// Dropping a `Connection` releases the same resources silently; `close`
+    /// reports SQLite errors and whether other handles still keep the
+    /// connection open. PostgreSQL sessions end in the background on the
+    /// runtime they ran on.
+    ///
+    /// ```ignore
+    /// let conn = Connection::new("sqlite://data/app.db")?;
+    /// // ...
+    /// conn.close()?;
+    /// std::fs::remove_file("data/app.db")?;
+    /// ```
+    ///
+    /// Closing a handle from `Connection::partition` returns its connection
+    /// to the partition.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::ConnectionError` if other handles — clones
+    /// or partition checkouts — still use the connection;
+    /// it is then closed once the last of them is dropped. Also returns one
+    /// if SQLite fails to close the database.
+    pub fn close(self) -> Result<(), RusticxError> {
+        if self.lease.is_some() {
+            return Ok(());
+        }
+        let others = self.pool.is_shared()
+            || self.partitions.values().any(|p| Arc::strong_count(p) > 1)
+            || self.replicas.as_ref().is_some_and(|r| Arc::strong_count(r) > 1);
+        if others {
+            return Err(RusticxError::ConnectionError(format!(
+                "Connection '{}' is still in use by other handles; it closes when the last one is dropped",
+                self.stats.name()
+            )));
+        }
+
+        let mut result = self.pool.close();
+        for partition in self.partitions.into_values() {
+            if let Ok(partition) = Arc::try_unwrap(partition) {
+                for slot in partition.into_slots() {
+                    result = result.and(slot.close());
+                }
+            }
+        }
+        if let Some(Ok(replicas)) = self.replicas.map(Arc::try_unwrap) {
+            for replica in replicas.connections {
+                result = result.and(replica.close());
+            }
+        }
+        result
+    }
+
+    /// Locks the PostgreSQL client to run `sql`, first opening a new session
+    /// if the old one has closed (see `reopen_postgres`).
+    ///
+    /// A session that closed during a transaction is not replaced: the server
+    /// rolled the transaction back, and statements after the lost `BEGIN`
+    /// would autocommit on a new session. They fail until the transaction is
+    /// ended with `ROLLBACK`.
+    #[cfg(feature = "postgres")]
+    fn lock_postgres<'p>(
+        &self,
+        client: &'p Mutex<tokio_postgres::Client>,
+        rt: &Handle,
+        statements: &StatementCache,
+        sql: &str,
+    ) -> Result<std::sync::MutexGuard<'p, tokio_postgres::Client>, RusticxError> {
+        let client_guard = self.reopen_postgres(client, rt, statements);
+        // Ending a transaction the server already rolled back is still noted,
+        // but a statement that never reached a server is not
+        if client_guard.is_ok() || statements.in_transaction() {
+            statements.track_transaction(sql);
+        }
+        client_guard
+    }
+
+    /// Locks the PostgreSQL client, first opening a new session in place of
+    /// the old one (whose cached statements are gone with it) if it has
+    /// closed and no transaction was open on it.
+    #[cfg(feature = "postgres")]
+    fn reopen_postgres<'p>(
+        &self,
+        client: &'p Mutex<tokio_postgres::Client>,
+        rt: &Handle,
+        statements: &StatementCache,
+    ) -> Result<std::sync::MutexGuard<'p, tokio_postgres::Client>, RusticxError> {
+        let mut client_guard = client.lock().map_err(|e| {
+            RusticxError::TransactionError(format!("Failed to acquire lock on connection: {}", e))
+        })?;
+        if client_guard.is_closed() {
+            if statements.in_transaction() {
+                return Err(RusticxError::ConnectionError(
+                    "The PostgreSQL session closed during a transaction; it is not reopened until the transaction is ended with ROLLBACK"
+                        .to_string(),
+                ));
+            }
+            log::warn!("PostgreSQL session closed; reconnecting");
+            statements.reset();
+            *client_guard = open_postgres(&self.url, rt, &self.session)?;
+        }
+        Ok(client_guard)
+    }
+
+    /// Runs `sql` on the session with `run`, preparing it through the
+    /// session's statement cache.
+    ///
+    /// A cached statement whose plan went stale because another session
+    /// changed the schema fails with "cached plan must not change result
+    /// type" (SQLSTATE 0A000); the cache is then cleared and the statement
+    /// prepared and run once more. Inside a transaction the failure has
+    /// already aborted it, so the error is returned instead.
+    #[cfg(feature = "postgres")]
+    fn run_cached<T, F, Fut>(
+        &self,
+        rt: &Handle,
+        client: &tokio_postgres::Client,
+        statements: &StatementCache,
+        sql: &str,
+        run: F,
+    ) -> Result<T, RusticxError>
+    where
+        F: Fn(tokio_postgres::Statement) -> Fut,
+        Fut: std::future::Future<Output = Result<T, tokio_postgres::Error>>,
+    {
+        if !statements.admit(sql) {
+            return self.run_postgres(rt, client, async { run(client.prepare(sql).await?).await });
+        }
+        let cached = statements.get(sql);
+        let retry = cached.is_some() && !statements.in_transaction();
+        self.run_postgres(rt, client, async {
+            let statement = match cached {
+                Some(statement) => statement,
+                None => {
+                    let statement = client.prepare(sql).await?;
+                    statements.insert(sql, &statement);
+                    statement
+                }
+            };
+            match run(statement).await {
+                Err(e) if e.code() == Some(&tokio_postgres::error::SqlState::FEATURE_NOT_SUPPORTED) => {
+                    statements.clear();
+                    if !retry {
+                        return Err(e);
+                    }
+                    log::debug!("Cached statement went stale; preparing it again");
+                    let statement = client.prepare(sql).await?;
+                    statements.insert(sql, &statement);
+                    run(statement).await
+                }
+                result => result,
+            }
+        })
+    }
+
+    /// Waits for a turn under the concurrency limit, if one is set.
+    fn admit(&self) -> Result<Option<Permit<'_>>, RusticxError> {
+        self.limiter.as_ref().map(|limiter| limiter.acquire()).transpose()
+    }
+
+    /// Runs `acquire` (locking the client or checking out a pooled
+    /// connection) while updating the main connection's gauges.
+    ///
+    /// Partition handles already hold their connection through a lease, which
+    /// the partition's own gauges account for, so they are not tracked here.
+    fn track<G, E>(&self, acquire: impl FnOnce() -> Result<G, E>) -> Result<(G, Option<InFlight<'_>>), E> {
+        if self.lease.is_some() {
+            return acquire().map(|guard| (guard, None));
+        }
+        self.stats.acquire(acquire).map(|(guard, in_flight)| (guard, Some(in_flight)))
+    }
+
+    /// Runs `statement` with `sql` through the `around_query` interceptors,
+    /// counts it and whether it failed in the gauges of the pool it runs on
+    /// (the partition's for a partition handle), and reports it to the
+    /// `on_query` hooks.
+    fn observe<T>(&self, sql: &str, statement: impl FnOnce(&str) -> Result<T, RusticxError>) -> Result<T, RusticxError> {
+        let started = std::time::Instant::now();
+        let cancelled = || self.cancellation.as_ref().is_some_and(|token| token.is_cancelled());
+        let (result, rewritten) = if cancelled() {
+            (Err(RusticxError::Cancelled("The statement was cancelled before it started".to_string())), None)
+        } else {
+            self.interceptors.run(sql, statement)
+        };
+        // MySQL and SQLite report a cancelled statement as interrupted
+        let result = match result {
+            Err(RusticxError::QueryError(_) | RusticxError::Timeout(_)) if cancelled() => {
+                Err(RusticxError::Cancelled("The statement was cancelled".to_string()))
+            }
+            result => result,
+        };
+        let stats = self.lease.as_ref().map_or(&*self.stats, |lease| lease.stats());
+        stats.record_statement(result.is_err());
+        self.interceptors.notify(&QueryInfo {
+            sql: rewritten.as_deref().unwrap_or(sql),
+            pool: stats.name(),
+            duration: started.elapsed(),
+            error: result.as_ref().err(),
+        });
+        result
+    }
+
+    /// Creates a table in the database based on the provided SQL model definition.
+    ///
+    /// This function uses the `SQLModel` trait to generate the appropriate
+    /// `CREATE TABLE` SQL statement for the current database type and
+    /// executes it, followed by the model's `#[model(index)]` indexes.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T`: The type representing the SQL model, which must implement `SQLModel`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` on successful table creation, or a `RusticxError`
+    /// if the SQL generation or execution fails.
+    pub fn create_table<T: SQLModel>(&self) -> Result<(), RusticxError> {
+        // The table name is not directly used here, but could be for logging or validation
+        let _table_name = T::table_name();
+        let sql = T::create_table_sql(&self.db_type);
+        self.execute(&sql, &[])?;
+        for index in T::create_index_sql(&self.db_type) {
+            self.execute(&index, &[])?;
+        }
+        Ok(())
+    }
+
+    /// Executes a SQL command (INSERT, UPDATE, DELETE, CREATE, DROP, etc.)
+    /// with the provided parameters.
+    ///
+    /// This function is typically used for commands that do not return a result set.
+    /// The number of affected rows (where applicable) is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `sql`: The SQL query string to execute.
+    /// * `params`: A slice of references to values to be used as query parameters.
+    ///   The specific type required depends on the database driver
+    ///   (e.g., `&(dyn ToSql + Sync + 'static)` for postgres).
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the number of rows affected on success,
+    /// or a `RusticxError` if the execution fails. Note that the meaning
+    /// and availability of "rows affected" can vary between database drivers.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::QueryError` on database query execution failure
+    /// or `RusticxError::ConnectionError` if the connection pool is not initialized.
+    pub fn execute(
+        &self,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync + 'static)],
+    ) -> Result<u64, RusticxError> {
+        self.observe(sql, |sql| match &self.pool {
+            #[cfg(feature = "postgres")]
+            ConnectionPool::PostgreSQL(client, rt, statements) => {
+                let _permit = self.admit()?;
+                let (client_guard, _in_flight) = self.track(|| self.lock_postgres(client, rt, statements, sql))?;
+                
+                let client = &*client_guard;
+                self.run_cached(rt, client, statements, sql, |statement| async move {
+                    client.execute(&statement, params).await
+                })
+            }
+
+            #[cfg(feature = "mysql")]
+            ConnectionPool::MySQL(pool, opts) => {
+                let _permit = self.admit()?;
+                let (mut conn, _in_flight) = self
+                    .track(|| pool.get_conn())
+                    .map_err(|e| RusticxError::QueryError(e.to_string()))?;
+                let _cancel = self.mysql_cancel(opts, &conn);
+                let sql = self.mysql_sql(sql);
+                conn.exec_drop(sql.as_ref(), crate::transaction_manager::mysql_params(params)?)
+                    .map_err(|e| RusticxError::QueryError(e.to_string()))?;
+                Ok(conn.affected_rows())
+            }
+
+            #[cfg(feature = "rusqlite")]
+            ConnectionPool::SQLite(conn) => {
+                let _permit = self.admit()?;
+                let (conn_guard, _in_flight) = self.track(|| conn.lock()).map_err(|e| {
+                    RusticxError::ConnectionError(format!("Failed to acquire lock on SQLite connection: {}", e))
+                })?;
+                let _deadline = self.sqlite_deadline(&conn_guard);
+                let _cancel = self.sqlite_cancel(&conn_guard);
+                let values = crate::transaction_manager::sqlite_params(params)?;
+                let result = conn_guard
+                    .prepare_cached(sql)
+                    .and_then(|mut stmt| stmt.execute(rusqlite::params_from_iter(values)))
+                    .map_err(sqlite_error)?;
+                Ok(result as u64)
+            }
 
             ConnectionPool::None => {
                 Err(RusticxError::ConnectionError(
@@ -275,6 +1644,123 @@ impl Connection {
             _ => Err(RusticxError::ConnectionError(
                 "Unsupported database type for execute operation".to_string(),
             )),
+        })
+    }
+
+    /// Runs the `INSERT` in `sql` and returns the id the database generated
+    /// for its `pk` column.
+    ///
+    /// The id is read on the session that ran the insert: through `RETURNING`
+    /// on PostgreSQL and SQLite, and from the statement's own result on
+    /// MySQL. A separate `lastval()` or `LAST_INSERT_ID()` query could run on
+    /// a replica, another pooled connection, or after another clone's insert.
+    pub(crate) fn insert_returning_id(
+        &self,
+        sql: &str,
+        pk: &str,
+        params: &[&(dyn ToSql + Sync + 'static)],
+    ) -> Result<i64, RusticxError> {
+        let missing = || RusticxError::QueryError("Failed to retrieve last inserted ID".to_string());
+        match &self.pool {
+            #[cfg(feature = "mysql")]
+            ConnectionPool::MySQL(pool, opts) => self.observe(sql, |sql| {
+                let _permit = self.admit()?;
+                let (mut conn, _in_flight) = self
+                    .track(|| pool.get_conn())
+                    .map_err(|e| RusticxError::QueryError(e.to_string()))?;
+                let _cancel = self.mysql_cancel(opts, &conn);
+                conn.exec_drop(sql, crate::transaction_manager::mysql_params(params)?)
+                    .map_err(|e| RusticxError::QueryError(e.to_string()))?;
+                i64::try_from(conn.last_insert_id()).ok().filter(|id| *id > 0).ok_or_else(missing)
+            }),
+            _ => {
+                let sql = format!("{} RETURNING {}", sql, self.db_type.quote_identifier(pk));
+                self.primary()
+                    .query_positional(&sql, params)?
+                    .first()
+                    .and_then(|row| row.first())
+                    .and_then(|id| id.as_i64())
+                    .ok_or_else(missing)
+            }
+        }
+    }
+
+    /// Runs a script of `;`-separated statements, such as a schema or seed
+    /// file, returning the number of statements run.
+    ///
+    /// ```ignore
+    /// conn.execute_batch(&std::fs::read_to_string("db/seed.sql")?)?;
+    /// ```
+    ///
+    /// Statements run in order on one session through the driver's text
+    /// protocol, so they take no parameters and any rows they return are
+    /// discarded. Semicolons in literals, comments, dollar-quoted bodies and
+    /// the `BEGIN ... END` body of a trigger or routine do not split a
+    /// statement; MySQL's client-side `DELIMITER` is not supported. The
+    /// script is not wrapped in a transaction: statements before a failing
+    /// one stay applied unless it has its own `BEGIN` and `COMMIT`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::QueryError` naming the failing statement's
+    /// number, line and text, or `RusticxError::ConnectionError` if the
+    /// connection pool is not initialized.
+    pub fn execute_batch(&self, script: &str) -> Result<usize, RusticxError> {
+        self.observe(script, |script| self.run_script(script))
+    }
+
+    /// Runs the statements of `script` in turn; see `execute_batch`.
+    fn run_script(&self, script: &str) -> Result<usize, RusticxError> {
+        let statements = crate::script::split_statements(script, &self.db_type);
+        match &self.pool {
+            #[cfg(feature = "postgres")]
+            ConnectionPool::PostgreSQL(client, rt, cache) => {
+                let _permit = self.admit()?;
+                let (client_guard, _in_flight) = self.track(|| self.lock_postgres(client, rt, cache, script))?;
+                // Schema changes can leave cached statements with stale result types
+                cache.clear();
+                for statement in &statements {
+                    self.run_postgres(rt, &client_guard, client_guard.batch_execute(statement.sql))
+                        .map_err(|e| statement.error(e))?;
+                }
+                Ok(statements.len())
+            }
+
+            #[cfg(feature = "mysql")]
+            ConnectionPool::MySQL(pool, opts) => {
+                let _permit = self.admit()?;
+                let (mut conn, _in_flight) = self
+                    .track(|| pool.get_conn())
+                    .map_err(|e| RusticxError::ConnectionError(e.to_string()))?;
+                let _cancel = self.mysql_cancel(opts, &conn);
+                for statement in &statements {
+                    conn.query_drop(self.mysql_sql(statement.sql).as_ref())
+                        .map_err(|e| statement.error(RusticxError::from(e)))?;
+                }
+                Ok(statements.len())
+            }
+
+            #[cfg(feature = "rusqlite")]
+            ConnectionPool::SQLite(conn) => {
+                let _permit = self.admit()?;
+                let (conn_guard, _in_flight) = self.track(|| conn.lock()).map_err(|e| {
+                    RusticxError::ConnectionError(format!("Failed to acquire lock on SQLite connection: {}", e))
+                })?;
+                for statement in &statements {
+                    let _deadline = self.sqlite_deadline(&conn_guard);
+                    let _cancel = self.sqlite_cancel(&conn_guard);
+                    conn_guard
+                        .execute_batch(statement.sql)
+                        .map_err(|e| statement.error(sqlite_error(e)))?;
+                }
+                Ok(statements.len())
+            }
+
+            ConnectionPool::None => {
+                Err(RusticxError::ConnectionError(
+                    "No active database connection pool initialized".to_string(),
+                ))
+            }
         }
     }
 
@@ -287,13 +1773,13 @@ impl Connection {
     /// # Type Parameters
     ///
     /// * `T`: The target type to deserialize the rows into. Must implement
-    ///        `serde::Deserialize<'de>` and `Debug`.
+    ///   `serde::Deserialize<'de>` and `Debug`.
     ///
     /// # Arguments
     ///
     /// * `sql`: The SQL query string (e.g., "SELECT id, name FROM users WHERE age > $1").
     /// * `params`: A slice of references to values to be used as query parameters.
-    ///             The specific type required depends on the database driver.
+    ///   The specific type required depends on the database driver.
     ///
     /// # Returns
     ///
@@ -310,67 +1796,60 @@ impl Connection {
     where
         T: for<'de> serde::Deserialize<'de> + Debug,
     {
-        match &self.pool {
+        if let Some(replica) = self.reader(sql) {
+            return replica.query_raw(sql, params);
+        }
+        self.observe(sql, |sql| match &self.pool {
             #[cfg(feature = "postgres")]
-            ConnectionPool::PostgreSQL(client, rt) => {
-                let client_guard = client.lock().map_err(|e| {
-                    RusticxError::TransactionError(format!("Failed to acquire lock on connection: {}", e))
+            ConnectionPool::PostgreSQL(client, rt, statements) => {
+                let _permit = self.admit()?;
+                let (client_guard, _in_flight) = self.track(|| self.lock_postgres(client, rt, statements, sql))?;
+                let client = &*client_guard;
+                let rows = self.run_cached(rt, client, statements, sql, |statement| async move {
+                    client.query(&statement, params).await
                 })?;
-                let rows = rt
-                    .block_on(async { client_guard.query(sql, params).await })
-                    .map_err(|e| RusticxError::QueryError(e.to_string()))?;
 
-                let mut models = Vec::with_capacity(rows.len());
-                for row in rows {
-                    let mut json_obj = serde_json::Map::new();
-                    for column in row.columns() {
-                        let name = column.name();
-                        // Assuming a helper function exists to convert pg row value to JSON
-                        let value = crate::transaction_manager::pg_row_value_to_json(&row, column)
-                            .unwrap_or(serde_json::Value::Null); // Use Null for unconvertible values
-                        json_obj.insert(name.to_string(), value);
-                    }
-                    let model = serde_json::from_value(serde_json::Value::Object(json_obj))
-                        .map_err(|e| RusticxError::SerializationError(e.to_string()))?;
-                    models.push(model);
-                }
-                Ok(models)
+                crate::transaction_manager::pg_rows_to_models(rows)
             }
 
             #[cfg(feature = "mysql")]
-            ConnectionPool::MySQL(pool) => {
-                let mut conn = pool
-                    .get_conn()
+            ConnectionPool::MySQL(pool, opts) => {
+                let _permit = self.admit()?;
+                let (mut conn, _in_flight) = self
+                    .track(|| pool.get_conn())
                     .map_err(|e| RusticxError::QueryError(e.to_string()))?;
+                let _cancel = self.mysql_cancel(opts, &conn);
+                let sql = self.mysql_sql(sql);
 
-                // Use query_map to iterate over results and convert
-                let rows: Vec<Result<T, mysql::Error>> = conn
-                    .query_map(sql, |row: mysql::Row| {
-                        let mut json_obj = serde_json::Map::new();
-                        let columns = row.columns_ref();
-
-                        for (i, column) in columns.iter().enumerate() {
-                            let name = column.name_str().to_string();
-                            // Assuming a helper function exists to convert mysql row value to JSON
-                            let value = crate::transaction_manager::mysql_row_value_to_json(
-                                &row,
-                                i,
-                                column.column_type(),
-                            )
-                            .unwrap_or(serde_json::Value::Null);
-                            json_obj.insert(name, value);
-                        }
+                let decode = |row: mysql::Row| {
+                    let mut json_obj = serde_json::Map::new();
+                    let columns = row.columns_ref();
 
-                        // Deserialize the JSON object into the target struct T
-                        serde_json::from_value(serde_json::Value::Object(json_obj)).map_err(|e| {
-                            // Convert serde_json error to a mysql error for compatibility with query_map
-                            mysql::Error::from(std::io::Error::new(
-                                std::io::ErrorKind::Other,
-                                e.to_string(),
-                            ))
-                        })
+                    for (i, column) in columns.iter().enumerate() {
+                        let name = column.name_str().to_string();
+                        // Assuming a helper function exists to convert mysql row value to JSON
+                        let value = crate::transaction_manager::mysql_row_value_to_json(
+                            &row,
+                            i,
+                            column.column_type(),
+                        )
+                        .unwrap_or(serde_json::Value::Null);
+                        json_obj.insert(name, value);
+                    }
+
+                    // Deserialize the JSON object into the target struct T
+                    serde_json::from_value(serde_json::Value::Object(json_obj)).map_err(|e| {
+                        // Convert serde_json error to a mysql error for compatibility with query_map
+                        mysql::Error::from(std::io::Error::other(e.to_string()))
                     })
-                    .map_err(|e| RusticxError::QueryError(e.to_string()))?;
+                };
+                // Statements without parameters keep the text protocol, which can run any statement
+                let rows: Vec<Result<T, mysql::Error>> = if params.is_empty() {
+                    conn.query_map(sql.as_ref(), decode)
+                } else {
+                    conn.exec_map(sql.as_ref(), crate::transaction_manager::mysql_params(params)?, decode)
+                }
+                .map_err(|e| RusticxError::QueryError(e.to_string()))?;
 
                 // Collect the results, converting the vector of Results into a single Result<Vec<T>>
                 let result: Vec<T> = rows
@@ -383,13 +1862,16 @@ impl Connection {
 
             #[cfg(feature = "rusqlite")]
             ConnectionPool::SQLite(conn) => {
-                let conn_guard = conn.lock().map_err(|e| {
+                let _permit = self.admit()?;
+                let (conn_guard, _in_flight) = self.track(|| conn.lock()).map_err(|e| {
                     RusticxError::ConnectionError(format!("Failed to acquire lock on SQLite connection: {}", e))
                 })?;
+                let _deadline = self.sqlite_deadline(&conn_guard);
+                let _cancel = self.sqlite_cancel(&conn_guard);
 
                 let mut stmt = conn_guard
-                    .prepare(sql)
-                    .map_err(|e| RusticxError::QueryError(e.to_string()))?;
+                    .prepare_cached(sql)
+                    .map_err(sqlite_error)?;
 
                 let column_names: Vec<String> = stmt
                     .column_names()
@@ -397,48 +1879,379 @@ impl Connection {
                     .map(|name| name.to_string())
                     .collect();
 
+                let values = crate::transaction_manager::sqlite_params(params)?;
                 let models = stmt
-                    .query_map([], |row| {
+                    .query_map(rusqlite::params_from_iter(values), |row| {
                         // Map each row to a JSON object
                         let mut json_obj = serde_json::Map::new();
                         for (i, name) in column_names.iter().enumerate() {
-                            // Assuming a helper function exists to convert sqlite row value to JSON
                             let value = crate::transaction_manager::sqlite_row_value_to_json(row, i)
                                 .unwrap_or(serde_json::Value::Null);
                             json_obj.insert(name.clone(), value);
                         }
                         // Deserialize the JSON object into the target struct T
-                        serde_json::from_value(serde_json::Value::Object(json_obj)).map_err(
+                        let model: T = serde_json::from_value(serde_json::Value::Object(json_obj)).map_err(
                             |e| {
                                 // Convert serde_json error to a rusqlite error
                                 rusqlite::Error::FromSqlConversionFailure(
-                                    i, // Column index where error occurred
-                                    rusqlite::types::Type::Text, // Assuming Text type for conversion
+                                    0,
+                                    rusqlite::types::Type::Text,
                                     Box::new(e),
                                 )
                             },
                         )?;
                         Ok(model)
                     })
-                    .map_err(|e| RusticxError::QueryError(e.to_string()))?
+                    .map_err(sqlite_error)?
                     .collect::<Result<Vec<_>, _>>()
-                    .map_err(|e| RusticxError::QueryError(e.to_string()))?; // Collect results and handle potential errors
+                    .map_err(sqlite_error)?; // Collect results and handle potential errors
+
+                Ok(models)
+            }
+
+            ConnectionPool::None => {
+                Err(RusticxError::ConnectionError(
+                    "No active database connection pool initialized".to_string(),
+                ))
+            }
+
+            // Fallback for unsupported or disabled database types
+            #[allow(unreachable_patterns)]
+            _ => Err(RusticxError::ConnectionError(
+                "Unsupported database type for query operation".to_string(),
+            )),
+        })
+    }
+
+    /// Executes a raw SQL query and returns each row as its column values, in
+    /// select-list order.
+    ///
+    /// This is the positional counterpart of `query_raw`: no per-row map keyed
+    /// by column name is built, which makes it cheaper for wide or numerous
+    /// rows and tolerant of duplicate column names. It backs
+    /// `RowMapping::ByPosition`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::QueryError` on database query execution failure
+    /// or `RusticxError::ConnectionError` if the connection pool is not initialized.
+    pub fn query_positional(&self, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<Vec<Vec<serde_json::Value>>, RusticxError> {
+        if let Some(replica) = self.reader(sql) {
+            return replica.query_positional(sql, params);
+        }
+        self.observe(sql, |sql| match &self.pool {
+            #[cfg(feature = "postgres")]
+            ConnectionPool::PostgreSQL(client, rt, statements) => {
+                let _permit = self.admit()?;
+                let (client_guard, _in_flight) = self.track(|| self.lock_postgres(client, rt, statements, sql))?;
+                let client = &*client_guard;
+                let rows = self.run_cached(rt, client, statements, sql, |statement| async move {
+                    client.query(&statement, params).await
+                })?;
+
+                Ok(rows.iter().map(crate::transaction_manager::pg_row_to_values).collect())
+            }
+
+            #[cfg(feature = "mysql")]
+            ConnectionPool::MySQL(pool, opts) => {
+                let _permit = self.admit()?;
+                let (mut conn, _in_flight) = self
+                    .track(|| pool.get_conn())
+                    .map_err(|e| RusticxError::QueryError(e.to_string()))?;
+                let _cancel = self.mysql_cancel(opts, &conn);
+                let sql = self.mysql_sql(sql);
+
+                let decode = |row: mysql::Row| {
+                    row.columns_ref()
+                        .iter()
+                        .enumerate()
+                        .map(|(i, column)| {
+                            crate::transaction_manager::mysql_row_value_to_json(&row, i, column.column_type())
+                                .unwrap_or(serde_json::Value::Null)
+                        })
+                        .collect::<Vec<_>>()
+                };
+                if params.is_empty() {
+                    conn.query_map(sql.as_ref(), decode)
+                } else {
+                    conn.exec_map(sql.as_ref(), crate::transaction_manager::mysql_params(params)?, decode)
+                }
+                .map_err(|e| RusticxError::QueryError(e.to_string()))
+            }
+
+            #[cfg(feature = "rusqlite")]
+            ConnectionPool::SQLite(conn) => {
+                let _permit = self.admit()?;
+                let (conn_guard, _in_flight) = self.track(|| conn.lock()).map_err(|e| {
+                    RusticxError::ConnectionError(format!("Failed to acquire lock on SQLite connection: {}", e))
+                })?;
+                let _deadline = self.sqlite_deadline(&conn_guard);
+                let _cancel = self.sqlite_cancel(&conn_guard);
+
+                let mut stmt = conn_guard
+                    .prepare_cached(sql)
+                    .map_err(sqlite_error)?;
+                let column_count = stmt.column_count();
+
+                let values = crate::transaction_manager::sqlite_params(params)?;
+                let rows = stmt
+                    .query_map(rusqlite::params_from_iter(values), |row| {
+                        (0..column_count)
+                            .map(|i| crate::transaction_manager::sqlite_row_value_to_json(row, i))
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .map_err(sqlite_error)?
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(sqlite_error)?;
+
+                Ok(rows)
+            }
+
+            ConnectionPool::None => {
+                Err(RusticxError::ConnectionError(
+                    "No active database connection pool initialized".to_string(),
+                ))
+            }
+        })
+    }
+
+    /// Executes a raw SQL query and returns an iterator that deserializes rows
+    /// into `T` as they are read.
+    ///
+    /// Unlike `query_raw`, the result set is never collected into a `Vec`, so
+    /// very large tables can be exported without holding every row in memory.
+    /// See `RowIter` for the restrictions that apply while the stream is open.
+    ///
+    /// # Arguments
+    ///
+    /// * `sql`: The SQL query string.
+    /// * `params`: A slice of references to values to be used as query parameters.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing a `RowIter<T>` whose items are
+    /// `Result<T, RusticxError>`, one per row.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::QueryError` if the query cannot be started.
+    /// Errors that occur while reading individual rows are yielded by the
+    /// iterator.
+    pub fn query_stream<T>(&self, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<RowIter<T>, RusticxError>
+    where
+        T: for<'de> serde::Deserialize<'de> + Debug,
+    {
+        if let Some(replica) = self.reader(sql) {
+            return replica.query_stream(sql, params);
+        }
+        self.observe(sql, |sql| match &self.pool {
+            #[cfg(feature = "postgres")]
+            ConnectionPool::PostgreSQL(client, rt, statements) => {
+                let _permit = self.admit()?;
+                let (client_guard, _in_flight) = self.track(|| self.lock_postgres(client, rt, statements, sql))?;
+                let client = &*client_guard;
+                let rows = self.run_cached(rt, client, statements, sql, |statement| async move {
+                    client.query_raw(&statement, params.iter().map(|p| *p as &(dyn ToSql + Sync))).await
+                })?;
+
+                Ok(RowIter::postgres(rows, rt.clone()))
+            }
+
+            #[cfg(feature = "mysql")]
+            ConnectionPool::MySQL(pool, _) => {
+                let _permit = self.admit()?;
+                let (conn, _in_flight) = self
+                    .track(|| pool.get_conn())
+                    .map_err(|e| RusticxError::QueryError(e.to_string()))?;
+                let params = crate::transaction_manager::mysql_params(params)?;
+                RowIter::mysql(conn, self.mysql_sql(sql).into_owned(), params)
+            }
+
+            #[cfg(feature = "rusqlite")]
+            ConnectionPool::SQLite(conn) => {
+                let _permit = self.admit()?;
+                let params = crate::transaction_manager::sqlite_params(params)?;
+                RowIter::sqlite(Arc::clone(conn), sql.to_string(), params)
+            }
+
+            ConnectionPool::None => {
+                Err(RusticxError::ConnectionError(
+                    "No active database connection pool initialized".to_string(),
+                ))
+            }
+        })
+    }
+
+    /// Prepares `sql` once for repeated execution with `query_prepared`.
+    ///
+    /// On PostgreSQL the statement is parsed and planned by the server now;
+    /// MySQL and SQLite keep the SQL text and run it as usual.
+    pub(crate) fn prepare(&self, sql: &str) -> Result<Prepared, RusticxError> {
+        match &self.pool {
+            #[cfg(feature = "postgres")]
+            ConnectionPool::PostgreSQL(client, rt, statements) => {
+                let _permit = self.admit()?;
+                let (client_guard, _in_flight) = self.track(|| self.lock_postgres(client, rt, statements, sql))?;
+                let statement = block_on(rt, async { client_guard.prepare(sql).await })
+                    .map_err(RusticxError::from)?;
+                Ok(Prepared::Postgres(statement, sql.to_string()))
+            }
+
+            #[cfg(feature = "mysql")]
+            ConnectionPool::MySQL(..) => Ok(Prepared::Text(sql.to_string())),
+
+            #[cfg(feature = "rusqlite")]
+            ConnectionPool::SQLite(_) => Ok(Prepared::Text(sql.to_string())),
+
+            ConnectionPool::None => {
+                Err(RusticxError::ConnectionError(
+                    "No active database connection pool initialized".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Runs a statement from `prepare`, returning each row keyed by column.
+    pub(crate) fn query_prepared(
+        &self,
+        statement: &Prepared,
+        params: &[&(dyn ToSql + Sync + 'static)],
+    ) -> Result<Vec<serde_json::Map<String, serde_json::Value>>, RusticxError> {
+        match (statement, &self.pool) {
+            #[cfg(feature = "postgres")]
+            // The statement is already prepared, so interceptors cannot rewrite it
+            (Prepared::Postgres(statement, sql), ConnectionPool::PostgreSQL(client, rt, statements)) => self.observe(sql, |_| {
+                let _permit = self.admit()?;
+                let (client_guard, _in_flight) = self.track(|| self.lock_postgres(client, rt, statements, sql))?;
+                let rows = self.run_postgres(rt, &client_guard, client_guard.query(statement, params))?;
+
+                crate::transaction_manager::pg_rows_to_models(rows)
+            }),
+            (Prepared::Text(sql), _) => self.query_raw(sql, params),
+            #[allow(unreachable_patterns)]
+            _ => Err(RusticxError::QueryError(
+                "The statement was prepared on a different kind of connection".to_string(),
+            )),
+        }
+    }
 
-                Ok(models)
+    /// Deletes the rows of `T` whose `#[model(expires_at)]` time has passed.
+    ///
+    /// Rows are removed in batches of 1000 so a large backlog of expired
+    /// sessions or tokens does not hold locks on the table for long. Returns
+    /// the total number of rows deleted.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::ValidationError` if `T` has no expiry column, or
+    /// a `RusticxError::QueryError` if a delete fails.
+    pub fn purge_expired<T: SQLModel>(&self) -> Result<u64, RusticxError> {
+        const BATCH_SIZE: i64 = 1000;
+
+        let expired = expired_condition::<T>(&self.db_type).ok_or_else(|| {
+            RusticxError::ValidationError(format!(
+                "Cannot purge {}: no field is marked #[model(expires_at)]",
+                T::table_name()
+            ))
+        })?;
+        let table = self.db_type.quote_identifier(&self.table_path(&T::table_name()));
+        let sql = match self.db_type {
+            // MySQL cannot select from the table being deleted from, but supports DELETE ... LIMIT
+            DatabaseType::MySQL => format!("DELETE FROM {} WHERE {} LIMIT {}", table, expired, BATCH_SIZE),
+            _ => {
+                let pk = self.db_type.quote_identifier(&T::primary_key_field());
+                format!(
+                    "DELETE FROM {table} WHERE {pk} IN (SELECT {pk} FROM {table} WHERE {expired} LIMIT {BATCH_SIZE})"
+                )
             }
+        };
 
-            ConnectionPool::None => {
-                Err(RusticxError::ConnectionError(
-                    "No active database connection pool initialized".to_string(),
-                ))
+        let mut purged = 0;
+        loop {
+            let deleted = self.execute(&sql, &[])?;
+            purged += deleted;
+            if deleted < BATCH_SIZE as u64 {
+                return Ok(purged);
             }
+        }
+    }
 
-            // Fallback for unsupported or disabled database types
-            #[allow(unreachable_patterns)]
-            _ => Err(RusticxError::ConnectionError(
-                "Unsupported database type for query operation".to_string(),
-            )),
+    /// Returns the schema version recorded by `MigrationManager::migrate_up`,
+    /// or `None` if no version has been stamped.
+    pub fn schema_version(&self) -> Result<Option<i64>, RusticxError> {
+        crate::migrations::read_schema_version(self)
+    }
+
+    /// Checks that the database's recorded schema version equals `expected`.
+    ///
+    /// Call this at startup so an application instance refuses to run against
+    /// a database whose schema is ahead of or behind its code, for example
+    /// during a rolling deploy.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::SchemaVersionMismatch` if no version is recorded
+    /// or the recorded version differs from `expected`.
+    pub fn assert_schema_version(&self, expected: i64) -> Result<(), RusticxError> {
+        match self.schema_version()? {
+            Some(found) if found == expected => Ok(()),
+            Some(found) => Err(RusticxError::SchemaVersionMismatch(format!(
+                "database schema is at version {} but the application expects version {} (database is {} the code)",
+                found,
+                expected,
+                if found > expected { "ahead of" } else { "behind" }
+            ))),
+            None => Err(RusticxError::SchemaVersionMismatch(format!(
+                "no schema version is recorded in the database; the application expects version {}",
+                expected
+            ))),
+        }
+    }
+
+    /// Executes a write statement with a `RETURNING` clause and deserializes
+    /// every returned row into `T`.
+    ///
+    /// This is the common building block for multi-row writes that need the
+    /// affected rows back (bulk inserts, updates or deletes returning rows), so
+    /// they share the same row handling as `query_raw`.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `T`: The target type to deserialize each returned row into.
+    ///
+    /// # Arguments
+    ///
+    /// * `sql`: An `INSERT`, `UPDATE` or `DELETE` statement ending in `RETURNING ...`.
+    /// * `params`: A slice of references to values to be used as query parameters.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing one `T` per returned row.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::QueryError` if the statement has no `RETURNING`
+    /// clause (outside string literals and comments), if the server does not support `RETURNING` (MySQL, MariaDB
+    /// before 10.5, SQLite before 3.35), or if the
+    /// execution or deserialization fails.
+    pub fn execute_returning<T>(&self, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<Vec<T>, RusticxError>
+    where
+        T: for<'de> serde::Deserialize<'de> + Debug,
+    {
+        if !crate::script::contains_keyword(sql, &self.db_type, "RETURNING") {
+            return Err(RusticxError::QueryError(
+                "execute_returning requires a statement with a RETURNING clause".to_string(),
+            ));
+        }
+
+        // Servers that support it return rows from writes just like a SELECT
+        if !self.capabilities()?.returning {
+            return Err(RusticxError::QueryError(format!(
+                "{} does not support RETURNING clauses",
+                self.server_version()?.text
+            )));
         }
+        self.query_raw(sql, params)
     }
 
     /// Executes a database transaction using the provided transaction function.
@@ -447,19 +2260,21 @@ impl Connection {
     /// and executes the code defined in the `transaction_fn` closure within
     /// the transaction's scope. The closure receives a `TransactionExecutor`
     /// which allows performing database operations within the transaction.
+    /// The closure is synchronous; to run model methods inside a transaction
+    /// use `begin`, and for async code `transaction_async`.
     ///
     /// # Type Parameters
     ///
     /// * `F`: The type of the closure that defines the transaction logic. Must
-    ///        implement `FnOnce(&dyn TransactionExecutor) -> Result<R, RusticxError>`,
-    ///        `Send`, and `'static`.
+    ///   implement `FnOnce(&mut dyn TransactionExecutor) -> Result<R, RusticxError>`,
+    ///   `Send`, and `'static`.
     /// * `R`: The return type of the transaction function. Must implement `Send`
-    ///        and `'static`.
+    ///   and `'static`.
     ///
     /// # Arguments
     ///
     /// * `transaction_fn`: The closure containing the database operations to be
-    ///                   executed within the transaction.
+    ///   executed within the transaction.
     ///
     /// # Returns
     ///
@@ -473,26 +2288,51 @@ impl Connection {
     /// or `RusticxError::ConnectionError` if the connection pool is not initialized.
     pub async fn transaction<F, R>(&self, transaction_fn: F) -> Result<R, RusticxError>
     where
-        F: FnOnce(&dyn TransactionExecutor) -> Result<R, RusticxError> + Send + 'static,
+        F: FnOnce(&mut dyn TransactionExecutor) -> Result<R, RusticxError> + Send + 'static,
         R: Send + 'static,
     {
-        match &self.pool {
+        self.transaction_with(TxOptions::default(), transaction_fn).await
+    }
+
+    /// Executes a database transaction like `transaction`, started with
+    /// `options`.
+    ///
+    /// ```ignore
+    /// let options = TxOptions { isolation: Some(IsolationLevel::Serializable), ..TxOptions::default() };
+    /// conn.transaction_with(options, move |tx| {
+    ///     tx.execute("UPDATE accounts SET balance = balance - $1 WHERE id = $2", &[&amount, &from])?;
+    ///     tx.execute("UPDATE accounts SET balance = balance + $1 WHERE id = $2", &[&amount, &to])
+    /// }).await?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::TransactionError` on transaction management failures,
+    /// or `RusticxError::ConnectionError` if the connection pool is not initialized.
+    pub async fn transaction_with<F, R>(&self, options: TxOptions, transaction_fn: F) -> Result<R, RusticxError>
+    where
+        F: FnOnce(&mut dyn TransactionExecutor) -> Result<R, RusticxError> + Send + 'static,
+        R: Send + 'static,
+    {
+        // Read-only transactions take turns on the replicas, like plain reads
+        let replica = if options.read_only { self.next_replica() } else { None };
+        match &replica.as_ref().unwrap_or(self).pool {
             #[cfg(feature = "postgres")]
-            ConnectionPool::PostgreSQL(client, _) => {
+            ConnectionPool::PostgreSQL(client, rt, _) => {
                 // Delegate to the PostgreSQL specific transaction runner
-                run_postgres_transaction(&client.clone(), transaction_fn).await
+                run_postgres_transaction(client, rt, &options, transaction_fn).await
             }
 
             #[cfg(feature = "mysql")]
-            ConnectionPool::MySQL(pool) => {
+            ConnectionPool::MySQL(pool, _) => {
                 // Delegate to the MySQL specific transaction runner
-                run_mysql_transaction(&pool.clone(), transaction_fn)
+                run_mysql_transaction(&pool.clone(), &options, transaction_fn)
             }
 
             #[cfg(feature = "rusqlite")]
             ConnectionPool::SQLite(conn) => {
                 // Delegate to the SQLite specific transaction runner
-                run_sqlite_transaction(&conn.clone(), transaction_fn)
+                run_sqlite_transaction(&conn.clone(), &options, transaction_fn)
             }
 
             ConnectionPool::None => {
@@ -509,6 +2349,616 @@ impl Connection {
         }
     }
 
+    /// Executes a transaction like `transaction_with`, running it again when
+    /// the database aborts it to serialize it with a concurrent one.
+    ///
+    /// ```ignore
+    /// let options = TxOptions { isolation: Some(IsolationLevel::Serializable), ..TxOptions::default() };
+    /// conn.transaction_with_retries(options, 5, move |tx| {
+    ///     tx.execute("UPDATE accounts SET balance = balance - $1 WHERE id = $2", &[&amount, &from])?;
+    ///     tx.execute("UPDATE accounts SET balance = balance + $1 WHERE id = $2", &[&amount, &to])
+    /// }).await?;
+    /// ```
+    ///
+    /// A transaction that fails with `RusticxError::SerializationFailure` (a
+    /// PostgreSQL serialization failure or deadlock, or a MySQL deadlock) has been
+    /// rolled back, and is run again up to `retries` times after a backoff
+    /// that starts at 10ms and doubles with each retry, up to a second. The
+    /// closure runs once per attempt, so it must not have effects outside
+    /// the transaction.
+    ///
+    /// # Errors
+    ///
+    /// Returns the last `RusticxError::SerializationFailure` once the retries
+    /// are used up, or any other error as `transaction_with` does, without
+    /// retrying; that includes an optimistic-locking `RusticxError::Conflict`,
+    /// which needs the record reloaded rather than the transaction rerun.
+    pub async fn transaction_with_retries<F, R>(
+        &self,
+        options: TxOptions,
+        retries: u32,
+        transaction_fn: F,
+    ) -> Result<R, RusticxError>
+    where
+        F: Fn(&mut dyn TransactionExecutor) -> Result<R, RusticxError> + Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        let transaction_fn = Arc::new(transaction_fn);
+        let mut attempt = 0;
+        loop {
+            let attempt_fn = Arc::clone(&transaction_fn);
+            match self.transaction_with(options.clone(), move |tx| attempt_fn(tx)).await {
+                Err(RusticxError::SerializationFailure(message)) if attempt < retries => {
+                    attempt += 1;
+                    let backoff = Duration::from_millis(10 << (attempt - 1).min(7)).min(Duration::from_secs(1));
+                    log::warn!("Transaction conflicted ({}); retry {} of {} in {:?}", message, attempt, retries, backoff);
+                    // Sleep on the library's runtime, so any executor can await the backoff
+                    let rt = match &self.runtime {
+                        Some(handle) => handle.clone(),
+                        None => shared_runtime()?,
+                    };
+                    let _ = rt.spawn(async move { tokio::time::sleep(backoff).await }).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Runs an async closure in a transaction, committing it if the closure's
+    /// future succeeds and rolling it back if it fails.
+    ///
+    /// The closure receives a handle whose statements run inside the
+    /// transaction, as from `begin`, and can await other work between them:
+    ///
+    /// ```ignore
+    /// let order = conn.transaction_async(|tx| async move {
+    ///     let quote = shipping.quote(&cart).await?;
+    ///     let mut order = Order::from_cart(&cart, quote);
+    ///     order.insert(&tx)?;
+    ///     Ok(order)
+    /// }).await?;
+    /// ```
+    ///
+    /// The handle's own methods block until their statement is done; on a
+    /// worker thread of a multi-threaded Tokio runtime they hand the
+    /// worker's other tasks to another thread while they wait, so they can be
+    /// called from tasks on a current-thread runtime only through
+    /// `spawn_blocking`. For options such as the isolation level, or to hold
+    /// a transaction across `.await`s without a closure, use `begin_with`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the closure's error once the transaction is rolled back, or a
+    /// `RusticxError::TransactionError` if it cannot be started or committed.
+    pub async fn transaction_async<F, Fut, R>(&self, transaction_fn: F) -> Result<R, RusticxError>
+    where
+        F: FnOnce(Connection) -> Fut,
+        Fut: std::future::Future<Output = Result<R, RusticxError>>,
+    {
+        let tx = self.begin()?;
+        match transaction_fn(Connection::clone(&tx)).await {
+            Ok(value) => {
+                tx.commit()?;
+                Ok(value)
+            }
+            Err(e) => {
+                if let Err(rollback_err) = tx.rollback() {
+                    log::error!("Error during transaction rollback: {}", rollback_err);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Begins a transaction and returns a handle to it.
+    ///
+    /// The `Transaction` derefs to a `Connection` whose statements all run
+    /// inside the transaction, so model methods and queries take part in it:
+    ///
+    /// ```ignore
+    /// let tx = conn.begin()?;
+    /// order.insert(&tx)?;
+    /// stock.update(&tx)?;
+    /// tx.commit()?;
+    /// ```
+    ///
+    /// A transaction dropped without `commit` is rolled back. PostgreSQL and
+    /// MySQL transactions run on a session of their own, so statements of
+    /// other handles stay out of them. These sessions are separate server
+    /// connections: one is opened when no idle one is left, up to the cap set
+    /// with `with_transaction_sessions`, and kept for later transactions. When
+    /// all are in use, `begin` waits for one to be released. A SQLite
+    /// connection is a single session: the transaction runs on it, and
+    /// statements other handles run on the connection before it ends are
+    /// part of it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::ConnectionError` if no session is released
+    /// in time or one cannot be opened, or a `RusticxError::TransactionError`
+    /// if the transaction cannot be started.
+    pub fn begin(&self) -> Result<Transaction, RusticxError> {
+        self.begin_with(TxOptions::default())
+    }
+
+    /// Begins a transaction like `begin`, started with `options`.
+    ///
+    /// ```ignore
+    /// let tx = conn.begin_with(TxOptions { isolation: Some(IsolationLevel::RepeatableRead), ..TxOptions::default() })?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::ConnectionError` if no session is released
+    /// in time or one cannot be opened, or a `RusticxError::TransactionError`
+    /// if the transaction cannot be started.
+    pub fn begin_with(&self, options: TxOptions) -> Result<Transaction, RusticxError> {
+        let replica = if options.read_only { self.next_replica() } else { None };
+        let (session, lease) = replica.as_ref().unwrap_or(self).transaction_session()?;
+        let deadline = options.deadline();
+        session
+            .execute_batch(&options.begin_sql(&self.db_type))
+            .map_err(|e| RusticxError::TransactionError(format!("Failed to start transaction: {}", e)))?;
+        Ok(Transaction::new(session, options.end_sql(&self.db_type), deadline, lease))
+    }
+
+    /// Begins a transaction that takes part in a two-phase commit under the
+    /// global identifier `gid`; see `DistributedTx`.
+    ///
+    /// ```ignore
+    /// let mut tx = conn.begin_distributed("transfer-42")?;
+    /// entry.insert(&tx)?;
+    /// tx.prepare()?;
+    /// broker.publish(&event)?;
+    /// tx.commit()?;
+    /// ```
+    ///
+    /// On PostgreSQL the transaction is prepared with `PREPARE TRANSACTION`,
+    /// which the server only allows when `max_prepared_transactions` is above
+    /// zero; on MySQL it is an XA transaction. SQLite has no prepared
+    /// transactions.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::ValidationError` if `gid` is not 1 to 64
+    /// printable ASCII characters without quotes or backslashes, a
+    /// `RusticxError::ConnectionError` if the session cannot be opened, or a
+    /// `RusticxError::TransactionError` on SQLite or if the transaction
+    /// cannot be started.
+    pub fn begin_distributed(&self, gid: &str) -> Result<DistributedTx, RusticxError> {
+        validate_gid(gid)?;
+        let begin_sql = match self.db_type {
+            DatabaseType::PostgreSQL => "BEGIN".to_string(),
+            DatabaseType::MySQL => format!("XA START '{}'", gid),
+            DatabaseType::SQLite => {
+                return Err(RusticxError::TransactionError(
+                    "Prepared transactions are not supported on SQLite".to_string(),
+                ))
+            }
+        };
+        // The session is given up rather than reused: once prepared, its
+        // transaction outlives the handle
+        let (session, _) = self.transaction_session()?;
+        session
+            .execute_batch(&begin_sql)
+            .map_err(|e| RusticxError::TransactionError(format!("Failed to start transaction: {}", e)))?;
+        Ok(DistributedTx::new(session, gid.to_string()))
+    }
+
+    /// Commits the prepared transaction `gid`, as a coordinator recovering
+    /// from a crash does for transactions every participant prepared.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::ValidationError` for an invalid `gid`, or a
+    /// `RusticxError::TransactionError` if no such transaction is prepared
+    /// or the commit fails.
+    pub fn commit_prepared(&self, gid: &str) -> Result<(), RusticxError> {
+        validate_gid(gid)?;
+        prepared_transaction(&self.primary(), "COMMIT", gid)
+    }
+
+    /// Rolls back the prepared transaction `gid`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::ValidationError` for an invalid `gid`, or a
+    /// `RusticxError::TransactionError` if no such transaction is prepared
+    /// or the rollback fails.
+    pub fn rollback_prepared(&self, gid: &str) -> Result<(), RusticxError> {
+        validate_gid(gid)?;
+        prepared_transaction(&self.primary(), "ROLLBACK", gid)
+    }
+
+    /// Lists the identifiers of the transactions prepared on the server and
+    /// not yet committed or rolled back (in this database, on PostgreSQL),
+    /// oldest first on PostgreSQL. SQLite has none.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError` if the query fails.
+    pub fn prepared_transactions(&self) -> Result<Vec<String>, RusticxError> {
+        #[derive(serde::Deserialize, Debug)]
+        struct PreparedRow {
+            // `XA RECOVER` reports the identifier as `data`
+            #[serde(alias = "data")]
+            gid: String,
+        }
+
+        let sql = match self.db_type {
+            DatabaseType::PostgreSQL => {
+                "SELECT gid FROM pg_prepared_xacts WHERE database = current_database() ORDER BY prepared"
+            }
+            DatabaseType::MySQL => "XA RECOVER",
+            DatabaseType::SQLite => return Ok(Vec::new()),
+        };
+        let rows: Vec<PreparedRow> = self.primary().query_raw(sql, &[])?;
+        Ok(rows.into_iter().map(|row| row.gid).collect())
+    }
+
+    /// A handle on the session a transaction from `begin` runs on, with the
+    /// lease returning the session for reuse: one of the connection's
+    /// transaction sessions, or on SQLite the connection itself.
+    fn transaction_session(&self) -> Result<(Connection, Option<SessionLease>), RusticxError> {
+        if let DatabaseType::SQLite = self.db_type {
+            return Ok((self.primary(), None));
+        }
+
+        // A pool of one connection, so every statement of the transaction runs
+        // on it, which keeps its session between statements: resetting it when
+        // a statement returns it would end the transaction
+        #[cfg(feature = "mysql")]
+        let url = match self.db_type {
+            DatabaseType::MySQL => {
+                let (url, _) = take_url_param(&self.url, "pool_min");
+                let (url, _) = take_url_param(&url, "pool_max");
+                let (url, _) = take_url_param(&url, "reset_connection");
+                format!(
+                    "{}{}pool_min=1&pool_max=1&reset_connection=false",
+                    url,
+                    if url.contains('?') { "&" } else { "?" }
+                )
+            }
+            _ => self.url.clone(),
+        };
+        #[cfg(not(feature = "mysql"))]
+        let url = self.url.clone();
+
+        let session = Connection {
+            url,
+            db_type: self.db_type.clone(),
+            pool: ConnectionPool::None,
+            partitions: HashMap::new(),
+            lease: None,
+            tx_sessions: Arc::new(TxSessions::default()),
+            stats: Arc::new(PoolStats::new(&format!("{}/transaction", self.stats.name()))),
+            limiter: self.limiter.clone(),
+            coercions: self.coercions.clone(),
+            mysql_tls: self.mysql_tls.clone(),
+            replicas: None,
+            timeout: self.timeout,
+            schema: self.schema.clone(),
+            runtime: self.runtime.clone(),
+            server_version: Arc::clone(&self.server_version),
+            session: Arc::clone(&self.session),
+            interceptors: Arc::clone(&self.interceptors),
+            cancellation: self.cancellation.clone(),
+        };
+        let (pool, lease) = self.tx_sessions.acquire(|| session.clone().connect().map(|conn| conn.pool))?;
+        Ok((Connection { pool, ..session }, Some(lease)))
+    }
+
+    /// Measures how far this connection's server lags behind its primary.
+    ///
+    /// On PostgreSQL this compares the replayed WAL position with the received
+    /// one and, when replay is behind, reports the age of the last replayed
+    /// transaction (`pg_last_xact_replay_timestamp`). On MySQL it reads
+    /// `Seconds_Behind_Master` from `SHOW SLAVE STATUS`. A server that is not a
+    /// replica (and SQLite, which has no replication) reports zero lag.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(Some(lag))` when the lag is known, or `Ok(None)` when the
+    /// replica cannot report it (e.g. replication is stopped or has not replayed
+    /// anything yet). Callers should treat `None` as "too stale".
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError` if the lag query fails.
+    pub fn replication_lag(&self) -> Result<Option<std::time::Duration>, RusticxError> {
+        match self.db_type {
+            DatabaseType::PostgreSQL => {
+                #[derive(serde::Deserialize, Debug)]
+                struct LagRow {
+                    lag_ms: Option<i64>,
+                }
+
+                let sql = "SELECT CASE \
+                        WHEN NOT pg_is_in_recovery() THEN 0 \
+                        WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0 \
+                        ELSE (EXTRACT(EPOCH FROM (now() - pg_last_xact_replay_timestamp())) * 1000)::bigint \
+                    END AS lag_ms";
+                let rows: Vec<LagRow> = self.query_raw(sql, &[])?;
+                Ok(rows
+                    .first()
+                    .and_then(|row| row.lag_ms)
+                    .map(|ms| std::time::Duration::from_millis(ms.max(0) as u64)))
+            }
+            DatabaseType::MySQL => {
+                let rows: Vec<serde_json::Map<String, serde_json::Value>> =
+                    self.query_raw("SHOW SLAVE STATUS", &[])?;
+                match rows.first() {
+                    // Not configured as a replica
+                    None => Ok(Some(std::time::Duration::ZERO)),
+                    Some(status) => Ok(status
+                        .get("Seconds_Behind_Master")
+                        .and_then(|v| v.as_u64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
+                        .map(std::time::Duration::from_secs)),
+                }
+            }
+            DatabaseType::SQLite => Ok(Some(std::time::Duration::ZERO)),
+        }
+    }
+
+    /// Copies the live SQLite database to the file at `path`.
+    ///
+    /// Uses SQLite's online backup API, so the copy is consistent even while
+    /// other statements run on this connection between steps. Equivalent to
+    /// `backup_to_with_progress` with a callback that ignores progress.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::QueryError` if the backup fails or the connection
+    /// is not a SQLite connection.
+    pub fn backup_to<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), RusticxError> {
+        self.backup_to_with_progress(path, |_| {})
+    }
+
+    /// Copies the live SQLite database to the file at `path`, calling
+    /// `progress` after every step.
+    ///
+    /// The database is copied 100 pages at a time; when a step is blocked by a
+    /// concurrent writer the backup pauses briefly and retries.
+    #[cfg_attr(not(feature = "rusqlite"), allow(unused_variables, unused_mut))]
+    pub fn backup_to_with_progress<P, F>(&self, path: P, mut progress: F) -> Result<(), RusticxError>
+    where
+        P: AsRef<std::path::Path>,
+        F: FnMut(BackupProgress),
+    {
+        match &self.pool {
+            #[cfg(feature = "rusqlite")]
+            ConnectionPool::SQLite(conn) => {
+                let _permit = self.admit()?;
+                let (src, _in_flight) = self.track(|| conn.lock()).map_err(|e| {
+                    RusticxError::ConnectionError(format!("Failed to acquire lock on SQLite connection: {}", e))
+                })?;
+                let mut dst = rusqlite::Connection::open(path)?;
+                let backup = rusqlite::backup::Backup::new(&src, &mut dst)?;
+                run_backup(&backup, &mut progress)
+            }
+            _ => Err(RusticxError::QueryError("backup_to is only supported for SQLite".to_string())),
+        }
+    }
+
+    /// Replaces the contents of the SQLite database with the database file at
+    /// `path`.
+    ///
+    /// Equivalent to `restore_from_with_progress` with a callback that ignores
+    /// progress.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::QueryError` if the restore fails or the
+    /// connection is not a SQLite connection.
+    pub fn restore_from<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), RusticxError> {
+        self.restore_from_with_progress(path, |_| {})
+    }
+
+    /// Replaces the contents of the SQLite database with the database file at
+    /// `path`, calling `progress` after every step.
+    #[cfg_attr(not(feature = "rusqlite"), allow(unused_variables, unused_mut))]
+    pub fn restore_from_with_progress<P, F>(&self, path: P, mut progress: F) -> Result<(), RusticxError>
+    where
+        P: AsRef<std::path::Path>,
+        F: FnMut(BackupProgress),
+    {
+        match &self.pool {
+            #[cfg(feature = "rusqlite")]
+            ConnectionPool::SQLite(conn) => {
+                let _permit = self.admit()?;
+                let (mut dst, _in_flight) = self.track(|| conn.lock()).map_err(|e| {
+                    RusticxError::ConnectionError(format!("Failed to acquire lock on SQLite connection: {}", e))
+                })?;
+                let src = rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+                let backup = rusqlite::backup::Backup::new(&src, &mut dst)?;
+                run_backup(&backup, &mut progress)
+            }
+            _ => Err(RusticxError::QueryError("restore_from is only supported for SQLite".to_string())),
+        }
+    }
+
+    /// Writes a compacted snapshot of the SQLite database to `path` with
+    /// `VACUUM INTO`.
+    ///
+    /// Unlike `backup_to` the snapshot is rebuilt rather than copied page by
+    /// page, so it is usually smaller. `path` must not already exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::QueryError` if the statement fails or the
+    /// connection is not a SQLite connection.
+    pub fn vacuum_into<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), RusticxError> {
+        if !matches!(self.db_type, DatabaseType::SQLite) {
+            return Err(RusticxError::QueryError("vacuum_into is only supported for SQLite".to_string()));
+        }
+        let path = path.as_ref().to_str().ok_or_else(|| {
+            RusticxError::ValidationError("vacuum_into requires a UTF-8 path".to_string())
+        })?;
+        // Inlined as a string literal: VACUUM INTO takes an expression and the
+        // path is not user-facing SQL, but quotes still need escaping
+        self.execute(&format!("VACUUM INTO '{}'", path.replace('\'', "''")), &[])?;
+        Ok(())
+    }
+
+    /// Bulk loads CSV rows read from `reader` into `columns` of `table` with
+    /// PostgreSQL's `COPY ... FROM STDIN`, much faster than inserting the
+    /// rows one by one.
+    ///
+    /// ```ignore
+    /// let file = std::fs::File::open("users.csv")?;
+    /// let loaded = conn.copy_in("users", &["name", "email"], file)?;
+    /// ```
+    ///
+    /// Rows are comma-separated without a header line; an unquoted empty
+    /// field is `NULL`. The load is all or nothing: if reading fails or a row
+    /// is rejected, no rows are added. The handle's timeout does not apply.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of rows loaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::QueryError` if the server rejects the data or
+    /// the connection is not a PostgreSQL connection, and
+    /// `RusticxError::DatabaseError` if reading from `reader` fails.
+    #[cfg_attr(not(feature = "postgres"), allow(unused_variables, unused_mut))]
+    pub fn copy_in<R: std::io::Read>(&self, table: &str, columns: &[&str], mut reader: R) -> Result<u64, RusticxError> {
+        match &self.pool {
+            #[cfg(feature = "postgres")]
+            ConnectionPool::PostgreSQL(client, rt, statements) => {
+                use futures_util::SinkExt;
+
+                let columns: Vec<String> = columns.iter().map(|c| self.db_type.quote_identifier(c)).collect();
+                let sql = format!(
+                    "COPY {} ({}) FROM STDIN WITH (FORMAT csv)",
+                    self.db_type.quote_identifier(&self.table_path(table)),
+                    columns.join(", ")
+                );
+
+                self.observe(&sql, |sql| {
+                    let _permit = self.admit()?;
+                    let (client_guard, _in_flight) = self.track(|| self.lock_postgres(client, rt, statements, sql))?;
+                    block_on(rt, async {
+                        let sink = client_guard.copy_in::<_, bytes::Bytes>(sql).await?;
+                        let mut sink = std::pin::pin!(sink);
+                        let mut buffer = vec![0u8; 64 * 1024];
+                        loop {
+                            let read = reader.read(&mut buffer).map_err(|e| {
+                                RusticxError::DatabaseError(format!("Failed to read COPY data: {}", e))
+                            })?;
+                            if read == 0 {
+                                break;
+                            }
+                            sink.send(bytes::Bytes::copy_from_slice(&buffer[..read])).await?;
+                        }
+                        // Dropping the sink before `finish` aborts the COPY
+                        Ok(sink.as_mut().finish().await?)
+                    })
+                })
+            }
+            _ => Err(RusticxError::QueryError("copy_in is only supported for PostgreSQL".to_string())),
+        }
+    }
+
+    /// Exports the rows of `query` as CSV to `writer` with PostgreSQL's
+    /// `COPY (...) TO STDOUT`, streaming them as the server sends them.
+    ///
+    /// ```ignore
+    /// let file = std::fs::File::create("active_users.csv")?;
+    /// conn.copy_out("SELECT id, email FROM users WHERE active", file)?;
+    /// ```
+    ///
+    /// Rows are written in the format `copy_in` reads: comma-separated,
+    /// without a header line, with `NULL` as an unquoted empty field.
+    /// `query` cannot take parameters. The handle's timeout does not apply.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::QueryError` if the query fails or the
+    /// connection is not a PostgreSQL connection, and
+    /// `RusticxError::DatabaseError` if writing to `writer` fails.
+    #[cfg_attr(not(feature = "postgres"), allow(unused_variables, unused_mut))]
+    pub fn copy_out<W: std::io::Write>(&self, query: &str, mut writer: W) -> Result<(), RusticxError> {
+        match &self.pool {
+            #[cfg(feature = "postgres")]
+            ConnectionPool::PostgreSQL(client, rt, statements) => {
+                use futures_util::StreamExt;
+
+                let sql = format!("COPY ({}) TO STDOUT WITH (FORMAT csv)", query.trim().trim_end_matches(';'));
+                self.observe(&sql, |sql| {
+                    let _permit = self.admit()?;
+                    let (client_guard, _in_flight) = self.track(|| self.lock_postgres(client, rt, statements, sql))?;
+                    block_on(rt, async {
+                        let stream = client_guard.copy_out(sql).await?;
+                        let mut stream = std::pin::pin!(stream);
+                        while let Some(chunk) = stream.next().await {
+                            writer.write_all(&chunk?).map_err(|e| {
+                                RusticxError::DatabaseError(format!("Failed to write COPY data: {}", e))
+                            })?;
+                        }
+                        writer
+                            .flush()
+                            .map_err(|e| RusticxError::DatabaseError(format!("Failed to write COPY data: {}", e)))
+                    })
+                })
+            }
+            _ => Err(RusticxError::QueryError("copy_out is only supported for PostgreSQL".to_string())),
+        }
+    }
+
+    /// The schema generated SQL qualifies table names with, set by
+    /// `ConnectionOptions::qualify_tables`.
+    pub fn schema(&self) -> Option<&str> {
+        self.schema.as_deref()
+    }
+
+    /// Qualifies generated SQL's table names with `schema`.
+    pub(crate) fn in_schema(mut self, schema: &str) -> Self {
+        self.schema = Some(schema.to_string());
+        self
+    }
+
+    /// `table` as generated SQL names it: prefixed with the connection's
+    /// schema when it qualifies table names.
+    pub(crate) fn table_path(&self, table: &str) -> String {
+        match &self.schema {
+            Some(schema) => format!("{}.{}", schema, table),
+            None => table.to_string(),
+        }
+    }
+
+    /// The version of the database server, read on first use.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::QueryError` if the server cannot be asked.
+    pub fn server_version(&self) -> Result<ServerVersion, RusticxError> {
+        if let Some(version) = self.server_version.get() {
+            return Ok(version.clone());
+        }
+        let sql = match self.db_type {
+            DatabaseType::PostgreSQL => "SHOW server_version",
+            DatabaseType::MySQL => "SELECT VERSION() AS version",
+            DatabaseType::SQLite => "SELECT sqlite_version() AS version",
+        };
+        let rows = self.primary().query_positional(sql, &[])?;
+        let text = rows.first().and_then(|row| row.first()).and_then(|value| value.as_str()).ok_or_else(|| {
+            RusticxError::QueryError("The server did not report its version".to_string())
+        })?;
+        Ok(self.server_version.get_or_init(|| ServerVersion::parse(text)).clone())
+    }
+
+    /// The SQL features the server supports, from its type and version, so
+    /// code can branch on them rather than on `DatabaseType` alone.
+    ///
+    /// # Errors
+    ///
+    /// Fails as `server_version` does.
+    pub fn capabilities(&self) -> Result<Capabilities, RusticxError> {
+        Ok(Capabilities::detect(&self.db_type, &self.server_version()?))
+    }
+
     /// Returns a reference to the database type of this connection.
     ///
     /// # Returns
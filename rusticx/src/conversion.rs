@@ -0,0 +1,137 @@
+use crate::error::RusticxError;
+
+/// The unit a `#[model(store_as = "...")]` field is persisted in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreUnit {
+    /// Whole seconds (`store_as = "seconds"`).
+    Seconds,
+    /// Milliseconds (`store_as = "millis"`).
+    Millis,
+    /// Microseconds (`store_as = "micros"`).
+    Micros,
+}
+
+/// Converts a typed Rust value to and from the integer stored in the database.
+///
+/// Fields marked `#[model(store_as = "seconds")]` (or `"millis"`, `"micros"`)
+/// are bound as `BIGINT` through `to_stored` and rebuilt in `from_row` through
+/// `from_stored`, so models can expose `Duration`s without converting by hand
+/// around every query. Implemented for `std::time::Duration` and
+/// `chrono::Duration`; implement it for other duration-like types to use them
+/// with `store_as`.
+pub trait StoreAs: Sized {
+    /// Returns the value expressed as a whole number of `unit`s.
+    fn to_stored(&self, unit: StoreUnit) -> i64;
+
+    /// Rebuilds the value from a stored number of `unit`s.
+    fn from_stored(value: i64, unit: StoreUnit) -> Result<Self, RusticxError>;
+}
+
+impl StoreAs for std::time::Duration {
+    fn to_stored(&self, unit: StoreUnit) -> i64 {
+        let value = match unit {
+            StoreUnit::Seconds => self.as_secs() as u128,
+            StoreUnit::Millis => self.as_millis(),
+            StoreUnit::Micros => self.as_micros(),
+        };
+        i64::try_from(value).unwrap_or(i64::MAX)
+    }
+
+    fn from_stored(value: i64, unit: StoreUnit) -> Result<Self, RusticxError> {
+        let value = u64::try_from(value).map_err(|_| {
+            RusticxError::DeserializationError(format!("Cannot store negative value {} in a std::time::Duration", value))
+        })?;
+        Ok(match unit {
+            StoreUnit::Seconds => std::time::Duration::from_secs(value),
+            StoreUnit::Millis => std::time::Duration::from_millis(value),
+            StoreUnit::Micros => std::time::Duration::from_micros(value),
+        })
+    }
+}
+
+impl StoreAs for chrono::Duration {
+    fn to_stored(&self, unit: StoreUnit) -> i64 {
+        match unit {
+            StoreUnit::Seconds => self.num_seconds(),
+            StoreUnit::Millis => self.num_milliseconds(),
+            StoreUnit::Micros => self.num_microseconds().unwrap_or(i64::MAX),
+        }
+    }
+
+    fn from_stored(value: i64, unit: StoreUnit) -> Result<Self, RusticxError> {
+        let duration = match unit {
+            StoreUnit::Seconds => chrono::Duration::try_seconds(value),
+            StoreUnit::Millis => chrono::Duration::try_milliseconds(value),
+            StoreUnit::Micros => Some(chrono::Duration::microseconds(value)),
+        };
+        duration.ok_or_else(|| {
+            RusticxError::DeserializationError(format!("Stored value {} is out of range for chrono::Duration", value))
+        })
+    }
+}
+
+/// The codec a `#[model(compressed = "...")]` field is stored with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Zstandard at its default level (`compressed = "zstd"`).
+    Zstd,
+}
+
+/// Compresses a value into the bytes stored in the database and back.
+///
+/// Fields marked `#[model(compressed = "zstd")]` are bound as `BYTEA`/`BLOB`
+/// through `compress` and rebuilt in `from_row` through `from_column`, which
+/// is meant for large log or body columns where storage cost matters.
+/// Implemented for `String` and `Vec<u8>`; requires the `compression` feature.
+#[cfg(feature = "compression")]
+pub trait Compress: Sized {
+    /// Returns the compressed bytes for the value, or a
+    /// `RusticxError::SerializationError` if the codec fails.
+    fn compress(&self, codec: Compression) -> Result<Vec<u8>, RusticxError>;
+
+    /// Rebuilds the value from its compressed bytes.
+    fn decompress(bytes: &[u8], codec: Compression) -> Result<Self, RusticxError>;
+
+    /// Rebuilds the value from a column as it appears in a decoded row, where
+    /// binary data is carried as a base64 string.
+    fn from_column(value: &serde_json::Value, codec: Compression) -> Result<Self, RusticxError> {
+        use base64::Engine;
+
+        let encoded = value.as_str().ok_or_else(|| {
+            RusticxError::DeserializationError(format!("Expected compressed binary data, got {}", value))
+        })?;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|e| RusticxError::DeserializationError(format!("Invalid compressed column data: {}", e)))?;
+        Self::decompress(&bytes, codec)
+    }
+}
+
+#[cfg(feature = "compression")]
+impl Compress for Vec<u8> {
+    fn compress(&self, codec: Compression) -> Result<Vec<u8>, RusticxError> {
+        match codec {
+            Compression::Zstd => zstd::bulk::compress(self, 0)
+                .map_err(|e| RusticxError::SerializationError(format!("Failed to compress zstd data: {}", e))),
+        }
+    }
+
+    fn decompress(bytes: &[u8], codec: Compression) -> Result<Self, RusticxError> {
+        match codec {
+            Compression::Zstd => zstd::stream::decode_all(bytes)
+                .map_err(|e| RusticxError::DeserializationError(format!("Failed to decompress zstd data: {}", e))),
+        }
+    }
+}
+
+#[cfg(feature = "compression")]
+impl Compress for String {
+    fn compress(&self, codec: Compression) -> Result<Vec<u8>, RusticxError> {
+        self.as_bytes().to_vec().compress(codec)
+    }
+
+    fn decompress(bytes: &[u8], codec: Compression) -> Result<Self, RusticxError> {
+        String::from_utf8(Vec::<u8>::decompress(bytes, codec)?)
+            .map_err(|e| RusticxError::DeserializationError(format!("Decompressed data is not valid UTF-8: {}", e)))
+    }
+}
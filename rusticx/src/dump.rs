@@ -0,0 +1,289 @@
+use std::io::{BufRead, Write};
+
+use crate::connection::{Connection, DatabaseType};
+use crate::error::RusticxError;
+use crate::model::SQLModel;
+use crate::sql_types::SqlType;
+
+/// Rows per `INSERT` statement in a dump.
+const DUMP_BATCH_SIZE: usize = 100;
+
+/// Writes every row of `T` as batched, backend-neutral `INSERT` statements.
+///
+/// Identifiers use ANSI double quotes, strings use doubled single quotes,
+/// binary columns are written as `X'..'` hex literals and booleans as
+/// `TRUE`/`FALSE`; `load` adapts these to the target backend.
+pub(crate) fn dump<T: SQLModel, W: Write>(conn: &Connection, mut writer: W) -> Result<u64, RusticxError> {
+    let db_type = conn.get_db_type();
+    let sql = format!(
+        "SELECT * FROM {} ORDER BY {}",
+        db_type.quote_identifier(&conn.table_path(&T::table_name())),
+        db_type.quote_identifier(&T::primary_key_field())
+    );
+    let columns = T::field_names();
+    let types = T::field_sql_types();
+    let prefix = insert_prefix::<T>();
+
+    let write_err = |e: std::io::Error| RusticxError::SerializationError(format!("Failed to write dump: {}", e));
+    writeln!(writer, "-- rusticx dump of {}", T::table_name()).map_err(write_err)?;
+
+    let mut dumped = 0;
+    let mut batch: Vec<String> = Vec::with_capacity(DUMP_BATCH_SIZE);
+    for row in conn.query_stream::<serde_json::Map<String, serde_json::Value>>(&sql, &[])? {
+        let row = row?;
+        let values: Vec<String> = columns.iter()
+            .zip(&types)
+            .map(|(column, sql_type)| render_value(row.get(*column).unwrap_or(&serde_json::Value::Null), sql_type))
+            .collect::<Result<_, _>>()?;
+        batch.push(format!("({})", values.join(", ")));
+        dumped += 1;
+
+        if batch.len() == DUMP_BATCH_SIZE {
+            writeln!(writer, "{}{};", prefix, batch.join(",\n")).map_err(write_err)?;
+            batch.clear();
+        }
+    }
+    if !batch.is_empty() {
+        writeln!(writer, "{}{};", prefix, batch.join(",\n")).map_err(write_err)?;
+    }
+    writer.flush().map_err(write_err)?;
+
+    Ok(dumped)
+}
+
+/// Executes the statements of a dump produced by `dump::<T>` against `conn`.
+///
+/// Only `INSERT`s into `T`'s table with `T`'s columns whose `VALUES` are
+/// plain literals are accepted, so a dump cannot smuggle in other statements,
+/// subqueries or function calls. On PostgreSQL the identity sequence is moved
+/// past the loaded ids afterwards.
+pub(crate) fn load<T: SQLModel, R: BufRead>(conn: &Connection, reader: R) -> Result<u64, RusticxError> {
+    let db_type = conn.get_db_type();
+    let prefix = insert_prefix::<T>();
+    let target_prefix = target_insert_prefix::<T>(conn);
+
+    let mut loaded = 0;
+    let mut statement = String::new();
+    for line in reader.lines() {
+        let line = line.map_err(|e| RusticxError::DeserializationError(format!("Failed to read dump: {}", e)))?;
+        if statement.is_empty() && (line.trim().is_empty() || line.starts_with("--")) {
+            continue;
+        }
+        if !statement.is_empty() {
+            statement.push('\n');
+        }
+        statement.push_str(&line);
+
+        // A statement ends at a `;` that is not inside a string literal
+        let in_literal = statement.matches('\'').count() % 2 == 1;
+        if in_literal || !statement.trim_end().ends_with(';') {
+            continue;
+        }
+
+        let values = statement.strip_prefix(prefix.as_str()).ok_or_else(|| {
+            RusticxError::ValidationError(format!("Dump statement is not an INSERT into {}", T::table_name()))
+        })?;
+        let rows: Vec<String> = parse_values(values, T::field_names().len())?
+            .iter()
+            .map(|row| format!("({})", row.iter().map(|value| value.to_sql(db_type)).collect::<Vec<_>>().join(", ")))
+            .collect();
+        let sql = format!("{}{}", target_prefix, rows.join(",\n"));
+        loaded += conn.execute(&sql, &[])?;
+        statement.clear();
+    }
+    if !statement.trim().is_empty() {
+        return Err(RusticxError::DeserializationError("Dump ends with an incomplete statement".to_string()));
+    }
+
+    if matches!(db_type, DatabaseType::PostgreSQL) && loaded > 0 {
+        let table = db_type.quote_identifier(&conn.table_path(&T::table_name()));
+        let primary_key = T::primary_key_field();
+        let sql = format!(
+            "SELECT setval(pg_get_serial_sequence('{}', '{}'), MAX({})) FROM {} WHERE pg_get_serial_sequence('{}', '{}') IS NOT NULL",
+            table.replace('\'', "''"),
+            primary_key.replace('\'', "''"),
+            db_type.quote_identifier(&primary_key),
+            table,
+            table.replace('\'', "''"),
+            primary_key.replace('\'', "''")
+        );
+        conn.query_raw::<serde_json::Value>(&sql, &[])?;
+    }
+
+    Ok(loaded)
+}
+
+/// The backend-neutral `INSERT INTO "table" ("a", "b") VALUES ` prefix of a
+/// dump statement.
+fn insert_prefix<T: SQLModel>() -> String {
+    let ansi = DatabaseType::PostgreSQL;
+    let columns: Vec<String> = T::field_names().iter().map(|c| ansi.quote_identifier(c)).collect();
+    format!("INSERT INTO {} ({}) VALUES\n", ansi.quote_identifier(&T::table_name()), columns.join(", "))
+}
+
+/// The prefix the statement is executed with on `conn`; PostgreSQL needs
+/// `OVERRIDING SYSTEM VALUE` to insert explicit ids into identity columns.
+fn target_insert_prefix<T: SQLModel>(conn: &Connection) -> String {
+    let db_type = conn.get_db_type();
+    let columns: Vec<String> = T::field_names().iter().map(|c| db_type.quote_identifier(c)).collect();
+    let overriding = if matches!(db_type, DatabaseType::PostgreSQL) { " OVERRIDING SYSTEM VALUE" } else { "" };
+    format!(
+        "INSERT INTO {} ({}){} VALUES\n",
+        db_type.quote_identifier(&conn.table_path(&T::table_name())),
+        columns.join(", "),
+        overriding
+    )
+}
+
+/// Renders a column value from a decoded row as a SQL literal, using the
+/// column's declared type to pick the representation.
+fn render_value(value: &serde_json::Value, sql_type: &SqlType) -> Result<String, RusticxError> {
+    use base64::Engine;
+
+    Ok(match (value, sql_type) {
+        (serde_json::Value::Null, _) => "NULL".to_string(),
+        (serde_json::Value::Bool(b), _) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        // SQLite stores booleans as integers
+        (serde_json::Value::Number(n), SqlType::Boolean) => if n.as_i64() == Some(0) { "FALSE" } else { "TRUE" }.to_string(),
+        (serde_json::Value::Number(n), _) => n.to_string(),
+        // Binary data is carried as base64 in decoded rows
+        (serde_json::Value::String(encoded), SqlType::Blob) => {
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|e| RusticxError::DeserializationError(format!("Invalid binary column data: {}", e)))?;
+            let hex: String = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+            format!("X'{}'", hex)
+        }
+        (serde_json::Value::String(text), _) => format!("'{}'", text.replace('\'', "''")),
+        (other, _) => format!("'{}'", other.to_string().replace('\'', "''")),
+    })
+}
+
+/// A literal of a dump's `VALUES` list.
+enum Literal {
+    Null,
+    Bool(bool),
+    Number(String),
+    Text(String),
+    /// Hex digits of a binary value.
+    Blob(String),
+}
+
+impl Literal {
+    /// Spells the literal for `db_type`: PostgreSQL writes binary values as
+    /// `'\x..'::bytea`, and MySQL treats backslashes in strings as escapes,
+    /// so they are doubled.
+    fn to_sql(&self, db_type: &DatabaseType) -> String {
+        match self {
+            Literal::Null => "NULL".to_string(),
+            Literal::Bool(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+            Literal::Number(n) => n.clone(),
+            Literal::Text(text) => {
+                let text = text.replace('\'', "''");
+                match db_type {
+                    DatabaseType::MySQL => format!("'{}'", text.replace('\\', "\\\\")),
+                    _ => format!("'{}'", text),
+                }
+            }
+            Literal::Blob(hex) => match db_type {
+                DatabaseType::PostgreSQL => format!("'\\x{}'::bytea", hex),
+                _ => format!("X'{}'", hex),
+            },
+        }
+    }
+}
+
+/// Parses a dump's `VALUES` list, up to and including its closing `;`, into
+/// rows of `width` literals. Anything but literals — subqueries, function
+/// calls, operators — is rejected, so loading a dump cannot run other SQL.
+fn parse_values(values: &str, width: usize) -> Result<Vec<Vec<Literal>>, RusticxError> {
+    let invalid = |what: &str| RusticxError::ValidationError(format!("Dump VALUES list {}; only literals are allowed", what));
+    let mut chars = values.chars().peekable();
+    let skip_whitespace = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    };
+
+    let mut rows = Vec::new();
+    loop {
+        skip_whitespace(&mut chars);
+        if chars.next() != Some('(') {
+            return Err(invalid("has a row that does not start with '('"));
+        }
+        let mut row = Vec::with_capacity(width);
+        loop {
+            skip_whitespace(&mut chars);
+            row.push(parse_literal(&mut chars).ok_or_else(|| invalid("has a value that is not a literal"))?);
+            skip_whitespace(&mut chars);
+            match chars.next() {
+                Some(',') => {}
+                Some(')') => break,
+                _ => return Err(invalid("has a value that is not a literal")),
+            }
+        }
+        if row.len() != width {
+            return Err(invalid(&format!("has a row of {} values instead of {}", row.len(), width)));
+        }
+        rows.push(row);
+
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            Some(',') => {}
+            Some(';') => break,
+            _ => return Err(invalid("has text between rows")),
+        }
+    }
+    skip_whitespace(&mut chars);
+    if chars.next().is_some() {
+        return Err(invalid("has text after its end"));
+    }
+    Ok(rows)
+}
+
+/// Reads one literal as written by `render_value`, or `None` if the next
+/// token is not one.
+fn parse_literal(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<Literal> {
+    match *chars.peek()? {
+        '\'' => {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next()? {
+                    '\'' if chars.next_if_eq(&'\'').is_some() => text.push('\''),
+                    '\'' => return Some(Literal::Text(text)),
+                    c => text.push(c),
+                }
+            }
+        }
+        'X' => {
+            chars.next();
+            chars.next_if_eq(&'\'')?;
+            let mut hex = String::new();
+            loop {
+                match chars.next()? {
+                    '\'' => return Some(Literal::Blob(hex)),
+                    c if c.is_ascii_hexdigit() => hex.push(c),
+                    _ => return None,
+                }
+            }
+        }
+        c if c == '-' || c.is_ascii_digit() => {
+            let mut number = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                number.push(c);
+            }
+            number.parse::<f64>().is_ok().then_some(Literal::Number(number))
+        }
+        _ => {
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                word.push(c);
+            }
+            match word.to_ascii_uppercase().as_str() {
+                "NULL" => Some(Literal::Null),
+                "TRUE" => Some(Literal::Bool(true)),
+                "FALSE" => Some(Literal::Bool(false)),
+                _ => None,
+            }
+        }
+    }
+}
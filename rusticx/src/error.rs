@@ -1,11 +1,16 @@
 use std::fmt;
+use crate::sql_types::SqlType;
 
 /// Represents the various errors that can occur in the Rusticx ORM.
 ///
 /// This enum encapsulates different types of errors that might arise
 /// during database operations, serialization/deserialization, validation,
 /// or connection management.
-#[derive(Debug)]
+///
+/// The enum is `#[non_exhaustive]`: new kinds of error may be added in minor
+/// releases, so matches on it need a wildcard arm.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum RusticxError {
     /// Represents a connection error with a message detailing the issue.
     ///
@@ -57,6 +62,48 @@ pub enum RusticxError {
     /// This error occurs when converting data received from the database
     /// (e.g., rows, JSON) into Rust data structures.
     DeserializationError(String),
+
+    /// Represents a mismatch between the schema version the application
+    /// expects and the one recorded in the database.
+    ///
+    /// Returned by `Connection::assert_schema_version` so instances can refuse
+    /// to start against a database that is ahead of or behind their code.
+    SchemaVersionMismatch(String),
+
+    /// Represents a write that lost a race with another writer.
+    ///
+    /// Returned by `SQLModel::update` when the record's `#[model(version)]`
+    /// no longer matches the stored row. Reload the record before trying
+    /// again.
+    Conflict(String),
+
+    /// Represents a statement or commit the database aborted to serialize
+    /// its transaction with a concurrent one: a PostgreSQL serialization
+    /// failure or deadlock (SQLSTATE 40001 or 40P01), or a MySQL deadlock
+    /// (error 1213).
+    ///
+    /// The transaction has been rolled back. Running all of it again usually
+    /// succeeds; see `Connection::transaction_with_retries`.
+    SerializationFailure(String),
+
+    /// Represents a statement that could not start because the connection's
+    /// concurrency limit stayed exhausted for the configured wait.
+    ///
+    /// Returned when a limit was set with `Connection::with_concurrency_limit`.
+    /// The statement was not run, so it is safe to retry later.
+    Busy(String),
+
+    /// Represents a statement cancelled because it ran longer than its
+    /// timeout.
+    ///
+    /// Returned for handles from `Connection::with_timeout` and for
+    /// statements PostgreSQL cancels under `statement_timeout`.
+    Timeout(String),
+
+    /// Represents a statement stopped through its `CancellationToken`.
+    ///
+    /// Returned for handles from `Connection::with_cancellation`.
+    Cancelled(String),
 }
 
 /// Implements the `fmt::Display` trait for `RusticxError`.
@@ -76,6 +123,12 @@ impl fmt::Display for RusticxError {
             RusticxError::DatabaseError(msg) => write!(f, "Database error: {}", msg),
             RusticxError::FeatureNotEnabled(msg) => write!(f, "Feature not enabled: {}", msg),
             RusticxError::DeserializationError(msg) => write!(f, "Deserialization error: {}", msg),
+            RusticxError::SchemaVersionMismatch(msg) => write!(f, "Schema version mismatch: {}", msg),
+            RusticxError::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            RusticxError::SerializationFailure(msg) => write!(f, "Serialization failure: {}", msg),
+            RusticxError::Busy(msg) => write!(f, "Busy: {}", msg),
+            RusticxError::Timeout(msg) => write!(f, "Timeout: {}", msg),
+            RusticxError::Cancelled(msg) => write!(f, "Cancelled: {}", msg),
         }
     }
 }
@@ -96,18 +149,106 @@ impl std::error::Error for RusticxError {}
 #[cfg(feature = "postgres")]
 impl From<tokio_postgres::Error> for RusticxError {
     fn from(err: tokio_postgres::Error) -> Self {
-        RusticxError::QueryError(err.to_string())
+        use tokio_postgres::error::SqlState;
+
+        match err.code() {
+            Some(&SqlState::QUERY_CANCELED) => RusticxError::Timeout(pg_error_message(&err)),
+            Some(&SqlState::T_R_SERIALIZATION_FAILURE | &SqlState::T_R_DEADLOCK_DETECTED) => {
+                RusticxError::SerializationFailure(pg_error_message(&err))
+            }
+            _ => RusticxError::QueryError(pg_error_message(&err)),
+        }
     }
 }
 
+/// Builds a readable message from a `tokio_postgres::Error`.
+///
+/// The driver's `Display` implementation only prints the error kind (e.g.
+/// "db error" or "error serializing parameter 1"), so the underlying cause
+/// is appended here to keep the server message or conversion failure.
+#[cfg(feature = "postgres")]
+pub(crate) fn pg_error_message(err: &tokio_postgres::Error) -> String {
+    match std::error::Error::source(err) {
+        Some(cause) => format!("{}: {}", err, cause),
+        None => err.to_string(),
+    }
+}
+
+/// Rewrites a parameter serialization failure into an actionable message.
+///
+/// When Postgres cannot bind a value (for example a `String` bound to an
+/// `INTEGER` column), the driver only reports the parameter index and the
+/// Rust/Postgres type names. This cross-references the index with the columns
+/// that were bound and the model's declared `SqlType`s, so the message names the
+/// field and the expected vs provided type. Errors of any other shape are
+/// returned unchanged.
+///
+/// # Arguments
+///
+/// * `err`: The error returned by the connection.
+/// * `bound_columns`: The column names in the order their values were bound.
+/// * `model_columns`: All column names of the model (`SQLModel::field_names`).
+/// * `model_types`: The declared SQL types, in the same order as `model_columns`.
+pub(crate) fn diagnose_param_error(
+    err: RusticxError,
+    bound_columns: &[&str],
+    model_columns: &[&str],
+    model_types: &[SqlType],
+) -> RusticxError {
+    let message = match &err {
+        RusticxError::QueryError(msg) => msg,
+        _ => return err,
+    };
+
+    // Expected shape: "error serializing parameter N: cannot convert between
+    // the Rust type `R` and the Postgres type `P`"
+    let index = match message
+        .strip_prefix("error serializing parameter ")
+        .and_then(|rest| rest.split(':').next())
+        .and_then(|idx| idx.trim().parse::<usize>().ok())
+    {
+        Some(index) => index,
+        None => return err,
+    };
+    let column = match bound_columns.get(index) {
+        Some(column) => *column,
+        None => return err,
+    };
+
+    let mut quoted = message.split('`').skip(1).step_by(2);
+    let rust_type = quoted.next().unwrap_or("unknown");
+    let pg_type = quoted.next().unwrap_or("unknown");
+
+    let declared = model_columns
+        .iter()
+        .position(|c| *c == column)
+        .and_then(|i| model_types.get(i))
+        .map(|t| t.pg_type())
+        .unwrap_or_else(|| pg_type.to_uppercase());
+
+    RusticxError::QueryError(format!(
+        "Type mismatch for parameter ${} (column `{}`): the column expects {} (Postgres type `{}`) but a value of Rust type `{}` was bound",
+        index + 1,
+        column,
+        declared,
+        pg_type,
+        rust_type
+    ))
+}
+
 /// Implements conversion from `mysql::Error` to `RusticxError`.
 ///
 /// This simplifies error handling by automatically converting errors from
-/// the `mysql` crate into a `RusticxError::QueryError`.
+/// the `mysql` crate into a `RusticxError::QueryError`, or a
+/// `RusticxError::SerializationFailure` for a deadlock.
 #[cfg(feature = "mysql")]
 impl From<mysql::Error> for RusticxError {
     fn from(err: mysql::Error) -> Self {
-        RusticxError::QueryError(err.to_string())
+        match &err {
+            // ER_LOCK_DEADLOCK: the server rolled the transaction back
+            mysql::Error::MySqlError(e) if e.code == 1213 => RusticxError::SerializationFailure(err.to_string()),
+            _ => RusticxError::QueryError(err.to_string()),
+        }
     }
 }
 
@@ -0,0 +1,111 @@
+//! In-process publish/subscribe of model lifecycle events.
+//!
+//! The default `SQLModel` CRUD methods publish an event after every successful
+//! insert, update and delete. Subscribers registered with `subscribe` are
+//! called synchronously on the thread that performed the write, which makes
+//! this suitable for invalidating in-process caches or forwarding changes to
+//! websocket broadcasters without polling the database.
+//!
+//! ```ignore
+//! let id = rusticx::events::subscribe::<User>(|event| {
+//!     if event.kind == ModelEventKind::Deleted {
+//!         cache.remove(&event.id);
+//!     }
+//! });
+//! // ...
+//! rusticx::events::unsubscribe(id);
+//! ```
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// The kind of change a `ModelEvent` describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelEventKind {
+    /// A record was inserted.
+    Inserted,
+    /// A record was updated.
+    Updated,
+    /// A record was deleted.
+    Deleted,
+}
+
+/// A lifecycle event for a model of type `T`.
+#[derive(Debug)]
+pub struct ModelEvent<'a, T> {
+    /// What happened to the record.
+    pub kind: ModelEventKind,
+    /// The primary key of the affected record, if known.
+    pub id: Option<i32>,
+    /// The model instance involved, when the write was made through one.
+    /// Deletes by id carry no instance.
+    pub model: Option<&'a T>,
+}
+
+/// Identifies a subscription so it can be removed with `unsubscribe`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+/// The callback type stored (type-erased) for subscribers of model `T`.
+type Callback<T> = Arc<dyn Fn(&ModelEvent<'_, T>) + Send + Sync>;
+
+/// Subscribers keyed by model type; each entry holds a type-erased `Callback<T>`.
+type Registry = RwLock<HashMap<TypeId, Vec<(SubscriptionId, Box<dyn Any + Send + Sync>)>>>;
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Registers `callback` to be called for every lifecycle event of model `T`.
+///
+/// Returns a `SubscriptionId` that can be passed to `unsubscribe`.
+pub fn subscribe<T: 'static>(
+    callback: impl Fn(&ModelEvent<'_, T>) + Send + Sync + 'static,
+) -> SubscriptionId {
+    let id = SubscriptionId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    let callback: Callback<T> = Arc::new(callback);
+
+    let mut subscribers = registry().write().unwrap_or_else(|e| e.into_inner());
+    subscribers
+        .entry(TypeId::of::<T>())
+        .or_default()
+        .push((id, Box::new(callback)));
+    id
+}
+
+/// Removes a subscription. Returns `true` if it was registered.
+pub fn unsubscribe(id: SubscriptionId) -> bool {
+    let mut subscribers = registry().write().unwrap_or_else(|e| e.into_inner());
+    for callbacks in subscribers.values_mut() {
+        if let Some(pos) = callbacks.iter().position(|(sub_id, _)| *sub_id == id) {
+            callbacks.remove(pos);
+            return true;
+        }
+    }
+    false
+}
+
+/// Delivers `event` to every subscriber of model `T`.
+///
+/// Callbacks are collected before being invoked so they may themselves
+/// subscribe or unsubscribe without deadlocking.
+pub(crate) fn publish<T: 'static>(event: ModelEvent<'_, T>) {
+    let callbacks: Vec<Callback<T>> = {
+        let subscribers = registry().read().unwrap_or_else(|e| e.into_inner());
+        match subscribers.get(&TypeId::of::<T>()) {
+            Some(entries) => entries
+                .iter()
+                .filter_map(|(_, callback)| callback.downcast_ref::<Callback<T>>().cloned())
+                .collect(),
+            None => return,
+        }
+    };
+
+    for callback in callbacks {
+        callback(&event);
+    }
+}
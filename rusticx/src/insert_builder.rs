@@ -0,0 +1,161 @@
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use serde::Deserialize;
+use crate::connection::Connection;
+use crate::error::RusticxError;
+use crate::model::{SQLModel, ToSql, ToSqlConvert};
+
+/// Builds a single, possibly multi-row, `INSERT` for a model.
+///
+/// Created with `QueryBuilder::insert`. Unlike `SQLModel::insert` it writes
+/// any number of rows in one statement and can return columns of the new
+/// rows, but it does not set primary keys on the instances or publish model
+/// events.
+///
+/// ```ignore
+/// let ids: Vec<NewId> = QueryBuilder::insert::<User>()
+///     .values(&[alice, bob])
+///     .returning(&["id"])
+///     .fetch_as(&conn)?;
+/// ```
+pub struct InsertBuilder<T: SQLModel> {
+    rows: Vec<Vec<Box<dyn ToSqlConvert>>>,
+    returning: Vec<String>,
+    model: PhantomData<T>,
+}
+
+impl<T: SQLModel> InsertBuilder<T> {
+    pub(crate) fn new() -> Self {
+        InsertBuilder { rows: Vec::new(), returning: Vec::new(), model: PhantomData }
+    }
+
+    /// Adds one row with the values of `model`.
+    pub fn value(mut self, model: &T) -> Self {
+        self.rows.push(model.to_sql_field_values());
+        self
+    }
+
+    /// Adds one row per model.
+    pub fn values<'m>(mut self, models: impl IntoIterator<Item = &'m T>) -> Self {
+        self.rows.extend(models.into_iter().map(|m| m.to_sql_field_values()));
+        self
+    }
+
+    /// Returns `columns` of the inserted rows, read with `fetch_as`. Without
+    /// it, `fetch_as` returns every column.
+    pub fn returning(mut self, columns: &[&str]) -> Self {
+        self.returning = columns.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    /// Runs the `INSERT`, returning the number of rows written.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::ValidationError` if some rows set the primary
+    /// key and others leave it to the database.
+    pub fn execute(self, conn: &Connection) -> Result<u64, RusticxError> {
+        if self.rows.is_empty() {
+            return Ok(0);
+        }
+        let mut params: Vec<&(dyn ToSql + Sync + 'static)> = Vec::new();
+        let sql = self.to_sql(conn, &mut params)?;
+        conn.execute(&sql, &params)
+    }
+
+    /// Runs the `INSERT ... RETURNING` and deserializes each returned row
+    /// into `R`.
+    ///
+    /// # Errors
+    ///
+    /// As for `execute`, plus `RusticxError::InvalidColumn` for a returned
+    /// column `T` does not have and `RusticxError::QueryError` on MySQL,
+    /// which has no `RETURNING`.
+    pub fn fetch_as<R: for<'de> Deserialize<'de> + Debug>(self, conn: &Connection) -> Result<Vec<R>, RusticxError> {
+        if self.rows.is_empty() {
+            return Ok(Vec::new());
+        }
+        let db_type = conn.get_db_type();
+        let returning = if self.returning.is_empty() {
+            "*".to_string()
+        } else {
+            self.returning
+                .iter()
+                .map(|column| {
+                    if T::field_names().contains(&column.as_str()) {
+                        Ok(db_type.quote_identifier(column))
+                    } else {
+                        Err(RusticxError::InvalidColumn(format!(
+                            "Cannot return {}: not a column of {}",
+                            column,
+                            T::table_name()
+                        )))
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?
+                .join(", ")
+        };
+
+        let mut params: Vec<&(dyn ToSql + Sync + 'static)> = Vec::new();
+        let sql = format!("{} RETURNING {}", self.to_sql(conn, &mut params)?, returning);
+        conn.execute_returning(&sql, &params)
+    }
+
+    /// Renders the `INSERT`, appending the row values to `params`.
+    ///
+    /// The primary key is left to the database when no row sets it; unset
+    /// optional values are written as `NULL`.
+    fn to_sql<'a>(
+        &'a self,
+        conn: &Connection,
+        params: &mut Vec<&'a (dyn ToSql + Sync + 'static)>,
+    ) -> Result<String, RusticxError> {
+        if let Some(e) = self.rows.iter().flatten().find_map(|value| value.conversion_error()) {
+            return Err(e.clone());
+        }
+        let db_type = conn.get_db_type();
+        let fields = T::field_names();
+        let primary_key = T::primary_key_field();
+        let pk_idx = fields.iter().position(|f| *f == primary_key);
+
+        let pk_set = |row: &Vec<Box<dyn ToSqlConvert>>| pk_idx.and_then(|i| row.get(i)).is_some_and(|v| !v.is_null());
+        let include_pk = match self.rows.iter().filter(|row| pk_set(row)).count() {
+            0 => pk_idx.is_none(),
+            n if n == self.rows.len() => true,
+            _ => {
+                return Err(RusticxError::ValidationError(format!(
+                    "Cannot insert into {}: either every row or none must set the primary key {}",
+                    T::table_name(),
+                    primary_key
+                )))
+            }
+        };
+        let included = |i: &usize| include_pk || Some(*i) != pk_idx;
+
+        let columns: Vec<String> = (0..fields.len())
+            .filter(included)
+            .map(|i| db_type.quote_identifier(fields[i]))
+            .collect();
+        let mut tuples = Vec::with_capacity(self.rows.len());
+        for row in &self.rows {
+            let mut placeholders = Vec::with_capacity(columns.len());
+            for value in row.iter().enumerate().filter(|(i, _)| included(i)).map(|(_, v)| v) {
+                match value.as_ref_postgres() {
+                    Some(param) => {
+                        params.push(param);
+                        placeholders.push(db_type.placeholder(params.len()));
+                    }
+                    None => placeholders.push("NULL".to_string()),
+                }
+            }
+            tuples.push(format!("({})", placeholders.join(", ")));
+        }
+
+        Ok(format!(
+            "INSERT INTO {} ({}) VALUES {}",
+            db_type.quote_identifier(&conn.table_path(&T::table_name())),
+            columns.join(", "),
+            tuples.join(", ")
+        ))
+    }
+}
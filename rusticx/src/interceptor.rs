@@ -0,0 +1,98 @@
+use std::sync::Arc;
+use std::time::Duration;
+use crate::error::RusticxError;
+
+/// A statement a `Connection` ran, as passed to `Connection::on_query`
+/// hooks.
+#[derive(Debug)]
+pub struct QueryInfo<'a> {
+    /// The SQL that ran, after any `around_query` rewriting.
+    pub sql: &'a str,
+    /// The pool the statement ran on, as named in `PoolMetrics`.
+    pub pool: &'a str,
+    /// How long the statement took, waiting for a connection included.
+    pub duration: Duration,
+    /// The error the statement failed with, or `None` if it succeeded.
+    pub error: Option<&'a RusticxError>,
+}
+
+/// Runs the rest of the interceptor chain, and then the statement, with
+/// the given SQL.
+pub type Next<'n> = dyn FnMut(&str) -> Result<(), RusticxError> + 'n;
+
+type Hook = Arc<dyn Fn(&QueryInfo) + Send + Sync>;
+type Around = Arc<dyn Fn(&str, &mut Next<'_>) -> Result<(), RusticxError> + Send + Sync>;
+
+/// The hooks and interceptors registered on a `Connection`.
+#[derive(Clone, Default)]
+pub(crate) struct Interceptors {
+    hooks: Vec<Hook>,
+    arounds: Vec<Around>,
+}
+
+impl Interceptors {
+    pub(crate) fn add_hook(&mut self, hook: impl Fn(&QueryInfo) + Send + Sync + 'static) {
+        self.hooks.push(Arc::new(hook));
+    }
+
+    pub(crate) fn add_around(
+        &mut self,
+        around: impl Fn(&str, &mut Next<'_>) -> Result<(), RusticxError> + Send + Sync + 'static,
+    ) {
+        self.arounds.push(Arc::new(around));
+    }
+
+    /// Runs `statement` through the interceptors, the first registered
+    /// outermost, and returns its result with the SQL it ran.
+    ///
+    /// An interceptor's error replaces the statement's result. One that
+    /// succeeds without running the statement fails it, since there is no
+    /// result to return.
+    pub(crate) fn run<T>(
+        &self,
+        sql: &str,
+        statement: impl FnOnce(&str) -> Result<T, RusticxError>,
+    ) -> (Result<T, RusticxError>, Option<String>) {
+        if self.arounds.is_empty() {
+            return (statement(sql), None);
+        }
+
+        let mut statement = Some(statement);
+        let mut output = None;
+        let mut ran = None;
+        let mut last = |sql: &str| {
+            let statement = statement.take().ok_or_else(|| {
+                RusticxError::QueryError("An around_query interceptor ran the statement twice".to_string())
+            })?;
+            ran = Some(sql.to_string());
+            let result = statement(sql);
+            let status = result.as_ref().map(|_| ()).map_err(Clone::clone);
+            output = Some(result);
+            status
+        };
+        let chained = chain(&self.arounds, sql, &mut last);
+
+        let result = match (chained, output) {
+            (Err(e), _) => Err(e),
+            (Ok(()), Some(result)) => result,
+            (Ok(()), None) => Err(RusticxError::QueryError(
+                "An around_query interceptor skipped the statement".to_string(),
+            )),
+        };
+        (result, ran)
+    }
+
+    /// Passes a finished statement to the `on_query` hooks.
+    pub(crate) fn notify(&self, info: &QueryInfo) {
+        for hook in &self.hooks {
+            hook(info);
+        }
+    }
+}
+
+fn chain(arounds: &[Around], sql: &str, last: &mut Next<'_>) -> Result<(), RusticxError> {
+    match arounds.split_first() {
+        Some((around, rest)) => around(sql, &mut |sql: &str| chain(rest, sql, last)),
+        None => last(sql),
+    }
+}
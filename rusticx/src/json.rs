@@ -0,0 +1,138 @@
+use serde_json::Value;
+use crate::connection::DatabaseType;
+use crate::error::RusticxError;
+use crate::model::{SQLModel, ToSqlConvert};
+use crate::sql_types::SqlType;
+
+/// Renders a condition matching rows of `T` whose JSON `column` contains
+/// `needle`, appending the values to bind to `params`.
+///
+/// Containment follows PostgreSQL's `@>`: every key of an object must be
+/// present with a containing value, and every element of an array must be
+/// contained in some element of the stored array. PostgreSQL and MySQL use
+/// their native operators; SQLite has none, so the needle is unrolled into
+/// `json_extract` and `json_each` comparisons.
+pub(crate) fn contains_condition<T: SQLModel>(
+    db_type: &DatabaseType,
+    column: &str,
+    needle: &Value,
+    params: &mut Vec<Box<dyn ToSqlConvert>>,
+) -> Result<String, RusticxError> {
+    let fields = T::field_names();
+    let Some(index) = fields.iter().position(|f| *f == column) else {
+        return Err(RusticxError::InvalidColumn(format!(
+            "Cannot search {}: not a column of {}",
+            column,
+            T::table_name()
+        )));
+    };
+    let quoted = db_type.quote_identifier(column);
+
+    Ok(match db_type {
+        DatabaseType::PostgreSQL => {
+            // jsonb columns are compared as-is so a GIN index can serve the
+            // query; json and text columns are cast first
+            let is_jsonb = matches!(
+                T::field_sql_types().get(index),
+                Some(SqlType::Custom(declared)) if declared.eq_ignore_ascii_case("jsonb")
+            );
+            let target = if is_jsonb { quoted } else { format!("CAST({} AS JSONB)", quoted) };
+            params.push(Box::new(needle.to_string()));
+            format!("{} @> CAST(CAST({} AS TEXT) AS JSONB)", target, db_type.placeholder(params.len()))
+        }
+        DatabaseType::MySQL => {
+            params.push(Box::new(needle.to_string()));
+            format!("JSON_CONTAINS({}, {})", quoted, db_type.placeholder(params.len()))
+        }
+        DatabaseType::SQLite => {
+            let conditions = sqlite_contains(db_type, &quoted, "$", needle, params, 0)?;
+            if conditions.is_empty() {
+                format!("json_valid({})", quoted)
+            } else {
+                conditions.join(" AND ")
+            }
+        }
+    })
+}
+
+/// Unrolls `needle` into SQLite conditions on the JSON text `doc` at `path`.
+fn sqlite_contains(
+    db_type: &DatabaseType,
+    doc: &str,
+    path: &str,
+    needle: &Value,
+    params: &mut Vec<Box<dyn ToSqlConvert>>,
+    depth: usize,
+) -> Result<Vec<String>, RusticxError> {
+    let bind = |params: &mut Vec<Box<dyn ToSqlConvert>>, value: Box<dyn ToSqlConvert>| {
+        params.push(value);
+        db_type.placeholder(params.len())
+    };
+
+    Ok(match needle {
+        Value::Object(entries) => {
+            let mut conditions = Vec::new();
+            if entries.is_empty() {
+                let path = bind(params, Box::new(path.to_string()));
+                conditions.push(format!("json_type({}, {}) = 'object'", doc, path));
+            }
+            for (key, value) in entries {
+                if key.contains('"') {
+                    return Err(RusticxError::ValidationError(format!(
+                        "JSON key {:?} cannot be searched on SQLite: keys must not contain '\"'",
+                        key
+                    )));
+                }
+                let path = format!("{}.\"{}\"", path, key);
+                conditions.extend(sqlite_contains(db_type, doc, &path, value, params, depth)?);
+            }
+            conditions
+        }
+        Value::Array(elements) => {
+            let mut conditions = Vec::new();
+            if elements.is_empty() {
+                let path = bind(params, Box::new(path.to_string()));
+                conditions.push(format!("json_type({}, {}) = 'array'", doc, path));
+            }
+            for element in elements {
+                let alias = format!("j{}", depth);
+                let path = bind(params, Box::new(path.to_string()));
+                let matches = match element {
+                    Value::Object(_) | Value::Array(_) => {
+                        let inner = sqlite_contains(db_type, &format!("{}.value", alias), "$", element, params, depth + 1)?;
+                        if inner.is_empty() { "1 = 1".to_string() } else { inner.join(" AND ") }
+                    }
+                    Value::Null => format!("{}.type = 'null'", alias),
+                    scalar => format!("{}.value = {}", alias, bind(params, sqlite_scalar(scalar))),
+                };
+                conditions.push(format!(
+                    "EXISTS (SELECT 1 FROM json_each({}, {}) AS {} WHERE {})",
+                    doc, path, alias, matches
+                ));
+            }
+            conditions
+        }
+        Value::Null => {
+            let path = bind(params, Box::new(path.to_string()));
+            vec![format!("json_type({}, {}) = 'null'", doc, path)]
+        }
+        scalar => {
+            let path = bind(params, Box::new(path.to_string()));
+            let value = bind(params, sqlite_scalar(scalar));
+            vec![format!("json_extract({}, {}) = {}", doc, path, value)]
+        }
+    })
+}
+
+/// The SQL value SQLite's JSON functions return for a JSON scalar.
+fn sqlite_scalar(value: &Value) -> Box<dyn ToSqlConvert> {
+    match value {
+        Value::Bool(b) => Box::new(*b as i64),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Box::new(i),
+            None => Box::new(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => Box::new(s.clone()),
+        other => Box::new(other.to_string()),
+    }
+}
@@ -3,21 +3,70 @@
 /// This module provides the core functionality for interacting with various databases
 /// through a unified interface. It includes connection management, error handling,
 /// and transaction management.
+mod cancel;
+mod capabilities;
+mod coercion;
 mod connection;
+mod conversion;
+mod dump;
+mod insert_builder;
+mod interceptor;
+mod json;
+mod limiter;
+mod manager;
+mod mask;
 mod model;
-// mod query_builder;
-// mod migrations;
+mod options;
+mod ordering;
+mod outbox;
+mod pagination;
+mod partition;
+mod projection;
+mod predicate;
+mod prepared;
+mod query_builder;
+mod script;
+mod sessions;
+mod migrations;
 mod error;
+pub mod events;
+pub mod metrics;
 mod sql_types;
+mod stream;
+mod template;
+mod tls;
 mod transaction_manager;
+mod window;
 
 /// Re-exporting types for easier access by users of the library.
-pub use connection::{Connection, DatabaseType}; // Re-exporting connection-related types
-pub use model::{SQLModel, ModelAttribute, ToSqlConvert}; // Re-exporting model-related types
-// pub use query_builder::QueryBuilder;
+pub use cancel::CancellationToken; // Re-exporting statement cancellation
+pub use capabilities::{Capabilities, ServerVersion}; // Re-exporting server feature detection
+pub use connection::{BackupProgress, Connection, DatabaseType}; // Re-exporting connection-related types
+pub use coercion::{Coercion, Coercions}; // Re-exporting decode-time coercions
+pub use conversion::{Compression, StoreAs, StoreUnit}; // Re-exporting field conversions
+#[cfg(feature = "compression")]
+pub use conversion::Compress; // Re-exporting compressed field conversions
+pub use manager::{ConnectionManager, ReadPreference, DEFAULT_DATABASE}; // Re-exporting multi-database routing types
+pub use mask::Mask; // Re-exporting column masking for exports
+pub use model::{SQLModel, ModelAttribute, Relations, RowMapping, ToSqlConvert}; // Re-exporting model-related types
+pub use options::ConnectionOptions; // Re-exporting the connection settings builder
+pub use ordering::OrderBy; // Re-exporting validated orderings
+pub use outbox::{Outbox, OutboxMessage}; // Re-exporting the transactional outbox
+pub use pagination::{CursorPage, Page}; // Re-exporting pagination types
+pub use partition::PartitionConfig; // Re-exporting connection partition settings
+pub use stream::{BatchIter, RowIter}; // Re-exporting the streaming row iterators
+pub use template::{RenderedSql, SqlTemplate}; // Re-exporting SQL templating types
+pub use tls::{MySqlSslMode, MySqlTls}; // Re-exporting MySQL TLS settings
+pub use predicate::{col, Col, Cond, InList, Predicate}; // Re-exporting reusable filter predicates
+pub use prepared::PreparedQuery; // Re-exporting prepared builder queries
+pub use insert_builder::InsertBuilder; // Re-exporting the insert builder
+pub use interceptor::{Next, QueryInfo}; // Re-exporting query hooks and interceptors
+pub use query_builder::{QueryBuilder, SqlParam, Subquery}; // Re-exporting the query builder
 pub use error::RusticxError; // Re-exporting the RusticxError type for error handling
-// pub use migrations::{Migration, MigrationManager};
+pub use migrations::{Migration, MigrationManager}; // Re-exporting migration types
 pub use sql_types::SqlType; // Re-exporting SQL type definitions
+pub use window::Window; // Re-exporting window function expressions
+pub use transaction_manager::{ConnectionLike, DistributedTx, IsolationLevel, QueryExecutor, QueryExecutorExt, Transaction, TransactionExecutor, TxConnection, TxOptions}; // Re-exporting transaction handles and settings
 #[cfg(feature = "mysql")]
 pub use transaction_manager::MySQLTransactionExecutor; // Re-exporting MySQL transaction executor
 #[cfg(feature = "rusqlite")]
@@ -0,0 +1,85 @@
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+use crate::error::RusticxError;
+
+/// Caps how many statements a `Connection` (and its clones) run at once.
+///
+/// Callers beyond the cap queue in arrival order and are admitted first come,
+/// first served as statements finish. A caller that cannot start within the
+/// configured wait gives up with `RusticxError::Busy` instead of piling onto
+/// the database lock.
+pub(crate) struct Limiter {
+    max_concurrent: usize,
+    max_wait: Duration,
+    state: Mutex<LimiterState>,
+    changed: Condvar,
+}
+
+struct LimiterState {
+    running: usize,
+    /// Tickets of the queued callers, oldest first.
+    queue: VecDeque<u64>,
+    next_ticket: u64,
+}
+
+impl Limiter {
+    pub(crate) fn new(max_concurrent: usize, max_wait: Duration) -> Self {
+        Limiter {
+            max_concurrent: max_concurrent.max(1),
+            max_wait,
+            state: Mutex::new(LimiterState { running: 0, queue: VecDeque::new(), next_ticket: 0 }),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Waits for a turn to run a statement; the turn ends when the permit is dropped.
+    pub(crate) fn acquire(&self) -> Result<Permit<'_>, RusticxError> {
+        let deadline = Instant::now() + self.max_wait;
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+        state.queue.push_back(ticket);
+
+        loop {
+            if state.queue.front() == Some(&ticket) && state.running < self.max_concurrent {
+                state.queue.pop_front();
+                state.running += 1;
+                // The next in line may fit too
+                self.changed.notify_all();
+                return Ok(Permit { limiter: self });
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                state.queue.retain(|queued| *queued != ticket);
+                let (running, queued) = (state.running, state.queue.len());
+                // Leaving the head of the queue can unblock the caller behind
+                self.changed.notify_all();
+                return Err(RusticxError::Busy(format!(
+                    "Timed out after {:?} waiting to run a statement ({} running, limit {}, {} queued)",
+                    self.max_wait, running, self.max_concurrent, queued
+                )));
+            }
+            state = self
+                .changed
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+}
+
+/// A turn to run a statement; lets the next queued caller in when dropped.
+pub(crate) struct Permit<'a> {
+    limiter: &'a Limiter,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let mut state = self.limiter.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.running -= 1;
+        drop(state);
+        self.limiter.changed.notify_all();
+    }
+}
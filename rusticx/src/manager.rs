@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use crate::connection::Connection;
+use crate::error::RusticxError;
+use crate::model::SQLModel;
+
+/// The name under which the default connection is registered.
+pub const DEFAULT_DATABASE: &str = "default";
+
+/// Describes where a read may be served from.
+///
+/// Used with `ConnectionManager::for_read` to choose between a database's
+/// primary connection and its registered read replicas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadPreference {
+    /// Always read from the primary.
+    Primary,
+    /// Read from a replica when one is registered, regardless of its lag.
+    Replica,
+    /// Read from a replica only if its replication lag is known and no larger
+    /// than the given duration; otherwise fall back to the primary.
+    MaxStaleness(Duration),
+}
+
+/// Holds named connections for applications that talk to several databases.
+///
+/// Models declare the database they live in with `#[model(database = "...")]`
+/// (see `SQLModel::database_name`), and `for_model` resolves the matching
+/// connection so finders cannot accidentally be run against the wrong one.
+/// Models without a declared database use the default connection.
+///
+/// Read replicas can be registered per database and are selected by
+/// `for_read` according to a `ReadPreference`.
+#[derive(Clone)]
+pub struct ConnectionManager {
+    /// Registered connections keyed by database name.
+    connections: HashMap<String, Connection>,
+    /// Read replicas keyed by the name of the database they replicate.
+    replicas: HashMap<String, Vec<Connection>>,
+}
+
+impl ConnectionManager {
+    /// Creates a manager with `default` registered as the default database.
+    pub fn new(default: Connection) -> Self {
+        let mut connections = HashMap::new();
+        connections.insert(DEFAULT_DATABASE.to_string(), default);
+        ConnectionManager {
+            connections,
+            replicas: HashMap::new(),
+        }
+    }
+
+    /// Registers (or replaces) the connection for the database `name`.
+    pub fn register(&mut self, name: &str, conn: Connection) -> &mut Self {
+        self.connections.insert(name.to_string(), conn);
+        self
+    }
+
+    /// Registers a read replica for the database `name`.
+    ///
+    /// Replicas are tried in registration order by `for_read`.
+    pub fn register_replica(&mut self, name: &str, conn: Connection) -> &mut Self {
+        self.replicas.entry(name.to_string()).or_default().push(conn);
+        self
+    }
+
+    /// Returns the connection registered under `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::ConnectionError` if no connection has been registered
+    /// under that name.
+    pub fn get(&self, name: &str) -> Result<&Connection, RusticxError> {
+        self.connections.get(name).ok_or_else(|| {
+            RusticxError::ConnectionError(format!("No connection registered for database '{}'", name))
+        })
+    }
+
+    /// Returns the default connection.
+    pub fn default_connection(&self) -> &Connection {
+        // The default entry is inserted in `new` and can only be replaced, never removed
+        &self.connections[DEFAULT_DATABASE]
+    }
+
+    /// Returns the connection for the database model `T` is routed to.
+    ///
+    /// ```ignore
+    /// let report = DailyReport::find_all(manager.for_model::<DailyReport>()?)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::ConnectionError` if `T` declares a database that has
+    /// not been registered. There is deliberately no fallback to the default
+    /// connection in that case.
+    pub fn for_model<T: SQLModel>(&self) -> Result<&Connection, RusticxError> {
+        match T::database_name() {
+            Some(name) => self.get(name).map_err(|_| {
+                RusticxError::ConnectionError(format!(
+                    "No connection registered for database '{}' required by model {}",
+                    name,
+                    T::table_name()
+                ))
+            }),
+            None => Ok(self.default_connection()),
+        }
+    }
+
+    /// Returns the connection to read model `T` from, honoring `preference`.
+    ///
+    /// With `ReadPreference::MaxStaleness`, each replica's lag is checked with
+    /// `Connection::replication_lag` before it is used; replicas that are too far
+    /// behind, cannot report their lag, or fail the check are skipped, and the
+    /// primary is returned when none qualifies.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::ConnectionError` if the model's database has not been
+    /// registered.
+    pub fn for_read<T: SQLModel>(&self, preference: ReadPreference) -> Result<&Connection, RusticxError> {
+        let primary = self.for_model::<T>()?;
+        let name = T::database_name().unwrap_or(DEFAULT_DATABASE);
+        let replicas = self.replicas.get(name).map(Vec::as_slice).unwrap_or(&[]);
+
+        let chosen = match preference {
+            ReadPreference::Primary => None,
+            ReadPreference::Replica => replicas.first(),
+            ReadPreference::MaxStaleness(max) => replicas.iter().find(|replica| {
+                matches!(replica.replication_lag(), Ok(Some(lag)) if lag <= max)
+            }),
+        };
+
+        Ok(chosen.unwrap_or(primary))
+    }
+}
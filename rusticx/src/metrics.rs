@@ -0,0 +1,205 @@
+//! Connection pool gauges for capacity monitoring.
+//!
+//! Every pool a `Connection` uses (its main connection and each partition)
+//! tracks how many connections it holds and how many of them are in use,
+//! how many callers are waiting for one, how long recent acquisitions
+//! waited, and how many statements it ran and how many of those failed. On the main
+//! connection a query is in use while it holds the client; on a partition a
+//! connection is in use while a handle from `Connection::partition` is alive. A hook installed with
+//! `set_hook` receives a `PoolMetrics` snapshot whenever one of these changes,
+//! which makes it easy to forward them to Prometheus, StatsD or logs:
+//!
+//! ```ignore
+//! rusticx::metrics::set_hook(|m| {
+//!     gauge!("db_in_flight", m.in_flight as f64, "pool" => m.pool.clone());
+//!     gauge!("db_waiting", m.waiting as f64, "pool" => m.pool.clone());
+//!     gauge!("db_acquire_wait_p95_ms", m.p95_acquire_wait.as_secs_f64() * 1000.0, "pool" => m.pool.clone());
+//! });
+//! ```
+//!
+//! The same snapshots can be polled with `Connection::metrics` and
+//! `Connection::pool_metrics`.
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+/// How many recent acquire waits the P95 is computed over.
+const WAIT_WINDOW: usize = 1024;
+
+/// A point-in-time view of one connection pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// The pool's name: `"default"` (or the name given to
+    /// `Connection::with_pool_name`), followed by `/<partition>` for partitions.
+    pub pool: String,
+    /// Connections the pool holds open. For MySQL this is the pool's
+    /// maximum, up to which the driver opens connections as needed.
+    pub size: usize,
+    /// Connections of the pool currently in use.
+    pub in_flight: usize,
+    /// Connections of the pool not in use.
+    pub idle: usize,
+    /// Callers currently waiting for a connection of the pool.
+    pub waiting: usize,
+    /// The 95th percentile of the last 1024 acquire waits.
+    pub p95_acquire_wait: Duration,
+    /// Statements run on the pool since it was opened.
+    pub statements: u64,
+    /// Statements that failed, of `statements`.
+    pub errors: u64,
+}
+
+/// The callback type stored for the metrics hook.
+type Hook = Arc<dyn Fn(&PoolMetrics) + Send + Sync>;
+
+fn hook() -> &'static RwLock<Option<Hook>> {
+    static HOOK: OnceLock<RwLock<Option<Hook>>> = OnceLock::new();
+    HOOK.get_or_init(|| RwLock::new(None))
+}
+
+/// Installs `callback` to receive pool snapshots, replacing any previous hook.
+///
+/// The callback runs synchronously on the thread that acquired or released
+/// the connection, so it should only record the values.
+pub fn set_hook(callback: impl Fn(&PoolMetrics) + Send + Sync + 'static) {
+    *hook().write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(callback));
+}
+
+/// Removes the metrics hook. Returns `true` if one was installed.
+pub fn clear_hook() -> bool {
+    hook().write().unwrap_or_else(|e| e.into_inner()).take().is_some()
+}
+
+/// Live counters for one pool, shared by every handle that uses it.
+pub(crate) struct PoolStats {
+    name: String,
+    size: AtomicUsize,
+    in_flight: AtomicUsize,
+    waiting: AtomicUsize,
+    waits: Mutex<VecDeque<Duration>>,
+    statements: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl PoolStats {
+    pub(crate) fn new(name: &str) -> Self {
+        PoolStats {
+            name: name.to_string(),
+            size: AtomicUsize::new(0),
+            in_flight: AtomicUsize::new(0),
+            waiting: AtomicUsize::new(0),
+            waits: Mutex::new(VecDeque::with_capacity(WAIT_WINDOW)),
+            statements: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The number of connections the pool holds.
+    pub(crate) fn size(&self) -> usize {
+        self.size.load(Ordering::SeqCst)
+    }
+
+    /// Sets the number of connections the pool holds.
+    pub(crate) fn set_size(&self, size: usize) {
+        self.size.store(size, Ordering::SeqCst);
+    }
+
+    /// Counts a statement run on the pool, and whether it failed.
+    pub(crate) fn record_statement(&self, failed: bool) {
+        self.statements.fetch_add(1, Ordering::SeqCst);
+        if failed {
+            self.errors.fetch_add(1, Ordering::SeqCst);
+        }
+        self.report();
+    }
+
+    /// Runs `acquire` as a wait for a connection of this pool.
+    ///
+    /// The caller counts as waiting until `acquire` returns, and the time it
+    /// took is recorded for the P95.
+    pub(crate) fn wait<G, E>(&self, acquire: impl FnOnce() -> Result<G, E>) -> Result<G, E> {
+        self.waiting.fetch_add(1, Ordering::SeqCst);
+        self.report();
+        let started = Instant::now();
+        let result = acquire();
+        self.record_wait(started.elapsed());
+        self.waiting.fetch_sub(1, Ordering::SeqCst);
+        self.report();
+        result
+    }
+
+    /// Counts a connection of this pool as in use.
+    pub(crate) fn start(&self) {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        self.report();
+    }
+
+    /// Releases a connection counted by `start`.
+    pub(crate) fn finish(&self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.report();
+    }
+
+    /// Waits for a connection with `wait` and counts it as in flight until
+    /// the returned `InFlight` is dropped.
+    pub(crate) fn acquire<G, E>(&self, acquire: impl FnOnce() -> Result<G, E>) -> Result<(G, InFlight<'_>), E> {
+        let guard = self.wait(acquire)?;
+        self.start();
+        Ok((guard, InFlight { stats: self }))
+    }
+
+    fn record_wait(&self, waited: Duration) {
+        let mut waits = self.waits.lock().unwrap_or_else(|e| e.into_inner());
+        if waits.len() == WAIT_WINDOW {
+            waits.pop_front();
+        }
+        waits.push_back(waited);
+    }
+
+    /// Takes a snapshot of the pool's gauges.
+    pub(crate) fn snapshot(&self) -> PoolMetrics {
+        let mut waits: Vec<Duration> = self.waits.lock().unwrap_or_else(|e| e.into_inner()).iter().copied().collect();
+        waits.sort_unstable();
+        let p95_acquire_wait = match waits.len() {
+            0 => Duration::ZERO,
+            len => waits[(len * 95).div_ceil(100) - 1],
+        };
+
+        let size = self.size();
+        let in_flight = self.in_flight.load(Ordering::SeqCst);
+        PoolMetrics {
+            pool: self.name.clone(),
+            size,
+            in_flight,
+            idle: size.saturating_sub(in_flight),
+            waiting: self.waiting.load(Ordering::SeqCst),
+            p95_acquire_wait,
+            statements: self.statements.load(Ordering::SeqCst),
+            errors: self.errors.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Sends a snapshot to the hook, if one is installed.
+    fn report(&self) {
+        let callback = hook().read().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some(callback) = callback {
+            callback(&self.snapshot());
+        }
+    }
+}
+
+/// Marks a query as in flight on a pool until dropped.
+pub(crate) struct InFlight<'a> {
+    stats: &'a PoolStats,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.stats.finish();
+    }
+}
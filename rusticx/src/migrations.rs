@@ -1,92 +1,228 @@
-// use crate::connection::Connection;
-// use crate::error::RustixError;
-
-// pub trait Migration {
-//     fn name(&self) -> &'static str; // Changed to &'static str
-//     fn up(&self, conn: &Connection) -> Result<(), RustixError>;
-//     fn down(&self, conn: &Connection) -> Result<(), RustixError>;
-// }
-
-// pub struct MigrationManager {
-//     conn: Connection,
-//     migrations: Vec<Box<dyn Migration>>,
-// }
-
-// impl MigrationManager {
-//     pub fn new(conn: Connection) -> Self {
-//         MigrationManager {
-//             conn,
-//             migrations: Vec::new(),
-//         }
-//     }
-
-//     pub fn register(&mut self, migration: Box<dyn Migration>) {
-//         self.migrations.push(migration);
-//     }
-
-//     pub fn migrate_up(&self) -> Result<(), RustixError> {
-//         println!("Running {} migrations", self.migrations.len());
-
-//         self.conn.execute(
-//             "CREATE TABLE IF NOT EXISTS migrations (
-//                 id SERIAL PRIMARY KEY,
-//                 name VARCHAR(255) NOT NULL UNIQUE,
-//                 applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-//             )",
-//             &[],
-//         )?;
-
-//         for migration in &self.migrations {
-//             let name = migration.name();
-//             println!("Checking migration: {}", name);
-
-//             let result = self.conn.query_raw(
-//                 "SELECT name FROM migrations WHERE name = ?",
-//                 &[&name],
-//             )?;
-
-//             let applied = !result.is_empty();
-
-//             if !applied {
-//                 println!("Applying migration: {}", name);
-//                 migration.up(&self.conn)?;
-
-//                 self.conn.execute(
-//                     "INSERT INTO migrations (name) VALUES (?)",
-//                     &[&name],
-//                 )?;
-//             } else {
-//                 println!("Migration already applied: {}", name);
-//             }
-//         }
-
-//         Ok(())
-//     }
-
-//     pub fn migrate_down(&self) -> Result<(), RustixError> {
-//         println!("Rolling back migrations");
-
-//         for migration in self.migrations.iter().rev() {
-//             let name = migration.name();
-//             println!("Rolling back migration: {}", name);
-
-//             let result = self.conn.query_raw(
-//                 "SELECT name FROM migrations WHERE name = ?",
-//                 &[&name],
-//             )?;
-
-//             let applied = !result.is_empty();
-
-//             if applied {
-//                 migration.down(&self.conn)?;
-
-//                 self.conn.execute(
-//                     "DELETE FROM migrations WHERE name = ?",
-//                     &[&name],
-//                 )?;
-//             }
-//         }
-
-//         Ok(())
-//     }
-// }
\ No newline at end of file
+use crate::connection::{Connection, DatabaseType};
+use crate::error::RusticxError;
+
+/// The table recording which migrations have been applied.
+const MIGRATIONS_TABLE: &str = "migrations";
+
+/// The single-row table holding the application schema version.
+const SCHEMA_VERSION_TABLE: &str = "rusticx_schema_version";
+
+/// A reversible schema change.
+pub trait Migration {
+    /// A unique name identifying the migration; recorded once applied.
+    fn name(&self) -> &'static str;
+    /// Applies the migration.
+    fn up(&self, conn: &Connection) -> Result<(), RusticxError>;
+    /// Reverts the migration.
+    fn down(&self, conn: &Connection) -> Result<(), RusticxError>;
+}
+
+/// Applies and rolls back registered migrations, recording them in the
+/// `migrations` table.
+///
+/// When a schema version is set with `set_schema_version`, `migrate_up` also
+/// stamps it into the database so application instances can check it at
+/// startup with `Connection::assert_schema_version`.
+pub struct MigrationManager {
+    conn: Connection,
+    migrations: Vec<Box<dyn Migration>>,
+    schema_version: Option<i64>,
+}
+
+impl MigrationManager {
+    /// Creates a manager that runs migrations on `conn`.
+    pub fn new(conn: Connection) -> Self {
+        MigrationManager {
+            conn,
+            migrations: Vec::new(),
+            schema_version: None,
+        }
+    }
+
+    /// Registers a migration. Migrations are applied in registration order.
+    pub fn register(&mut self, migration: Box<dyn Migration>) {
+        self.migrations.push(migration);
+    }
+
+    /// Sets the application schema version that `migrate_up` records once all
+    /// migrations have been applied.
+    pub fn set_schema_version(&mut self, version: i64) {
+        self.schema_version = Some(version);
+    }
+
+    /// Applies every registered migration that has not been applied yet, then
+    /// stamps the schema version if one was set.
+    ///
+    /// Stamping fails with `SchemaVersionMismatch`, leaving the stored stamp
+    /// alone, when the database already carries a higher version.
+    pub fn migrate_up(&self) -> Result<(), RusticxError> {
+        let db_type = self.conn.get_db_type();
+        log::info!("Running {} migrations", self.migrations.len());
+
+        self.conn.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS {} (name VARCHAR(255) PRIMARY KEY, applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP)",
+                db_type.quote_identifier(MIGRATIONS_TABLE)
+            ),
+            &[],
+        )?;
+
+        for migration in &self.migrations {
+            let name = migration.name();
+            if self.is_applied(name)? {
+                log::info!("Migration already applied: {}", name);
+                continue;
+            }
+
+            log::info!("Applying migration: {}", name);
+            migration.up(&self.conn)?;
+            self.conn.execute(
+                &format!(
+                    "INSERT INTO {} (name) VALUES ({})",
+                    db_type.quote_identifier(MIGRATIONS_TABLE),
+                    db_type.placeholder(1)
+                ),
+                &[&name],
+            )?;
+        }
+
+        if let Some(version) = self.schema_version {
+            stamp_schema_version(&self.conn, version)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reverts every applied migration in reverse registration order and
+    /// removes the schema version stamp.
+    pub fn migrate_down(&self) -> Result<(), RusticxError> {
+        let db_type = self.conn.get_db_type();
+        log::info!("Rolling back migrations");
+
+        for migration in self.migrations.iter().rev() {
+            let name = migration.name();
+            if !self.is_applied(name)? {
+                continue;
+            }
+
+            log::info!("Rolling back migration: {}", name);
+            migration.down(&self.conn)?;
+            self.conn.execute(
+                &format!(
+                    "DELETE FROM {} WHERE name = {}",
+                    db_type.quote_identifier(MIGRATIONS_TABLE),
+                    db_type.placeholder(1)
+                ),
+                &[&name],
+            )?;
+        }
+
+        if schema_version_table_exists(&self.conn)? {
+            self.conn.execute(&format!("DELETE FROM {}", db_type.quote_identifier(SCHEMA_VERSION_TABLE)), &[])?;
+        }
+
+        Ok(())
+    }
+
+    fn is_applied(&self, name: &'static str) -> Result<bool, RusticxError> {
+        let db_type = self.conn.get_db_type();
+        let rows: Vec<serde_json::Map<String, serde_json::Value>> = self.conn.query_raw(
+            &format!(
+                "SELECT name FROM {} WHERE name = {}",
+                db_type.quote_identifier(MIGRATIONS_TABLE),
+                db_type.placeholder(1)
+            ),
+            &[&name],
+        )?;
+        Ok(!rows.is_empty())
+    }
+}
+
+/// Records `version` as the database's schema version.
+///
+/// The stamp only moves forward: a stored version higher than `version`,
+/// left by a newer release, is kept and reported as `SchemaVersionMismatch`.
+/// The check and the upsert of the single row run in one transaction.
+fn stamp_schema_version(conn: &Connection, version: i64) -> Result<(), RusticxError> {
+    let db_type = conn.get_db_type();
+    let table = db_type.quote_identifier(SCHEMA_VERSION_TABLE);
+
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} (id INTEGER PRIMARY KEY, version BIGINT NOT NULL, stamped_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP)",
+            table
+        ),
+        &[],
+    )?;
+
+    let tx = conn.begin()?;
+    let locking = match db_type {
+        DatabaseType::SQLite => "",
+        _ => " FOR UPDATE",
+    };
+    if let Some(stored) = stored_schema_version(&tx, locking)?.filter(|stored| *stored > version) {
+        return Err(newer_schema_version(stored, version));
+    }
+
+    // The guard keeps a newer stamp written by a concurrent migrator after the read above
+    let upsert = match db_type {
+        DatabaseType::MySQL => format!(
+            "INSERT INTO {0} (id, version) VALUES (1, ?) ON DUPLICATE KEY UPDATE \
+             stamped_at = IF(version <= VALUES(version), CURRENT_TIMESTAMP, stamped_at), \
+             version = GREATEST(version, VALUES(version))",
+            table
+        ),
+        _ => format!(
+            "INSERT INTO {0} (id, version) VALUES (1, {1}) ON CONFLICT (id) DO UPDATE \
+             SET version = excluded.version, stamped_at = CURRENT_TIMESTAMP WHERE {0}.version <= excluded.version",
+            table,
+            db_type.placeholder(1)
+        ),
+    };
+    tx.execute(&upsert, &[&version])?;
+
+    if let Some(stored) = stored_schema_version(&tx, "")?.filter(|stored| *stored > version) {
+        return Err(newer_schema_version(stored, version));
+    }
+    tx.commit()
+}
+
+fn newer_schema_version(stored: i64, version: i64) -> RusticxError {
+    RusticxError::SchemaVersionMismatch(format!(
+        "database is stamped with schema version {}, newer than version {}; refusing to stamp it back",
+        stored, version
+    ))
+}
+
+/// Reads the stamped version row, appending `locking` to the query.
+fn stored_schema_version(conn: &Connection, locking: &str) -> Result<Option<i64>, RusticxError> {
+    let rows: Vec<serde_json::Map<String, serde_json::Value>> = conn.query_raw(
+        &format!(
+            "SELECT version FROM {} WHERE id = 1{}",
+            conn.get_db_type().quote_identifier(SCHEMA_VERSION_TABLE),
+            locking
+        ),
+        &[],
+    )?;
+    Ok(rows.first().and_then(|row| row.get("version")).and_then(|v| v.as_i64()))
+}
+
+/// Returns whether the schema version table exists, without creating it.
+fn schema_version_table_exists(conn: &Connection) -> Result<bool, RusticxError> {
+    let sql = match conn.get_db_type() {
+        DatabaseType::PostgreSQL => "SELECT 1 AS present FROM pg_catalog.pg_tables WHERE tablename = $1 AND schemaname = ANY(current_schemas(false))",
+        DatabaseType::MySQL => "SELECT 1 AS present FROM information_schema.tables WHERE table_schema = DATABASE() AND table_name = ?",
+        DatabaseType::SQLite => "SELECT 1 AS present FROM sqlite_master WHERE type = 'table' AND name = ?",
+    };
+    let rows: Vec<serde_json::Map<String, serde_json::Value>> = conn.query_raw(sql, &[&SCHEMA_VERSION_TABLE])?;
+    Ok(!rows.is_empty())
+}
+
+/// Reads the schema version stamped by `MigrationManager::migrate_up`.
+pub(crate) fn read_schema_version(conn: &Connection) -> Result<Option<i64>, RusticxError> {
+    if !schema_version_table_exists(conn)? {
+        return Ok(None);
+    }
+
+    stored_schema_version(conn, "")
+}
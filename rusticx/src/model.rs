@@ -1,7 +1,19 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
 use serde::{Deserialize, Serialize};
 use crate::connection::{Connection, DatabaseType};
-use crate::error::RusticxError;
+use crate::error::{diagnose_param_error, RusticxError};
+use crate::events::{self, ModelEvent, ModelEventKind};
+use crate::mask::Mask;
+use crate::projection::projection_columns;
+use crate::predicate::Predicate;
+use crate::query_builder::SqlParam;
+use crate::ordering::OrderBy;
+use crate::pagination::{decode_cursor, encode_cursor, CursorPage, Page};
+use crate::sql_types::SqlType;
+use crate::stream::RowIter;
+#[cfg(not(feature = "postgres"))]
+use crate::transaction_manager::ParamValue;
 
 // Required for find_by method using Any downcasting
 use std::any::Any;
@@ -11,12 +23,114 @@ use std::any::Any;
 #[cfg(feature = "postgres")]
 pub use postgres::types::ToSql;
 
-// Define a placeholder ToSql trait if postgres feature is not enabled.
-// Code requiring `dyn ToSql` will compile but effectively only work
-// when the postgres feature is enabled, due to the design of ToSqlConvert
-// and the expected parameter types in Connection.
+// Without the postgres feature, parameters are bound through this trait
+// instead, which hands each value to MySQL and SQLite in the form they bind.
+#[cfg(not(feature = "postgres"))]
+pub trait ToSql: Debug {
+    #[doc(hidden)]
+    fn to_param(&self) -> ParamValue;
+}
+
+#[cfg(not(feature = "postgres"))]
+macro_rules! bind_as {
+    ($variant:ident: $($ty:ty),+) => {
+        $(impl ToSql for $ty {
+            fn to_param(&self) -> ParamValue {
+                ParamValue::$variant((*self).into())
+            }
+        })+
+    };
+}
+
+#[cfg(not(feature = "postgres"))]
+bind_as!(Integer: bool, i8, i16, i32, i64, u32);
+#[cfg(not(feature = "postgres"))]
+bind_as!(Real: f32, f64);
+
+#[cfg(not(feature = "postgres"))]
+impl<T: ToSql + ?Sized> ToSql for &T {
+    fn to_param(&self) -> ParamValue {
+        (**self).to_param()
+    }
+}
+
+#[cfg(not(feature = "postgres"))]
+impl<T: ToSql> ToSql for Option<T> {
+    fn to_param(&self) -> ParamValue {
+        self.as_ref().map_or(ParamValue::Null, ToSql::to_param)
+    }
+}
+
+#[cfg(not(feature = "postgres"))]
+impl ToSql for str {
+    fn to_param(&self) -> ParamValue {
+        ParamValue::Text(self.to_string())
+    }
+}
+
+#[cfg(not(feature = "postgres"))]
+impl ToSql for String {
+    fn to_param(&self) -> ParamValue {
+        ParamValue::Text(self.clone())
+    }
+}
+
+#[cfg(not(feature = "postgres"))]
+impl ToSql for [u8] {
+    fn to_param(&self) -> ParamValue {
+        ParamValue::Blob(self.to_vec())
+    }
+}
+
+#[cfg(not(feature = "postgres"))]
+impl ToSql for Vec<u8> {
+    fn to_param(&self) -> ParamValue {
+        ParamValue::Blob(self.clone())
+    }
+}
+
+// Dates, times and JSON are bound as text in the form serde writes them
+#[cfg(not(feature = "postgres"))]
+impl ToSql for chrono::NaiveDateTime {
+    fn to_param(&self) -> ParamValue {
+        ParamValue::Text(self.format("%Y-%m-%dT%H:%M:%S%.f").to_string())
+    }
+}
+
+#[cfg(not(feature = "postgres"))]
+impl ToSql for chrono::DateTime<chrono::Utc> {
+    fn to_param(&self) -> ParamValue {
+        ParamValue::Text(self.to_rfc3339())
+    }
+}
+
 #[cfg(not(feature = "postgres"))]
-pub trait ToSql {}
+impl ToSql for chrono::NaiveDate {
+    fn to_param(&self) -> ParamValue {
+        ParamValue::Text(self.format("%Y-%m-%d").to_string())
+    }
+}
+
+#[cfg(not(feature = "postgres"))]
+impl ToSql for chrono::NaiveTime {
+    fn to_param(&self) -> ParamValue {
+        ParamValue::Text(self.format("%H:%M:%S%.f").to_string())
+    }
+}
+
+#[cfg(not(feature = "postgres"))]
+impl ToSql for serde_json::Value {
+    fn to_param(&self) -> ParamValue {
+        ParamValue::Text(self.to_string())
+    }
+}
+
+#[cfg(all(not(feature = "postgres"), feature = "uuid"))]
+impl ToSql for uuid::Uuid {
+    fn to_param(&self) -> ParamValue {
+        ParamValue::Text(self.to_string())
+    }
+}
 
 
 /// A trait for database models providing common CRUD operations.
@@ -24,12 +138,24 @@ pub trait ToSql {}
 /// Implement this trait for your structs to give them basic database
 /// persistence capabilities. Requires models to be `Debug`, `Serialize`,
 /// and `Deserialize` for handling data conversion.
-pub trait SQLModel: Sized + Debug + Serialize + for<'de> Deserialize<'de> {
+///
+/// Successful inserts, updates and deletes are published to subscribers
+/// registered through `rusticx::events::subscribe`.
+pub trait SQLModel: Sized + Debug + Serialize + for<'de> Deserialize<'de> + 'static {
     /// Returns the name of the database table for this model.
     ///
     /// This should be a static string or derived from the model name.
     fn table_name() -> String;
 
+    /// Returns the name of the database this model lives in, if it is not the
+    /// default one.
+    ///
+    /// Used by `ConnectionManager::for_model` to route the model to the right
+    /// connection. Set with `#[model(database = "...")]` when deriving.
+    fn database_name() -> Option<&'static str> {
+        None
+    }
+
     /// Returns the name of the primary key field.
     ///
     /// This field is used for `find_by_id`, `update`, and `delete`.
@@ -53,6 +179,105 @@ pub trait SQLModel: Sized + Debug + Serialize + for<'de> Deserialize<'de> {
     /// This method is crucial for schema management or initial setup.
     fn create_table_sql(db_type: &DatabaseType) -> String;
 
+    /// Returns the column holding the record's last-modified timestamp, if any.
+    ///
+    /// Set with `#[model(updated_at)]` when deriving; used by `touch`.
+    fn updated_at_field() -> Option<&'static str> {
+        None
+    }
+
+    /// Returns the column holding the record's expiry time, if any.
+    ///
+    /// Set with `#[model(expires_at)]` when deriving. Rows whose expiry time has
+    /// passed are excluded by the finders and removed by
+    /// `Connection::purge_expired`.
+    fn expires_at_field() -> Option<&'static str> {
+        None
+    }
+
+    /// Returns the column recording when the record was soft-deleted, if any.
+    ///
+    /// Set with `#[model(soft_delete)]` when deriving. `delete` and
+    /// `delete_by_id` then stamp the column instead of removing the row, and the
+    /// finders skip rows where it is set.
+    fn soft_delete_field() -> Option<&'static str> {
+        None
+    }
+
+    /// Returns a SQL condition every generated `SELECT` of this model applies,
+    /// if any (e.g. `"published = TRUE"`).
+    ///
+    /// Set with `#[model(default_scope = "...")]` when deriving. The condition
+    /// is inserted verbatim, so quote identifiers that need it. Run code under
+    /// `unscoped` to bypass it.
+    fn default_scope() -> Option<&'static str> {
+        None
+    }
+
+    /// Runs `f` with this model's `default_scope` disabled on the current
+    /// thread, so finders called inside it see every row.
+    ///
+    /// ```ignore
+    /// let drafts_included = Article::unscoped(|| Article::find_all(&conn))?;
+    /// ```
+    ///
+    /// Expiry and soft-delete filtering still apply; use
+    /// `find_all_with_deleted` for the latter.
+    fn unscoped<R>(f: impl FnOnce() -> R) -> R {
+        let _guard = UnscopedGuard::enter(std::any::TypeId::of::<Self>());
+        f()
+    }
+
+    /// Returns the column used for optimistic locking, if any.
+    ///
+    /// Set with `#[model(version)]` when deriving; checked and incremented by
+    /// `update`.
+    fn version_field() -> Option<&'static str> {
+        None
+    }
+
+    /// Increments the instance's `#[model(version)]` value, once `update`
+    /// has incremented the stored one. Generated with `version_field`.
+    fn increment_version(&mut self) {}
+
+    /// Returns the columns anonymized by `export_masked` and how each is masked.
+    ///
+    /// Set with `#[model(mask = "email")]` or `#[model(mask = "hash")]` when
+    /// deriving.
+    fn masked_fields() -> Vec<(&'static str, Mask)> {
+        Vec::new()
+    }
+
+    /// Returns the columns given a secondary index.
+    ///
+    /// Set with `#[model(index)]` when deriving.
+    fn indexed_fields() -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// Returns the statements creating the indexes of `indexed_fields`, each
+    /// named `idx_<table>_<column>`.
+    ///
+    /// MySQL has no `CREATE INDEX IF NOT EXISTS`, so its indexes are declared
+    /// inside `create_table_sql` and none are returned for it.
+    fn create_index_sql(db_type: &DatabaseType) -> Vec<String> {
+        if *db_type == DatabaseType::MySQL {
+            return Vec::new();
+        }
+        let table = Self::table_name();
+        Self::indexed_fields()
+            .into_iter()
+            .map(|column| {
+                format!(
+                    "CREATE INDEX IF NOT EXISTS {} ON {} ({})",
+                    db_type.quote_identifier(&format!("idx_{}_{}", table, column)),
+                    db_type.quote_identifier(&table),
+                    db_type.quote_identifier(column)
+                )
+            })
+            .collect()
+    }
+
     /// Returns a list of all field names in the model,
     /// typically corresponding to database columns.
     ///
@@ -60,6 +285,16 @@ pub trait SQLModel: Sized + Debug + Serialize + for<'de> Deserialize<'de> {
     /// `to_sql_field_values`.
     fn field_names() -> Vec<&'static str>;
 
+    /// Returns the declared SQL type of every field, in the same order as
+    /// `field_names`.
+    ///
+    /// Used to explain parameter binding failures in terms of the model's
+    /// columns. Defaults to an empty list, in which case driver errors are
+    /// reported as-is.
+    fn field_sql_types() -> Vec<SqlType> {
+        Vec::new()
+    }
+
     /// Returns a vector of boxed values for all fields.
     ///
     /// Each value must be boxed (`Box<dyn ToSqlConvert>`) and implement
@@ -75,23 +310,39 @@ pub trait SQLModel: Sized + Debug + Serialize + for<'de> Deserialize<'de> {
     /// `query_raw` method doesn't directly deserialize into the model type `Self`.
     fn from_row(row: &serde_json::Value) -> Result<Self, RusticxError>;
 
+    /// Converts a row given as column values in `field_names` order into a
+    /// model instance.
+    ///
+    /// Used by `RowMapping::ByPosition`. The default pairs the values with
+    /// `field_names` and defers to `from_row`; the derive reads each field
+    /// straight from its index instead.
+    fn from_row_positional(values: &[serde_json::Value]) -> Result<Self, RusticxError> {
+        let row: serde_json::Map<String, serde_json::Value> = Self::field_names()
+            .into_iter()
+            .map(str::to_string)
+            .zip(values.iter().cloned())
+            .collect();
+        Self::from_row(&serde_json::Value::Object(row))
+    }
+
     /// Inserts a new record into the database table based on the model instance.
     ///
     /// If the model instance's primary key value is `None`, it assumes the
-    /// database handles auto-increment and attempts to retrieve the last
-    /// inserted ID after the insert, setting it on the model instance.
+    /// database handles auto-increment and reads the generated ID back on the
+    /// session that ran the insert (never a replica), setting it on the model
+    /// instance.
     /// If the primary key value is `Some`, it includes the primary key
     /// in the INSERT statement.
     fn insert(&mut self, conn: &Connection) -> Result<(), RusticxError> {
         let fields = Self::field_names();
         let primary_key_field = Self::primary_key_field();
-        let field_values = self.to_sql_field_values();
+        let field_values = field_values(self)?;
 
         // Find the primary key field index and check if PK should be included in INSERT
         let pk_idx = fields.iter().position(|f| *f == primary_key_field);
         let include_pk = if let Some(idx) = pk_idx {
             // Include PK if the corresponding value is NOT null (user provided it)
-            !field_values.get(idx).map_or(true, |v| v.is_null()) // Handle case where pk_idx is found but field_values is shorter
+            !field_values.get(idx).is_none_or(|v| v.is_null()) // Handle case where pk_idx is found but field_values is shorter
         } else {
             // No PK field found in fields, include all (which is fields itself)
             true
@@ -99,7 +350,7 @@ pub trait SQLModel: Sized + Debug + Serialize + for<'de> Deserialize<'de> {
 
         // Filter fields and values based on whether to include PK
         let (insert_fields, insert_values): (Vec<&'static str>, Vec<Box<dyn ToSqlConvert>>) = fields.into_iter()
-            .zip(field_values.into_iter())
+            .zip(field_values)
             .filter(|(field_name, _)| include_pk || *field_name != primary_key_field)
             .unzip();
 
@@ -108,66 +359,44 @@ pub trait SQLModel: Sized + Debug + Serialize + for<'de> Deserialize<'de> {
             return Err(RusticxError::QueryError("No fields to insert".to_string()));
         }
 
-        // Generate SQL placeholders based on the database type
-        let placeholders: Vec<String> = match conn.get_db_type() {
-            DatabaseType::PostgreSQL => (1..=insert_fields.len()).map(|i| format!("${}", i)).collect(),
-            _ => (0..insert_fields.len()).map(|_| "?".to_string()).collect()
-        };
+        let db_type = conn.get_db_type();
+
+        // Bind each value, writing NULL literally for unset optional fields
+        let mut params: Vec<&(dyn ToSql + Sync + 'static)> = Vec::new();
+        let mut bound_fields: Vec<&str> = Vec::new();
+        let mut placeholders: Vec<String> = Vec::new();
+        for (field_name, value) in insert_fields.iter().zip(&insert_values) {
+            match value.as_ref_postgres() {
+                Some(param) => {
+                    params.push(param);
+                    bound_fields.push(field_name);
+                    placeholders.push(db_type.placeholder(params.len()));
+                }
+                None => placeholders.push("NULL".to_string()),
+            }
+        }
 
+        let columns: Vec<String> = insert_fields.iter().map(|f| db_type.quote_identifier(f)).collect();
         let sql = format!(
             "INSERT INTO {} ({}) VALUES ({})",
-            Self::table_name().to_lowercase(),
-            insert_fields.join(", "),
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            columns.join(", "),
             placeholders.join(", ")
         );
 
-        // Prepare parameters as references to dyn ToSql + Sync + 'static
-        let params: Vec<&(dyn ToSql + Sync + 'static)> = insert_values.iter()
-             .filter_map(|v| v.as_ref_postgres()) // Use filter_map to handle Option values
-            .collect();
+        // Execute the query, explaining type mismatches in terms of the model's fields
+        let diagnose = |e| diagnose_param_error(e, &bound_fields, &Self::field_names(), &Self::field_sql_types());
 
-         // Ensure the number of parameters matches the number of placeholders
-        if params.len() != insert_fields.len() {
-            // This indicates an issue in ToSqlConvert implementations not returning Some(_)
-             return Err(RusticxError::QueryError(format!(
-                "Parameter count mismatch: expected {} but got {}. Check ToSqlConvert implementations.",
-                insert_fields.len(),
-                params.len()
-            )));
+        // If PK was not included in the insert, read the generated ID back on
+        // the session that ran it and set it
+        if !include_pk && pk_idx.is_some() {
+            let id = conn.insert_returning_id(&sql, &primary_key_field, &params).map_err(diagnose)?;
+            self.set_primary_key(id as i32);
+        } else {
+            conn.execute(&sql, &params).map_err(diagnose)?;
         }
 
-
-        // Execute the query
-        conn.execute(&sql, &params)?;
-
-        // If PK was not included in the insert, get the last inserted ID and set it
-        if !include_pk {
-            if let Some(_) = pk_idx { // Check if PK field was defined at all
-                let last_id_sql = match conn.get_db_type() {
-                    DatabaseType::PostgreSQL => "SELECT lastval() as id".to_string(),
-                    DatabaseType::MySQL => "SELECT LAST_INSERT_ID() as id".to_string(),
-                    DatabaseType::SQLite => "SELECT last_insert_rowid() as id".to_string(),
-                };
-
-                #[derive(Deserialize, Debug)]
-                struct IdRow {
-                    id: i64,
-                }
-
-                let ids: Vec<IdRow> = conn.query_raw(&last_id_sql, &[])?;
-                if let Some(id_row) = ids.first() {
-                    self.set_primary_key(id_row.id as i32);
-                } else {
-                    // This should not happen if the insert was successful and table has auto-increment
-                    return Err(RusticxError::QueryError("Failed to retrieve last inserted ID".to_string()));
-                }
-            } else {
-                 // This case implies PK field was defined but not found in field_names,
-                 // or there's no auto-increment PK setup handled.
-                 // Depending on the model structure, this might be an error.
-                 // For now, we assume if !include_pk, it's an auto-increment scenario.
-            }
-        }
+        events::publish(ModelEvent { kind: ModelEventKind::Inserted, id: self.primary_key_value(), model: Some(&*self) });
 
         Ok(())
     }
@@ -175,78 +404,105 @@ pub trait SQLModel: Sized + Debug + Serialize + for<'de> Deserialize<'de> {
     /// Updates an existing record in the database table based on the model instance's primary key.
     ///
     /// Requires the model instance to have a primary key value set (`primary_key_value()`).
-    fn update(&self, conn: &Connection) -> Result<(), RusticxError> {
+    /// Returns the number of rows affected.
+    ///
+    /// If the model has a `#[model(version)]` column, the row is only updated
+    /// while its stored version still equals the instance's, and the version is
+    /// incremented. Returns `Err(RusticxError::Conflict)` when no row matched
+    /// because another writer changed (or deleted) the record first; reload
+    /// the record before updating it again. On success the instance's version
+    /// is incremented too, so it can be updated again.
+    fn update(&mut self, conn: &Connection) -> Result<u64, RusticxError> {
         let id = self.primary_key_value().ok_or_else(|| {
             RusticxError::QueryError("Cannot update a model without a primary key value".to_string())
         })?;
 
         let fields = Self::field_names();
         let primary_key_field = Self::primary_key_field();
-        let field_values = self.to_sql_field_values();
+        let version_field = Self::version_field();
+        let field_values = field_values(self)?;
 
         // Collect fields and values, excluding the primary key field
-        let update_fields_values: Vec<(&'static str, Box<dyn ToSqlConvert>)> = fields.into_iter()
-            .zip(field_values.into_iter())
+        let (update_fields_values, version_value): (Vec<_>, Vec<_>) = fields.into_iter()
+            .zip(field_values)
             .filter(|(field_name, _)| *field_name != primary_key_field)
-            .collect();
+            .partition(|(field_name, _)| Some(*field_name) != version_field);
 
          // Skip update if there are no non-PK fields to update
-        if update_fields_values.is_empty() {
-            return Ok(()); // No fields to update, return Ok
+        if update_fields_values.is_empty() && version_field.is_none() {
+            return Ok(0); // No fields to update
         }
 
-
-        // Generate SET clause for the UPDATE statement
-        let field_params: Vec<String> = update_fields_values.iter()
-            .enumerate()
-            .map(|(i, (field_name, _))| {
-                match conn.get_db_type() {
-                    // PostgreSQL parameters are 1-indexed
-                    DatabaseType::PostgreSQL => format!("{} = ${}", field_name, i + 1),
-                    // Other databases use ?
-                    _ => format!("{} = ?", field_name)
+        let db_type = conn.get_db_type();
+
+        // Parameters and the columns they belong to, in placeholder order
+        let mut params: Vec<&(dyn ToSql + Sync + 'static)> = Vec::new();
+        let mut bound_columns: Vec<&str> = Vec::new();
+
+        // Generate SET clause for the UPDATE statement, writing NULL literally for
+        // unset optional fields; the version column is bumped in SQL
+        let mut field_params: Vec<String> = Vec::new();
+        for (field_name, value) in &update_fields_values {
+            let column = db_type.quote_identifier(field_name);
+            match value.as_ref_postgres() {
+                Some(param) => {
+                    params.push(param);
+                    bound_columns.push(field_name);
+                    field_params.push(format!("{} = {}", column, db_type.placeholder(params.len())));
                 }
-            })
-            .collect();
+                None => field_params.push(format!("{} = NULL", column)),
+            }
+        }
+        if let Some(version) = version_field {
+            let column = db_type.quote_identifier(version);
+            field_params.push(format!("{} = {} + 1", column, column));
+        }
 
-        // Generate WHERE clause using the primary key.
-        // The primary key parameter index depends on the number of SET parameters.
-        let where_clause = match conn.get_db_type() {
-            DatabaseType::PostgreSQL => format!("{} = ${}", primary_key_field, field_params.len() + 1),
-            _ => format!("{} = ?", primary_key_field)
-        };
+        // Generate WHERE clause using the primary key (and the expected version)
+        let id_param = &id as &(dyn ToSql + Sync + 'static); // Cast &i32 to the required trait object
+        params.push(id_param);
+        bound_columns.push(&primary_key_field);
+        let mut where_clause = format!(
+            "{} = {}",
+            db_type.quote_identifier(&primary_key_field),
+            db_type.placeholder(params.len())
+        );
+        if let Some((version, value)) = version_value.first() {
+            let column = db_type.quote_identifier(version);
+            match value.as_ref_postgres() {
+                Some(param) => {
+                    params.push(param);
+                    bound_columns.push(version);
+                    where_clause.push_str(&format!(" AND {} = {}", column, db_type.placeholder(params.len())));
+                }
+                None => where_clause.push_str(&format!(" AND {} IS NULL", column)),
+            }
+        }
 
         let sql = format!(
             "UPDATE {} SET {} WHERE {}",
-            Self::table_name(),
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
             field_params.join(", "),
             where_clause
         );
 
-        // Prepare parameters: values for SET clause followed by the primary key value
-        let mut params: Vec<&(dyn ToSql + Sync + 'static)> = update_fields_values.iter()
-            .filter_map(|(_, value)| value.as_ref_postgres()) // Use filter_map for values
-            .collect();
-
-        // Add the primary key as the last parameter for the WHERE clause
-        // Assumes i32 implements the necessary ToSql, Sync, and 'static bounds via a ToSqlConvert implementation.
-        // An explicit cast is used for clarity and safety, assuming `&id` can be cast to `dyn ToSql`.
-         let id_param = &id as &(dyn ToSql + Sync + 'static); // Cast &i32 to the required trait object
-        params.push(id_param);
+        let updated = conn.execute(&sql, &params)
+            .map_err(|e| diagnose_param_error(e, &bound_columns, &Self::field_names(), &Self::field_sql_types()))?;
 
-         // Ensure parameter count matches generated placeholders + PK
-         if params.len() != update_fields_values.len() + 1 {
-             return Err(RusticxError::QueryError(format!(
-                "Parameter count mismatch for update: expected {} but got {}. Check ToSqlConvert implementations.",
-                update_fields_values.len() + 1,
-                params.len()
+        if updated == 0 && version_field.is_some() {
+            return Err(RusticxError::Conflict(format!(
+                "{} record {} was changed or deleted since it was loaded (stale version)",
+                Self::table_name(),
+                id
             )));
-         }
-
+        }
 
-        conn.execute(&sql, &params)?;
+        if version_field.is_some() {
+            self.increment_version();
+        }
+        events::publish(ModelEvent { kind: ModelEventKind::Updated, id: Some(id), model: Some(self) });
 
-        Ok(())
+        Ok(updated)
     }
 
     /// Finds a single record by its primary key.
@@ -259,123 +515,791 @@ pub trait SQLModel: Sized + Debug + Serialize + for<'de> Deserialize<'de> {
         // Use database-specific placeholder syntax
         #[cfg(feature = "postgres")]
         let sql = format!(
-            "SELECT * FROM {} WHERE {} = $1 LIMIT 1", // Added LIMIT 1 for efficiency
-            Self::table_name(),
-            primary_key_field
+            "SELECT * FROM {} WHERE {} = $1{} LIMIT 1", // Added LIMIT 1 for efficiency
+            conn.get_db_type().quote_identifier(&conn.table_path(&Self::table_name())),
+            conn.get_db_type().quote_identifier(&primary_key_field),
+            live_and::<Self>(conn.get_db_type())
+        );
+
+        #[cfg(not(feature = "postgres"))]
+        let sql = format!(
+            "SELECT * FROM {} WHERE {} = ?{} LIMIT 1", // Added LIMIT 1 for efficiency
+            conn.get_db_type().quote_identifier(&conn.table_path(&Self::table_name())),
+            conn.get_db_type().quote_identifier(&primary_key_field),
+            live_and::<Self>(conn.get_db_type())
+        );
+
+        // Prepare parameters using dyn ToSql. &id needs to be cast to the trait object.
+        let id_param = &id as &(dyn ToSql + Sync + 'static); // Cast &i32 to the required trait object
+        let params: &[&(dyn ToSql + Sync + 'static)] = &[id_param];
+
+        query_models::<Self>(conn, &sql, params)?
+            .pop() // Use pop to get the single model
+            .ok_or_else(|| RusticxError::NotFound(format!("{} with id {} not found", Self::table_name(), id)))
+    }
+
+    /// Finds a single record by its primary key and locks it until the
+    /// surrounding transaction ends.
+    ///
+    /// Appends `FOR UPDATE` on PostgreSQL and MySQL, so concurrent writers (and
+    /// other `find_by_id_for_update` callers) wait instead of overwriting a
+    /// read-modify-write in progress. The lock only lasts as long as the
+    /// transaction, so call it on the `Transaction` from `Connection::begin`:
+    ///
+    /// ```ignore
+    /// let tx = conn.begin()?;
+    /// let mut item = StockItem::find_by_id_for_update(&tx, id)?;
+    /// item.quantity -= 1;
+    /// item.update(&tx)?;
+    /// tx.commit()?;
+    /// ```
+    ///
+    /// SQLite has no row locks, so there this is a plain `find_by_id` that
+    /// writes nothing. Begin the transaction with `TxOptions::immediate` to
+    /// take the database's write lock up front instead:
+    ///
+    /// ```ignore
+    /// let tx = conn.begin_with(TxOptions { immediate: true, ..TxOptions::default() })?;
+    /// ```
+    ///
+    /// Returns `Err(RusticxError::NotFound)` if no record is found.
+    fn find_by_id_for_update(conn: &Connection, id: i32) -> Result<Self, RusticxError> {
+        let db_type = conn.get_db_type();
+        let locking = match db_type {
+            DatabaseType::SQLite => "",
+            _ => " FOR UPDATE",
+        };
+        let sql = format!(
+            "SELECT * FROM {} WHERE {} = {}{} LIMIT 1{}",
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            db_type.quote_identifier(&Self::primary_key_field()),
+            db_type.placeholder(1),
+            live_and::<Self>(db_type),
+            locking
+        );
+        let id_param = &id as &(dyn ToSql + Sync + 'static);
+
+        Self::find_with_sql(conn, &sql, &[id_param])?
+            .pop()
+            .ok_or_else(|| RusticxError::NotFound(format!("{} with id {} not found", Self::table_name(), id)))
+    }
+
+    /// Finds all records in the table.
+    ///
+    /// Returns a vector of all model instances found in the table.
+    fn find_all(conn: &Connection) -> Result<Vec<Self>, RusticxError> {
+        let sql = format!(
+            "SELECT * FROM {}{}",
+            conn.get_db_type().quote_identifier(&conn.table_path(&Self::table_name())),
+            live_where::<Self>(conn.get_db_type())
+        );
+        // No parameters for SELECT all
+        query_models::<Self>(conn, &sql, &[])
+    }
+
+    /// Streams all records in the table one at a time.
+    ///
+    /// Unlike `find_all`, rows are deserialized as the returned iterator is
+    /// advanced instead of being collected into a `Vec`, so tables far larger
+    /// than memory can be exported. Do not run other statements on `conn` until
+    /// the iterator is exhausted or dropped (see `RowIter`).
+    fn stream_all(conn: &Connection) -> Result<RowIter<Self>, RusticxError> {
+        let sql = format!(
+            "SELECT * FROM {}{}",
+            conn.get_db_type().quote_identifier(&conn.table_path(&Self::table_name())),
+            live_where::<Self>(conn.get_db_type())
+        );
+        conn.query_stream(&sql, &[])
+    }
+
+    /// Writes every row of the table to `writer` as JSON Lines (one object per
+    /// row, keyed by column), with `#[model(mask = "...")]` columns anonymized.
+    ///
+    /// Masked values are HMAC-SHA256 digests under `key`, which should be a
+    /// secret kept out of the export: anyone holding it can confirm guesses
+    /// about masked values. Reuse the same key across tables to keep masked
+    /// columns joinable.
+    ///
+    /// Rows are streamed rather than loaded at once, so production-sized
+    /// tables can be exported for staging. Returns the number of rows written.
+    /// Write failures are reported as `RusticxError::SerializationError`.
+    fn export_masked<W: std::io::Write>(conn: &Connection, key: &[u8], mut writer: W) -> Result<u64, RusticxError> {
+        let db_type = conn.get_db_type();
+        let sql = format!(
+            "SELECT * FROM {} ORDER BY {}",
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            db_type.quote_identifier(&Self::primary_key_field())
+        );
+        let masks = Self::masked_fields();
+
+        let mut exported = 0;
+        for row in conn.query_stream::<serde_json::Map<String, serde_json::Value>>(&sql, &[])? {
+            let mut row = row?;
+            for (column, mask) in &masks {
+                if let Some(value) = row.get_mut(*column) {
+                    *value = mask.apply(key, value);
+                }
+            }
+            serde_json::to_writer(&mut writer, &row)
+                .map_err(|e| RusticxError::SerializationError(format!("Failed to write export: {}", e)))?;
+            writer.write_all(b"\n")
+                .map_err(|e| RusticxError::SerializationError(format!("Failed to write export: {}", e)))?;
+            exported += 1;
+        }
+        writer.flush()
+            .map_err(|e| RusticxError::SerializationError(format!("Failed to write export: {}", e)))?;
+
+        Ok(exported)
+    }
+
+    /// Writes every row of the table to `writer` as batched `INSERT`
+    /// statements that `load_sql` can replay on any supported backend.
+    ///
+    /// Soft-deleted and expired rows are included and primary keys are kept,
+    /// so a dump from SQLite can seed PostgreSQL or MySQL as-is. Column values
+    /// are rendered from the model's declared SQL types (booleans as
+    /// `TRUE`/`FALSE`, binary columns as hex literals). Returns the number of
+    /// rows written.
+    fn dump_sql<W: std::io::Write>(conn: &Connection, writer: W) -> Result<u64, RusticxError> {
+        crate::dump::dump::<Self, W>(conn, writer)
+    }
+
+    /// Replays a dump written by `dump_sql` into this model's table on `conn`.
+    ///
+    /// Statements are adapted to the connection's backend before running.
+    /// Anything other than an `INSERT` into this table whose values are
+    /// plain literals (no subqueries, function calls or operators) is
+    /// rejected with `RusticxError::ValidationError`, so a dump cannot run
+    /// arbitrary SQL. On
+    /// PostgreSQL the id sequence is advanced past the loaded rows. Statements
+    /// run one by one; wrap the call in a transaction for all-or-nothing loads.
+    /// Returns the number of rows inserted.
+    fn load_sql<R: std::io::BufRead>(conn: &Connection, reader: R) -> Result<u64, RusticxError> {
+        crate::dump::load::<Self, R>(conn, reader)
+    }
+
+    /// Finds all records sorted by `order_by`, optionally limited and offset.
+    ///
+    /// `order_by` must be one of the model's columns (see `field_names`); it is
+    /// validated rather than interpolated blindly. `limit` and `offset` are bound
+    /// as parameters.
+    ///
+    /// Returns `Err(RusticxError::InvalidColumn)` if `order_by` is not a column of the model.
+    fn find_all_ordered(
+        conn: &Connection,
+        order_by: &str,
+        ascending: bool,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<Vec<Self>, RusticxError> {
+        if !Self::field_names().contains(&order_by) {
+            return Err(RusticxError::InvalidColumn(format!(
+                "Cannot order by {}: not a column of {}",
+                order_by,
+                Self::table_name()
+            )));
+        }
+
+        let db_type = conn.get_db_type();
+        let mut sql = format!(
+            "SELECT * FROM {}{} ORDER BY {} {}",
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            live_where::<Self>(db_type),
+            db_type.quote_identifier(order_by),
+            if ascending { "ASC" } else { "DESC" }
+        );
+        let mut params: Vec<&(dyn ToSql + Sync + 'static)> = Vec::new();
+
+        match (&limit, &offset) {
+            (Some(limit), _) => {
+                params.push(limit);
+                sql.push_str(&format!(" LIMIT {}", db_type.placeholder(params.len())));
+            }
+            // MySQL and SQLite only accept OFFSET after a LIMIT
+            (None, Some(_)) => match db_type {
+                DatabaseType::MySQL => sql.push_str(" LIMIT 18446744073709551615"),
+                DatabaseType::SQLite => sql.push_str(" LIMIT -1"),
+                DatabaseType::PostgreSQL => {}
+            },
+            (None, None) => {}
+        }
+        if let Some(offset) = &offset {
+            params.push(offset);
+            sql.push_str(&format!(" OFFSET {}", db_type.placeholder(params.len())));
+        }
+
+        Self::find_with_sql(conn, &sql, &params)
+    }
+
+    /// Finds all records, selecting only the columns of the projection `D`.
+    ///
+    /// `D` is typically a smaller DTO struct (e.g. `UserSummary { id, name }`);
+    /// its field names (after `#[serde(rename)]`) are used as the column list,
+    /// so only those columns are fetched and deserialized.
+    ///
+    /// Returns `Err(RusticxError::InvalidColumn)` if `D` is not a plain struct or
+    /// names a field that is not a column of this model.
+    fn find_all_as<D>(conn: &Connection) -> Result<Vec<D>, RusticxError>
+    where
+        D: for<'de> Deserialize<'de> + Debug,
+    {
+        let db_type = conn.get_db_type();
+        let sql = format!(
+            "SELECT {} FROM {}{}",
+            projection_columns::<D>(db_type, &Self::table_name(), &Self::field_names())?,
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            live_where::<Self>(db_type)
+        );
+        conn.query_raw(&sql, &[])
+    }
+
+    /// Finds a record by primary key, selecting only the columns of the projection `D`.
+    ///
+    /// See `find_all_as` for how the columns are derived. Returns
+    /// `Err(RusticxError::NotFound)` if no record has the given id.
+    fn find_by_id_as<D>(conn: &Connection, id: i32) -> Result<D, RusticxError>
+    where
+        D: for<'de> Deserialize<'de> + Debug,
+    {
+        let db_type = conn.get_db_type();
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {} = {}{} LIMIT 1",
+            projection_columns::<D>(db_type, &Self::table_name(), &Self::field_names())?,
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            db_type.quote_identifier(&Self::primary_key_field()),
+            db_type.placeholder(1),
+            live_and::<Self>(db_type)
         );
+        conn.query_raw(&sql, &[&id])?
+            .into_iter()
+            .next()
+            .ok_or_else(|| RusticxError::NotFound(format!("{} with id {} not found", Self::table_name(), id)))
+    }
+
+    /// Fetches a single column of every record as a vector of scalars.
+    ///
+    /// Generates `SELECT column FROM table`, so only that column is read and no
+    /// model instances are built. `column` must be one of the model's columns
+    /// (see `field_names`).
+    ///
+    /// Returns `Err(RusticxError::InvalidColumn)` for unknown columns and
+    /// `Err(RusticxError::SerializationError)` if a value cannot be converted to `V`.
+    fn pluck<V>(conn: &Connection, column: &str) -> Result<Vec<V>, RusticxError>
+    where
+        V: for<'de> Deserialize<'de>,
+    {
+        if !Self::field_names().contains(&column) {
+            return Err(RusticxError::InvalidColumn(format!(
+                "Cannot pluck {}: not a column of {}",
+                column,
+                Self::table_name()
+            )));
+        }
+
+        let db_type = conn.get_db_type();
+        let sql = format!(
+            "SELECT {} FROM {}{}",
+            db_type.quote_identifier(column),
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            live_where::<Self>(db_type)
+        );
+        let rows: Vec<serde_json::Map<String, serde_json::Value>> = conn.query_raw(&sql, &[])?;
+
+        rows.into_iter()
+            .map(|mut row| {
+                let value = row.remove(column).unwrap_or(serde_json::Value::Null);
+                serde_json::from_value(value).map_err(|e| {
+                    RusticxError::SerializationError(format!("Cannot read {} value: {}", column, e))
+                })
+            })
+            .collect()
+    }
+
+    /// Loads the children of `parents` in one query, grouped by parent id.
+    ///
+    /// Issues `SELECT * FROM <child table> WHERE foreign_key IN (...)` with one
+    /// bound id per parent, instead of one query per parent. Every parent with
+    /// a primary key gets an entry, empty if it has no children; children keep
+    /// the order the database returned them in.
+    ///
+    /// Returns `Err(RusticxError::InvalidColumn)` if `foreign_key` is not a
+    /// column of `C`.
+    fn load_children<C: SQLModel>(conn: &Connection, parents: &[Self], foreign_key: &str) -> Result<HashMap<i32, Vec<C>>, RusticxError> {
+        if !C::field_names().contains(&foreign_key) {
+            return Err(RusticxError::InvalidColumn(format!(
+                "Cannot load children by {}: not a column of {}",
+                foreign_key,
+                C::table_name()
+            )));
+        }
+
+        let mut children: HashMap<i32, Vec<C>> = parents.iter()
+            .filter_map(|parent| parent.primary_key_value())
+            .map(|id| (id, Vec::new()))
+            .collect();
+        if children.is_empty() {
+            return Ok(children);
+        }
+
+        let db_type = conn.get_db_type();
+        let ids: Vec<i32> = children.keys().copied().collect();
+        let placeholders: Vec<String> = (1..=ids.len()).map(|i| db_type.placeholder(i)).collect();
+        let sql = format!(
+            "SELECT * FROM {} WHERE {} IN ({}){}",
+            db_type.quote_identifier(&conn.table_path(&C::table_name())),
+            db_type.quote_identifier(foreign_key),
+            placeholders.join(", "),
+            live_and::<C>(db_type)
+        );
+        let params: Vec<&(dyn ToSql + Sync + 'static)> = ids.iter().map(|id| id as &(dyn ToSql + Sync + 'static)).collect();
+
+        let rows: Vec<serde_json::Map<String, serde_json::Value>> = conn.query_raw(&sql, &params)?;
+        for mut row in rows {
+            if let Some(coercions) = conn.coercions() {
+                coercions.apply::<C>(&mut row);
+            }
+            let parent_id = row.get(foreign_key)
+                .and_then(|id| id.as_i64())
+                .and_then(|id| i32::try_from(id).ok())
+                .ok_or_else(|| RusticxError::DeserializationError(format!(
+                    "{}.{} is not an integer id",
+                    C::table_name(),
+                    foreign_key
+                )))?;
+            if let Some(group) = children.get_mut(&parent_id) {
+                group.push(C::from_row(&serde_json::Value::Object(row))?);
+            }
+        }
+
+        Ok(children)
+    }
+
+    /// Deletes the current record from the database using its primary key.
+    ///
+    /// Requires the model instance to have a primary key value set (`primary_key_value()`).
+    /// Returns `Err(RusticxError::ValidationError)` if the primary key is not set.
+    fn delete(&self, conn: &Connection) -> Result<(), RusticxError> {
+        if let Some(id) = self.primary_key_value() {
+            Self::delete_by_id(conn, id)
+        } else {
+            Err(RusticxError::ValidationError("Cannot delete a record without a primary key value".to_string()))
+        }
+    }
+
+    /// Deletes a record by its primary key.
+    ///
+    /// For models with a `#[model(soft_delete)]` column the row is kept and
+    /// the column is set to the current time instead.
+    ///
+    /// Returns `Ok(())` on success.
+    fn delete_by_id(conn: &Connection, id: i32) -> Result<(), RusticxError> {
+        let db_type = conn.get_db_type();
+        // Use database-specific placeholder syntax
+        let sql = delete_sql::<Self>(conn, &format!(
+            "{} = {}",
+            db_type.quote_identifier(&Self::primary_key_field()),
+            db_type.placeholder(1)
+        ));
+
+        // Prepare parameters using dyn ToSql. &id needs to be cast.
+        let id_param = &id as &(dyn ToSql + Sync + 'static); // Cast &i32 to the required trait object
+        let params: &[&(dyn ToSql + Sync + 'static)] = &[id_param];
+
+        conn.execute(&sql, params)?;
+
+        events::publish(ModelEvent::<Self> { kind: ModelEventKind::Deleted, id: Some(id), model: None });
+
+        Ok(())
+    }
+
+    /// Deletes every record whose primary key is in `ids` with
+    /// `DELETE ... WHERE pk IN (...)` (an `UPDATE` for soft-deleted models).
+    ///
+    /// More ids than one statement can bind are deleted in chunks, all in one
+    /// transaction, so the delete still succeeds or fails as a whole.
+    ///
+    /// Returns the number of rows removed, which is less than `ids.len()` when
+    /// some of the ids do not exist. A `Deleted` event is published for each
+    /// removed row only: the statement returns their ids with `RETURNING`
+    /// where the server supports it, and otherwise they are read, and locked,
+    /// in a transaction before the delete. An empty slice deletes nothing and
+    /// does not touch the database.
+    fn delete_by_ids(conn: &Connection, ids: &[i32]) -> Result<u64, RusticxError> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let db_type = conn.get_db_type();
+        let primary_key = db_type.quote_identifier(&Self::primary_key_field());
+        let returning = conn.capabilities()?.returning;
+        let chunk_size = db_type.max_bind_params();
+
+        // Without RETURNING this runs in a transaction, which keeps the rows read locked
+        let delete_chunk = |conn: &Connection, chunk: &[i32]| -> Result<Vec<i32>, RusticxError> {
+            let placeholders: Vec<String> = (1..=chunk.len()).map(|i| db_type.placeholder(i)).collect();
+            let condition = format!("{} IN ({})", primary_key, placeholders.join(", "));
+            let params: Vec<&(dyn ToSql + Sync + 'static)> = chunk.iter().map(|id| id as &(dyn ToSql + Sync + 'static)).collect();
+
+            if returning {
+                let sql = format!("{} RETURNING {}", delete_sql::<Self>(conn, &condition), primary_key);
+                return Ok(row_ids(conn.execute_returning(&sql, &params)?));
+            }
+            let live = match Self::soft_delete_field() {
+                Some(column) => format!(" AND {} IS NULL", db_type.quote_identifier(column)),
+                None => String::new(),
+            };
+            let locking = if matches!(db_type, DatabaseType::MySQL) { " FOR UPDATE" } else { "" };
+            let sql = format!(
+                "SELECT {} FROM {} WHERE {}{}{}",
+                primary_key,
+                db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+                condition,
+                live,
+                locking
+            );
+            let existing = row_ids(conn.query_raw(&sql, &params)?);
+            conn.execute(&delete_sql::<Self>(conn, &condition), &params)?;
+            Ok(existing)
+        };
+
+        let deleted = if returning && ids.len() <= chunk_size {
+            delete_chunk(conn, ids)?
+        } else {
+            let tx = conn.begin()?;
+            let mut deleted = Vec::new();
+            for chunk in ids.chunks(chunk_size) {
+                deleted.extend(delete_chunk(&tx, chunk)?);
+            }
+            tx.commit()?;
+            deleted
+        };
+
+        for id in &deleted {
+            events::publish(ModelEvent::<Self> { kind: ModelEventKind::Deleted, id: Some(*id), model: None });
+        }
+
+        Ok(deleted.len() as u64)
+    }
+
+    /// Deletes every record matching `predicate` in one statement (an
+    /// `UPDATE` for soft-deleted models) and returns the number of rows
+    /// removed.
+    ///
+    /// The default scope does not restrict deletes. No per-record events are
+    /// published, since the deleted ids are not known; use `delete_by_ids`
+    /// when subscribers need them.
+    fn delete_where(conn: &Connection, predicate: &Predicate) -> Result<u64, RusticxError> {
+        let db_type = conn.get_db_type();
+        let mut bound = Vec::new();
+        let condition = predicate.to_sql::<Self>(db_type, &mut bound)?;
+        let params = SqlParam::values(&bound);
+        conn.execute(&delete_sql::<Self>(conn, &condition), &params)
+    }
+
+    /// Finds all records in the table, including soft-deleted ones.
+    ///
+    /// Expired rows are still excluded. For models without a
+    /// `#[model(soft_delete)]` column this is the same as `find_all`.
+    fn find_all_with_deleted(conn: &Connection) -> Result<Vec<Self>, RusticxError> {
+        let db_type = conn.get_db_type();
+        let sql = format!(
+            "SELECT * FROM {}{}",
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            unexpired_condition::<Self>(db_type)
+                .into_iter()
+                .chain(scope_condition::<Self>())
+                .reduce(|a, b| format!("{} AND {}", a, b))
+                .map(|c| format!(" WHERE {}", c))
+                .unwrap_or_default()
+        );
+        conn.query_raw(&sql, &[])
+    }
+
+    /// Finds only the soft-deleted records in the table.
+    ///
+    /// Returns `Err(RusticxError::ValidationError)` if the model has no
+    /// `#[model(soft_delete)]` column.
+    fn only_deleted(conn: &Connection) -> Result<Vec<Self>, RusticxError> {
+        let db_type = conn.get_db_type();
+        let deleted = deleted_condition::<Self>(db_type, true).ok_or_else(|| {
+            RusticxError::ValidationError(format!(
+                "Cannot list deleted {}: no field is marked #[model(soft_delete)]",
+                Self::table_name()
+            ))
+        })?;
+        let sql = format!(
+            "SELECT * FROM {} WHERE {}{}",
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            deleted,
+            unexpired_condition::<Self>(db_type)
+                .into_iter()
+                .chain(scope_condition::<Self>())
+                .map(|c| format!(" AND {}", c))
+                .collect::<String>()
+        );
+        conn.query_raw(&sql, &[])
+    }
 
-        #[cfg(not(feature = "postgres"))]
+    /// Un-deletes a soft-deleted record by clearing its `#[model(soft_delete)]`
+    /// column. The in-memory instance is not changed, and restoring a record
+    /// that is not deleted does nothing.
+    ///
+    /// Returns `Err(RusticxError::ValidationError)` if the model has no
+    /// soft-delete column or no primary key value, and
+    /// `Err(RusticxError::NotFound)` if the record does not exist.
+    fn restore(&self, conn: &Connection) -> Result<(), RusticxError> {
+        let column = Self::soft_delete_field().ok_or_else(|| {
+            RusticxError::ValidationError(format!(
+                "Cannot restore {}: no field is marked #[model(soft_delete)]",
+                Self::table_name()
+            ))
+        })?;
+        let id = self.primary_key_value().ok_or_else(|| {
+            RusticxError::ValidationError("Cannot restore a record without a primary key value".to_string())
+        })?;
+
+        let db_type = conn.get_db_type();
         let sql = format!(
-            "SELECT * FROM {} WHERE {} = ? LIMIT 1", // Added LIMIT 1 for efficiency
-            Self::table_name(),
-            primary_key_field
+            "UPDATE {} SET {} = NULL WHERE {} = {}",
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            db_type.quote_identifier(column),
+            db_type.quote_identifier(&Self::primary_key_field()),
+            db_type.placeholder(1)
         );
+        let affected = conn.execute(&sql, &[&id])?;
+        if update_missed::<Self>(conn, affected, id)? {
+            return Err(RusticxError::NotFound(format!("{} with id {} not found", Self::table_name(), id)));
+        }
 
-        // Prepare parameters using dyn ToSql. &id needs to be cast to the trait object.
-        let id_param = &id as &(dyn ToSql + Sync + 'static); // Cast &i32 to the required trait object
-        let params: &[&(dyn ToSql + Sync + 'static)] = &[id_param];
+        events::publish(ModelEvent { kind: ModelEventKind::Updated, id: Some(id), model: Some(self) });
 
-        // Attempt direct deserialization from the database result first
-        let results: Result<Vec<Self>, RusticxError> = conn.query_raw(&sql, params);
+        Ok(())
+    }
 
-        match results {
-            Ok(mut models) => {
-                // Check if any rows were returned
-                if let Some(model) = models.pop() { // Use pop to get the single model
-                    Ok(model)
-                } else {
-                    // No rows returned, record not found
-                    Err(RusticxError::NotFound(format!("{} with id {} not found", Self::table_name(), id)))
-                }
-            },
-            Err(e) => {
-                // If direct deserialization failed, fallback to manual row processing
-                // In a production environment, you might log 'e' here for debugging
-                eprintln!("Warning: Direct deserialization failed for find_by_id, falling back to manual row processing: {:?}", e);
+    /// Finds the record matching `defaults` on `lookup_fields`, inserting
+    /// `defaults` if there is none.
+    ///
+    /// Returns the stored record and whether it was created. The insert skips
+    /// a row that conflicts on the lookup fields (`ON CONFLICT (...) DO
+    /// NOTHING` on PostgreSQL and SQLite, `ON DUPLICATE KEY UPDATE` of the
+    /// primary key to itself on MySQL), so when two callers race, one creates
+    /// the row and the other finds it. Other failures, such as a `NOT NULL`
+    /// or `CHECK` violation or a clash on another unique key, are still
+    /// reported. `lookup_fields` must therefore be covered by a unique
+    /// constraint.
+    ///
+    /// Returns `Err(RusticxError::InvalidColumn)` if `lookup_fields` is empty or
+    /// names a column the model does not have, and
+    /// `Err(RusticxError::QueryError)` if the insert conflicts on another
+    /// unique key.
+    fn get_or_create(conn: &Connection, lookup_fields: &[&str], defaults: Self) -> Result<(Self, bool), RusticxError> {
+        let fields = Self::field_names();
+        let db_type = conn.get_db_type();
+        let table = db_type.quote_identifier(&conn.table_path(&Self::table_name()));
+        let values = field_values(&defaults)?;
+        let (select_sql, lookup_params) = lookup_query::<Self>(conn, "get_or_create", lookup_fields, &values)?;
 
-                let rows: Vec<serde_json::Map<String, serde_json::Value>> = conn.query_raw(&sql, params)?;
+        if let Some(existing) = Self::find_with_sql(conn, &select_sql, &lookup_params)?.into_iter().next() {
+            return Ok((existing, false));
+        }
 
-                // Process the fallback result
-                if let Some(row) = rows.first() {
-                    Self::from_row(&serde_json::Value::Object(row.clone()))
+        // Insert every column except an unset (auto-generated) primary key
+        let primary_key_field = Self::primary_key_field();
+        let (insert_fields, insert_params): (Vec<&str>, Vec<&(dyn ToSql + Sync + 'static)>) = fields.iter()
+            .zip(values.iter())
+            .filter(|(field, value)| **field != primary_key_field || !value.is_null())
+            .filter_map(|(field, value)| value.as_ref_postgres().map(|v| (*field, v)))
+            .unzip();
+        let columns: Vec<String> = insert_fields.iter().map(|f| db_type.quote_identifier(f)).collect();
+        let placeholders: Vec<String> = (1..=insert_fields.len()).map(|i| db_type.placeholder(i)).collect();
+        let conflict_target: Vec<String> = lookup_fields.iter().map(|f| db_type.quote_identifier(f)).collect();
+
+        let created = match db_type {
+            DatabaseType::PostgreSQL => {
+                let sql = format!(
+                    "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT ({}) DO NOTHING RETURNING *",
+                    table,
+                    columns.join(", "),
+                    placeholders.join(", "),
+                    conflict_target.join(", ")
+                );
+                // Run the insert once and decode what it returned, so a row that
+                // fails to decode is reported rather than inserted again
+                let inserted: Vec<serde_json::Map<String, serde_json::Value>> = conn.query_raw(&sql, &insert_params)
+                    .map_err(|e| diagnose_param_error(e, &insert_fields, &fields, &Self::field_sql_types()))?;
+                inserted.into_iter().next().map(|row| decode_row::<Self>(conn, row)).transpose()?
+            }
+            _ => {
+                let on_conflict = match db_type {
+                    // Assigning the key to itself changes nothing, so a duplicate affects no rows
+                    DatabaseType::MySQL => {
+                        let primary_key = db_type.quote_identifier(&primary_key_field);
+                        format!("ON DUPLICATE KEY UPDATE {} = {}", primary_key, primary_key)
+                    }
+                    _ => format!("ON CONFLICT ({}) DO NOTHING", conflict_target.join(", ")),
+                };
+                let sql = format!(
+                    "INSERT INTO {} ({}) VALUES ({}) {}",
+                    table,
+                    columns.join(", "),
+                    placeholders.join(", "),
+                    on_conflict
+                );
+                let affected = conn.execute(&sql, &insert_params)
+                    .map_err(|e| diagnose_param_error(e, &insert_fields, &fields, &Self::field_sql_types()))?;
+                if affected > 0 {
+                    Self::find_with_sql(conn, &select_sql, &lookup_params)?.into_iter().next()
                 } else {
-                    // Still no rows in fallback, record not found
-                    Err(RusticxError::NotFound(format!("{} with id {} not found", Self::table_name(), id)))
+                    None
                 }
             }
+        };
+
+        if let Some(model) = created {
+            events::publish(ModelEvent { kind: ModelEventKind::Inserted, id: model.primary_key_value(), model: Some(&model) });
+            return Ok((model, true));
         }
+
+        // Another writer inserted a conflicting row first. MySQL's update
+        // absorbs a clash on any unique key, so without a row matching the
+        // lookup fields the insert was a duplicate on another key.
+        Self::find_with_sql(conn, &select_sql, &lookup_params)?
+            .into_iter()
+            .next()
+            .map(|existing| (existing, false))
+            .ok_or_else(|| RusticxError::QueryError(format!(
+                "Duplicate entry: get_or_create on {} conflicts with a row on a unique key other than the lookup fields",
+                Self::table_name()
+            )))
     }
 
-    /// Finds all records in the table.
+    /// Finds the record matching `defaults` on `lookup_fields`, or returns
+    /// `defaults` itself, unsaved, if there is none.
     ///
-    /// Returns a vector of all model instances found in the table.
-    fn find_all(conn: &Connection) -> Result<Vec<Self>, RusticxError> {
-        let sql = format!("SELECT * FROM {}", Self::table_name());
-        // No parameters for SELECT all
-        let params: &[&(dyn ToSql + Sync + 'static)] = &[];
-
-        // Attempt direct deserialization from the database result first
-        let direct_results: Result<Vec<Self>, RusticxError> = conn.query_raw(&sql, params);
-
-        match direct_results {
-            Ok(models) => {
-                // Direct deserialization successful
-                Ok(models)
-            },
-            Err(e) => {
-                // If direct deserialization failed, fallback to manual row processing
-                // In a production environment, you might log 'e' here for debugging
-                eprintln!("Warning: Direct deserialization failed for find_all, falling back to manual row processing: {:?}", e);
-
-                let rows: Vec<serde_json::Map<String, serde_json::Value>> = conn.query_raw(&sql, params)?;
-
-                // Manually deserialize each row using from_row
-                rows.into_iter().map(|row| Self::from_row(&serde_json::Value::Object(row))).collect()
-            }
+    /// Returns the record and, like `get_or_create`, whether it is new: `true`
+    /// for the unsaved `defaults`, `false` for a stored record. Unlike
+    /// `get_or_create` nothing is written, so the caller can fill in the
+    /// remaining fields of a new record before persisting it with `save`.
+    ///
+    /// Returns `Err(RusticxError::InvalidColumn)` if `lookup_fields` is empty or
+    /// names a column the model does not have.
+    fn find_or_initialize(conn: &Connection, lookup_fields: &[&str], defaults: Self) -> Result<(Self, bool), RusticxError> {
+        let values = field_values(&defaults)?;
+        let (select_sql, lookup_params) = lookup_query::<Self>(conn, "find_or_initialize", lookup_fields, &values)?;
+
+        match Self::find_with_sql(conn, &select_sql, &lookup_params)?.into_iter().next() {
+            Some(existing) => Ok((existing, false)),
+            None => Ok((defaults, true)),
         }
     }
 
-    /// Deletes the current record from the database using its primary key.
-    ///
-    /// Requires the model instance to have a primary key value set (`primary_key_value()`).
-    /// Returns `Err(RusticxError::ValidationError)` if the primary key is not set.
-    fn delete(&self, conn: &Connection) -> Result<(), RusticxError> {
-        if let Some(id) = self.primary_key_value() {
-            Self::delete_by_id(conn, id)
+    /// Persists the record: inserts it if it has no primary key value yet,
+    /// otherwise updates the existing row.
+    fn save(&mut self, conn: &Connection) -> Result<(), RusticxError> {
+        if self.primary_key_value().is_some() {
+            self.update(conn).map(|_| ())
         } else {
-            Err(RusticxError::ValidationError("Cannot delete a record without a primary key value".to_string()))
+            self.insert(conn)
         }
     }
 
-    /// Deletes a record by its primary key.
+    /// Sets the record's `updated_at` column to the current database time
+    /// without writing any other column.
     ///
-    /// Returns `Ok(())` on success.
-    fn delete_by_id(conn: &Connection, id: i32) -> Result<(), RusticxError> {
-        let primary_key_field = Self::primary_key_field();
-        // Use database-specific placeholder syntax
-        #[cfg(feature = "postgres")]
+    /// Useful for marking a parent row as changed when one of its children is
+    /// modified. The in-memory instance is not changed.
+    ///
+    /// Returns `Err(RusticxError::ValidationError)` if the model has no
+    /// `#[model(updated_at)]` column or no primary key value, and
+    /// `Err(RusticxError::NotFound)` if the record no longer exists.
+    fn touch(&self, conn: &Connection) -> Result<(), RusticxError> {
+        let column = Self::updated_at_field().ok_or_else(|| {
+            RusticxError::ValidationError(format!(
+                "Cannot touch {}: no field is marked #[model(updated_at)]",
+                Self::table_name()
+            ))
+        })?;
+        let id = self.primary_key_value().ok_or_else(|| {
+            RusticxError::ValidationError("Cannot touch a record without a primary key value".to_string())
+        })?;
+
+        let db_type = conn.get_db_type();
         let sql = format!(
-            "DELETE FROM {} WHERE {} = $1",
-            Self::table_name(),
-            primary_key_field
+            "UPDATE {} SET {} = CURRENT_TIMESTAMP WHERE {} = {}",
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            db_type.quote_identifier(column),
+            db_type.quote_identifier(&Self::primary_key_field()),
+            db_type.placeholder(1)
         );
 
-        #[cfg(not(feature = "postgres"))]
-         let sql = format!(
-            "DELETE FROM {} WHERE {} = ?",
-            Self::table_name(),
-            primary_key_field
+        let affected = conn.execute(&sql, &[&id])?;
+        if update_missed::<Self>(conn, affected, id)? {
+            return Err(RusticxError::NotFound(format!("{} with id {} not found", Self::table_name(), id)));
+        }
+
+        events::publish(ModelEvent::<Self> { kind: ModelEventKind::Updated, id: Some(id), model: None });
+
+        Ok(())
+    }
+
+    /// Atomically adds `delta` to a numeric column of the record with the given id.
+    ///
+    /// Generates `UPDATE table SET column = column + $1 WHERE pk = $2`, so the
+    /// database applies the change and concurrent adjustments (view counters,
+    /// stock levels) cannot overwrite each other as a read-modify-write would.
+    ///
+    /// Returns `Err(RusticxError::InvalidColumn)` if `column` is not a non-key
+    /// column of the model, or `Err(RusticxError::NotFound)` if no record has
+    /// the given id.
+    fn increment(conn: &Connection, id: i32, column: &str, delta: i64) -> Result<(), RusticxError> {
+        let primary_key_field = Self::primary_key_field();
+        if column == primary_key_field || !Self::field_names().contains(&column) {
+            return Err(RusticxError::InvalidColumn(format!(
+                "Cannot increment {}: not a non-key column of {}",
+                column,
+                Self::table_name()
+            )));
+        }
+
+        let db_type = conn.get_db_type();
+        // PostgreSQL infers the parameter type from the column, so widen it to
+        // accept an i64 delta against INTEGER columns as well
+        let delta_placeholder = match db_type {
+            DatabaseType::PostgreSQL => format!("CAST({} AS BIGINT)", db_type.placeholder(1)),
+            _ => db_type.placeholder(1),
+        };
+        let sql = format!(
+            "UPDATE {} SET {} = {} + {} WHERE {} = {}",
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            db_type.quote_identifier(column),
+            db_type.quote_identifier(column),
+            delta_placeholder,
+            db_type.quote_identifier(&primary_key_field),
+            db_type.placeholder(2)
         );
 
-        // Prepare parameters using dyn ToSql. &id needs to be cast.
-        let id_param = &id as &(dyn ToSql + Sync + 'static); // Cast &i32 to the required trait object
-        let params: &[&(dyn ToSql + Sync + 'static)] = &[id_param];
+        let affected = conn.execute(&sql, &[&delta, &id])?;
+        if affected == 0 {
+            return Err(RusticxError::NotFound(format!("{} with id {} not found", Self::table_name(), id)));
+        }
 
-        conn.execute(&sql, &params)?;
+        events::publish(ModelEvent::<Self> { kind: ModelEventKind::Updated, id: Some(id), model: None });
 
         Ok(())
     }
 
+    /// Atomically subtracts `delta` from a numeric column of the record with the
+    /// given id. See `increment`.
+    ///
+    /// Returns `Err(RusticxError::ValidationError)` if `delta` is `i64::MIN`,
+    /// which has no positive counterpart to add.
+    fn decrement(conn: &Connection, id: i32, column: &str, delta: i64) -> Result<(), RusticxError> {
+        let delta = delta.checked_neg().ok_or_else(|| {
+            RusticxError::ValidationError(format!("Cannot decrement {} by {}: the negated delta overflows", column, delta))
+        })?;
+        Self::increment(conn, id, column, delta)
+    }
+
     /// Finds records based on a single field's value.
     ///
     /// This method uses `std::any::Any` downcasting to handle parameter
@@ -399,16 +1323,18 @@ pub trait SQLModel: Sized + Debug + Serialize + for<'de> Deserialize<'de> {
         // Use database-specific placeholder syntax
         #[cfg(feature = "postgres")]
         let sql = format!(
-            "SELECT * FROM {} WHERE {} = $1",
-            Self::table_name(),
-            field
+            "SELECT * FROM {} WHERE {} = $1{}",
+            conn.get_db_type().quote_identifier(&conn.table_path(&Self::table_name())),
+            conn.get_db_type().quote_identifier(field),
+            live_and::<Self>(conn.get_db_type())
         );
 
         #[cfg(not(feature = "postgres"))]
         let sql = format!(
-            "SELECT * FROM {} WHERE {} = ?",
-            Self::table_name(),
-            field
+            "SELECT * FROM {} WHERE {} = ?{}",
+            conn.get_db_type().quote_identifier(&conn.table_path(&Self::table_name())),
+            conn.get_db_type().quote_identifier(field),
+            live_and::<Self>(conn.get_db_type())
         );
 
         // Attempt to downcast the value to common SQL types and create the dyn ToSql reference
@@ -434,20 +1360,7 @@ pub trait SQLModel: Sized + Debug + Serialize + for<'de> Deserialize<'de> {
         let params: &[&(dyn ToSql + Sync + 'static)] = &[param];
 
 
-        // Attempt direct deserialization first
-        let direct_results: Result<Vec<Self>, RusticxError> = conn.query_raw(&sql, params);
-
-        match direct_results {
-            Ok(models) => Ok(models),
-            Err(e) => {
-                 // In a production environment, you might log 'e' here for debugging
-                eprintln!("Warning: Direct deserialization failed for find_by, falling back to manual row processing: {:?}", e);
-                // Fallback to manual row processing
-                let rows: Vec<serde_json::Map<String, serde_json::Value>> = conn.query_raw(&sql, params)?;
-
-                rows.into_iter().map(|row| Self::from_row(&serde_json::Value::Object(row))).collect()
-            }
-        }
+        query_models::<Self>(conn, &sql, params)
     }
 
     /// Executes a raw SQL query and attempts to deserialize the results into models.
@@ -459,18 +1372,85 @@ pub trait SQLModel: Sized + Debug + Serialize + for<'de> Deserialize<'de> {
     /// `ToSql + Sync + 'static` (effectively types supported by `ToSqlConvert`
     /// and cast to the trait object).
     fn find_with_sql(conn: &Connection, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<Vec<Self>, RusticxError> {
-        // Attempt direct deserialization first
-        let direct_results: Result<Vec<Self>, RusticxError> = conn.query_raw(sql, params);
-
-        match direct_results {
-            Ok(models) => Ok(models),
-            Err(e) => {
-                // If direct deserialization failed, fallback to manual row processing
-                 // In a production environment, you might log 'e' here for debugging
-                eprintln!("Warning: Direct deserialization failed for find_with_sql, falling back to manual row processing: {:?}", e);
-                let rows: Vec<serde_json::Map<String, serde_json::Value>> = conn.query_raw(sql, params)?;
-
-                 rows.into_iter().map(|row| Self::from_row(&serde_json::Value::Object(row))).collect()
+        query_models::<Self>(conn, sql, params)
+    }
+
+    /// Executes a raw write statement against the model's table and returns
+    /// the number of affected rows.
+    ///
+    /// The write counterpart of `find_with_sql`. Every `{table}` in `sql` is
+    /// replaced with the model's quoted table name, so the statement follows
+    /// `#[model(table = "...")]` renames:
+    ///
+    /// ```ignore
+    /// let archived = Order::execute_sql(
+    ///     &conn,
+    ///     "UPDATE {table} SET status = 'archived' WHERE placed_at < $1",
+    ///     &[&cutoff],
+    /// )?;
+    /// ```
+    ///
+    /// Values must still be passed as parameters; only the table name is
+    /// substituted. No model events are published.
+    fn execute_sql(conn: &Connection, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<u64, RusticxError> {
+        let table = conn.get_db_type().quote_identifier(&conn.table_path(&Self::table_name()));
+        conn.execute(&sql.replace("{table}", &table), params)
+    }
+
+    /// Finds all records in the table, mapping result columns as `mapping` says.
+    ///
+    /// With `RowMapping::ByPosition` the query selects the model's columns by
+    /// name in `field_names` order rather than `SELECT *`, so the mapping is
+    /// correct however the table's columns are ordered.
+    fn find_all_mapped(conn: &Connection, mapping: RowMapping) -> Result<Vec<Self>, RusticxError> {
+        let db_type = conn.get_db_type();
+        let columns = match mapping {
+            RowMapping::ByName => "*".to_string(),
+            RowMapping::ByPosition => Self::field_names()
+                .iter()
+                .map(|name| db_type.quote_identifier(name))
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        let sql = format!(
+            "SELECT {} FROM {}{}",
+            columns,
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            live_where::<Self>(db_type)
+        );
+        Self::find_with_sql_mapped(conn, &sql, &[], mapping)
+    }
+
+    /// Executes a raw SQL query, mapping result columns as `mapping` says.
+    ///
+    /// `RowMapping::ByName` behaves like `find_with_sql`. `RowMapping::ByPosition`
+    /// skips building a map per row and requires the query to select exactly
+    /// the model's columns in `field_names` order; a query returning a
+    /// different number of columns fails with `RusticxError::DeserializationError`.
+    fn find_with_sql_mapped(
+        conn: &Connection,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync + 'static)],
+        mapping: RowMapping,
+    ) -> Result<Vec<Self>, RusticxError> {
+        match mapping {
+            RowMapping::ByName => Self::find_with_sql(conn, sql, params),
+            RowMapping::ByPosition => {
+                let expected = Self::field_names().len();
+                conn.query_positional(sql, params)?
+                    .iter()
+                    .map(|values| {
+                        if values.len() != expected {
+                            return Err(RusticxError::DeserializationError(format!(
+                                "Positional mapping of {} expects {} columns, query returned {}",
+                                Self::table_name(),
+                                expected,
+                                values.len()
+                            )));
+                        }
+                        Self::from_row_positional(values)
+                    })
+                    .collect()
             }
         }
     }
@@ -479,7 +1459,11 @@ pub trait SQLModel: Sized + Debug + Serialize + for<'de> Deserialize<'de> {
     ///
     /// Returns the total count as an `i64`.
     fn count(conn: &Connection) -> Result<i64, RusticxError> {
-        let sql = format!("SELECT COUNT(*) as count FROM {}", Self::table_name());
+        let sql = format!(
+            "SELECT COUNT(*) as count FROM {}{}",
+            conn.get_db_type().quote_identifier(&conn.table_path(&Self::table_name())),
+            live_where::<Self>(conn.get_db_type())
+        );
 
         // Helper struct for deserializing the count result
         #[derive(Deserialize, Debug)]
@@ -498,6 +1482,521 @@ pub trait SQLModel: Sized + Debug + Serialize + for<'de> Deserialize<'de> {
             Ok(0)
         }
     }
+
+    /// Finds the records matching `predicate`.
+    ///
+    /// Like the other finders, expired, soft-deleted and out-of-scope rows are
+    /// excluded.
+    ///
+    /// Returns `Err(RusticxError::InvalidColumn)` if the predicate references a
+    /// column the model does not have.
+    fn find_where(conn: &Connection, predicate: &Predicate) -> Result<Vec<Self>, RusticxError> {
+        let db_type = conn.get_db_type();
+        let mut bound = Vec::new();
+        let condition = predicate.to_sql::<Self>(db_type, &mut bound)?;
+        let params = SqlParam::values(&bound);
+        let sql = format!(
+            "SELECT * FROM {} WHERE {}{}",
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            condition,
+            live_and::<Self>(db_type)
+        );
+        Self::find_with_sql(conn, &sql, &params)
+    }
+
+    /// Finds the records whose JSON `column` contains `needle`, as with
+    /// PostgreSQL's `@>`.
+    ///
+    /// ```ignore
+    /// let admins = User::find_by_json_contains(&conn, "profile", &json!({"role": "admin"}))?;
+    /// ```
+    ///
+    /// PostgreSQL uses `@>` (casting the column to `JSONB` unless it is
+    /// declared `sql_type = "JSONB"`), MySQL `JSON_CONTAINS`, and SQLite an
+    /// equivalent set of `json_extract` comparisons. The finders' usual
+    /// filtering applies.
+    ///
+    /// Returns `Err(RusticxError::InvalidColumn)` if `column` is not a column
+    /// of the model.
+    fn find_by_json_contains(conn: &Connection, column: &str, needle: &serde_json::Value) -> Result<Vec<Self>, RusticxError> {
+        let db_type = conn.get_db_type();
+        let mut values = Vec::new();
+        let condition = crate::json::contains_condition::<Self>(db_type, column, needle, &mut values)?;
+        let params: Vec<&(dyn ToSql + Sync + 'static)> = values.iter().filter_map(|v| v.as_ref_postgres()).collect();
+        let sql = format!(
+            "SELECT * FROM {} WHERE {}{}",
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            condition,
+            live_and::<Self>(db_type)
+        );
+        Self::find_with_sql(conn, &sql, &params)
+    }
+
+    /// Counts the records matching `predicate`, with the same filtering as
+    /// `find_where`.
+    fn count_where(conn: &Connection, predicate: &Predicate) -> Result<i64, RusticxError> {
+        let db_type = conn.get_db_type();
+        let mut bound = Vec::new();
+        let condition = predicate.to_sql::<Self>(db_type, &mut bound)?;
+        let params = SqlParam::values(&bound);
+        let sql = format!(
+            "SELECT COUNT(*) as count FROM {} WHERE {}{}",
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            condition,
+            live_and::<Self>(db_type)
+        );
+
+        #[derive(Deserialize, Debug)]
+        struct CountResult {
+            count: i64,
+        }
+
+        let counts: Vec<CountResult> = conn.query_raw(&sql, &params)?;
+        Ok(counts.first().map_or(0, |c| c.count))
+    }
+
+    /// Fetches one page of records, ordered by the primary key.
+    ///
+    /// `page` is 1-based. On PostgreSQL the total row count is computed in the
+    /// same round-trip with a `COUNT(*) OVER()` window; other databases issue a
+    /// separate `COUNT(*)` query.
+    ///
+    /// Returns `Err(RusticxError::ValidationError)` if `page` or `per_page` is less than 1,
+    /// or so large that the offset or page count overflows.
+    fn paginate(conn: &Connection, page: i64, per_page: i64) -> Result<Page<Self>, RusticxError> {
+        paginate_by::<Self>(conn, None, page, per_page)
+    }
+
+    /// Fetches one page of records sorted by `order`, with ties broken by the
+    /// primary key so rows do not move between pages.
+    ///
+    /// Otherwise behaves like `paginate`. Build `order` from request parameters
+    /// with `OrderBy::try_from_user_input`.
+    ///
+    /// Returns `Err(RusticxError::InvalidColumn)` if `order` names a column this
+    /// model does not have, and `Err(RusticxError::ValidationError)` if `page`
+    /// or `per_page` is less than 1 or overflows the offset or page count.
+    fn paginate_ordered(conn: &Connection, order: &OrderBy, page: i64, per_page: i64) -> Result<Page<Self>, RusticxError> {
+        if !Self::field_names().contains(&order.column()) {
+            return Err(RusticxError::InvalidColumn(format!(
+                "Cannot order by {}: not a column of {}",
+                order.column(),
+                Self::table_name()
+            )));
+        }
+        paginate_by::<Self>(conn, Some(order), page, per_page)
+    }
+
+    /// Fetches up to `limit` records after `cursor`, ordered by `cursor_column`
+    /// with ties broken by the primary key.
+    ///
+    /// Keyset pagination avoids the deep `OFFSET` scans of `paginate` on large
+    /// tables. Pass `None` as the cursor to start from the beginning, then the
+    /// returned `next_cursor` to continue. `cursor_column` must be one of the
+    /// model's columns; rows where it is NULL come after all others.
+    ///
+    /// Returns `Err(RusticxError::InvalidColumn)` for an unknown column and
+    /// `Err(RusticxError::ValidationError)` for a malformed cursor or a `limit` below 1
+    /// or of `i64::MAX`.
+    fn find_after(
+        conn: &Connection,
+        cursor_column: &str,
+        cursor: Option<&str>,
+        limit: i64,
+    ) -> Result<CursorPage<Self>, RusticxError> {
+        let fields = Self::field_names();
+        if !fields.contains(&cursor_column) {
+            return Err(RusticxError::InvalidColumn(format!(
+                "{} is not a column of {}",
+                cursor_column,
+                Self::table_name()
+            )));
+        }
+        if limit < 1 {
+            return Err(RusticxError::ValidationError(format!("Invalid limit {}: must be at least 1", limit)));
+        }
+        // Fetch one extra row to know whether another page exists
+        let fetch = limit.checked_add(1).ok_or_else(|| {
+            RusticxError::ValidationError(format!("Invalid limit {}: must be below {}", limit, i64::MAX))
+        })?;
+
+        let primary_key_field = Self::primary_key_field();
+        let db_type = conn.get_db_type();
+        let sql_types = Self::field_sql_types();
+
+        // Cursor values are bound as text. PostgreSQL binds parameters strictly, so
+        // the text is cast back to the declared column type; MySQL and SQLite coerce it.
+        let bind = |column: &str, index: usize| -> String {
+            let placeholder = db_type.placeholder(index);
+            let declared = fields.iter().position(|f| *f == column).and_then(|i| sql_types.get(i));
+            match (db_type, declared) {
+                (DatabaseType::PostgreSQL, Some(sql_type)) => {
+                    format!("CAST(CAST({} AS TEXT) AS {})", placeholder, sql_type.pg_type())
+                }
+                _ => placeholder,
+            }
+        };
+
+        let decoded = cursor.map(decode_cursor).transpose()?;
+        let keyed_by_pk = cursor_column == primary_key_field;
+
+        let quoted_column = db_type.quote_identifier(cursor_column);
+        let quoted_pk = db_type.quote_identifier(&primary_key_field);
+
+        let mut sql = format!(
+            "SELECT * FROM {}{}",
+            db_type.quote_identifier(&conn.table_path(&Self::table_name())),
+            live_where::<Self>(db_type)
+        );
+        let joiner = if live_condition::<Self>(db_type).is_some() { "AND" } else { "WHERE" };
+        let mut params: Vec<&(dyn ToSql + Sync + 'static)> = Vec::new();
+        // NULLs sort after every value, so a page can end on or past them
+        match &decoded {
+            Some((_, pk_value)) if keyed_by_pk => {
+                sql.push_str(&format!(" {} {} > {}", joiner, quoted_pk, bind(&primary_key_field, 1)));
+                params.push(pk_value);
+            }
+            Some((Some(cursor_value), pk_value)) => {
+                sql.push_str(&format!(
+                    " {} ({} IS NULL OR ({}, {}) > ({}, {}))",
+                    joiner,
+                    quoted_column,
+                    quoted_column,
+                    quoted_pk,
+                    bind(cursor_column, 1),
+                    bind(&primary_key_field, 2)
+                ));
+                params.push(cursor_value);
+                params.push(pk_value);
+            }
+            Some((None, pk_value)) => {
+                sql.push_str(&format!(
+                    " {} {} IS NULL AND {} > {}",
+                    joiner,
+                    quoted_column,
+                    quoted_pk,
+                    bind(&primary_key_field, 1)
+                ));
+                params.push(pk_value);
+            }
+            None => {}
+        }
+
+        if keyed_by_pk {
+            sql.push_str(&format!(" ORDER BY {}", quoted_pk));
+        } else {
+            sql.push_str(&format!(" ORDER BY {} IS NULL, {}, {}", quoted_column, quoted_column, quoted_pk));
+        }
+
+        sql.push_str(&format!(" LIMIT {}", db_type.placeholder(params.len() + 1)));
+        params.push(&fetch);
+
+        let mut rows: Vec<serde_json::Map<String, serde_json::Value>> = conn.query_raw(&sql, &params)?;
+        let has_more = rows.len() as i64 > limit;
+        rows.truncate(limit as usize);
+
+        let next_cursor = if has_more {
+            rows.last().map(|row| {
+                encode_cursor(
+                    row.get(cursor_column).unwrap_or(&serde_json::Value::Null),
+                    row.get(&primary_key_field).unwrap_or(&serde_json::Value::Null),
+                )
+            })
+        } else {
+            None
+        };
+
+        let items = rows.into_iter()
+            .map(|row| decode_row::<Self>(conn, row))
+            .collect::<Result<Vec<Self>, RusticxError>>()?;
+
+        Ok(CursorPage { items, next_cursor })
+    }
+}
+
+/// Eager loading for models with `#[model(has_many)]` / `#[model(has_one)]`
+/// relation fields, implemented by `#[derive(Model)]`.
+///
+/// ```ignore
+/// let users = User::find_all_with(&conn, &[UserInclude::Posts, UserInclude::Profile])?;
+/// ```
+pub trait Relations: SQLModel {
+    /// The relations that can be requested, one variant per relation field.
+    type Include: Copy + Debug;
+
+    /// Loads the requested relations for `models` and stores them in their
+    /// relation fields, issuing one query per relation rather than per model.
+    ///
+    /// Use it to eager-load the results of any finder.
+    fn load_includes(conn: &Connection, models: &mut [Self], includes: &[Self::Include]) -> Result<(), RusticxError>;
+
+    /// Finds all records, like `find_all`, and loads the requested relations.
+    fn find_all_with(conn: &Connection, includes: &[Self::Include]) -> Result<Vec<Self>, RusticxError> {
+        let mut models = Self::find_all(conn)?;
+        Self::load_includes(conn, &mut models, includes)?;
+        Ok(models)
+    }
+}
+
+/// Builds the `SELECT` used by `get_or_create` and `find_or_initialize`: a
+/// single row of `T` whose `lookup_fields` equal the matching entries of
+/// `values` (`IS NULL` for null values).
+fn lookup_query<'a, T: SQLModel>(
+    conn: &Connection,
+    operation: &str,
+    lookup_fields: &[&str],
+    values: &'a [Box<dyn ToSqlConvert>],
+) -> Result<(String, Vec<&'a (dyn ToSql + Sync + 'static)>), RusticxError> {
+    let db_type = conn.get_db_type();
+    let fields = T::field_names();
+    if lookup_fields.is_empty() {
+        return Err(RusticxError::InvalidColumn(format!(
+            "{} on {} needs at least one lookup field",
+            operation,
+            T::table_name()
+        )));
+    }
+    if let Some(unknown) = lookup_fields.iter().find(|f| !fields.contains(f)) {
+        return Err(RusticxError::InvalidColumn(format!(
+            "Cannot look up by {}: not a column of {}",
+            unknown,
+            T::table_name()
+        )));
+    }
+
+    let mut conditions = Vec::new();
+    let mut params: Vec<&(dyn ToSql + Sync + 'static)> = Vec::new();
+    for field in lookup_fields {
+        let column = db_type.quote_identifier(field);
+        let value = fields.iter().position(|f| f == field).and_then(|i| values[i].as_ref_postgres());
+        match value {
+            Some(value) => {
+                params.push(value);
+                conditions.push(format!("{} = {}", column, db_type.placeholder(params.len())));
+            }
+            None => conditions.push(format!("{} IS NULL", column)),
+        }
+    }
+    let sql = format!(
+        "SELECT * FROM {} WHERE {} LIMIT 1",
+        db_type.quote_identifier(&conn.table_path(&T::table_name())),
+        conditions.join(" AND ")
+    );
+    Ok((sql, params))
+}
+
+/// Whether an `UPDATE` of the row with primary key `id` that affected
+/// `affected` rows found no row. MySQL counts only the rows an `UPDATE`
+/// changed, so there a row it left as it was is looked up on the primary.
+fn update_missed<T: SQLModel>(conn: &Connection, affected: u64, id: i32) -> Result<bool, RusticxError> {
+    if affected > 0 || !matches!(conn.get_db_type(), DatabaseType::MySQL) {
+        return Ok(affected == 0);
+    }
+    let db_type = conn.get_db_type();
+    let sql = format!(
+        "SELECT 1 FROM {} WHERE {} = {}",
+        db_type.quote_identifier(&conn.table_path(&T::table_name())),
+        db_type.quote_identifier(&T::primary_key_field()),
+        db_type.placeholder(1)
+    );
+    Ok(conn.primary().query_positional(&sql, &[&id])?.is_empty())
+}
+
+/// Builds the statement deleting the rows of `T` matching `condition`: a
+/// `DELETE`, or for soft-deleted models an `UPDATE` stamping the soft-delete
+/// column of the rows not already deleted.
+fn delete_sql<T: SQLModel>(conn: &Connection, condition: &str) -> String {
+    let db_type = conn.get_db_type();
+    let table = db_type.quote_identifier(&conn.table_path(&T::table_name()));
+    match T::soft_delete_field() {
+        Some(column) => {
+            let column = db_type.quote_identifier(column);
+            format!("UPDATE {} SET {} = CURRENT_TIMESTAMP WHERE {} AND {} IS NULL", table, column, condition, column)
+        }
+        None => format!("DELETE FROM {} WHERE {}", table, condition),
+    }
+}
+
+/// The ids in rows of a single primary key column.
+fn row_ids(rows: Vec<serde_json::Map<String, serde_json::Value>>) -> Vec<i32> {
+    rows.into_iter()
+        .filter_map(|row| row.into_iter().next().and_then(|(_, id)| id.as_i64()))
+        .filter_map(|id| i32::try_from(id).ok())
+        .collect()
+}
+
+/// Returns the condition matching rows of `T` whose expiry time has passed, if
+/// `T` has an `#[model(expires_at)]` column.
+pub(crate) fn expired_condition<T: SQLModel>(db_type: &DatabaseType) -> Option<String> {
+    T::expires_at_field().map(|column| {
+        let column = db_type.quote_identifier(column);
+        format!("{} IS NOT NULL AND {} <= CURRENT_TIMESTAMP", column, column)
+    })
+}
+
+/// Returns the condition matching rows of `T` that have not expired, if `T`
+/// has an `#[model(expires_at)]` column. Rows without an expiry never expire.
+fn unexpired_condition<T: SQLModel>(db_type: &DatabaseType) -> Option<String> {
+    T::expires_at_field().map(|column| {
+        let column = db_type.quote_identifier(column);
+        format!("({} IS NULL OR {} > CURRENT_TIMESTAMP)", column, column)
+    })
+}
+
+/// Returns the condition matching rows of `T` that are (`deleted == true`) or
+/// are not soft-deleted, if `T` has an `#[model(soft_delete)]` column.
+fn deleted_condition<T: SQLModel>(db_type: &DatabaseType, deleted: bool) -> Option<String> {
+    T::soft_delete_field().map(|column| {
+        format!("{} IS {}NULL", db_type.quote_identifier(column), if deleted { "NOT " } else { "" })
+    })
+}
+
+/// Returns the condition matching the rows of `T` the finders return: neither
+/// expired nor soft-deleted, and within the default scope.
+pub(crate) fn live_condition<T: SQLModel>(db_type: &DatabaseType) -> Option<String> {
+    let conditions: Vec<String> = unexpired_condition::<T>(db_type)
+        .into_iter()
+        .chain(deleted_condition::<T>(db_type, false))
+        .chain(scope_condition::<T>())
+        .collect();
+    (!conditions.is_empty()).then(|| conditions.join(" AND "))
+}
+
+thread_local! {
+    /// Models whose default scope is disabled on this thread by `unscoped`.
+    static UNSCOPED: std::cell::RefCell<Vec<std::any::TypeId>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Disables a model's default scope on this thread until dropped, so the
+/// scope comes back even if the closure passed to `unscoped` panics.
+struct UnscopedGuard(std::any::TypeId);
+
+impl UnscopedGuard {
+    fn enter(model: std::any::TypeId) -> Self {
+        UNSCOPED.with(|unscoped| unscoped.borrow_mut().push(model));
+        UnscopedGuard(model)
+    }
+}
+
+impl Drop for UnscopedGuard {
+    fn drop(&mut self) {
+        UNSCOPED.with(|unscoped| {
+            let mut unscoped = unscoped.borrow_mut();
+            if let Some(pos) = unscoped.iter().rposition(|model| *model == self.0) {
+                unscoped.remove(pos);
+            }
+        });
+    }
+}
+
+/// `T`'s default scope, parenthesized, unless disabled by `unscoped`.
+fn scope_condition<T: SQLModel>() -> Option<String> {
+    let scope = T::default_scope()?;
+    let disabled = UNSCOPED.with(|unscoped| unscoped.borrow().contains(&std::any::TypeId::of::<T>()));
+    (!disabled).then(|| format!("({})", scope))
+}
+
+/// Offset pagination shared by `SQLModel::paginate` and `paginate_ordered`,
+/// ordering by `order` (then the primary key) or by the primary key alone.
+fn paginate_by<T: SQLModel>(conn: &Connection, order: Option<&OrderBy>, page: i64, per_page: i64) -> Result<Page<T>, RusticxError> {
+    if page < 1 || per_page < 1 {
+        return Err(RusticxError::ValidationError(format!(
+            "Invalid pagination: page ({}) and per_page ({}) must be at least 1",
+            page, per_page
+        )));
+    }
+
+    let offset = (page - 1).checked_mul(per_page).ok_or_else(|| {
+        RusticxError::ValidationError(format!(
+            "Invalid pagination: page ({}) of {} records starts past the largest offset",
+            page, per_page
+        ))
+    })?;
+    let db_type = conn.get_db_type();
+    let params: &[&(dyn ToSql + Sync + 'static)] = &[&per_page, &offset];
+
+    // The primary key breaks ties so rows keep a stable position across pages
+    let primary_key = db_type.quote_identifier(&T::primary_key_field());
+    let order_clause = match order {
+        Some(order) if order.column() != T::primary_key_field() => format!("{}, {}", order.to_sql(db_type), primary_key),
+        Some(order) => order.to_sql(db_type),
+        None => primary_key,
+    };
+
+    match db_type {
+        DatabaseType::PostgreSQL => {
+            let sql = format!(
+                "SELECT *, COUNT(*) OVER() AS __total_count FROM {}{} ORDER BY {} LIMIT {} OFFSET {}",
+                db_type.quote_identifier(&conn.table_path(&T::table_name())),
+                live_where::<T>(db_type),
+                order_clause,
+                db_type.placeholder(1),
+                db_type.placeholder(2)
+            );
+            let rows: Vec<serde_json::Map<String, serde_json::Value>> = conn.query_raw(&sql, params)?;
+
+            // A page past the end has no rows to carry the window count, so fall back to COUNT(*)
+            let total = match rows.first().and_then(|row| row.get("__total_count")).and_then(|v| v.as_i64()) {
+                Some(total) => total,
+                None => T::count(conn)?,
+            };
+
+            let items = rows.into_iter()
+                .map(|mut row| {
+                    row.remove("__total_count");
+                    decode_row::<T>(conn, row)
+                })
+                .collect::<Result<Vec<T>, RusticxError>>()?;
+
+            Page::new(items, total, page, per_page)
+        }
+        _ => {
+            let total = T::count(conn)?;
+            let sql = format!(
+                "SELECT * FROM {}{} ORDER BY {} LIMIT {} OFFSET {}",
+                db_type.quote_identifier(&conn.table_path(&T::table_name())),
+                live_where::<T>(db_type),
+                order_clause,
+                db_type.placeholder(1),
+                db_type.placeholder(2)
+            );
+            let items = T::find_with_sql(conn, &sql, params)?;
+
+            Page::new(items, total, page, per_page)
+        }
+    }
+}
+
+/// Runs `sql` once and decodes the rows into `T` with `decode_row`, so
+/// converted fields and the connection's coercions apply without running
+/// the query again.
+fn query_models<T: SQLModel>(
+    conn: &Connection,
+    sql: &str,
+    params: &[&(dyn ToSql + Sync + 'static)],
+) -> Result<Vec<T>, RusticxError> {
+    let rows: Vec<serde_json::Map<String, serde_json::Value>> = conn.query_raw(sql, params)?;
+    rows.into_iter().map(|row| decode_row::<T>(conn, row)).collect()
+}
+
+/// Decodes one row into `T` after applying the connection's coercions.
+pub(crate) fn decode_row<T: SQLModel>(conn: &Connection, mut row: serde_json::Map<String, serde_json::Value>) -> Result<T, RusticxError> {
+    if let Some(coercions) = conn.coercions() {
+        coercions.apply::<T>(&mut row);
+    }
+    let row = serde_json::Value::Object(row);
+    // Direct deserialization first, falling back to the generated from_row
+    serde_json::from_value(row.clone()).or_else(|_| T::from_row(&row))
+}
+
+/// ` WHERE <live condition>` for models with one, otherwise empty.
+fn live_where<T: SQLModel>(db_type: &DatabaseType) -> String {
+    live_condition::<T>(db_type).map(|c| format!(" WHERE {}", c)).unwrap_or_default()
+}
+
+/// ` AND <live condition>` for models with one, otherwise empty.
+fn live_and<T: SQLModel>(db_type: &DatabaseType) -> String {
+    live_condition::<T>(db_type).map(|c| format!(" AND {}", c)).unwrap_or_default()
 }
 
 /// Helper trait to bridge the gap between specific model field types and `dyn ToSql`.
@@ -520,6 +2019,22 @@ pub trait ToSqlConvert: Debug + Sync + Send {
     fn is_null(&self) -> bool {
         false
     }
+
+    /// The error converting the field into this value, such as a failed
+    /// compression, if there was one. The ORM reports it instead of binding.
+    fn conversion_error(&self) -> Option<&RusticxError> {
+        None
+    }
+}
+
+/// The values of `model`'s fields for binding, or the first error
+/// converting one of them.
+pub(crate) fn field_values<T: SQLModel>(model: &T) -> Result<Vec<Box<dyn ToSqlConvert>>, RusticxError> {
+    let values = model.to_sql_field_values();
+    match values.iter().find_map(|value| value.conversion_error()) {
+        Some(e) => Err(e.clone()),
+        None => Ok(values),
+    }
 }
 
 // --- Implementations of ToSqlConvert for common types ---
@@ -547,6 +2062,10 @@ impl<T: ToSqlConvert + Clone + Debug + Sync + Send + 'static> ToSqlConvert for O
     fn is_null(&self) -> bool {
         self.is_none()
     }
+
+    fn conversion_error(&self) -> Option<&RusticxError> {
+        self.as_ref().and_then(|inner| inner.conversion_error())
+    }
 }
 
 // Implementation for Box<T> where T itself implements ToSqlConvert
@@ -558,6 +2077,26 @@ impl<T: ToSqlConvert + ?Sized + Debug + Sync + Send + 'static> ToSqlConvert for
     fn is_null(&self) -> bool {
         (**self).is_null()
     }
+
+    fn conversion_error(&self) -> Option<&RusticxError> {
+        (**self).conversion_error()
+    }
+}
+
+// Implementation for a converted value, such as a compressed field, that may
+// have failed to convert
+impl<T: ToSqlConvert + 'static> ToSqlConvert for Result<T, RusticxError> {
+    fn as_ref_postgres(&self) -> Option<&(dyn ToSql + Sync + 'static)> {
+        self.as_ref().ok().and_then(|inner| inner.as_ref_postgres())
+    }
+
+    fn is_null(&self) -> bool {
+        self.as_ref().is_ok_and(|inner| inner.is_null())
+    }
+
+    fn conversion_error(&self) -> Option<&RusticxError> {
+        self.as_ref().err()
+    }
 }
 
 // Implementation for String
@@ -567,12 +2106,12 @@ impl ToSqlConvert for String {
     }
 }
 
-// // Implementation for &str
-// impl ToSqlConvert for &str {
-//      fn as_ref_postgres(&self) -> Option<&(dyn ToSql + Sync + 'static)> {
-//          Some(self)
-//      }
-// }
+// Implementation for string literals
+impl ToSqlConvert for &'static str {
+    fn as_ref_postgres(&self) -> Option<&(dyn ToSql + Sync + 'static)> {
+        Some(self)
+    }
+}
 
 
 // Implementation for i32
@@ -639,6 +2178,13 @@ impl ToSqlConvert for chrono::NaiveTime {
     }
 }
 
+// Implementation for serde_json::Value (for JSON/JSONB columns)
+impl ToSqlConvert for serde_json::Value {
+    fn as_ref_postgres(&self) -> Option<&(dyn ToSql + Sync + 'static)> {
+        Some(self)
+    }
+}
+
 // TODO: For true multi-database support using this trait structure,
 // the ToSqlConvert trait would need to provide references compatible
 // with *each* enabled database driver's parameter trait (e.g.,
@@ -648,6 +2194,19 @@ impl ToSqlConvert for chrono::NaiveTime {
 // As currently designed, parameter binding via ToSqlConvert is strongly
 // coupled to the `postgres` crate's `ToSql` trait signature.
 
+/// How result columns are matched to model fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RowMapping {
+    /// Match columns by name. Tolerates any column order, such as `SELECT *`
+    /// against a table whose columns were added or reordered.
+    #[default]
+    ByName,
+    /// Match columns by position in `field_names` order. Faster, as no
+    /// per-row map is built, but the query must select the model's columns
+    /// in that order.
+    ByPosition,
+}
+
 #[derive(Debug, Clone)]
 pub enum ModelAttribute {
     PrimaryKey,
@@ -0,0 +1,622 @@
+use std::time::Duration;
+use tokio::runtime::Handle;
+use crate::connection::{Connection, DatabaseType};
+use crate::error::RusticxError;
+use crate::tls::MySqlTls;
+
+/// Connection settings given one by one instead of as a URL.
+///
+/// Created with `Connection::builder`:
+///
+/// ```ignore
+/// let conn = Connection::builder(DatabaseType::PostgreSQL)
+///     .host("db.internal")
+///     .user("app")
+///     .password("p@ss/word")
+///     .database("shop")
+///     .connect_timeout(Duration::from_secs(5))
+///     .statement_timeout(Duration::from_secs(30))
+///     .application_name("checkout")
+///     .connect()?;
+/// ```
+///
+/// Values are escaped for the URL the connection is opened with, so
+/// passwords may contain any character. For SQLite only `database`, the file
+/// path, and `statement_timeout` apply.
+#[derive(Debug, Clone)]
+pub struct ConnectionOptions {
+    db_type: DatabaseType,
+    host: String,
+    port: Option<u16>,
+    user: Option<String>,
+    password: Option<String>,
+    database: Option<String>,
+    pool_size: Option<(usize, usize)>,
+    connect_timeout: Option<Duration>,
+    statement_timeout: Option<Duration>,
+    application_name: Option<String>,
+    tls: Option<MySqlTls>,
+    schema: Option<String>,
+    qualify_tables: bool,
+    runtime: Option<Handle>,
+    statement_cache: Option<usize>,
+}
+
+impl ConnectionOptions {
+    /// Settings for a `db_type` server on `localhost` at its default port.
+    pub fn new(db_type: DatabaseType) -> Self {
+        ConnectionOptions {
+            db_type,
+            host: "localhost".to_string(),
+            port: None,
+            user: None,
+            password: None,
+            database: None,
+            pool_size: None,
+            connect_timeout: None,
+            statement_timeout: None,
+            application_name: None,
+            tls: None,
+            schema: None,
+            qualify_tables: false,
+            runtime: None,
+            statement_cache: None,
+        }
+    }
+
+    /// The server's host name or IP address.
+    pub fn host(mut self, host: &str) -> Self {
+        self.host = host.to_string();
+        self
+    }
+
+    /// The server's port (5432 for PostgreSQL and 3306 for MySQL otherwise).
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// The user to log in as.
+    pub fn user(mut self, user: &str) -> Self {
+        self.user = Some(user.to_string());
+        self
+    }
+
+    /// The user's password.
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = Some(password.to_string());
+        self
+    }
+
+    /// The database to use, or the file path for SQLite.
+    pub fn database(mut self, database: &str) -> Self {
+        self.database = Some(database.to_string());
+        self
+    }
+
+    /// Keeps between `min` and `max` connections open (MySQL only; a
+    /// PostgreSQL or SQLite `Connection` is a single session, see
+    /// `Connection::with_partition`).
+    pub fn pool_size(mut self, min: usize, max: usize) -> Self {
+        self.pool_size = Some((min, max));
+        self
+    }
+
+    /// Fails connecting after `timeout` instead of waiting on the OS.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Makes the server cancel statements that run longer than `timeout`.
+    ///
+    /// On MySQL this sets `max_execution_time`, which only applies to
+    /// `SELECT` statements. On SQLite it sets `busy_timeout`, how long a
+    /// statement waits for another connection's lock before failing. See
+    /// `Connection::with_timeout` for a limit on individual queries.
+    pub fn statement_timeout(mut self, timeout: Duration) -> Self {
+        self.statement_timeout = Some(timeout);
+        self
+    }
+
+    /// Names the client in the server's session list (`pg_stat_activity`,
+    /// or the `program_name` connection attribute on MySQL).
+    pub fn application_name(mut self, name: &str) -> Self {
+        self.application_name = Some(name.to_string());
+        self
+    }
+
+    /// Uses the TLS settings `tls` (MySQL only).
+    pub fn tls(mut self, tls: MySqlTls) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// The schema unqualified table names resolve in: the `search_path` on
+    /// PostgreSQL, or the database on MySQL (in place of `database`).
+    ///
+    /// ```ignore
+    /// let staging = Connection::builder(DatabaseType::PostgreSQL)
+    ///     .database("shop")
+    ///     .schema("staging")
+    ///     .qualify_tables(true)
+    ///     .connect()?;
+    /// ```
+    pub fn schema(mut self, schema: &str) -> Self {
+        self.schema = Some(schema.to_string());
+        self
+    }
+
+    /// Also names tables as `schema.table` in the SQL models and query
+    /// builders generate, so statements do not depend on the session's
+    /// search path (for example behind a transaction-pooling proxy).
+    /// `create_table` still relies on the search path.
+    pub fn qualify_tables(mut self, qualify: bool) -> Self {
+        self.qualify_tables = qualify;
+        self
+    }
+
+    /// Runs the session on the Tokio runtime behind `runtime` (PostgreSQL
+    /// only); see `Connection::new_with_runtime`.
+    pub fn runtime(mut self, runtime: Handle) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    /// Keeps up to `capacity` prepared statements per session, keyed by
+    /// their SQL, so repeated queries skip parsing and planning.
+    ///
+    /// PostgreSQL sessions cache none unless this is set. MySQL and SQLite
+    /// sessions cache 32 and 16 statements by default; `0` turns their
+    /// caches off.
+    pub fn statement_cache(mut self, capacity: usize) -> Self {
+        self.statement_cache = Some(capacity);
+        self
+    }
+
+    /// Opens the connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::ValidationError` for settings the database
+    /// does not support, an invalid pool size, or `qualify_tables` without
+    /// a schema, and otherwise fails as `Connection::new` does.
+    pub fn connect(self) -> Result<Connection, RusticxError> {
+        if self.qualify_tables && self.schema.is_none() {
+            return Err(RusticxError::ValidationError(
+                "Table names can only be qualified when a schema is set".to_string(),
+            ));
+        }
+        let url = self.to_url()?;
+        let conn = match (self.tls, self.runtime) {
+            (Some(tls), _) => Connection::new_with_tls(&url, tls)?,
+            (None, Some(runtime)) => Connection::new_with_runtime(&url, runtime)?,
+            (None, None) => Connection::new(&url)?,
+        };
+        match self.schema {
+            Some(schema) if self.qualify_tables => Ok(conn.in_schema(&schema)),
+            _ => Ok(conn),
+        }
+    }
+
+    /// Renders the settings as a connection URL.
+    fn to_url(&self) -> Result<String, RusticxError> {
+        let unsupported = |setting: &str| {
+            Err(RusticxError::ValidationError(format!(
+                "{:?} connections do not support the {} setting",
+                self.db_type, setting
+            )))
+        };
+
+        if let DatabaseType::SQLite = self.db_type {
+            let database = self.database.as_deref().ok_or_else(|| {
+                RusticxError::ValidationError("SQLite connections need a database file path".to_string())
+            })?;
+            return match self {
+                ConnectionOptions { port: Some(_), .. } => unsupported("port"),
+                ConnectionOptions { user: Some(_), .. } | ConnectionOptions { password: Some(_), .. } => {
+                    unsupported("user or password")
+                }
+                ConnectionOptions { pool_size: Some(_), .. } => unsupported("pool size"),
+                ConnectionOptions { connect_timeout: Some(_), .. } => unsupported("connect timeout"),
+                ConnectionOptions { application_name: Some(_), .. } => unsupported("application name"),
+                ConnectionOptions { tls: Some(_), .. } => unsupported("TLS"),
+                ConnectionOptions { schema: Some(_), .. } => unsupported("schema"),
+                ConnectionOptions { runtime: Some(_), .. } => unsupported("runtime"),
+                _ => {
+                    let mut params = Vec::new();
+                    if let Some(timeout) = self.statement_timeout {
+                        params.push(format!("busy_timeout={}", timeout.as_millis()));
+                    }
+                    if let Some(capacity) = self.statement_cache {
+                        params.push(format!("statement_cache_size={}", capacity));
+                    }
+                    Ok(if params.is_empty() {
+                        format!("sqlite://{}", database)
+                    } else {
+                        format!("sqlite://{}?{}", database, params.join("&"))
+                    })
+                }
+            };
+        }
+
+        let mysql = matches!(self.db_type, DatabaseType::MySQL);
+        let mut url = if mysql { "mysql://".to_string() } else { "postgresql://".to_string() };
+        if let Some(user) = &self.user {
+            url.push_str(&percent_encode(user));
+            if let Some(password) = &self.password {
+                url.push(':');
+                url.push_str(&percent_encode(password));
+            }
+            url.push('@');
+        } else if self.password.is_some() {
+            return Err(RusticxError::ValidationError("A password was given without a user".to_string()));
+        }
+        let host = if self.host.contains(':') { format!("[{}]", self.host) } else { self.host.clone() };
+        url.push_str(&format!("{}:{}", host, self.port.unwrap_or(if mysql { 3306 } else { 5432 })));
+        let database = match (&self.database, &self.schema) {
+            (Some(database), Some(schema)) if mysql && database != schema => {
+                return Err(RusticxError::ValidationError(format!(
+                    "MySQL schemas are databases: schema '{}' conflicts with database '{}'",
+                    schema, database
+                )))
+            }
+            (_, Some(schema)) if mysql => Some(schema),
+            (database, _) => database.as_ref(),
+        };
+        if let Some(database) = database {
+            url.push('/');
+            url.push_str(&percent_encode(database));
+        }
+
+        let mut params = Vec::new();
+        if let Some((min, max)) = self.pool_size {
+            if !mysql {
+                return unsupported("pool size");
+            }
+            if max == 0 || min > max {
+                return Err(RusticxError::ValidationError(format!(
+                    "Invalid pool size {}..{}: the maximum must be at least 1 and the minimum",
+                    min, max
+                )));
+            }
+            params.push(format!("pool_min={}", min));
+            params.push(format!("pool_max={}", max));
+        }
+        if let Some(timeout) = self.connect_timeout {
+            if mysql {
+                params.push(format!("tcp_connect_timeout_ms={}", timeout.as_millis()));
+            } else {
+                // PostgreSQL takes whole seconds; 0 would mean no timeout.
+                let seconds = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+                params.push(format!("connect_timeout={}", seconds.max(1)));
+            }
+        }
+        // PostgreSQL takes session settings as `-c name=value` server options
+        let mut options = Vec::new();
+        if let Some(timeout) = self.statement_timeout {
+            if mysql {
+                params.push(format!("statement_timeout={}", timeout.as_millis()));
+            } else {
+                options.push(format!("-c statement_timeout={}", timeout.as_millis()));
+            }
+        }
+        if let (Some(schema), false) = (&self.schema, mysql) {
+            // Quoted so the name keeps its case; spaces and backslashes in
+            // server options are escaped with a backslash
+            let quoted = format!("\"{}\"", schema.replace('"', "\"\""));
+            options.push(format!("-c search_path={}", quoted.replace('\\', "\\\\").replace(' ', "\\ ")));
+        }
+        if !options.is_empty() {
+            params.push(format!("options={}", percent_encode(&options.join(" "))));
+        }
+        if let Some(name) = &self.application_name {
+            params.push(format!("application_name={}", percent_encode(name)));
+        }
+        if let Some(capacity) = self.statement_cache {
+            params.push(format!("statement_cache_size={}", capacity));
+        }
+        if self.tls.is_some() && !mysql {
+            return unsupported("TLS");
+        }
+        if self.runtime.is_some() && mysql {
+            return unsupported("runtime");
+        }
+
+        if !params.is_empty() {
+            url.push('?');
+            url.push_str(&params.join("&"));
+        }
+        Ok(url)
+    }
+}
+
+/// Splits the `statement_timeout` (milliseconds) and `application_name`
+/// parameters, which the MySQL driver does not know, off a MySQL URL.
+#[cfg_attr(not(feature = "mysql"), allow(dead_code))]
+pub(crate) fn split_mysql_session(url: &str) -> Result<(String, Option<u64>, Option<String>), RusticxError> {
+    let Some((base, query)) = url.split_once('?') else {
+        return Ok((url.to_string(), None, None));
+    };
+
+    let (mut statement_timeout, mut application_name) = (None, None);
+    let mut rest = Vec::new();
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        match pair.split_once('=') {
+            Some(("statement_timeout", value)) => {
+                statement_timeout = Some(value.parse::<u64>().map_err(|_| {
+                    RusticxError::ValidationError(format!(
+                        "Invalid statement_timeout '{}': expected milliseconds",
+                        value
+                    ))
+                })?)
+            }
+            Some(("application_name", value)) => application_name = Some(crate::tls::percent_decode(value)),
+            _ => rest.push(pair),
+        }
+    }
+
+    let url = if rest.is_empty() { base.to_string() } else { format!("{}?{}", base, rest.join("&")) };
+    Ok((url, statement_timeout, application_name))
+}
+
+/// Removes the parameter `name` from `url`, returning the URL without it
+/// and the parameter's value.
+pub(crate) fn take_url_param(url: &str, name: &str) -> (String, Option<String>) {
+    let Some((base, query)) = url.split_once('?') else {
+        return (url.to_string(), None);
+    };
+
+    let mut value = None;
+    let mut rest = Vec::new();
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        match pair.split_once('=') {
+            Some((key, v)) if key == name => value = Some(crate::tls::percent_decode(v)),
+            _ => rest.push(pair),
+        }
+    }
+
+    let url = if rest.is_empty() { base.to_string() } else { format!("{}?{}", base, rest.join("&")) };
+    (url, value)
+}
+
+/// The `statement_cache_size` a URL sets: how many prepared statements each
+/// session keeps for reuse.
+#[cfg_attr(not(any(feature = "postgres", feature = "rusqlite")), allow(dead_code))]
+pub(crate) fn statement_cache_size(url: &str) -> Result<Option<usize>, RusticxError> {
+    take_url_param(url, "statement_cache_size")
+        .1
+        .map(|size| {
+            size.parse::<usize>().map_err(|_| {
+                RusticxError::ValidationError(format!(
+                    "Invalid statement_cache_size '{}': expected a whole number",
+                    size
+                ))
+            })
+        })
+        .transpose()
+}
+
+/// The environment variables `Connection::from_env` reads besides
+/// `DATABASE_URL`, with the URL parameter each sets.
+const ENV_PARAMS: [(&str, &str); 5] = [
+    ("DATABASE_POOL_SIZE", "pool_max_size"),
+    ("DATABASE_CONNECT_TIMEOUT", "connect_timeout"),
+    ("DATABASE_SSLMODE", "sslmode"),
+    ("DATABASE_APPLICATION_NAME", "application_name"),
+    ("DATABASE_STATEMENT_CACHE_SIZE", "statement_cache_size"),
+];
+
+/// The connection URL the variables `lookup` returns describe:
+/// `DATABASE_URL`, with the parameters of the other `DATABASE_*` variables
+/// that are set replacing those in it.
+pub(crate) fn url_from_env<F>(lookup: F) -> Result<String, RusticxError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let var = |name: &str| lookup(name).filter(|value| !value.trim().is_empty());
+    let mut url = var("DATABASE_URL")
+        .ok_or_else(|| RusticxError::ValidationError("The DATABASE_URL environment variable is not set".to_string()))?;
+
+    for (name, param) in ENV_PARAMS {
+        if let Some(value) = var(name) {
+            // Only MySQL connections keep a pool to size
+            if param == "pool_max_size" && !url.starts_with("mysql://") {
+                log::info!("Ignoring {}: only MySQL connections are pooled", name);
+                continue;
+            }
+            url = take_url_param(&url, param).0;
+            url.push(if url.contains('?') { '&' } else { '?' });
+            url.push_str(&format!("{}={}", param, percent_encode(value.trim())));
+        }
+    }
+    Ok(url)
+}
+
+/// Checks the settings given to `Connection::set_session` for `db_type`.
+pub(crate) fn session_settings(
+    db_type: &DatabaseType,
+    settings: &[(&str, &str)],
+) -> Result<Vec<(String, String)>, RusticxError> {
+    settings
+        .iter()
+        .map(|&(name, value)| {
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+                return Err(RusticxError::ValidationError(format!("Invalid session setting name '{}'", name)));
+            }
+            let lower = name.to_ascii_lowercase();
+            if matches!(db_type, DatabaseType::SQLite) && matches!(lower.as_str(), "timezone" | "search_path" | "names") {
+                return Err(RusticxError::ValidationError(format!("SQLite has no {} setting", name)));
+            }
+            Ok((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// The statements that apply `set_session` settings to a MySQL session.
+#[cfg_attr(not(feature = "mysql"), allow(dead_code))]
+pub(crate) fn mysql_session_statements(session: &[(String, String)]) -> Vec<String> {
+    let literal = |value: &str| match value.parse::<f64>() {
+        Ok(_) => value.to_string(),
+        Err(_) => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "''")),
+    };
+    session
+        .iter()
+        .map(|(name, value)| match name.to_ascii_lowercase().as_str() {
+            "names" => format!("SET NAMES {}", literal(value)),
+            "timezone" | "time_zone" => {
+                // Named zones need the server's time zone tables; UTC does not
+                let zone = if value.eq_ignore_ascii_case("UTC") { "+00:00" } else { value };
+                format!("SET time_zone = {}", literal(zone))
+            }
+            "search_path" => format!("USE {}", DatabaseType::MySQL.quote_identifier(value)),
+            _ => format!("SET SESSION {} = {}", name, literal(value)),
+        })
+        .collect()
+}
+
+/// The `PRAGMA` statements that apply `set_session` settings to a SQLite
+/// connection.
+#[cfg_attr(not(feature = "rusqlite"), allow(dead_code))]
+pub(crate) fn sqlite_pragmas(session: &[(String, String)]) -> String {
+    session
+        .iter()
+        .map(|(name, value)| {
+            let bare = !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            if bare {
+                format!("PRAGMA {} = {};", name, value)
+            } else {
+                format!("PRAGMA {} = '{}';", name, value.replace('\'', "''"))
+            }
+        })
+        .collect()
+}
+
+/// Splits a URL naming several hosts (`mysql://user@a:3306,b:3306/db`)
+/// into one URL per host, in order, each with the host it names.
+#[cfg_attr(not(feature = "mysql"), allow(dead_code))]
+pub(crate) fn split_hosts(url: &str) -> Vec<(String, String)> {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+    let authority_end = rest.find(['/', '?']).unwrap_or(rest.len());
+    let (authority, tail) = rest.split_at(authority_end);
+    let (user, hosts) = match authority.rsplit_once('@') {
+        Some((user, hosts)) => (format!("{}@", user), hosts),
+        None => (String::new(), authority),
+    };
+    hosts
+        .split(',')
+        .map(|host| (host.to_string(), format!("{}://{}{}{}", scheme, user, host, tail)))
+        .collect()
+}
+
+/// Translates the standard URL parameters `connect_timeout` (seconds),
+/// `sslmode`, `application_name`, `pool_max_size` and
+/// `target_session_attrs` into the driver's own, rejecting those `db_type`
+/// connections cannot honour.
+pub(crate) fn apply_url_params(db_type: &DatabaseType, url: &str) -> Result<String, RusticxError> {
+    let Some((base, query)) = url.split_once('?') else {
+        return Ok(url.to_string());
+    };
+    let pairs: Vec<&str> = query.split('&').filter(|p| !p.is_empty()).collect();
+    let has_pool_min = pairs.iter().any(|p| p.starts_with("pool_min="));
+
+    let mut params = Vec::with_capacity(pairs.len());
+    for pair in pairs {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        if name == "statement_cache_size" {
+            if let DatabaseType::MySQL = db_type {
+                // The MySQL driver caches statements itself, under its own name
+                params.push(format!("stmt_cache_size={}", value));
+            } else {
+                params.push(pair.to_string());
+            }
+            continue;
+        }
+        if !matches!(name, "connect_timeout" | "sslmode" | "application_name" | "pool_max_size" | "target_session_attrs") {
+            params.push(pair.to_string());
+            continue;
+        }
+        if let DatabaseType::SQLite = db_type {
+            return Err(RusticxError::ValidationError(format!(
+                "SQLite connections do not support the {} parameter",
+                name
+            )));
+        }
+        let mysql = matches!(db_type, DatabaseType::MySQL);
+        let value = crate::tls::percent_decode(value);
+        let number = || {
+            value.parse::<u64>().map_err(|_| {
+                RusticxError::ValidationError(format!("Invalid {} '{}': expected a whole number", name, value))
+            })
+        };
+
+        match name {
+            "connect_timeout" if mysql => params.push(format!("tcp_connect_timeout_ms={}", number()? * 1000)),
+            "connect_timeout" => params.push(format!("connect_timeout={}", number()?)),
+            "sslmode" => params.push(ssl_mode_param(mysql, &value)?),
+            "pool_max_size" if mysql => {
+                let max = number()?;
+                if max == 0 {
+                    return Err(RusticxError::ValidationError("pool_max_size must be at least 1".to_string()));
+                }
+                // The driver keeps 10 connections by default, more than a small maximum
+                if !has_pool_min {
+                    params.push(format!("pool_min={}", max.min(10)));
+                }
+                params.push(format!("pool_max={}", max));
+            }
+            "pool_max_size" => {
+                return Err(RusticxError::ValidationError(
+                    "A PostgreSQL connection is a single session and has no pool_max_size; see Connection::with_partition"
+                        .to_string(),
+                ))
+            }
+            "target_session_attrs" if mysql && !matches!(value.as_str(), "any" | "read-write") => {
+                return Err(RusticxError::ValidationError(format!(
+                    "Unsupported target_session_attrs '{}' for MySQL: expected any or read-write",
+                    value
+                )))
+            }
+            // application_name and target_session_attrs: PostgreSQL's driver takes
+            // them as they are; for MySQL see split_mysql_session and Connection::connect
+            _ => params.push(pair.to_string()),
+        }
+    }
+
+    Ok(if params.is_empty() { base.to_string() } else { format!("{}?{}", base, params.join("&")) })
+}
+
+/// The driver parameter for a libpq-style `sslmode`.
+fn ssl_mode_param(mysql: bool, mode: &str) -> Result<String, RusticxError> {
+    match (mysql, mode) {
+        (false, "disable" | "allow") => Ok("sslmode=disable".to_string()),
+        (false, "prefer") => Ok("sslmode=prefer".to_string()),
+        (false, "require" | "verify-ca" | "verify-full") => Err(RusticxError::FeatureNotEnabled(format!(
+            "sslmode={} needs TLS, which PostgreSQL connections do not support yet",
+            mode
+        ))),
+        (true, "disable") => Ok("ssl-mode=DISABLED".to_string()),
+        (true, "require") => Ok("ssl-mode=REQUIRED".to_string()),
+        (true, "verify-ca") => Ok("ssl-mode=VERIFY_CA".to_string()),
+        (true, "verify-full") => Ok("ssl-mode=VERIFY_IDENTITY".to_string()),
+        (true, "allow" | "prefer") => Err(RusticxError::ValidationError(format!(
+            "sslmode={} is not supported for MySQL, which cannot fall back between TLS and plain connections; use disable or require",
+            mode
+        ))),
+        _ => Err(RusticxError::ValidationError(format!(
+            "Unknown sslmode '{}': expected disable, allow, prefer, require, verify-ca or verify-full",
+            mode
+        ))),
+    }
+}
+
+/// Escapes everything but unreserved characters for use in a URL.
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,73 @@
+use crate::connection::DatabaseType;
+use crate::error::RusticxError;
+
+/// A validated `ORDER BY` column and direction.
+///
+/// Built from untrusted input such as HTTP sort parameters with
+/// `try_from_user_input`, which only accepts one of the given columns, so the
+/// ordering can be interpolated into SQL safely. Pass it to
+/// `QueryBuilder::order` or `SQLModel::paginate_ordered`.
+///
+/// ```ignore
+/// // GET /users?sort=name&dir=desc
+/// let order = OrderBy::try_from_user_input(&sort, &dir, &User::field_names())?;
+/// let page = User::paginate_ordered(&conn, &order, 1, 20)?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderBy {
+    column: String,
+    ascending: bool,
+}
+
+impl OrderBy {
+    /// Validates a sort column and direction supplied by a user.
+    ///
+    /// `column` must be one of `allowed_columns`, typically `T::field_names()`.
+    /// `direction` is `asc` or `desc` in any case; an empty direction means
+    /// ascending. Surrounding whitespace is ignored in both.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::InvalidColumn` if `column` is not allowed and
+    /// `RusticxError::ValidationError` for an unrecognised direction, so callers
+    /// can answer with a client error rather than a failed query.
+    pub fn try_from_user_input(column: &str, direction: &str, allowed_columns: &[&str]) -> Result<Self, RusticxError> {
+        let column = column.trim();
+        if !allowed_columns.contains(&column) {
+            return Err(RusticxError::InvalidColumn(format!("Cannot order by {}: not a sortable column", column)));
+        }
+
+        let direction = direction.trim();
+        let ascending = if direction.is_empty() || direction.eq_ignore_ascii_case("asc") {
+            true
+        } else if direction.eq_ignore_ascii_case("desc") {
+            false
+        } else {
+            return Err(RusticxError::ValidationError(format!(
+                "Invalid sort direction {}: expected asc or desc",
+                direction
+            )));
+        };
+
+        Ok(OrderBy { column: column.to_string(), ascending })
+    }
+
+    /// The column to order by.
+    pub fn column(&self) -> &str {
+        &self.column
+    }
+
+    /// Whether the ordering is ascending.
+    pub fn is_ascending(&self) -> bool {
+        self.ascending
+    }
+
+    /// Renders `<column> ASC|DESC` for the given dialect.
+    pub(crate) fn to_sql(&self, db_type: &DatabaseType) -> String {
+        format!(
+            "{} {}",
+            db_type.quote_identifier(&self.column),
+            if self.ascending { "ASC" } else { "DESC" }
+        )
+    }
+}
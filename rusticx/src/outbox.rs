@@ -0,0 +1,294 @@
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::connection::{Connection, DatabaseType};
+use crate::error::RusticxError;
+use crate::model::{SQLModel, ToSqlConvert};
+use crate::sql_types::SqlType;
+
+/// A message recorded in the outbox table (`rusticx_outbox`).
+///
+/// Messages are written alongside the application's own changes and later
+/// claimed by a relay that delivers them (to a broker, a webhook, ...) and
+/// marks them done. It is a regular model, so `find_all`, `count` and the
+/// other `SQLModel` methods work on the outbox table as well.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxMessage {
+    /// Auto-incremented primary key.
+    pub id: Option<i32>,
+    /// Routing key the relay uses to pick the destination.
+    pub topic: String,
+    /// The message body as JSON text.
+    pub payload: String,
+    /// How many times the message has been claimed.
+    pub attempts: i32,
+    /// When the message was enqueued (UTC).
+    pub created_at: NaiveDateTime,
+    /// While set and in the future, the message is leased to a worker (UTC).
+    pub claimed_until: Option<NaiveDateTime>,
+    /// When the message was marked done (UTC); `None` while pending.
+    pub processed_at: Option<NaiveDateTime>,
+}
+
+impl OutboxMessage {
+    /// Deserializes the JSON payload into `T`.
+    pub fn payload_as<T>(&self) -> Result<T, RusticxError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        serde_json::from_str(&self.payload).map_err(|e| RusticxError::DeserializationError(e.to_string()))
+    }
+}
+
+impl SQLModel for OutboxMessage {
+    fn table_name() -> String {
+        "rusticx_outbox".to_string()
+    }
+
+    fn primary_key_field() -> String {
+        "id".to_string()
+    }
+
+    fn primary_key_value(&self) -> Option<i32> {
+        self.id
+    }
+
+    fn set_primary_key(&mut self, id: i32) {
+        self.id = Some(id);
+    }
+
+    fn create_table_sql(db_type: &DatabaseType) -> String {
+        let id = match db_type {
+            DatabaseType::PostgreSQL => "INTEGER PRIMARY KEY GENERATED ALWAYS AS IDENTITY",
+            DatabaseType::MySQL => "INT PRIMARY KEY AUTO_INCREMENT",
+            DatabaseType::SQLite => "INTEGER PRIMARY KEY AUTOINCREMENT",
+        };
+        let columns: Vec<String> = Self::field_names()
+            .into_iter()
+            .zip(Self::field_sql_types())
+            .map(|(name, sql_type)| {
+                let definition = match name {
+                    "id" => id.to_string(),
+                    _ => {
+                        let sql_type = match db_type {
+                            DatabaseType::PostgreSQL => sql_type.pg_type(),
+                            DatabaseType::MySQL => sql_type.mysql_type(),
+                            DatabaseType::SQLite => sql_type.sqlite_type(),
+                        };
+                        let nullable = matches!(name, "claimed_until" | "processed_at");
+                        format!("{}{}", sql_type, if nullable { "" } else { " NOT NULL" })
+                    }
+                };
+                format!("{} {}", db_type.quote_identifier(name), definition)
+            })
+            .collect();
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} ({})",
+            db_type.quote_identifier(&Self::table_name()),
+            columns.join(", ")
+        )
+    }
+
+    fn field_names() -> Vec<&'static str> {
+        vec!["id", "topic", "payload", "attempts", "created_at", "claimed_until", "processed_at"]
+    }
+
+    fn field_sql_types() -> Vec<SqlType> {
+        vec![
+            SqlType::Integer,
+            SqlType::Text,
+            SqlType::Text,
+            SqlType::Integer,
+            SqlType::DateTime,
+            SqlType::DateTime,
+            SqlType::DateTime,
+        ]
+    }
+
+    fn to_sql_field_values(&self) -> Vec<Box<dyn ToSqlConvert>> {
+        vec![
+            Box::new(self.id),
+            Box::new(self.topic.clone()),
+            Box::new(self.payload.clone()),
+            Box::new(self.attempts),
+            Box::new(self.created_at),
+            Box::new(self.claimed_until),
+            Box::new(self.processed_at),
+        ]
+    }
+
+    fn from_row(row: &serde_json::Value) -> Result<Self, RusticxError> {
+        serde_json::from_value(row.clone()).map_err(|e| RusticxError::DeserializationError(e.to_string()))
+    }
+}
+
+/// A transactional outbox stored in the `rusticx_outbox` table.
+///
+/// Writers enqueue messages in the same transaction as the change they
+/// describe (`enqueue_in_tx`), so a message exists if and only if the change
+/// was committed. Relays call `claim_batch` to lease pending messages —
+/// concurrent relays skip each other's rows (`FOR UPDATE SKIP LOCKED` on
+/// PostgreSQL and MySQL) — deliver them, and `mark_done` each one. A message whose lease
+/// runs out before it is marked done is claimed again, so delivery is
+/// at-least-once.
+pub struct Outbox {
+    conn: Connection,
+    lease: Duration,
+}
+
+impl Outbox {
+    /// Creates an outbox on `conn` with a 30 second claim lease.
+    pub fn new(conn: Connection) -> Self {
+        Outbox {
+            conn,
+            lease: Duration::from_secs(30),
+        }
+    }
+
+    /// Sets how long claimed messages stay hidden from other relays.
+    pub fn lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    /// Creates the outbox table if it does not exist.
+    pub fn create_table(&self) -> Result<(), RusticxError> {
+        self.conn.create_table::<OutboxMessage>()
+    }
+
+    /// Appends a message outside of any transaction and returns it.
+    pub fn enqueue<P: Serialize>(&self, topic: &str, payload: &P) -> Result<OutboxMessage, RusticxError> {
+        Self::insert_message(&self.conn, topic, payload)
+    }
+
+    /// Writes a new message for `payload` through `conn`.
+    fn insert_message<P: Serialize>(conn: &Connection, topic: &str, payload: &P) -> Result<OutboxMessage, RusticxError> {
+        let payload = serde_json::to_string(payload).map_err(|e| RusticxError::SerializationError(e.to_string()))?;
+        let mut message = OutboxMessage {
+            id: None,
+            topic: topic.to_string(),
+            payload,
+            attempts: 0,
+            created_at: chrono::Utc::now().naive_utc(),
+            claimed_until: None,
+            processed_at: None,
+        };
+
+        let db_type = conn.get_db_type();
+        let columns = ["topic", "payload", "attempts", "created_at"];
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            db_type.quote_identifier(&conn.table_path(&OutboxMessage::table_name())),
+            columns.iter().map(|c| db_type.quote_identifier(c)).collect::<Vec<_>>().join(", "),
+            (1..=columns.len()).map(|i| db_type.placeholder(i)).collect::<Vec<_>>().join(", ")
+        );
+        // Read on the session that ran the insert, never a replica
+        let id = conn.insert_returning_id(
+            &sql,
+            "id",
+            &[&message.topic, &message.payload, &message.attempts, &message.created_at],
+        )?;
+        message.set_primary_key(id as i32);
+
+        Ok(message)
+    }
+
+    /// Runs `work` and enqueues a message in one transaction.
+    ///
+    /// The message is only written if `work` succeeds, and `work`'s changes
+    /// are rolled back if enqueueing fails. `work` receives the transaction
+    /// from `Connection::begin`, so on PostgreSQL and MySQL its statements run
+    /// on a session of their own and other handles' statements stay out of
+    /// the transaction.
+    pub fn enqueue_in_tx<P, R, F>(&self, topic: &str, payload: &P, work: F) -> Result<R, RusticxError>
+    where
+        P: Serialize,
+        F: FnOnce(&Connection) -> Result<R, RusticxError>,
+    {
+        let tx = self.conn.begin()?;
+        let value = work(&tx)?;
+        Self::insert_message(&tx, topic, payload)?;
+        tx.commit()?;
+        Ok(value)
+    }
+
+    /// Leases up to `limit` pending messages of `topic`, oldest first.
+    ///
+    /// Each claimed message has its `attempts` incremented and is hidden from
+    /// other claims until the lease expires or it is marked done. Messages
+    /// are picked and leased in one transaction, skipping rows another relay
+    /// is claiming (`FOR UPDATE SKIP LOCKED` on PostgreSQL and MySQL 8; SQLite
+    /// serializes writers), so concurrent relays never receive the same
+    /// message twice within a lease.
+    pub fn claim_batch(&self, topic: &str, limit: i64) -> Result<Vec<OutboxMessage>, RusticxError> {
+        let now = chrono::Utc::now().naive_utc();
+        let lease = chrono::Duration::from_std(self.lease)
+            .map_err(|e| RusticxError::ValidationError(format!("Invalid outbox lease: {}", e)))?;
+        let claimed_until = now + lease;
+
+        let db_type = self.conn.get_db_type();
+        let table = db_type.quote_identifier(&self.conn.table_path(&OutboxMessage::table_name()));
+        let id = db_type.quote_identifier("id");
+        let claimed = db_type.quote_identifier("claimed_until");
+        let locking = match db_type {
+            DatabaseType::PostgreSQL | DatabaseType::MySQL => " FOR UPDATE SKIP LOCKED",
+            DatabaseType::SQLite => "",
+        };
+        let pending_sql = format!(
+            "SELECT {id} FROM {table} WHERE {topic} = {p1} AND {processed} IS NULL \
+             AND ({claimed} IS NULL OR {claimed} < {p2}) ORDER BY {id} LIMIT {p3}{locking}",
+            topic = db_type.quote_identifier("topic"),
+            processed = db_type.quote_identifier("processed_at"),
+            p1 = db_type.placeholder(1),
+            p2 = db_type.placeholder(2),
+            p3 = db_type.placeholder(3),
+        );
+
+        let tx = self.conn.begin()?;
+        let topic = topic.to_string();
+        let pending: Vec<serde_json::Value> = tx.query_raw(&pending_sql, &[&topic, &now, &limit])?;
+        let ids: Vec<i64> = pending.iter().filter_map(|row| row["id"].as_i64()).collect();
+        if ids.is_empty() {
+            tx.commit()?;
+            return Ok(Vec::new());
+        }
+
+        let id_list = ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ");
+        let attempts = db_type.quote_identifier("attempts");
+        tx.execute(
+            &format!(
+                "UPDATE {table} SET {claimed} = {p1}, {attempts} = {attempts} + 1 WHERE {id} IN ({id_list})",
+                p1 = db_type.placeholder(1),
+            ),
+            &[&claimed_until],
+        )?;
+        let messages: Vec<OutboxMessage> =
+            tx.query_raw(&format!("SELECT * FROM {table} WHERE {id} IN ({id_list}) ORDER BY {id}"), &[])?;
+        tx.commit()?;
+        Ok(messages)
+    }
+
+    /// Marks a claimed message as delivered so it is never claimed again.
+    ///
+    /// Returns `Err(RusticxError::NotFound)` if no pending message has that id.
+    pub fn mark_done(&self, id: i32) -> Result<(), RusticxError> {
+        let db_type = self.conn.get_db_type();
+        let processed = db_type.quote_identifier("processed_at");
+        let sql = format!(
+            "UPDATE {} SET {} = {} WHERE {} = {} AND {} IS NULL",
+            db_type.quote_identifier(&self.conn.table_path(&OutboxMessage::table_name())),
+            processed,
+            db_type.placeholder(1),
+            db_type.quote_identifier("id"),
+            db_type.placeholder(2),
+            processed
+        );
+        let now = chrono::Utc::now().naive_utc();
+        if self.conn.execute(&sql, &[&now, &id])? == 0 {
+            return Err(RusticxError::NotFound(format!("No pending outbox message with id {}", id)));
+        }
+        Ok(())
+    }
+}
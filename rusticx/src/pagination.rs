@@ -0,0 +1,112 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use crate::error::RusticxError;
+
+/// A single page of results produced by offset pagination.
+///
+/// Returned by `SQLModel::paginate`. Besides the items of the requested page
+/// it carries the total number of rows so callers can render page links.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    /// The records on this page.
+    pub items: Vec<T>,
+    /// The total number of records across all pages.
+    pub total: i64,
+    /// The 1-based number of this page.
+    pub page: i64,
+    /// The maximum number of records per page.
+    pub per_page: i64,
+    /// The total number of pages (0 when there are no records).
+    pub total_pages: i64,
+}
+
+impl<T> Page<T> {
+    /// Creates a page, deriving `total_pages` from `total` and `per_page`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::ValidationError` if `total_pages` cannot be
+    /// computed without overflowing an `i64`.
+    pub fn new(items: Vec<T>, total: i64, page: i64, per_page: i64) -> Result<Self, RusticxError> {
+        let total_pages = if per_page > 0 {
+            total
+                .checked_add(per_page - 1)
+                .ok_or_else(|| {
+                    RusticxError::ValidationError(format!(
+                        "Invalid pagination: {} records in pages of {} overflow the page count",
+                        total, per_page
+                    ))
+                })?
+                / per_page
+        } else {
+            0
+        };
+        Ok(Page {
+            items,
+            total,
+            page,
+            per_page,
+            total_pages,
+        })
+    }
+
+    /// Returns `true` if there is a page after this one.
+    pub fn has_next(&self) -> bool {
+        self.page < self.total_pages
+    }
+
+    /// Returns `true` if there is a page before this one.
+    pub fn has_prev(&self) -> bool {
+        self.page > 1
+    }
+}
+
+/// A slice of results produced by keyset (cursor-based) pagination.
+///
+/// Returned by `SQLModel::find_after`. Pass `next_cursor` back to fetch the
+/// following slice; it is `None` once the end of the table is reached.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CursorPage<T> {
+    /// The records in this slice.
+    pub items: Vec<T>,
+    /// Opaque cursor pointing after the last item, if more records exist.
+    pub next_cursor: Option<String>,
+}
+
+/// Encodes the cursor column value and primary key of the last row into an
+/// opaque, URL-safe cursor string.
+pub(crate) fn encode_cursor(cursor_value: &serde_json::Value, pk_value: &serde_json::Value) -> String {
+    let payload = serde_json::Value::Array(vec![cursor_value.clone(), pk_value.clone()]);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(payload.to_string())
+}
+
+/// Decodes a cursor produced by `encode_cursor` into the textual form of the
+/// cursor column value (`None` when it was NULL) and primary key, ready to be
+/// bound as parameters.
+pub(crate) fn decode_cursor(cursor: &str) -> Result<(Option<String>, String), RusticxError> {
+    let invalid = || RusticxError::ValidationError(format!("Invalid pagination cursor: {}", cursor));
+
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(cursor)
+        .map_err(|_| invalid())?;
+    let payload: Vec<serde_json::Value> = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+
+    match payload.as_slice() {
+        [serde_json::Value::Null, pk_value] => Ok((None, cursor_value_to_text(pk_value).ok_or_else(invalid)?)),
+        [cursor_value, pk_value] => Ok((
+            Some(cursor_value_to_text(cursor_value).ok_or_else(invalid)?),
+            cursor_value_to_text(pk_value).ok_or_else(invalid)?,
+        )),
+        _ => Err(invalid()),
+    }
+}
+
+/// Converts a JSON scalar into text; the query casts it back to the column type.
+fn cursor_value_to_text(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        serde_json::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
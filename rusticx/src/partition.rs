@@ -0,0 +1,130 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use crate::connection::ConnectionPool;
+use crate::error::RusticxError;
+use crate::metrics::PoolStats;
+
+/// Sizing and timeout settings for a named connection partition.
+///
+/// Partitions give separate workloads (for example "interactive" request
+/// traffic and "batch" exports) their own dedicated connections, so a long
+/// batch scan cannot hold the connection latency-sensitive queries need.
+/// Register one with `Connection::with_partition` and select it per call with
+/// `Connection::partition`.
+#[derive(Debug, Clone)]
+pub struct PartitionConfig {
+    /// The number of dedicated connections in the partition.
+    pub size: usize,
+    /// How long `Connection::partition` waits for a free connection before
+    /// failing.
+    pub acquire_timeout: Duration,
+}
+
+impl PartitionConfig {
+    /// Creates a partition of `size` connections with a 30 second acquire timeout.
+    pub fn new(size: usize) -> Self {
+        PartitionConfig {
+            size,
+            acquire_timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Sets how long to wait for a free connection.
+    pub fn acquire_timeout(mut self, timeout: Duration) -> Self {
+        self.acquire_timeout = timeout;
+        self
+    }
+}
+
+/// A named set of dedicated connections with checkout tracking.
+pub(crate) struct Partition {
+    name: String,
+    config: PartitionConfig,
+    slots: Vec<ConnectionPool>,
+    in_use: Mutex<Vec<bool>>,
+    released: Condvar,
+    stats: PoolStats,
+}
+
+impl Partition {
+    pub(crate) fn new(name: &str, config: PartitionConfig, slots: Vec<ConnectionPool>, stats: PoolStats) -> Self {
+        let in_use = Mutex::new(vec![false; slots.len()]);
+        stats.set_size(slots.len());
+        Partition {
+            name: name.to_string(),
+            config,
+            slots,
+            in_use,
+            released: Condvar::new(),
+            stats,
+        }
+    }
+
+    /// Takes the partition's connections, to close them.
+    pub(crate) fn into_slots(self) -> Vec<ConnectionPool> {
+        self.slots
+    }
+
+    /// The partition's gauges; a leased connection counts as in flight.
+    pub(crate) fn stats(&self) -> &PoolStats {
+        &self.stats
+    }
+
+    /// Checks out a free connection, waiting up to the configured acquire timeout.
+    pub(crate) fn acquire(self: &Arc<Self>) -> Result<(ConnectionPool, Lease), RusticxError> {
+        let acquired = self.stats.wait(|| self.wait_for_slot())?;
+        self.stats.start();
+        Ok(acquired)
+    }
+
+    fn wait_for_slot(self: &Arc<Self>) -> Result<(ConnectionPool, Lease), RusticxError> {
+        let deadline = Instant::now() + self.config.acquire_timeout;
+        let mut in_use = self.in_use.lock().unwrap_or_else(|e| e.into_inner());
+
+        loop {
+            if let Some(slot) = in_use.iter().position(|busy| !busy) {
+                in_use[slot] = true;
+                let lease = Lease { partition: Arc::clone(self), slot };
+                return Ok((self.slots[slot].clone(), lease));
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(RusticxError::ConnectionError(format!(
+                    "Timed out after {:?} waiting for a connection in partition '{}' ({} connections, all in use)",
+                    self.config.acquire_timeout,
+                    self.name,
+                    self.slots.len()
+                )));
+            }
+            in_use = self
+                .released
+                .wait_timeout(in_use, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+}
+
+/// A checked-out partition connection; returned to the partition when dropped.
+pub(crate) struct Lease {
+    partition: Arc<Partition>,
+    slot: usize,
+}
+
+impl Lease {
+    /// The gauges of the partition the connection belongs to.
+    pub(crate) fn stats(&self) -> &PoolStats {
+        self.partition.stats()
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        let mut in_use = self.partition.in_use.lock().unwrap_or_else(|e| e.into_inner());
+        in_use[self.slot] = false;
+        self.partition.released.notify_one();
+        drop(in_use);
+        self.partition.stats.finish();
+    }
+}
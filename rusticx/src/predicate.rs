@@ -0,0 +1,405 @@
+use std::sync::Arc;
+
+use crate::connection::DatabaseType;
+use crate::error::RusticxError;
+use crate::model::{SQLModel, ToSqlConvert};
+use crate::query_builder::{SqlParam, Subquery};
+
+/// A reusable, parameterized `WHERE` condition.
+///
+/// Predicates are built with `col` and combined with `and`, `or` and `not`.
+/// They own their values and are cheap to clone, so a common filter can be
+/// defined once and passed to `SQLModel::find_where`, `count_where`,
+/// `delete_where` and `QueryBuilder::filter_pred` alike.
+///
+/// ```ignore
+/// let active_adults = col("age").ge(18).and(col("status").eq("active"));
+/// let users = User::find_where(&conn, &active_adults)?;
+/// let purged = User::delete_where(&conn, &active_adults.clone().not())?;
+/// ```
+///
+/// Column names are checked against the model's `field_names` when the
+/// predicate is used and quoted for the connection's dialect; values are
+/// always bound as parameters.
+#[derive(Debug, Clone)]
+pub struct Predicate {
+    node: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Compare { column: String, op: &'static str, value: Arc<dyn ToSqlConvert> },
+    Null { column: String, negated: bool },
+    In { column: String, values: Vec<Arc<dyn ToSqlConvert>>, negated: bool },
+    InQuery { column: String, query: Arc<Subquery>, negated: bool },
+    ILike { column: String, pattern: String },
+    EqIgnoreCase { column: String, value: String },
+    Between { column: String, low: Arc<dyn ToSqlConvert>, high: Arc<dyn ToSqlConvert> },
+    Raw(String),
+    Sql { condition: String, values: Vec<Arc<dyn ToSqlConvert>> },
+    And(Vec<Node>),
+    Or(Vec<Node>),
+    Not(Box<Node>),
+}
+
+/// Starts a predicate on the column `name`.
+pub fn col(name: &str) -> Col {
+    Col { name: name.to_string() }
+}
+
+/// A column reference, turned into a `Predicate` by one of its comparisons.
+#[derive(Debug, Clone)]
+pub struct Col {
+    name: String,
+}
+
+impl Col {
+    fn compare<V: ToSqlConvert + 'static>(self, op: &'static str, value: V) -> Predicate {
+        Predicate { node: Node::Compare { column: self.name, op, value: Arc::new(value) } }
+    }
+
+    /// `column = value`, or `column IS NULL` when `value` is `None`.
+    pub fn eq<V: ToSqlConvert + 'static>(self, value: V) -> Predicate {
+        self.compare("=", value)
+    }
+
+    /// `column <> value`, or `column IS NOT NULL` when `value` is `None`.
+    pub fn ne<V: ToSqlConvert + 'static>(self, value: V) -> Predicate {
+        self.compare("<>", value)
+    }
+
+    /// `column > value`.
+    pub fn gt<V: ToSqlConvert + 'static>(self, value: V) -> Predicate {
+        self.compare(">", value)
+    }
+
+    /// `column >= value`.
+    pub fn ge<V: ToSqlConvert + 'static>(self, value: V) -> Predicate {
+        self.compare(">=", value)
+    }
+
+    /// `column < value`.
+    pub fn lt<V: ToSqlConvert + 'static>(self, value: V) -> Predicate {
+        self.compare("<", value)
+    }
+
+    /// `column <= value`.
+    pub fn le<V: ToSqlConvert + 'static>(self, value: V) -> Predicate {
+        self.compare("<=", value)
+    }
+
+    /// `column LIKE pattern`.
+    pub fn like(self, pattern: &str) -> Predicate {
+        self.compare("LIKE", pattern.to_string())
+    }
+
+    /// Case-insensitive `column LIKE pattern`: `ILIKE` on PostgreSQL and
+    /// `LOWER(column) LIKE LOWER(pattern)` elsewhere.
+    pub fn ilike(self, pattern: &str) -> Predicate {
+        Predicate { node: Node::ILike { column: self.name, pattern: pattern.to_string() } }
+    }
+
+    /// Case-insensitive `column = value`, as `LOWER(column) = LOWER(value)`.
+    ///
+    /// Unlike `ilike`, `%` and `_` in `value` match only themselves, and an
+    /// index on `LOWER(column)` can serve the lookup.
+    pub fn eq_ignore_case(self, value: &str) -> Predicate {
+        Predicate { node: Node::EqIgnoreCase { column: self.name, value: value.to_string() } }
+    }
+
+    /// `column BETWEEN low AND high`, bounds included.
+    pub fn between<V: ToSqlConvert + 'static>(self, low: V, high: V) -> Predicate {
+        Predicate { node: Node::Between { column: self.name, low: Arc::new(low), high: Arc::new(high) } }
+    }
+
+    /// `column IS NULL`.
+    pub fn is_null(self) -> Predicate {
+        Predicate { node: Node::Null { column: self.name, negated: false } }
+    }
+
+    /// `column IS NOT NULL`.
+    pub fn is_not_null(self) -> Predicate {
+        Predicate { node: Node::Null { column: self.name, negated: true } }
+    }
+
+    fn membership<V: ToSqlConvert + 'static>(self, values: impl IntoIterator<Item = V>, negated: bool) -> Predicate {
+        let values = values.into_iter().map(|v| Arc::new(v) as Arc<dyn ToSqlConvert>).collect();
+        Predicate { node: Node::In { column: self.name, values, negated } }
+    }
+
+    /// `column IN (...)` with one bound parameter per value. An empty list
+    /// matches no rows.
+    pub fn is_in<V: ToSqlConvert + 'static>(self, values: impl IntoIterator<Item = V>) -> Predicate {
+        self.membership(values, false)
+    }
+
+    /// `column NOT IN (...)`. An empty list matches every row.
+    pub fn not_in<V: ToSqlConvert + 'static>(self, values: impl IntoIterator<Item = V>) -> Predicate {
+        self.membership(values, true)
+    }
+
+    /// `column IN (SELECT ...)`.
+    pub fn in_subquery(self, subquery: Subquery) -> Predicate {
+        InList::membership(subquery, self, false)
+    }
+
+    /// `column NOT IN (SELECT ...)`.
+    pub fn not_in_subquery(self, subquery: Subquery) -> Predicate {
+        InList::membership(subquery, self, true)
+    }
+}
+
+/// The right-hand side of an `IN` condition: a list of values or a
+/// `Subquery`.
+pub trait InList {
+    /// Builds `column IN (...)`, or `column NOT IN (...)` if `negated`.
+    fn membership(self, column: Col, negated: bool) -> Predicate;
+}
+
+impl<I> InList for I
+where
+    I: IntoIterator,
+    I::Item: ToSqlConvert + 'static,
+{
+    fn membership(self, column: Col, negated: bool) -> Predicate {
+        column.membership(self, negated)
+    }
+}
+
+impl InList for Subquery {
+    fn membership(self, column: Col, negated: bool) -> Predicate {
+        Predicate { node: Node::InQuery { column: column.name, query: Arc::new(self), negated } }
+    }
+}
+
+impl Predicate {
+    /// A condition written as SQL, inserted verbatim and without parameters.
+    ///
+    /// Never build it from user input; use `col` for values.
+    pub fn raw(condition: &str) -> Self {
+        Predicate { node: Node::Raw(condition.to_string()) }
+    }
+
+    /// A condition written as SQL with a `?` (or `$?`) marker for each of
+    /// `values`, which are bound as parameters.
+    ///
+    /// Markers are renumbered for the connection's dialect (`$n` on
+    /// PostgreSQL). A `?` inside a quoted string or identifier is left alone;
+    /// operators spelled with `?` (such as PostgreSQL's jsonb `?`) cannot be
+    /// used. Box values of different types as `Box<dyn ToSqlConvert>`.
+    pub fn raw_with<V: ToSqlConvert + 'static>(condition: &str, values: impl IntoIterator<Item = V>) -> Self {
+        let values = values.into_iter().map(|v| Arc::new(v) as Arc<dyn ToSqlConvert>).collect();
+        Predicate { node: Node::Sql { condition: condition.to_string(), values } }
+    }
+
+    /// Both `self` and `other` must hold.
+    pub fn and(self, other: Predicate) -> Self {
+        let nodes = match (self.node, other.node) {
+            (Node::And(mut left), Node::And(right)) => {
+                left.extend(right);
+                left
+            }
+            (Node::And(mut left), right) => {
+                left.push(right);
+                left
+            }
+            (left, right) => vec![left, right],
+        };
+        Predicate { node: Node::And(nodes) }
+    }
+
+    /// At least one of `self` and `other` must hold.
+    pub fn or(self, other: Predicate) -> Self {
+        let nodes = match (self.node, other.node) {
+            (Node::Or(mut left), Node::Or(right)) => {
+                left.extend(right);
+                left
+            }
+            (Node::Or(mut left), right) => {
+                left.push(right);
+                left
+            }
+            (left, right) => vec![left, right],
+        };
+        Predicate { node: Node::Or(nodes) }
+    }
+
+    /// Negates the predicate.
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        Predicate { node: Node::Not(Box::new(self.node)) }
+    }
+
+    /// Renders the condition for model `T`, appending its values to `params`.
+    ///
+    /// Placeholders are numbered after the parameters already in `params`, so
+    /// the condition can be spliced into a larger statement.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::InvalidColumn` if the predicate references a
+    /// column that `T` does not have.
+    pub fn to_sql<T: SQLModel>(&self, db_type: &DatabaseType, params: &mut Vec<SqlParam>) -> Result<String, RusticxError> {
+        render::<T>(&self.node, db_type, params)
+    }
+}
+
+/// Combinators that build a `Predicate` from a group of conditions.
+///
+/// Groups nest, so `(a AND b) OR (c AND d)` is written without SQL text:
+///
+/// ```ignore
+/// let cond = Cond::any([
+///     Cond::all([col("role").eq("admin"), col("active").eq(true)]),
+///     Cond::all([col("role").eq("owner"), col("verified").eq(true)]),
+/// ]);
+/// ```
+pub struct Cond;
+
+impl Cond {
+    /// At least one of `conditions` must hold. An empty group matches no rows.
+    pub fn any(conditions: impl IntoIterator<Item = Predicate>) -> Predicate {
+        Predicate { node: Node::Or(conditions.into_iter().map(|c| c.node).collect()) }
+    }
+
+    /// Every one of `conditions` must hold. An empty group matches every row.
+    pub fn all(conditions: impl IntoIterator<Item = Predicate>) -> Predicate {
+        Predicate { node: Node::And(conditions.into_iter().map(|c| c.node).collect()) }
+    }
+}
+
+fn render<T: SQLModel>(node: &Node, db_type: &DatabaseType, params: &mut Vec<SqlParam>) -> Result<String, RusticxError> {
+    Ok(match node {
+        Node::Compare { column, op, value } => {
+            let column = quoted_column::<T>(column, db_type)?;
+            match SqlParam::bind(value) {
+                Some(bound) => {
+                    params.push(bound);
+                    format!("{} {} {}", column, op, db_type.placeholder(params.len()))
+                }
+                // `= NULL` never matches, so equality with None means IS NULL
+                _ => match *op {
+                    "=" => format!("{} IS NULL", column),
+                    "<>" => format!("{} IS NOT NULL", column),
+                    op => format!("{} {} NULL", column, op),
+                },
+            }
+        }
+        Node::Null { column, negated } => {
+            format!("{} IS {}NULL", quoted_column::<T>(column, db_type)?, if *negated { "NOT " } else { "" })
+        }
+        Node::In { column, values, negated } => {
+            let column = quoted_column::<T>(column, db_type)?;
+            let values: Vec<SqlParam> = values.iter().filter_map(SqlParam::bind).collect();
+            if values.is_empty() {
+                return Ok(if *negated { "1 = 1" } else { "1 = 0" }.to_string());
+            }
+            let mut placeholders = Vec::with_capacity(values.len());
+            for value in values {
+                params.push(value);
+                placeholders.push(db_type.placeholder(params.len()));
+            }
+            format!("{} {}IN ({})", column, if *negated { "NOT " } else { "" }, placeholders.join(", "))
+        }
+        Node::InQuery { column, query, negated } => {
+            let column = quoted_column::<T>(column, db_type)?;
+            format!("{} {}IN ({})", column, if *negated { "NOT " } else { "" }, query.to_sql(db_type, params)?)
+        }
+        Node::ILike { column, pattern } => {
+            let column = quoted_column::<T>(column, db_type)?;
+            params.push(SqlParam::text(pattern));
+            let placeholder = db_type.placeholder(params.len());
+            match db_type {
+                DatabaseType::PostgreSQL => format!("{} ILIKE {}", column, placeholder),
+                _ => format!("LOWER({}) LIKE LOWER({})", column, placeholder),
+            }
+        }
+        Node::EqIgnoreCase { column, value } => {
+            let column = quoted_column::<T>(column, db_type)?;
+            params.push(SqlParam::text(value));
+            format!("LOWER({}) = LOWER({})", column, db_type.placeholder(params.len()))
+        }
+        Node::Between { column, low, high } => {
+            let column = quoted_column::<T>(column, db_type)?;
+            let low = bind(low, db_type, params);
+            format!("{} BETWEEN {} AND {}", column, low, bind(high, db_type, params))
+        }
+        Node::Raw(condition) => format!("({})", condition),
+        Node::Sql { condition, values } => format!("({})", bind_markers(condition, values, db_type, params)?),
+        Node::And(nodes) if nodes.is_empty() => "1 = 1".to_string(),
+        Node::Or(nodes) if nodes.is_empty() => "1 = 0".to_string(),
+        Node::And(nodes) | Node::Or(nodes) => {
+            let joiner = if matches!(node, Node::And(_)) { " AND " } else { " OR " };
+            let parts = nodes
+                .iter()
+                .map(|n| render::<T>(n, db_type, params))
+                .collect::<Result<Vec<_>, _>>()?;
+            format!("({})", parts.join(joiner))
+        }
+        Node::Not(inner) => format!("NOT ({})", render::<T>(inner, db_type, params)?),
+    })
+}
+
+/// Replaces each `?` marker outside quotes in `condition` with a placeholder
+/// bound to the matching value.
+fn bind_markers(
+    condition: &str,
+    values: &[Arc<dyn ToSqlConvert>],
+    db_type: &DatabaseType,
+    params: &mut Vec<SqlParam>,
+) -> Result<String, RusticxError> {
+    let mismatch = || {
+        RusticxError::ValidationError(format!(
+            "Condition `{}` has a different number of ? markers than its {} values",
+            condition,
+            values.len()
+        ))
+    };
+
+    let mut sql = String::with_capacity(condition.len());
+    let mut quote = None;
+    let mut values_iter = values.iter();
+    for c in condition.chars() {
+        match (c, quote) {
+            ('\'', None) | ('"', None) | ('`', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            ('?', None) => {
+                let value = values_iter.next().ok_or_else(mismatch)?;
+                if sql.ends_with('$') {
+                    sql.pop();
+                }
+                sql.push_str(&bind(value, db_type, params));
+                continue;
+            }
+            _ => {}
+        }
+        sql.push(c);
+    }
+    if values_iter.next().is_some() {
+        return Err(mismatch());
+    }
+    Ok(sql)
+}
+
+/// Binds `value` and returns its placeholder, or `NULL` for a null value.
+fn bind(value: &Arc<dyn ToSqlConvert>, db_type: &DatabaseType, params: &mut Vec<SqlParam>) -> String {
+    match SqlParam::bind(value) {
+        Some(bound) => {
+            params.push(bound);
+            db_type.placeholder(params.len())
+        }
+        _ => "NULL".to_string(),
+    }
+}
+
+/// Validates `column` against `T`'s columns and quotes it.
+fn quoted_column<T: SQLModel>(column: &str, db_type: &DatabaseType) -> Result<String, RusticxError> {
+    if !T::field_names().contains(&column) {
+        return Err(RusticxError::InvalidColumn(format!(
+            "Cannot filter on {}: not a column of {}",
+            column,
+            T::table_name()
+        )));
+    }
+    Ok(db_type.quote_identifier(column))
+}
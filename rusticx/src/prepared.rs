@@ -0,0 +1,178 @@
+use std::marker::PhantomData;
+use crate::connection::{Connection, Prepared};
+use crate::error::RusticxError;
+use crate::model::{decode_row, SQLModel, ToSql};
+use crate::query_builder::{QueryBuilder, SqlParam};
+
+/// A `QueryBuilder` query rendered and prepared once on a `Connection`, for
+/// hot paths that run the same query many times.
+///
+/// Created with `QueryBuilder::prepare`. On PostgreSQL the statement is
+/// planned by the server once; each run only binds values.
+///
+/// ```ignore
+/// let by_email = QueryBuilder::new()
+///     .filter(UserColumn::Email.eq(""))
+///     .prepare::<User>(&conn)?;
+/// for email in emails {
+///     let users = by_email.fetch_with(&[&email])?;
+/// }
+/// ```
+///
+/// The statement belongs to the connection's session: use the
+/// `PreparedQuery` only with the connection it was prepared on.
+pub struct PreparedQuery<'c, T: SQLModel> {
+    conn: &'c Connection,
+    statement: Prepared,
+    params: Vec<SqlParam>,
+    model: PhantomData<T>,
+}
+
+impl<'c, T: SQLModel> PreparedQuery<'c, T> {
+    pub(crate) fn new(conn: &'c Connection, query: QueryBuilder) -> Result<Self, RusticxError> {
+        let (sql, params) = query.on(conn).to_sql::<T>(conn.get_db_type())?;
+        let statement = conn.prepare(&sql)?;
+        Ok(PreparedQuery { conn, statement, params, model: PhantomData })
+    }
+
+    /// The number of parameters the statement binds.
+    pub fn param_count(&self) -> usize {
+        self.params.len()
+    }
+
+    /// Runs the statement with the values the builder was created with.
+    pub fn fetch(&self) -> Result<Vec<T>, RusticxError> {
+        self.fetch_with(&SqlParam::values(&self.params))
+    }
+
+    /// Runs the statement with `params` in place of the builder's values,
+    /// in placeholder order (see `QueryBuilder::to_sql`).
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::ValidationError` if `params` does not have
+    /// `param_count` values.
+    pub fn fetch_with(&self, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<Vec<T>, RusticxError> {
+        if params.len() != self.params.len() {
+            return Err(RusticxError::ValidationError(format!(
+                "The prepared query binds {} parameters, but {} were given",
+                self.params.len(),
+                params.len()
+            )));
+        }
+        self.conn
+            .query_prepared(&self.statement, params)?
+            .into_iter()
+            .map(|row| decode_row::<T>(self.conn, row))
+            .collect()
+    }
+}
+
+/// Prepared statements of one PostgreSQL session, keyed by SQL text, so
+/// repeated statements skip parsing and planning, and whether the session
+/// has a transaction open.
+///
+/// Holds up to `capacity` statements, evicting the least recently used; a
+/// capacity of 0 disables caching.
+#[cfg(feature = "postgres")]
+pub(crate) struct StatementCache {
+    capacity: usize,
+    entries: std::sync::Mutex<CacheEntries>,
+    in_transaction: std::sync::atomic::AtomicBool,
+}
+
+#[cfg(feature = "postgres")]
+#[derive(Default)]
+struct CacheEntries {
+    statements: std::collections::HashMap<String, (tokio_postgres::Statement, u64)>,
+    uses: u64,
+}
+
+#[cfg(feature = "postgres")]
+impl StatementCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        StatementCache {
+            capacity,
+            entries: std::sync::Mutex::new(CacheEntries::default()),
+            in_transaction: std::sync::atomic::AtomicBool::new(false),
+        }
+    }
+
+    /// The cached statement for `sql`, if there is one.
+    pub(crate) fn get(&self, sql: &str) -> Option<tokio_postgres::Statement> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.uses += 1;
+        let uses = entries.uses;
+        entries.statements.get_mut(sql).map(|(statement, last_used)| {
+            *last_used = uses;
+            statement.clone()
+        })
+    }
+
+    /// Caches `statement` for `sql`, evicting the least recently used
+    /// statement when full.
+    pub(crate) fn insert(&self, sql: &str, statement: &tokio_postgres::Statement) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.statements.len() >= self.capacity {
+            let oldest = entries.statements.iter().min_by_key(|(_, (_, last_used))| *last_used).map(|(sql, _)| sql.clone());
+            if let Some(oldest) = oldest {
+                entries.statements.remove(&oldest);
+            }
+        }
+        entries.uses += 1;
+        let uses = entries.uses;
+        entries.statements.insert(sql.to_string(), (statement.clone(), uses));
+    }
+
+    /// Forgets every statement, for when the session they belong to ended
+    /// or the schema they were planned against may have changed.
+    pub(crate) fn clear(&self) {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).statements.clear();
+    }
+
+    /// Whether `sql` may be cached. Only queries, DML and transaction control
+    /// are; anything else (DDL, `SET`, `ROLLBACK`, which can undo DDL) may
+    /// change what cached statements refer to, so the cache is cleared
+    /// instead.
+    pub(crate) fn admit(&self, sql: &str) -> bool {
+        let keyword = sql.split_whitespace().next().unwrap_or_default().to_ascii_uppercase();
+        let cacheable = matches!(
+            keyword.trim_start_matches('('),
+            "SELECT" | "WITH" | "VALUES" | "TABLE" | "INSERT" | "UPDATE" | "DELETE" | "MERGE"
+                | "BEGIN" | "START" | "COMMIT" | "END" | "SAVEPOINT" | "RELEASE"
+        );
+        if !cacheable {
+            self.clear();
+        }
+        cacheable
+    }
+
+    /// Notes whether the statements of `sql` open or end a transaction.
+    pub(crate) fn track_transaction(&self, sql: &str) {
+        for statement in sql.split(';') {
+            let mut words = statement.split_whitespace().map(|word| word.to_ascii_uppercase());
+            let open = match (words.next().as_deref(), words.next().as_deref()) {
+                (Some("BEGIN"), _) | (Some("START"), Some("TRANSACTION")) => true,
+                (Some("ROLLBACK"), Some("TO")) => continue,
+                (Some("COMMIT" | "END" | "ABORT" | "ROLLBACK"), _) | (Some("PREPARE"), Some("TRANSACTION")) => false,
+                _ => continue,
+            };
+            self.in_transaction.store(open, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    /// Whether a transaction was open on the session when it last ran a
+    /// statement.
+    pub(crate) fn in_transaction(&self) -> bool {
+        self.in_transaction.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Forgets the session's state once it has been replaced.
+    pub(crate) fn reset(&self) {
+        self.clear();
+        self.in_transaction.store(false, std::sync::atomic::Ordering::Relaxed);
+    }
+}
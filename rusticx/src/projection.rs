@@ -0,0 +1,77 @@
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use crate::connection::DatabaseType;
+use crate::error::RusticxError;
+
+/// Returns the comma-separated, quoted column list for projecting `table`
+/// into `D`, validated against the model's `field_names`.
+pub(crate) fn projection_columns<D>(db_type: &DatabaseType, table: &str, field_names: &[&str]) -> Result<String, RusticxError>
+where
+    D: for<'de> Deserialize<'de>,
+{
+    let columns = projected_columns::<D>().ok_or_else(|| {
+        RusticxError::InvalidColumn(format!(
+            "Cannot project {} into {}: the target must be a struct with named fields",
+            table,
+            std::any::type_name::<D>()
+        ))
+    })?;
+
+    if let Some(unknown) = columns.iter().find(|column| !field_names.contains(column)) {
+        return Err(RusticxError::InvalidColumn(format!(
+            "Cannot project {} into {}: {} is not a column of {}",
+            table,
+            std::any::type_name::<D>(),
+            unknown,
+            table
+        )));
+    }
+
+    let quoted: Vec<String> = columns.iter().map(|column| db_type.quote_identifier(column)).collect();
+    Ok(quoted.join(", "))
+}
+
+/// Returns the field names a struct expects when deserialized, honouring
+/// `#[serde(rename = "...")]`.
+///
+/// Rows are deserialized from objects keyed by column name, so these are also
+/// the columns a projection into `D` needs to select. Returns `None` if `D`
+/// does not deserialize as a plain struct (e.g. maps or `#[serde(flatten)]`).
+pub(crate) fn projected_columns<D>() -> Option<&'static [&'static str]>
+where
+    D: for<'de> Deserialize<'de>,
+{
+    let mut fields = None;
+    let _ = D::deserialize(FieldNameCollector { fields: &mut fields });
+    fields
+}
+
+/// A deserializer that records the field list passed to `deserialize_struct`
+/// and then aborts; it never produces a value.
+struct FieldNameCollector<'a> {
+    fields: &'a mut Option<&'static [&'static str]>,
+}
+
+impl<'de> Deserializer<'de> for FieldNameCollector<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("projection target is not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.fields = Some(fields);
+        Err(de::Error::custom("field names collected"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
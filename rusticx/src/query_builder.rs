@@ -1,83 +1,875 @@
-// use crate::connection::Connection;
-// use crate::model::SQLModel;
-// use crate::error::RustixError;
-
-// pub struct QueryBuilder {
-//     filters: Vec<(String, Vec<Box<dyn std::fmt::Debug>>)>,
-//     order_by_field: Option<String>,
-//     order_asc: bool,
-//     limit_val: Option<usize>,
-//     offset_val: Option<usize>,
-// }
-
-// impl QueryBuilder {
-//     pub fn new() -> Self {
-//         QueryBuilder {
-//             filters: Vec::new(),
-//             order_by_field: None,
-//             order_asc: true,
-//             limit_val: None,
-//             offset_val: None,
-//         }
-//     }
-    
-//     pub fn filter<T>(mut self, condition: &str, params: &[T]) -> Self
-// where
-//     T: std::fmt::Debug + Clone + 'static,
-// {
-//     let boxed_params = params
-//         .iter()
-//         .map(|p| Box::new(p.clone()) as Box<dyn std::fmt::Debug>)
-//         .collect();
-//     self.filters.push((condition.to_string(), boxed_params));
-//     self
-// }
-    
-//     pub fn order_by(mut self, field: &str, asc: bool) -> Self {
-//         self.order_by_field = Some(field.to_string());
-//         self.order_asc = asc;
-//         self
-//     }
-    
-//     pub fn limit(mut self, limit: usize) -> Self {
-//         self.limit_val = Some(limit);
-//         self
-//     }
-    
-//     pub fn offset(mut self, offset: usize) -> Self {
-//         self.offset_val = Some(offset);
-//         self
-//     }
-    
-//     pub fn find_all<T: SQLModel>(self, conn: &Connection) -> Result<Vec<T>, RustixError> {
-//         // Build SQL from the query components
-//         let mut sql = format!("SELECT * FROM {}", T::table_name());
-        
-//         if !self.filters.is_empty() {
-//             sql.push_str(" WHERE ");
-//             for (i, (condition, _)) in self.filters.iter().enumerate() {
-//                 if i > 0 {
-//                     sql.push_str(" AND ");
-//                 }
-//                 sql.push_str(condition);
-//             }
-//         }
-        
-//         if let Some(field) = self.order_by_field {
-//             sql.push_str(&format!(" ORDER BY {} {}", field, if self.order_asc { "ASC" } else { "DESC" }));
-//         }
-        
-//         if let Some(limit) = self.limit_val {
-//             sql.push_str(&format!(" LIMIT {}", limit));
-//         }
-        
-//         if let Some(offset) = self.offset_val {
-//             sql.push_str(&format!(" OFFSET {}", offset));
-//         }
-        
-//         println!("Generated SQL: {}", sql);
-        
-//         // In a real implementation, this would execute the SQL and map results
-//         Ok(Vec::new())
-//     }
-// }
\ No newline at end of file
+use std::fmt::Debug;
+use std::sync::Arc;
+use serde::Deserialize;
+use crate::connection::{Connection, DatabaseType};
+use crate::error::RusticxError;
+use crate::insert_builder::InsertBuilder;
+use crate::model::{live_condition, SQLModel, ToSql, ToSqlConvert};
+use crate::ordering::OrderBy;
+use crate::projection::{projected_columns, projection_columns};
+use crate::predicate::{col, Cond, InList, Predicate};
+use crate::prepared::PreparedQuery;
+use crate::sql_types::SqlType;
+use crate::stream::BatchIter;
+use crate::window::Window;
+
+/// Builds a `SELECT` for a model from filters, an ordering and a page window.
+///
+/// Filters are typed predicates, ANDed together; `or_filter` and `Cond`
+/// build alternatives. The model's expiry, soft-delete and default scope
+/// filtering applies as with the `SQLModel` finders.
+///
+/// ```ignore
+/// let adults = col("age").ge(18);
+/// let users: Vec<User> = QueryBuilder::new()
+///     .filter_pred(&adults)
+///     .filter(col("status").eq("active").or(UserColumn::Role.eq("admin")))
+///     .order_by("name", true)
+///     .limit(20)
+///     .find_all(&conn)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct QueryBuilder {
+    ctes: Vec<(String, Vec<String>, Cte)>,
+    recursive: bool,
+    columns: Vec<String>,
+    windows: Vec<(String, Window)>,
+    projection: Option<ProjectFn>,
+    filters: Vec<Predicate>,
+    group_by_fields: Vec<String>,
+    having: Vec<Predicate>,
+    unions: Vec<(bool, QueryBuilder)>,
+    order_by_field: Option<String>,
+    order_asc: bool,
+    limit_val: Option<i64>,
+    offset_val: Option<i64>,
+    lock: Option<&'static str>,
+    skip_locked: bool,
+    tie_break: bool,
+    schema: Option<String>,
+}
+
+impl Default for QueryBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QueryBuilder {
+    /// Creates a builder that selects every row.
+    pub fn new() -> Self {
+        QueryBuilder {
+            ctes: Vec::new(),
+            recursive: false,
+            columns: Vec::new(),
+            windows: Vec::new(),
+            projection: None,
+            filters: Vec::new(),
+            group_by_fields: Vec::new(),
+            having: Vec::new(),
+            unions: Vec::new(),
+            order_by_field: None,
+            order_asc: true,
+            limit_val: None,
+            offset_val: None,
+            lock: None,
+            skip_locked: false,
+            tie_break: false,
+            schema: None,
+        }
+    }
+
+    /// Starts a multi-row `INSERT` into `T`'s table.
+    pub fn insert<T: SQLModel>() -> InsertBuilder<T> {
+        InsertBuilder::new()
+    }
+
+    /// Selects `columns` instead of `*`.
+    ///
+    /// Each entry is a column of the model, which is quoted, or an SQL
+    /// expression such as `COUNT(*) AS orders`, inserted verbatim. Read the
+    /// results with `find_all_as`.
+    pub fn select(mut self, columns: &[&str]) -> Self {
+        self.columns = columns.iter().map(|c| c.to_string()).collect();
+        self.projection = None;
+        self
+    }
+
+    /// Adds the window expression `window` to the selected columns as
+    /// `alias`, after the `select` list (or `*`).
+    pub fn select_window(mut self, alias: &str, window: Window) -> Self {
+        self.windows.push((alias.to_string(), window));
+        self
+    }
+
+    /// Selects only the columns the struct `D` deserializes, by field name
+    /// (after `#[serde(rename)]`), to read them with `find_all_as`.
+    ///
+    /// ```ignore
+    /// #[derive(Debug, Deserialize)]
+    /// struct UserSummary { id: i32, name: String }
+    ///
+    /// let summaries: Vec<UserSummary> = QueryBuilder::new()
+    ///     .select_as::<UserSummary>()
+    ///     .filter(UserColumn::Active.eq(true))
+    ///     .find_all_as::<User, UserSummary>(&conn)?;
+    /// ```
+    ///
+    /// Running the query returns `RusticxError::InvalidColumn` if `D` is not
+    /// a plain struct or names a field that is not a column of the model.
+    pub fn select_as<D: for<'de> Deserialize<'de>>(mut self) -> Self {
+        self.columns = projected_columns::<D>().unwrap_or_default().iter().map(|c| c.to_string()).collect();
+        self.projection = Some(projection_columns::<D>);
+        self
+    }
+
+    /// Adds a condition built with `col` or the model's generated column
+    /// enum (`UserColumn::Email.eq("a@b.c")`); its values are bound as
+    /// parameters.
+    ///
+    /// SQL text is not accepted here. Use `filter_raw`, or `Predicate::raw`
+    /// for a condition without values, where no predicate fits.
+    pub fn filter(mut self, condition: Predicate) -> Self {
+        self.filters.push(condition);
+        self
+    }
+
+    /// Adds an alternative: the rows matched so far, or those matching
+    /// `condition`.
+    ///
+    /// `.filter(a).filter(b).or_filter(c)` selects `(a AND b) OR c`; filters
+    /// added afterwards are ANDed with the whole group. Without earlier
+    /// filters it acts as `filter`. Use `Cond::any` and `Cond::all` for other
+    /// groupings.
+    pub fn or_filter(mut self, condition: Predicate) -> Self {
+        if self.filters.is_empty() {
+            self.filters.push(condition);
+        } else {
+            let matched = Cond::all(std::mem::take(&mut self.filters));
+            self.filters.push(matched.or(condition));
+        }
+        self
+    }
+
+    /// Adds a condition written as SQL with a `?` (or `$?`) marker for each
+    /// of `values`, which are bound as parameters and numbered after the
+    /// other filters' values.
+    ///
+    /// ```ignore
+    /// .filter_raw("length(name) > $?", [5])
+    /// .filter_raw(
+    ///     "created_at > ? AND status <> ?",
+    ///     [Box::new(cutoff) as Box<dyn ToSqlConvert>, Box::new("banned")],
+    /// )
+    /// ```
+    ///
+    /// The SQL is inserted verbatim, so never build it from user input.
+    pub fn filter_raw<V: ToSqlConvert + 'static>(self, condition: &str, values: impl IntoIterator<Item = V>) -> Self {
+        self.filter(Predicate::raw_with(condition, values))
+    }
+
+    /// Adds a `Predicate`, binding its values as parameters.
+    pub fn filter_pred(mut self, predicate: &Predicate) -> Self {
+        self.filters.push(predicate.clone());
+        self
+    }
+
+    /// Groups the rows by `fields`, which must be columns of the model.
+    pub fn group_by(mut self, fields: &[&str]) -> Self {
+        self.group_by_fields = fields.iter().map(|f| f.to_string()).collect();
+        self
+    }
+
+    /// Adds a condition on the groups, written as SQL with a `?` marker for
+    /// each of `values`. Multiple conditions are ANDed.
+    ///
+    /// ```ignore
+    /// .group_by(&["region"])
+    /// .having("SUM(amount) >= ?", [100_i64])
+    /// ```
+    ///
+    /// PostgreSQL binds parameters strictly: `COUNT` and integer `SUM`s are
+    /// `BIGINT`, so compare them with `i64` values.
+    pub fn having<V: ToSqlConvert + 'static>(mut self, condition: &str, values: impl IntoIterator<Item = V>) -> Self {
+        self.having.push(Predicate::raw_with(condition, values));
+        self
+    }
+
+    /// Keeps the rows whose `column` is among `values`: a list, with one
+    /// bound parameter per element, or the values a `Subquery` selects.
+    ///
+    /// ```ignore
+    /// let picked: Vec<User> = QueryBuilder::new().filter_in("id", [3, 5, 8]).find_all(&conn)?;
+    ///
+    /// let prolific = QueryBuilder::new()
+    ///     .select(&["author_id"])
+    ///     .filter(BookColumn::Pages.gt(300))
+    ///     .subquery::<Book>();
+    /// let authors: Vec<Author> = QueryBuilder::new().filter_in("id", prolific).find_all(&conn)?;
+    /// ```
+    ///
+    /// An empty list matches no rows.
+    pub fn filter_in(self, column: &str, values: impl InList) -> Self {
+        self.filter(values.membership(col(column), false))
+    }
+
+    /// Keeps the rows whose `column` is not among `values`, as with
+    /// `filter_in`. An empty list matches every row.
+    pub fn filter_not_in(self, column: &str, values: impl InList) -> Self {
+        self.filter(values.membership(col(column), true))
+    }
+
+    /// Keeps the rows where `column IS NULL`.
+    pub fn is_null(self, column: &str) -> Self {
+        self.filter(col(column).is_null())
+    }
+
+    /// Keeps the rows where `column IS NOT NULL`.
+    pub fn is_not_null(self, column: &str) -> Self {
+        self.filter(col(column).is_not_null())
+    }
+
+    /// Keeps the rows where `column LIKE pattern`.
+    pub fn like(self, column: &str, pattern: &str) -> Self {
+        self.filter(col(column).like(pattern))
+    }
+
+    /// Keeps the rows where `column` matches `pattern` ignoring case
+    /// (`ILIKE` on PostgreSQL, `LOWER(column) LIKE LOWER(pattern)` elsewhere).
+    pub fn ilike(self, column: &str, pattern: &str) -> Self {
+        self.filter(col(column).ilike(pattern))
+    }
+
+    /// Keeps the rows where `column` equals `value` ignoring case, as
+    /// `LOWER(column) = LOWER(value)`; for email and username lookups.
+    pub fn eq_ignore_case(self, column: &str, value: &str) -> Self {
+        self.filter(col(column).eq_ignore_case(value))
+    }
+
+    /// Keeps the rows where `column` lies between `low` and `high`, inclusive.
+    pub fn between<V: ToSqlConvert + 'static>(self, column: &str, low: V, high: V) -> Self {
+        self.filter(col(column).between(low, high))
+    }
+
+    /// Turns this builder into a subquery over `T`'s table, for `filter_in`
+    /// and `Col::in_subquery`.
+    ///
+    /// The builder must `select` exactly one column. Its values are bound
+    /// along with the outer query's, so placeholders stay in order.
+    pub fn subquery<T: SQLModel>(self) -> Subquery {
+        Subquery { query: self, table: T::table_name(), render: QueryBuilder::render::<T> }
+    }
+
+    /// Defines the common table expression `alias` as `subquery`, for the
+    /// query's raw conditions to read (`WITH alias AS (SELECT ...)`).
+    ///
+    /// ```ignore
+    /// let recent = QueryBuilder::new()
+    ///     .select(&["user_id"])
+    ///     .filter(OrderColumn::PlacedAt.gt(cutoff))
+    ///     .subquery::<Order>();
+    /// let buyers: Vec<User> = QueryBuilder::new()
+    ///     .with("recent_buyers", recent)
+    ///     .filter(Predicate::raw("id IN (SELECT user_id FROM recent_buyers)"))
+    ///     .find_all(&conn)?;
+    /// ```
+    ///
+    /// Unlike in `filter_in`, the subquery may select any number of columns.
+    pub fn with(mut self, alias: &str, subquery: Subquery) -> Self {
+        self.ctes.push((alias.to_string(), Vec::new(), Cte::Query(Box::new(subquery))));
+        self
+    }
+
+    /// Defines the recursive common table expression `alias(columns)`,
+    /// written as SQL with a `?` marker for each of `values`, and marks the
+    /// query `WITH RECURSIVE`.
+    ///
+    /// ```ignore
+    /// // Everyone below a manager in the org chart
+    /// let reports: Vec<Employee> = QueryBuilder::new()
+    ///     .with_recursive(
+    ///         "reports",
+    ///         &["id"],
+    ///         "SELECT id FROM employees WHERE manager_id = ? \
+    ///          UNION ALL SELECT e.id FROM employees e JOIN reports r ON e.manager_id = r.id",
+    ///         [manager_id],
+    ///     )
+    ///     .filter(Predicate::raw("id IN (SELECT id FROM reports)"))
+    ///     .find_all(&conn)?;
+    /// ```
+    ///
+    /// The SQL is inserted verbatim, so never build it from user input.
+    pub fn with_recursive<V: ToSqlConvert + 'static>(
+        mut self,
+        alias: &str,
+        columns: &[&str],
+        sql: &str,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        let columns = columns.iter().map(|c| c.to_string()).collect();
+        self.ctes.push((alias.to_string(), columns, Cte::Sql(Predicate::raw_with(sql, values))));
+        self.recursive = true;
+        self
+    }
+
+    /// Appends the rows of `other`, a query over the same model, dropping
+    /// duplicates (`UNION`).
+    ///
+    /// Both queries must select compatible columns. Their values are bound
+    /// in one placeholder sequence. `order_by`, `limit` and `offset` on this
+    /// builder apply to the combined rows; `other` must not set them.
+    ///
+    /// ```ignore
+    /// let flagged: Vec<User> = QueryBuilder::new()
+    ///     .filter(UserColumn::Banned.eq(true))
+    ///     .union(QueryBuilder::new().filter(UserColumn::Reports.gt(3)))
+    ///     .order_by("name", true)
+    ///     .find_all(&conn)?;
+    /// ```
+    pub fn union(mut self, other: QueryBuilder) -> Self {
+        self.unions.push((false, other));
+        self
+    }
+
+    /// Appends the rows of `other`, keeping duplicates (`UNION ALL`). See
+    /// `union`.
+    pub fn union_all(mut self, other: QueryBuilder) -> Self {
+        self.unions.push((true, other));
+        self
+    }
+
+    /// Orders the results by `field`, ascending if `asc`.
+    pub fn order_by(mut self, field: &str, asc: bool) -> Self {
+        self.order_by_field = Some(field.to_string());
+        self.order_asc = asc;
+        self
+    }
+
+    /// Orders the results by a validated `OrderBy`, such as one built from
+    /// request parameters.
+    pub fn order(mut self, order: &OrderBy) -> Self {
+        self.order_by_field = Some(order.column().to_string());
+        self.order_asc = order.is_ascending();
+        self
+    }
+
+    /// Returns at most `limit` rows.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit_val = Some(i64::try_from(limit).unwrap_or(i64::MAX));
+        self
+    }
+
+    /// Skips the first `offset` rows.
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset_val = Some(i64::try_from(offset).unwrap_or(i64::MAX));
+        self
+    }
+
+    /// Locks the selected rows against other writers until the transaction
+    /// ends (`FOR UPDATE`). A no-op on SQLite, which serializes writers.
+    ///
+    /// ```ignore
+    /// // Claim the next job; concurrent workers skip it instead of waiting
+    /// conn.execute("BEGIN", &[])?;
+    /// let job: Vec<Job> = QueryBuilder::new()
+    ///     .filter(JobColumn::Status.eq("queued"))
+    ///     .order_by("id", true)
+    ///     .limit(1)
+    ///     .for_update()
+    ///     .skip_locked()
+    ///     .find_all(&conn)?;
+    /// ```
+    pub fn for_update(mut self) -> Self {
+        self.lock = Some("FOR UPDATE");
+        self
+    }
+
+    /// Locks the selected rows against writers while allowing other readers
+    /// to share the lock (`FOR SHARE`). A no-op on SQLite.
+    pub fn for_share(mut self) -> Self {
+        self.lock = Some("FOR SHARE");
+        self
+    }
+
+    /// Skips rows another transaction has locked instead of waiting for them
+    /// (`SKIP LOCKED`). Requires `for_update` or `for_share`.
+    pub fn skip_locked(mut self) -> Self {
+        self.skip_locked = true;
+        self
+    }
+
+    /// Runs the query against `T`'s table.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::InvalidColumn` if a predicate, the grouping or
+    /// the ordering references a column `T` does not have, or a
+    /// `RusticxError::QueryError` if the query fails.
+    pub fn find_all<T: SQLModel>(self, conn: &Connection) -> Result<Vec<T>, RusticxError> {
+        let query = self.on(conn);
+        let mut params = Vec::new();
+        let sql = query.render::<T>(conn.get_db_type(), &mut params)?;
+        T::find_with_sql(conn, &sql, &SqlParam::values(&params))
+    }
+
+    /// Runs the query with `LIMIT 1` and returns the first row, or `None` if
+    /// no row matches.
+    pub fn optional<T: SQLModel>(self, conn: &Connection) -> Result<Option<T>, RusticxError> {
+        Ok(self.limit(1).find_all::<T>(conn)?.into_iter().next())
+    }
+
+    /// Runs the query with `LIMIT 1` and returns the first row.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::NotFound` if no row matches, or the errors of
+    /// `find_all`.
+    pub fn first<T: SQLModel>(self, conn: &Connection) -> Result<T, RusticxError> {
+        self.optional(conn)?
+            .ok_or_else(|| RusticxError::NotFound(format!("No {} row matches the query", T::table_name())))
+    }
+
+    /// Returns the only matching row, reading at most two to tell.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::NotFound` if no row matches and
+    /// `RusticxError::QueryError` if more than one does.
+    pub fn one<T: SQLModel>(self, conn: &Connection) -> Result<T, RusticxError> {
+        let mut rows = self.limit(2).find_all::<T>(conn)?;
+        match rows.len() {
+            0 => Err(RusticxError::NotFound(format!("No {} row matches the query", T::table_name()))),
+            1 => Ok(rows.remove(0)),
+            _ => Err(RusticxError::QueryError(format!(
+                "Expected one {} row, but the query matches more",
+                T::table_name()
+            ))),
+        }
+    }
+
+    /// Renders the query for `T` once and prepares it on `conn`, for running
+    /// it repeatedly. See `PreparedQuery`.
+    ///
+    /// # Errors
+    ///
+    /// As for `to_sql`, plus `RusticxError::QueryError` if the database
+    /// rejects the statement.
+    pub fn prepare<T: SQLModel>(self, conn: &Connection) -> Result<PreparedQuery<'_, T>, RusticxError> {
+        PreparedQuery::new(conn, self)
+    }
+
+    /// Streams the results, fetching `batch_size` rows per query, so large
+    /// result sets can be processed without loading them at once.
+    ///
+    /// ```ignore
+    /// for user in QueryBuilder::new().filter(UserColumn::Active.eq(true)).stream::<User>(&conn, 500) {
+    ///     reindex(&user?)?;
+    /// }
+    /// ```
+    ///
+    /// `limit` and `offset` bound the stream as a whole. See `BatchIter`.
+    pub fn stream<T: SQLModel>(mut self, conn: &Connection, batch_size: usize) -> BatchIter<'_, T> {
+        let offset = self.offset_val.take().map_or(0, |o| o as usize);
+        let limit = self.limit_val.take().map(|l| l as usize);
+        self.tie_break = true;
+        BatchIter::new(conn, self, batch_size, offset, limit)
+    }
+
+    /// Runs the query against `T`'s table and deserializes each row into `R`,
+    /// typically a struct matching the `select` list.
+    ///
+    /// ```ignore
+    /// #[derive(Deserialize)]
+    /// struct RegionTotal { region: String, orders: i64, total: i64 }
+    ///
+    /// let totals: Vec<RegionTotal> = QueryBuilder::new()
+    ///     .select(&["region", "COUNT(*) AS orders", "SUM(amount) AS total"])
+    ///     .group_by(&["region"])
+    ///     .having("COUNT(*) > ?", [1_i64])
+    ///     .find_all_as::<Sale, RegionTotal>(&conn)?;
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// As for `find_all`, plus `RusticxError::QueryError` if a row does not
+    /// deserialize into `R`.
+    pub fn find_all_as<T: SQLModel, R: for<'de> Deserialize<'de> + Debug>(self, conn: &Connection) -> Result<Vec<R>, RusticxError> {
+        let query = self.on(conn);
+        let mut params = Vec::new();
+        let sql = query.render::<T>(conn.get_db_type(), &mut params)?;
+        conn.query_raw(&sql, &SqlParam::values(&params))
+    }
+
+    /// Counts the rows of `T` matching the filters.
+    ///
+    /// Like the other aggregates, this ignores `select`, grouping, `having`,
+    /// ordering and paging: it covers every row the filters match. With
+    /// `union`, the aggregates cover the rows of the combined query instead.
+    pub fn count<T: SQLModel>(self, conn: &Connection) -> Result<i64, RusticxError> {
+        Ok(self.aggregate::<T, i64>(conn, "COUNT(*)".to_string())?.unwrap_or(0))
+    }
+
+    /// Sums `column` over the matching rows, or `None` if no row matches.
+    ///
+    /// Integer columns are summed as `i64` and float columns as `f64`.
+    pub fn sum<T: SQLModel, V: for<'de> Deserialize<'de>>(self, conn: &Connection, column: &str) -> Result<Option<V>, RusticxError> {
+        let db_type = conn.get_db_type();
+        let index = aggregated_column::<T>(column, "sum")?;
+        let sum = format!("SUM({})", db_type.quote_identifier(column));
+        // Sums widen to NUMERIC/DECIMAL on PostgreSQL and MySQL; cast them back
+        let cast = match (T::field_sql_types().get(index), db_type) {
+            (_, DatabaseType::SQLite) => None,
+            (Some(SqlType::Integer | SqlType::BigInt), DatabaseType::PostgreSQL) => Some("BIGINT"),
+            (Some(SqlType::Integer | SqlType::BigInt), DatabaseType::MySQL) => Some("SIGNED"),
+            (Some(SqlType::Float), DatabaseType::PostgreSQL) => Some("DOUBLE PRECISION"),
+            (Some(SqlType::Float), DatabaseType::MySQL) => Some("DOUBLE"),
+            _ => None,
+        };
+        let expr = match cast {
+            Some(cast) => format!("CAST({} AS {})", sum, cast),
+            None => sum,
+        };
+        self.aggregate::<T, V>(conn, expr)
+    }
+
+    /// Averages `column` over the matching rows, or `None` if no row matches.
+    pub fn avg<T: SQLModel>(self, conn: &Connection, column: &str) -> Result<Option<f64>, RusticxError> {
+        let db_type = conn.get_db_type();
+        aggregated_column::<T>(column, "average")?;
+        let avg = format!("AVG({})", db_type.quote_identifier(column));
+        let expr = match db_type {
+            DatabaseType::PostgreSQL => format!("CAST({} AS DOUBLE PRECISION)", avg),
+            DatabaseType::MySQL => format!("CAST({} AS DOUBLE)", avg),
+            DatabaseType::SQLite => avg,
+        };
+        self.aggregate::<T, f64>(conn, expr)
+    }
+
+    /// The smallest value of `column` among the matching rows, or `None` if
+    /// no row matches.
+    pub fn min<T: SQLModel, V: for<'de> Deserialize<'de>>(self, conn: &Connection, column: &str) -> Result<Option<V>, RusticxError> {
+        aggregated_column::<T>(column, "take the minimum of")?;
+        let expr = format!("MIN({})", conn.get_db_type().quote_identifier(column));
+        self.aggregate::<T, V>(conn, expr)
+    }
+
+    /// The largest value of `column` among the matching rows, or `None` if
+    /// no row matches.
+    pub fn max<T: SQLModel, V: for<'de> Deserialize<'de>>(self, conn: &Connection, column: &str) -> Result<Option<V>, RusticxError> {
+        aggregated_column::<T>(column, "take the maximum of")?;
+        let expr = format!("MAX({})", conn.get_db_type().quote_identifier(column));
+        self.aggregate::<T, V>(conn, expr)
+    }
+
+    /// Selects the single aggregate `expr` over the filtered rows.
+    fn aggregate<T: SQLModel, V: for<'de> Deserialize<'de>>(mut self, conn: &Connection, expr: String) -> Result<Option<V>, RusticxError> {
+        self = self.on(conn);
+        let db_type = conn.get_db_type();
+        self.order_by_field = None;
+        self.limit_val = None;
+        self.offset_val = None;
+        self.lock = None;
+        self.skip_locked = false;
+        let combined = !self.unions.is_empty();
+        if !combined {
+            self.columns = vec![format!("{} AS __aggregate", expr)];
+            self.windows.clear();
+            self.projection = None;
+            self.group_by_fields.clear();
+            self.having.clear();
+        }
+
+        let mut params = Vec::new();
+        let mut sql = self.render::<T>(db_type, &mut params)?;
+        if combined {
+            sql = format!("SELECT {} AS __aggregate FROM ({}) AS {}", expr, sql, db_type.quote_identifier("combined"));
+        }
+        let mut rows: Vec<serde_json::Map<String, serde_json::Value>> = conn.query_raw(&sql, &SqlParam::values(&params))?;
+        let value = rows
+            .first_mut()
+            .and_then(|row| row.remove("__aggregate"))
+            .unwrap_or(serde_json::Value::Null);
+        serde_json::from_value(value)
+            .map_err(|e| RusticxError::SerializationError(format!("Cannot read {} value: {}", expr, e)))
+    }
+
+    /// Renders the `SELECT` for `T` without running it, returning the SQL and
+    /// its parameters in placeholder order.
+    ///
+    /// ```ignore
+    /// let (sql, params) = QueryBuilder::new()
+    ///     .filter(UserColumn::Email.eq("a@b.c"))
+    ///     .to_sql::<User>(&DatabaseType::PostgreSQL)?;
+    /// assert_eq!(sql, r#"SELECT * FROM "users" WHERE "email" = $1"#);
+    /// eprintln!("{} with {:?}", sql, params.iter().map(ToString::to_string).collect::<Vec<_>>());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// As for `find_all`, without the query errors.
+    pub fn to_sql<T: SQLModel>(&self, db_type: &DatabaseType) -> Result<(String, Vec<SqlParam>), RusticxError> {
+        let mut params = Vec::new();
+        let sql = self.render::<T>(db_type, &mut params)?;
+        Ok((sql, params))
+    }
+
+    /// Names tables in `conn`'s schema when it qualifies table names.
+    pub(crate) fn on(mut self, conn: &Connection) -> Self {
+        self.schema = conn.schema().map(str::to_string);
+        self.unions = self.unions.into_iter().map(|(all, other)| (all, other.on(conn))).collect();
+        self
+    }
+
+    /// Renders the `SELECT` for `T`, appending its values to `params`.
+    fn render<T: SQLModel>(&self, db_type: &DatabaseType, params: &mut Vec<SqlParam>) -> Result<String, RusticxError> {
+        let fields = T::field_names();
+        let check = |field: &str, action: &str| -> Result<String, RusticxError> {
+            if !fields.contains(&field) {
+                return Err(RusticxError::InvalidColumn(format!(
+                    "Cannot {} {}: not a column of {}",
+                    action,
+                    field,
+                    T::table_name()
+                )));
+            }
+            Ok(db_type.quote_identifier(field))
+        };
+
+        let mut columns = if let Some(project) = self.projection {
+            project(db_type, &T::table_name(), &fields)?
+        } else if self.columns.is_empty() {
+            "*".to_string()
+        } else {
+            self.columns
+                .iter()
+                .map(|c| if fields.contains(&c.as_str()) { db_type.quote_identifier(c) } else { c.clone() })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        for (alias, window) in &self.windows {
+            columns.push_str(&format!(", {} AS {}", window.to_sql::<T>(db_type)?, db_type.quote_identifier(alias)));
+        }
+
+        let mut conditions = Vec::with_capacity(self.filters.len() + 1);
+        for filter in &self.filters {
+            conditions.push(filter.to_sql::<T>(db_type, params)?);
+        }
+        conditions.extend(live_condition::<T>(db_type));
+
+        let mut sql = String::new();
+        if !self.ctes.is_empty() {
+            let mut ctes = Vec::with_capacity(self.ctes.len());
+            for (alias, columns, cte) in &self.ctes {
+                let mut name = db_type.quote_identifier(alias);
+                if !columns.is_empty() {
+                    let columns: Vec<String> = columns.iter().map(|c| db_type.quote_identifier(c)).collect();
+                    name = format!("{}({})", name, columns.join(", "));
+                }
+                let body = match cte {
+                    Cte::Query(subquery) => format!("({})", subquery.select_sql(db_type, params)?),
+                    // Rendered SQL conditions are already parenthesized
+                    Cte::Sql(sql) => sql.to_sql::<T>(db_type, params)?,
+                };
+                ctes.push(format!("{} AS {}", name, body));
+            }
+            sql.push_str(if self.recursive { "WITH RECURSIVE " } else { "WITH " });
+            sql.push_str(&ctes.join(", "));
+            sql.push(' ');
+        }
+        let table = match &self.schema {
+            Some(schema) => format!("{}.{}", schema, T::table_name()),
+            None => T::table_name(),
+        };
+        sql.push_str(&format!("SELECT {} FROM {}", columns, db_type.quote_identifier(&table)));
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+
+        if !self.group_by_fields.is_empty() {
+            let grouped = self
+                .group_by_fields
+                .iter()
+                .map(|field| check(field, "group by"))
+                .collect::<Result<Vec<_>, _>>()?;
+            sql.push_str(" GROUP BY ");
+            sql.push_str(&grouped.join(", "));
+        }
+
+        if !self.having.is_empty() {
+            let having = self
+                .having
+                .iter()
+                .map(|condition| condition.to_sql::<T>(db_type, params))
+                .collect::<Result<Vec<_>, _>>()?;
+            sql.push_str(" HAVING ");
+            sql.push_str(&having.join(" AND "));
+        }
+
+        for (all, other) in &self.unions {
+            if other.order_by_field.is_some() || other.limit_val.is_some() || other.offset_val.is_some() {
+                return Err(RusticxError::ValidationError(
+                    "A query combined with union cannot be ordered or paged; order and page the combined query instead"
+                        .to_string(),
+                ));
+            }
+            if !other.ctes.is_empty() {
+                return Err(RusticxError::ValidationError(
+                    "A query combined with union cannot define common table expressions; define them on the first query"
+                        .to_string(),
+                ));
+            }
+            sql.push_str(if *all { " UNION ALL " } else { " UNION " });
+            sql.push_str(&other.render::<T>(db_type, params)?);
+        }
+
+        let primary_key = T::primary_key_field();
+        if let Some(field) = &self.order_by_field {
+            sql.push_str(&format!(
+                " ORDER BY {} {}",
+                check(field, "order by")?,
+                if self.order_asc { "ASC" } else { "DESC" }
+            ));
+            if self.tie_break && *field != primary_key {
+                sql.push_str(&format!(", {} ASC", db_type.quote_identifier(&primary_key)));
+            }
+        } else if self.tie_break {
+            sql.push_str(&format!(" ORDER BY {} ASC", db_type.quote_identifier(&primary_key)));
+        }
+
+        // The window is bound like any other value rather than formatted into the SQL
+        match (&self.limit_val, &self.offset_val) {
+            (Some(limit), _) => {
+                params.push(SqlParam::int(*limit));
+                sql.push_str(&format!(" LIMIT {}", db_type.placeholder(params.len())));
+            }
+            // MySQL and SQLite only accept OFFSET after a LIMIT
+            (None, Some(_)) => match db_type {
+                DatabaseType::MySQL => sql.push_str(" LIMIT 18446744073709551615"),
+                DatabaseType::SQLite => sql.push_str(" LIMIT -1"),
+                DatabaseType::PostgreSQL => {}
+            },
+            (None, None) => {}
+        }
+        if let Some(offset) = self.offset_val {
+            params.push(SqlParam::int(offset));
+            sql.push_str(&format!(" OFFSET {}", db_type.placeholder(params.len())));
+        }
+
+        match (self.lock, db_type) {
+            (None, _) if self.skip_locked => {
+                return Err(RusticxError::ValidationError(
+                    "skip_locked requires for_update or for_share".to_string(),
+                ))
+            }
+            // SQLite locks the whole database for writes, so there are no row locks to take
+            (None, _) | (Some(_), DatabaseType::SQLite) => {}
+            (Some(lock), _) => {
+                sql.push(' ');
+                sql.push_str(lock);
+                if self.skip_locked {
+                    sql.push_str(" SKIP LOCKED");
+                }
+            }
+        }
+
+        Ok(sql)
+    }
+}
+
+/// A parameter of a query rendered with `QueryBuilder::to_sql`.
+///
+/// It shares the value with the builder it was rendered from, so it can be
+/// kept and bound again without rendering the query anew. `Display` redacts
+/// the value, so it is safe to log; `Debug` shows it, for assertions in
+/// tests.
+#[derive(Clone)]
+pub struct SqlParam {
+    value: Arc<dyn ToSqlConvert>,
+}
+
+impl SqlParam {
+    /// The value, ready to bind with `Connection::query_raw` or `execute`.
+    pub fn value(&self) -> &(dyn ToSql + Sync + 'static) {
+        self.value.as_ref_postgres().expect("SqlParam only holds values that bind")
+    }
+
+    /// The parameter binding `value`, or `None` for a null value, which is
+    /// written as `NULL` instead.
+    pub(crate) fn bind(value: &Arc<dyn ToSqlConvert>) -> Option<SqlParam> {
+        (!value.is_null() && value.as_ref_postgres().is_some()).then(|| SqlParam { value: Arc::clone(value) })
+    }
+
+    pub(crate) fn text(value: &str) -> SqlParam {
+        SqlParam { value: Arc::new(value.to_string()) }
+    }
+
+    pub(crate) fn int(value: i64) -> SqlParam {
+        SqlParam { value: Arc::new(value) }
+    }
+
+    /// The values of `params`, in order, for binding.
+    pub(crate) fn values(params: &[SqlParam]) -> Vec<&(dyn ToSql + Sync + 'static)> {
+        params.iter().map(SqlParam::value).collect()
+    }
+}
+
+impl std::fmt::Display for SqlParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+impl Debug for SqlParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.value, f)
+    }
+}
+
+type ProjectFn = fn(&DatabaseType, &str, &[&str]) -> Result<String, RusticxError>;
+
+type RenderFn = fn(&QueryBuilder, &DatabaseType, &mut Vec<SqlParam>) -> Result<String, RusticxError>;
+
+/// A `SELECT` of one column, used as the value list of an `IN` condition.
+///
+/// Built with `QueryBuilder::subquery`.
+#[derive(Debug, Clone)]
+pub struct Subquery {
+    query: QueryBuilder,
+    table: String,
+    render: RenderFn,
+}
+
+impl Subquery {
+    /// Renders the `SELECT`, appending its values to `params`.
+    pub(crate) fn to_sql(&self, db_type: &DatabaseType, params: &mut Vec<SqlParam>) -> Result<String, RusticxError> {
+        let selected = self.query.columns.len() + self.query.windows.len();
+        if selected != 1 {
+            return Err(RusticxError::ValidationError(format!(
+                "A subquery on {} must select exactly one column, not {}",
+                self.table, selected
+            )));
+        }
+        self.select_sql(db_type, params)
+    }
+
+    /// Renders the `SELECT` whatever its columns, appending its values to `params`.
+    fn select_sql(&self, db_type: &DatabaseType, params: &mut Vec<SqlParam>) -> Result<String, RusticxError> {
+        (self.render)(&self.query, db_type, params)
+    }
+}
+
+/// The body of a common table expression.
+#[derive(Debug, Clone)]
+enum Cte {
+    Query(Box<Subquery>),
+    Sql(Predicate),
+}
+
+/// Validates that `column` is a column of `T`, returning its index in `field_names`.
+fn aggregated_column<T: SQLModel>(column: &str, action: &str) -> Result<usize, RusticxError> {
+    T::field_names().iter().position(|f| *f == column).ok_or_else(|| {
+        RusticxError::InvalidColumn(format!("Cannot {} {}: not a column of {}", action, column, T::table_name()))
+    })
+}
@@ -0,0 +1,220 @@
+use crate::connection::DatabaseType;
+use crate::error::RusticxError;
+
+/// One statement of a SQL script.
+pub(crate) struct ScriptStatement<'s> {
+    /// The statement's position in the script, counting from 1.
+    pub(crate) number: usize,
+    /// The line the statement starts on, counting from 1.
+    pub(crate) line: usize,
+    /// The statement's text, without the terminating `;`.
+    pub(crate) sql: &'s str,
+}
+
+impl ScriptStatement<'_> {
+    /// Adds the statement's position and first line to a query error.
+    pub(crate) fn error(&self, err: RusticxError) -> RusticxError {
+        match err {
+            RusticxError::QueryError(message) => {
+                let first_line = self.sql.lines().next().unwrap_or_default();
+                RusticxError::QueryError(format!(
+                    "Statement {} of the script (line {}) failed: {} [{}{}]",
+                    self.number,
+                    self.line,
+                    message,
+                    first_line,
+                    if self.sql.contains('\n') { " ..." } else { "" }
+                ))
+            }
+            other => other,
+        }
+    }
+}
+
+/// Splits a script into its statements at top-level `;`s.
+///
+/// Semicolons inside string literals, quoted identifiers, comments and
+/// PostgreSQL dollar-quoted bodies do not end a statement, nor do those in
+/// the `BEGIN ... END` body of a `CREATE TRIGGER` or stored routine.
+/// Statements of only whitespace and comments are skipped.
+pub(crate) fn split_statements<'s>(script: &'s str, db_type: &DatabaseType) -> Vec<ScriptStatement<'s>> {
+    let bytes = script.as_bytes();
+    let mysql = matches!(db_type, DatabaseType::MySQL);
+    let postgres = matches!(db_type, DatabaseType::PostgreSQL);
+
+    let mut statements = Vec::new();
+    let mut code_start: Option<usize> = None;
+    let mut first_word: Option<String> = None;
+    // Whether a `CREATE` statement makes a trigger or routine, once known
+    let mut routine: Option<bool> = None;
+    let mut depth = 0usize;
+    let mut after_end = false;
+    let mut i = 0;
+
+    let finish = |code_start: &mut Option<usize>, end: usize, statements: &mut Vec<ScriptStatement<'s>>| {
+        if let Some(start) = code_start.take() {
+            statements.push(ScriptStatement {
+                number: statements.len() + 1,
+                line: script[..start].matches('\n').count() + 1,
+                sql: script[start..end].trim_end(),
+            });
+        }
+    };
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        match b {
+            b';' => {
+                if after_end {
+                    after_end = false;
+                    depth = depth.saturating_sub(1);
+                }
+                if depth == 0 {
+                    finish(&mut code_start, i, &mut statements);
+                    first_word = None;
+                    routine = None;
+                }
+                i += 1;
+                continue;
+            }
+            _ if b.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i = line_end(bytes, i);
+                continue;
+            }
+            b'#' if mysql => {
+                i = line_end(bytes, i);
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = script[i + 2..].find("*/").map_or(bytes.len(), |end| i + 2 + end + 2);
+                continue;
+            }
+            _ => {}
+        }
+
+        code_start.get_or_insert(i);
+        match b {
+            b'\'' => i = quoted_end(bytes, i, b'\'', mysql),
+            b'"' => i = quoted_end(bytes, i, b'"', false),
+            b'`' if mysql => i = quoted_end(bytes, i, b'`', false),
+            b'$' if postgres => match dollar_tag(&script[i..]) {
+                Some(tag) => {
+                    let body = i + tag.len();
+                    i = script[body..].find(tag).map_or(bytes.len(), |end| body + end + tag.len());
+                }
+                None => i += 1,
+            },
+            _ if b.is_ascii_alphanumeric() || b == b'_' => {
+                let start = i;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] == b'$') {
+                    i += 1;
+                }
+                let word = script[start..i].to_ascii_uppercase();
+
+                // `END IF`, `END LOOP` and friends close blocks that did not open one
+                if after_end {
+                    after_end = false;
+                    if matches!(word.as_str(), "IF" | "LOOP" | "WHILE" | "REPEAT") {
+                        continue;
+                    }
+                    depth = depth.saturating_sub(1);
+                }
+                let creating = first_word.get_or_insert_with(|| word.clone()) == "CREATE";
+                if creating && routine.is_none() {
+                    routine = match word.as_str() {
+                        "TRIGGER" | "FUNCTION" | "PROCEDURE" | "EVENT" => Some(true),
+                        "TABLE" | "VIEW" | "INDEX" | "SEQUENCE" | "SCHEMA" | "DATABASE" | "TYPE" | "DOMAIN" => Some(false),
+                        _ => None,
+                    };
+                }
+                // Only a trigger or routine body has blocks; elsewhere `BEGIN`
+                // may be a column name
+                if routine == Some(true) {
+                    match word.as_str() {
+                        "BEGIN" | "CASE" => depth += 1,
+                        "END" => after_end = true,
+                        _ => {}
+                    }
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    finish(&mut code_start, bytes.len(), &mut statements);
+    statements
+}
+
+/// Whether `sql` has the word `keyword` outside string literals, quoted
+/// identifiers, comments and dollar-quoted bodies.
+pub(crate) fn contains_keyword(sql: &str, db_type: &DatabaseType, keyword: &str) -> bool {
+    let bytes = sql.as_bytes();
+    let mysql = matches!(db_type, DatabaseType::MySQL);
+    let postgres = matches!(db_type, DatabaseType::PostgreSQL);
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        match b {
+            b'-' if bytes.get(i + 1) == Some(&b'-') => i = line_end(bytes, i),
+            b'#' if mysql => i = line_end(bytes, i),
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = sql[i + 2..].find("*/").map_or(bytes.len(), |end| i + 2 + end + 2);
+            }
+            b'\'' => i = quoted_end(bytes, i, b'\'', mysql),
+            b'"' => i = quoted_end(bytes, i, b'"', false),
+            b'`' if mysql => i = quoted_end(bytes, i, b'`', false),
+            b'$' if postgres => match dollar_tag(&sql[i..]) {
+                Some(tag) => {
+                    let body = i + tag.len();
+                    i = sql[body..].find(tag).map_or(bytes.len(), |end| body + end + tag.len());
+                }
+                None => i += 1,
+            },
+            _ if b.is_ascii_alphanumeric() || b == b'_' => {
+                let start = i;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] == b'$') {
+                    i += 1;
+                }
+                if sql[start..i].eq_ignore_ascii_case(keyword) {
+                    return true;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    false
+}
+
+/// The index just past the end of the line `i` is on.
+fn line_end(bytes: &[u8], i: usize) -> usize {
+    bytes[i..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |end| i + end + 1)
+}
+
+/// The index just past the quoted text opening at `i`; a doubled quote is
+/// part of the text, as is a backslash-escaped one when `backslashes` is set.
+fn quoted_end(bytes: &[u8], i: usize, quote: u8, backslashes: bool) -> usize {
+    let mut j = i + 1;
+    while j < bytes.len() {
+        match bytes[j] {
+            b'\\' if backslashes => j += 2,
+            b if b == quote && bytes.get(j + 1) == Some(&quote) => j += 2,
+            b if b == quote => return j + 1,
+            _ => j += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// The `$tag$` opening a PostgreSQL dollar-quoted string at the start of
+/// `sql`, if there is one.
+fn dollar_tag(sql: &str) -> Option<&str> {
+    let end = sql[1..].find('$')? + 2;
+    let tag = &sql[1..end - 1];
+    let valid = tag.chars().next().is_none_or(|c| c.is_ascii_alphabetic() || c == '_')
+        && tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then(|| &sql[..end])
+}
@@ -0,0 +1,119 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use crate::connection::ConnectionPool;
+use crate::error::RusticxError;
+
+/// How many transaction sessions a connection opens unless configured with
+/// `Connection::with_transaction_sessions`.
+const DEFAULT_MAX_SESSIONS: usize = 10;
+
+/// The sessions `Connection::begin` runs transactions on, shared by a
+/// connection's clones.
+///
+/// A PostgreSQL or MySQL transaction needs a session of its own so other
+/// handles' statements stay out of it. Sessions are opened as transactions
+/// need them, up to a cap, and kept open between transactions instead of
+/// being opened and closed for each one.
+pub(crate) struct TxSessions {
+    max: usize,
+    acquire_timeout: Duration,
+    state: Mutex<SessionState>,
+    released: Condvar,
+}
+
+#[derive(Default)]
+struct SessionState {
+    idle: Vec<ConnectionPool>,
+    open: usize,
+}
+
+impl TxSessions {
+    pub(crate) fn new(max: usize, acquire_timeout: Duration) -> Self {
+        TxSessions {
+            max,
+            acquire_timeout,
+            state: Mutex::new(SessionState::default()),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Checks out an idle session, or opens one with `open` while fewer than
+    /// the cap are open, waiting up to the acquire timeout otherwise.
+    pub(crate) fn acquire<F>(self: &Arc<Self>, open: F) -> Result<(ConnectionPool, SessionLease), RusticxError>
+    where
+        F: FnOnce() -> Result<ConnectionPool, RusticxError>,
+    {
+        let deadline = Instant::now() + self.acquire_timeout;
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        loop {
+            if let Some(pool) = state.idle.pop() {
+                return Ok(self.lease(pool));
+            }
+            if state.open < self.max {
+                state.open += 1;
+                drop(state);
+                return match open() {
+                    Ok(pool) => Ok(self.lease(pool)),
+                    Err(e) => {
+                        self.forget();
+                        Err(e)
+                    }
+                };
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(RusticxError::ConnectionError(format!(
+                    "Timed out after {:?} waiting for a transaction session ({} open, all in use)",
+                    self.acquire_timeout, self.max
+                )));
+            }
+            state = self
+                .released
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    fn lease(self: &Arc<Self>, pool: ConnectionPool) -> (ConnectionPool, SessionLease) {
+        (pool.clone(), SessionLease { sessions: Arc::clone(self), pool })
+    }
+
+    /// Stops counting a session that is no longer kept, freeing its place.
+    fn forget(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.open -= 1;
+        self.released.notify_one();
+    }
+}
+
+impl Default for TxSessions {
+    fn default() -> Self {
+        TxSessions::new(DEFAULT_MAX_SESSIONS, Duration::from_secs(30))
+    }
+}
+
+/// A checked-out transaction session, returned to the idle sessions when
+/// dropped.
+///
+/// A session that a clone of the transaction's handle still uses, or that
+/// cannot run another transaction, is given up instead.
+pub(crate) struct SessionLease {
+    sessions: Arc<TxSessions>,
+    pool: ConnectionPool,
+}
+
+impl Drop for SessionLease {
+    fn drop(&mut self) {
+        let pool = std::mem::replace(&mut self.pool, ConnectionPool::None);
+        if pool.is_shared() || !pool.reset_for_reuse() {
+            self.sessions.forget();
+            return;
+        }
+        let mut state = self.sessions.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.idle.push(pool);
+        self.sessions.released.notify_one();
+    }
+}
@@ -0,0 +1,249 @@
+#[cfg(feature = "postgres")]
+use std::pin::Pin;
+use crate::connection::Connection;
+use crate::error::RusticxError;
+use crate::model::SQLModel;
+use crate::query_builder::QueryBuilder;
+use std::marker::PhantomData;
+#[cfg(feature = "postgres")]
+use futures_util::StreamExt;
+#[cfg(feature = "postgres")]
+use tokio::runtime::Handle;
+#[cfg(any(feature = "mysql", feature = "rusqlite"))]
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+
+/// How many decoded rows a MySQL or SQLite stream reads ahead of its iterator.
+#[cfg(any(feature = "mysql", feature = "rusqlite"))]
+const READ_AHEAD: usize = 64;
+
+#[cfg(any(feature = "mysql", feature = "rusqlite"))]
+type RowSender = SyncSender<Result<serde_json::Value, RusticxError>>;
+
+/// An iterator that yields query results one row at a time.
+///
+/// Returned by `Connection::query_stream` and `SQLModel::stream_all`. Rows
+/// are pulled from the server as the iterator advances, so memory use stays
+/// bounded regardless of the size of the result set. On MySQL and SQLite a
+/// reader thread stays a few rows ahead of the iterator.
+///
+/// While a stream is alive its rows occupy the connection (on MySQL, one of
+/// the pool's connections): issue other statements on the same `Connection`
+/// only after the iterator has been exhausted or dropped, or use a second
+/// `Connection` for them.
+pub struct RowIter<T> {
+    source: RowSource,
+    model: PhantomData<fn() -> T>,
+}
+
+enum RowSource {
+    #[cfg(feature = "postgres")]
+    Postgres {
+        rows: Pin<Box<tokio_postgres::RowStream>>,
+        rt: Handle,
+    },
+    #[cfg(any(feature = "mysql", feature = "rusqlite"))]
+    Reader {
+        first: Option<Result<serde_json::Value, RusticxError>>,
+        rows: Receiver<Result<serde_json::Value, RusticxError>>,
+    },
+}
+
+impl<T> RowIter<T> {
+    #[cfg(feature = "postgres")]
+    pub(crate) fn postgres(rows: tokio_postgres::RowStream, rt: Handle) -> Self {
+        RowIter {
+            source: RowSource::Postgres { rows: Box::pin(rows), rt },
+            model: PhantomData,
+        }
+    }
+
+    /// Streams the rows of `sql` from a connection checked out of a MySQL pool.
+    ///
+    /// Statements without parameters keep the text protocol, as in `query_raw`.
+    #[cfg(feature = "mysql")]
+    pub(crate) fn mysql(mut conn: mysql::PooledConn, sql: String, params: mysql::Params) -> Result<Self, RusticxError> {
+        use mysql::prelude::Queryable;
+
+        Self::reader(move |rows| {
+            let started = |e: mysql::Error| RusticxError::QueryError(e.to_string());
+            match params {
+                mysql::Params::Empty => send_mysql_rows(conn.query_iter(sql).map_err(started)?, rows),
+                params => send_mysql_rows(conn.exec_iter(sql, params).map_err(started)?, rows),
+            }
+        })
+    }
+
+    /// Streams the rows of `sql` from a SQLite connection, which stays locked
+    /// until the stream ends.
+    #[cfg(feature = "rusqlite")]
+    pub(crate) fn sqlite(
+        conn: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+        sql: String,
+        params: Vec<rusqlite::types::Value>,
+    ) -> Result<Self, RusticxError> {
+        Self::reader(move |rows| {
+            let conn = conn.lock().map_err(|e| {
+                RusticxError::ConnectionError(format!("Failed to acquire lock on SQLite connection: {}", e))
+            })?;
+            let mut stmt = conn.prepare_cached(&sql).map_err(crate::connection::sqlite_error)?;
+            let column_names: Vec<String> = stmt.column_names().iter().map(|name| name.to_string()).collect();
+            let mut results = stmt
+                .query(rusqlite::params_from_iter(params))
+                .map_err(crate::connection::sqlite_error)?;
+
+            while let Some(row) = results.next().map_err(crate::connection::sqlite_error)? {
+                let mut json_obj = serde_json::Map::new();
+                for (i, name) in column_names.iter().enumerate() {
+                    let value = crate::transaction_manager::sqlite_row_value_to_json(row, i)
+                        .unwrap_or(serde_json::Value::Null);
+                    json_obj.insert(name.clone(), value);
+                }
+                // The iterator was dropped
+                if rows.send(Ok(serde_json::Value::Object(json_obj))).is_err() {
+                    break;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Runs `produce` on a reader thread that sends rows through a bounded
+    /// channel, and waits for its first row so that a statement failing to
+    /// start is reported by `query_stream` itself.
+    #[cfg(any(feature = "mysql", feature = "rusqlite"))]
+    fn reader<F>(produce: F) -> Result<Self, RusticxError>
+    where
+        F: FnOnce(&RowSender) -> Result<(), RusticxError> + Send + 'static,
+    {
+        let (sender, rows) = sync_channel(READ_AHEAD);
+        std::thread::Builder::new()
+            .name("rusticx-stream".to_string())
+            .spawn(move || {
+                if let Err(e) = produce(&sender) {
+                    let _ = sender.send(Err(e));
+                }
+            })
+            .map_err(|e| RusticxError::QueryError(format!("Failed to start the stream reader: {}", e)))?;
+
+        match rows.recv().ok() {
+            Some(Err(e)) => Err(e),
+            first => Ok(RowIter {
+                source: RowSource::Reader { first, rows },
+                model: PhantomData,
+            }),
+        }
+    }
+}
+
+/// Sends the rows of a MySQL result set until it ends or the iterator is dropped.
+#[cfg(feature = "mysql")]
+fn send_mysql_rows<P: mysql::prelude::Protocol>(
+    result: mysql::QueryResult<'_, '_, '_, P>,
+    rows: &RowSender,
+) -> Result<(), RusticxError> {
+    for row in result {
+        let row = row.map_err(|e| RusticxError::QueryError(e.to_string()))?;
+        let mut json_obj = serde_json::Map::new();
+        for (i, column) in row.columns_ref().iter().enumerate() {
+            let value = crate::transaction_manager::mysql_row_value_to_json(&row, i, column.column_type())
+                .unwrap_or(serde_json::Value::Null);
+            json_obj.insert(column.name_str().to_string(), value);
+        }
+        // The iterator was dropped
+        if rows.send(Ok(serde_json::Value::Object(json_obj))).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+impl<T> Iterator for RowIter<T>
+where
+    T: for<'de> serde::Deserialize<'de>,
+{
+    type Item = Result<T, RusticxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.source {
+            #[cfg(feature = "postgres")]
+            RowSource::Postgres { rows, rt } => match crate::connection::block_on(rt, rows.next())? {
+                Ok(row) => Some(crate::transaction_manager::pg_row_to_model(&row)),
+                Err(e) => Some(Err(RusticxError::from(e))),
+            },
+            #[cfg(any(feature = "mysql", feature = "rusqlite"))]
+            RowSource::Reader { first, rows } => {
+                let row = first.take().or_else(|| rows.recv().ok())?;
+                Some(row.and_then(|row| {
+                    serde_json::from_value(row).map_err(|e| RusticxError::SerializationError(e.to_string()))
+                }))
+            }
+        }
+    }
+}
+
+/// An iterator over the results of a `QueryBuilder`, fetched in batches.
+///
+/// Returned by `QueryBuilder::stream`. Each batch is a separate query with
+/// `LIMIT` and `OFFSET`, ordered with the primary key as a tie-breaker so
+/// rows are neither repeated nor skipped between batches. The connection is
+/// free between batches, but rows written meanwhile may shift later pages.
+///
+/// Iteration stops after the first error, which is yielded.
+pub struct BatchIter<'c, T> {
+    conn: &'c Connection,
+    query: QueryBuilder,
+    batch_size: usize,
+    next_offset: usize,
+    remaining: Option<usize>,
+    buffer: std::vec::IntoIter<T>,
+    done: bool,
+}
+
+impl<'c, T> BatchIter<'c, T> {
+    pub(crate) fn new(conn: &'c Connection, query: QueryBuilder, batch_size: usize, offset: usize, limit: Option<usize>) -> Self {
+        BatchIter {
+            conn,
+            query,
+            batch_size: batch_size.max(1),
+            next_offset: offset,
+            remaining: limit,
+            buffer: Vec::new().into_iter(),
+            done: false,
+        }
+    }
+}
+
+impl<T: SQLModel> Iterator for BatchIter<'_, T> {
+    type Item = Result<T, RusticxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(row) = self.buffer.next() {
+            return Some(Ok(row));
+        }
+        if self.done {
+            return None;
+        }
+
+        let size = self.remaining.map_or(self.batch_size, |left| left.min(self.batch_size));
+        if size == 0 {
+            self.done = true;
+            return None;
+        }
+        let batch = match self.query.clone().limit(size).offset(self.next_offset).find_all::<T>(self.conn) {
+            Ok(batch) => batch,
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+        };
+
+        // A short batch is the last one
+        self.done = batch.len() < size;
+        self.next_offset += batch.len();
+        if let Some(left) = &mut self.remaining {
+            *left -= batch.len();
+        }
+        self.buffer = batch.into_iter();
+        self.buffer.next().map(Ok)
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::{HashMap, HashSet};
+use crate::connection::DatabaseType;
+use crate::error::RusticxError;
+use crate::model::ToSql;
+#[cfg(feature = "postgres")]
+use postgres::types::{IsNull, Type};
+
+/// A SQL statement template with named identifier and value placeholders.
+///
+/// Covers the middle ground between the model finders and fully raw SQL:
+/// instead of splicing table or column names in with `format!`, write them as
+/// placeholders and let the template validate and quote them.
+///
+/// * `{name:ident}` is replaced by the argument `name` as a quoted identifier.
+///   The argument must be a string made of letters, digits and underscores
+///   (not starting with a digit), optionally dotted (`schema.table`).
+/// * `{name}` is replaced by a bind placeholder and the argument is passed as
+///   a query parameter.
+/// * `{{` and `}}` produce literal braces.
+///
+/// Templates are usually built with the `sql!` macro and turned into SQL for a
+/// specific database with `render`.
+pub struct SqlTemplate<'a> {
+    template: &'a str,
+    args: Vec<(&'a str, &'a (dyn ToSql + Sync + 'static))>,
+}
+
+/// A rendered `SqlTemplate`: the final SQL text and its bound parameters, in
+/// placeholder order, ready for `Connection::query_raw` or `Connection::execute`.
+pub struct RenderedSql<'a> {
+    /// The SQL text with identifiers quoted and values replaced by placeholders.
+    pub sql: String,
+    /// The parameters to bind, in placeholder order.
+    pub params: Vec<&'a (dyn ToSql + Sync + 'static)>,
+}
+
+impl<'a> SqlTemplate<'a> {
+    /// Creates a template from its SQL text.
+    pub fn new(template: &'a str) -> Self {
+        SqlTemplate { template, args: Vec::new() }
+    }
+
+    /// Supplies the argument for the placeholder `name`.
+    pub fn arg(mut self, name: &'a str, value: &'a (dyn ToSql + Sync + 'static)) -> Self {
+        self.args.push((name, value));
+        self
+    }
+
+    /// Renders the template for `db_type`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `RusticxError::ValidationError` if the template is malformed,
+    /// a placeholder has no argument, an argument is never used, or an
+    /// identifier argument is not a valid identifier.
+    pub fn render(&self, db_type: &DatabaseType) -> Result<RenderedSql<'a>, RusticxError> {
+        let args: HashMap<&str, &'a (dyn ToSql + Sync + 'static)> = self.args.iter().copied().collect();
+        let mut used: HashSet<&str> = HashSet::new();
+        let mut bound: HashMap<&str, usize> = HashMap::new();
+        let mut sql = String::with_capacity(self.template.len());
+        let mut params = Vec::new();
+
+        let mut chars = self.template.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            match c {
+                '{' if chars.peek().map(|&(_, next)| next) == Some('{') => {
+                    chars.next();
+                    sql.push('{');
+                }
+                '}' if chars.peek().map(|&(_, next)| next) == Some('}') => {
+                    chars.next();
+                    sql.push('}');
+                }
+                '{' => {
+                    let end = loop {
+                        match chars.next() {
+                            Some((i, '}')) => break i,
+                            Some(_) => continue,
+                            None => {
+                                return Err(RusticxError::ValidationError(format!(
+                                    "Unclosed placeholder at byte {} in SQL template", start
+                                )))
+                            }
+                        }
+                    };
+                    let spec = &self.template[start + 1..end];
+                    let (name, kind) = match spec.split_once(':') {
+                        Some((name, kind)) => (name.trim(), Some(kind.trim())),
+                        None => (spec.trim(), None),
+                    };
+                    let value = *args.get(name).ok_or_else(|| {
+                        RusticxError::ValidationError(format!("No argument supplied for placeholder `{{{}}}`", spec))
+                    })?;
+
+                    match kind {
+                        Some("ident") => {
+                            let ident = identifier_text(value).ok_or_else(|| {
+                                RusticxError::ValidationError(format!(
+                                    "Identifier argument `{}` must be a non-null string", name
+                                ))
+                            })?;
+                            sql.push_str(&quote_identifier(&ident, db_type)?);
+                        }
+                        None => match (db_type, bound.get(name)) {
+                            // PostgreSQL can refer to the same numbered parameter twice
+                            (DatabaseType::PostgreSQL, Some(&index)) => sql.push_str(&db_type.placeholder(index)),
+                            _ => {
+                                params.push(value);
+                                sql.push_str(&db_type.placeholder(params.len()));
+                                bound.insert(name, params.len());
+                            }
+                        },
+                        Some(other) => {
+                            return Err(RusticxError::ValidationError(format!(
+                                "Unknown placeholder kind `{}` for `{}`; expected `ident`", other, name
+                            )))
+                        }
+                    }
+                    used.insert(name);
+                }
+                '}' => {
+                    return Err(RusticxError::ValidationError(format!(
+                        "Unmatched `}}` at byte {} in SQL template; use `}}}}` for a literal brace", start
+                    )))
+                }
+                _ => sql.push(c),
+            }
+        }
+
+        if let Some((name, _)) = self.args.iter().find(|(name, _)| !used.contains(name)) {
+            return Err(RusticxError::ValidationError(format!(
+                "Argument `{}` is not used by the SQL template", name
+            )));
+        }
+
+        Ok(RenderedSql { sql, params })
+    }
+}
+
+/// Extracts the text of an identifier argument by encoding it as `TEXT`.
+///
+/// Only values that Postgres would accept as text (`String`, `&str`, ...) succeed.
+#[cfg(feature = "postgres")]
+fn identifier_text(value: &(dyn ToSql + Sync)) -> Option<String> {
+    let mut buf = bytes::BytesMut::new();
+    match value.to_sql_checked(&Type::TEXT, &mut buf) {
+        Ok(IsNull::No) => String::from_utf8(buf.to_vec()).ok(),
+        _ => None,
+    }
+}
+
+/// Extracts the text of an identifier argument bound as text.
+#[cfg(not(feature = "postgres"))]
+fn identifier_text(value: &(dyn ToSql + Sync)) -> Option<String> {
+    match value.to_param() {
+        crate::transaction_manager::ParamValue::Text(text) => Some(text),
+        _ => None,
+    }
+}
+
+/// Validates `ident` (optionally `schema.name`) and quotes it for `db_type`.
+fn quote_identifier(ident: &str, db_type: &DatabaseType) -> Result<String, RusticxError> {
+    for part in ident.split('.') {
+        let valid = part.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(RusticxError::ValidationError(format!("`{}` is not a valid SQL identifier", ident)));
+        }
+    }
+
+    Ok(db_type.quote_identifier(ident))
+}
+
+/// Builds a `SqlTemplate` from a template string and named arguments.
+///
+/// Each `name = value` pair supplies the placeholder `{name}` or
+/// `{name:ident}`; values are borrowed, so pass owned variables rather than
+/// references. Call `render` with the connection's database type to obtain
+/// the SQL and parameters.
+///
+/// ```ignore
+/// let column = "email";
+/// let email = "alice@example.com".to_string();
+/// let query = rusticx::sql!(
+///     "SELECT * FROM {table:ident} WHERE {column:ident} = {email}",
+///     table = "users",
+///     column = column,
+///     email = email,
+/// )
+/// .render(conn.get_db_type())?;
+/// let users: Vec<User> = conn.query_raw(&query.sql, &query.params)?;
+/// ```
+#[macro_export]
+macro_rules! sql {
+    ($template:expr $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::SqlTemplate::new($template)$(.arg(stringify!($name), &$value))*
+    };
+}
@@ -0,0 +1,167 @@
+use std::path::PathBuf;
+use crate::error::RusticxError;
+
+/// How a MySQL connection uses TLS, named after the MySQL client's
+/// `--ssl-mode` values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MySqlSslMode {
+    /// Plain, unencrypted connections.
+    Disabled,
+    /// Encrypted, but any server certificate is accepted.
+    Required,
+    /// Encrypted; the server certificate must chain to a trusted CA, but its
+    /// host name is not checked.
+    VerifyCa,
+    /// Encrypted; the server certificate must chain to a trusted CA and name
+    /// the host connected to.
+    #[default]
+    VerifyIdentity,
+}
+
+impl MySqlSslMode {
+    fn parse(value: &str) -> Result<Self, RusticxError> {
+        match value.to_ascii_uppercase().as_str() {
+            "DISABLED" => Ok(MySqlSslMode::Disabled),
+            "REQUIRED" => Ok(MySqlSslMode::Required),
+            "VERIFY_CA" => Ok(MySqlSslMode::VerifyCa),
+            "VERIFY_IDENTITY" => Ok(MySqlSslMode::VerifyIdentity),
+            _ => Err(RusticxError::ValidationError(format!(
+                "Unknown ssl-mode '{}': expected DISABLED, REQUIRED, VERIFY_CA or VERIFY_IDENTITY",
+                value
+            ))),
+        }
+    }
+}
+
+/// TLS settings for MySQL connections.
+///
+/// Pass them to `Connection::new_with_tls`, or set them in the URL with the
+/// MySQL client's option names:
+///
+/// ```ignore
+/// let conn = Connection::new("mysql://app:secret@db:3306/shop?ssl-mode=VERIFY_CA&ssl-ca=/etc/ssl/ca.pem")?;
+///
+/// let tls = MySqlTls::new(MySqlSslMode::VerifyIdentity)
+///     .ca_cert("/etc/ssl/ca.pem")
+///     .client_cert("/etc/ssl/app.crt", "/etc/ssl/app.key");
+/// let conn = Connection::new_with_tls("mysql://app@db:3306/shop", tls)?;
+/// ```
+///
+/// Without `ssl-ca` the platform's root certificates are trusted. Encrypted
+/// modes need the `mysql-tls` feature; connecting with one without it fails
+/// with `RusticxError::FeatureNotEnabled`.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(not(feature = "mysql"), allow(dead_code))]
+pub struct MySqlTls {
+    mode: MySqlSslMode,
+    ca_cert: Option<PathBuf>,
+    client_cert: Option<(PathBuf, PathBuf)>,
+}
+
+impl MySqlTls {
+    /// TLS in `mode`, trusting the platform's root certificates.
+    pub fn new(mode: MySqlSslMode) -> Self {
+        MySqlTls { mode, ..Default::default() }
+    }
+
+    /// Trusts the CA certificate(s) in the PEM file at `path` instead of the
+    /// platform's roots.
+    pub fn ca_cert(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ca_cert = Some(path.into());
+        self
+    }
+
+    /// Authenticates with the PEM client certificate `cert` and its private
+    /// key `key`.
+    pub fn client_cert(mut self, cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        self.client_cert = Some((cert.into(), key.into()));
+        self
+    }
+
+    /// Splits the `ssl-mode`, `ssl-ca`, `ssl-cert` and `ssl-key` parameters
+    /// off a MySQL URL, returning the remaining URL and the settings, if any
+    /// were given.
+    #[cfg_attr(not(feature = "mysql"), allow(dead_code))]
+    pub(crate) fn from_url(url: &str) -> Result<(String, Option<MySqlTls>), RusticxError> {
+        let Some((base, query)) = url.split_once('?') else {
+            return Ok((url.to_string(), None));
+        };
+
+        let (mut mode, mut ca, mut cert, mut key) = (None, None, None, None);
+        let mut rest = Vec::new();
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match name {
+                "ssl-mode" => mode = Some(MySqlSslMode::parse(&percent_decode(value))?),
+                "ssl-ca" => ca = Some(PathBuf::from(percent_decode(value))),
+                "ssl-cert" => cert = Some(PathBuf::from(percent_decode(value))),
+                "ssl-key" => key = Some(PathBuf::from(percent_decode(value))),
+                _ => rest.push(pair),
+            }
+        }
+
+        let url = if rest.is_empty() { base.to_string() } else { format!("{}?{}", base, rest.join("&")) };
+        if mode.is_none() && ca.is_none() && cert.is_none() && key.is_none() {
+            return Ok((url, None));
+        }
+        let client_cert = match (cert, key) {
+            (Some(cert), Some(key)) => Some((cert, key)),
+            (None, None) => None,
+            _ => {
+                return Err(RusticxError::ValidationError(
+                    "ssl-cert and ssl-key must be given together".to_string(),
+                ))
+            }
+        };
+        Ok((url, Some(MySqlTls { mode: mode.unwrap_or_default(), ca_cert: ca, client_cert })))
+    }
+
+    /// The driver's TLS options for these settings, `None` when disabled.
+    #[cfg(feature = "mysql-tls")]
+    pub(crate) fn ssl_opts(&self) -> Result<Option<mysql::SslOpts>, RusticxError> {
+        if self.mode == MySqlSslMode::Disabled {
+            return Ok(None);
+        }
+        let mut opts = mysql::SslOpts::default()
+            .with_root_cert_path(self.ca_cert.clone())
+            .with_danger_accept_invalid_certs(self.mode == MySqlSslMode::Required)
+            .with_danger_skip_domain_validation(self.mode != MySqlSslMode::VerifyIdentity);
+        if let Some((cert, key)) = &self.client_cert {
+            opts = opts.with_client_identity(Some(mysql::ClientIdentity::new(cert.clone(), key.clone())));
+        }
+        Ok(Some(opts))
+    }
+
+    /// Without a TLS backend only `MySqlSslMode::Disabled` can connect.
+    #[cfg(all(feature = "mysql", not(feature = "mysql-tls")))]
+    pub(crate) fn ssl_opts(&self) -> Result<Option<mysql::SslOpts>, RusticxError> {
+        if self.mode == MySqlSslMode::Disabled {
+            return Ok(None);
+        }
+        Err(RusticxError::FeatureNotEnabled("MySQL TLS requires the mysql-tls feature".to_string()))
+    }
+}
+
+/// Decodes `%XX` escapes in a URL parameter value.
+pub(crate) fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -1,9 +1,15 @@
+use std::cell::RefCell;
 use std::fmt::Debug;
+use std::ops::Deref;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::connection::{Connection, DatabaseType};
 use crate::error::RusticxError;
+use crate::sessions::SessionLease;
+use crate::model::{SQLModel, ToSql};
 #[cfg(feature = "mysql")]
 use mysql::prelude::Queryable;
-#[cfg(feature = "rusqlite")]
+#[cfg(any(feature = "postgres", feature = "rusqlite"))]
 use base64::Engine;
 
 // Re-export needed types for external users
@@ -15,83 +21,932 @@ pub use mysql;
 pub use rusqlite;
 
 /// A trait for executing transactions in a database.
-pub trait TransactionExecutor {
-    /// Executes an SQL statement with parameters.
+///
+/// Queries run through its `QueryExecutor` supertrait, so the executor a
+/// transaction closure receives can read as well as write:
+///
+/// ```ignore
+/// use rusticx::QueryExecutorExt;
+///
+/// conn.transaction(|tx| {
+///     let accounts: Vec<Account> = tx.query_raw("SELECT * FROM accounts WHERE id = $1 FOR UPDATE", &[&id])?;
+///     tx.execute("UPDATE accounts SET balance = $1 WHERE id = $2", &[&(accounts[0].balance - 10), &id])
+/// }).await?;
+/// ```
+pub trait TransactionExecutor: QueryExecutor {
+    /// Executes an SQL statement with parameters, bound to its placeholders
+    /// in order as in `Connection::execute`.
     /// Returns the number of rows affected.
-    fn execute(&mut self, sql: &str, params: &[&dyn Debug]) -> Result<u64, RusticxError>;
+    fn execute(&mut self, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<u64, RusticxError>;
+
+    /// Executes an SQL statement once for each set of parameters, preparing
+    /// it only once:
+    ///
+    /// ```ignore
+    /// conn.transaction(|tx| {
+    ///     tx.execute_many(
+    ///         "INSERT INTO prices (sku, cents) VALUES ($1, $2)",
+    ///         &[&[&"A-1", &250], &[&"B-2", &1200]],
+    ///     )
+    /// }).await?;
+    /// ```
+    ///
+    /// Returns the total number of rows affected. The first set that fails
+    /// stops the batch with its error, and rolling back the transaction
+    /// undoes the sets before it.
+    fn execute_many(
+        &mut self,
+        sql: &str,
+        param_sets: &[&[&(dyn ToSql + Sync + 'static)]],
+    ) -> Result<u64, RusticxError>;
+
+    /// Registers a callback to run if the transaction commits; see
+    /// `after_commit`.
+    fn add_after_commit(&mut self, hook: Box<dyn FnOnce() + Send>);
+
+    /// Registers a callback to run if the transaction is rolled back; see
+    /// `after_rollback`.
+    fn add_after_rollback(&mut self, hook: Box<dyn FnOnce() + Send>);
+
+    /// Defers checking the named constraints until the transaction commits,
+    /// with `SET CONSTRAINTS ... DEFERRED`, so rows that reference each other
+    /// can be inserted one at a time. Only constraints declared `DEFERRABLE`
+    /// can be deferred.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError` if a constraint cannot be deferred, or a
+    /// `RusticxError::QueryError` outside PostgreSQL.
+    fn set_constraints_deferred(&mut self, names: &[&str]) -> Result<(), RusticxError>;
+
+    /// Defers checking every deferrable constraint until the transaction
+    /// commits (`SET CONSTRAINTS ALL DEFERRED`; on SQLite, every foreign key
+    /// with `PRAGMA defer_foreign_keys`).
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError` if the statement fails, or a
+    /// `RusticxError::QueryError` on MySQL, which checks constraints
+    /// immediately.
+    fn set_all_constraints_deferred(&mut self) -> Result<(), RusticxError>;
+
+    /// The type of database the transaction runs on.
+    fn get_db_type(&self) -> &DatabaseType;
+}
+
+impl dyn TransactionExecutor + '_ {
+    /// Runs `hook` once the transaction has committed, and not at all if it
+    /// is rolled back, so effects outside the database such as sending mail
+    /// or invalidating a cache only happen for changes that were kept:
+    ///
+    /// ```ignore
+    /// conn.transaction(move |tx| {
+    ///     tx.execute("UPDATE orders SET status = 'paid' WHERE id = $1", &[&id])?;
+    ///     tx.after_commit(move || mailer.send_receipt(id));
+    ///     Ok(())
+    /// }).await?;
+    /// ```
+    ///
+    /// Hooks run in the order they were registered, after the connection is
+    /// released, so they may run statements of their own.
+    pub fn after_commit<F: FnOnce() + Send + 'static>(&mut self, hook: F) {
+        self.add_after_commit(Box::new(hook));
+    }
+
+    /// Runs `hook` once the transaction has been rolled back, whether the
+    /// closure failed or the commit did.
+    pub fn after_rollback<F: FnOnce() + Send + 'static>(&mut self, hook: F) {
+        self.add_after_rollback(Box::new(hook));
+    }
+}
+
+/// Callbacks registered with `after_commit` and `after_rollback`.
+#[derive(Default)]
+pub(crate) struct TxHooks {
+    after_commit: Vec<Box<dyn FnOnce() + Send>>,
+    after_rollback: Vec<Box<dyn FnOnce() + Send>>,
+}
+
+impl TxHooks {
+    /// Runs the callbacks for how the transaction ended, in the order they
+    /// were registered.
+    pub(crate) fn finish(self, committed: bool) {
+        let hooks = if committed { self.after_commit } else { self.after_rollback };
+        for hook in hooks {
+            hook();
+        }
+    }
 }
 
 /// A trait for executing queries in a database.
+///
+/// Rows come back as JSON objects keyed by column name, which keeps the
+/// trait usable as `dyn QueryExecutor`; `QueryExecutorExt::query_raw`
+/// deserializes them into a type.
 pub trait QueryExecutor {
+    /// Executes a query with parameters and returns one JSON object per row.
+    fn query_json(
+        &mut self,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync + 'static)],
+    ) -> Result<Vec<serde_json::Value>, RusticxError>;
+}
+
+/// Typed queries for every `QueryExecutor`, trait objects included.
+pub trait QueryExecutorExt: QueryExecutor {
     /// Executes a query and returns the results as a vector of deserialized objects.
-    /// Note: Due to Rust's trait object limitations with generic methods,
-    /// `query_raw` makes this trait not fully dyn compatible if `T` varies at runtime.
-    /// For true dynamic dispatch on return types, consider returning a standard
-    /// intermediate representation (like `serde_json::Value`).
-    #[allow(dead_code)]
-    fn query_raw<T>(&mut self, sql: &str, params: &[&dyn Debug]) -> Result<Vec<T>, RusticxError>
+    fn query_raw<T>(&mut self, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<Vec<T>, RusticxError>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        self.query_json(sql, params)?
+            .into_iter()
+            .map(|row| serde_json::from_value(row).map_err(|e| RusticxError::SerializationError(e.to_string())))
+            .collect()
+    }
+}
+
+impl<E: QueryExecutor + ?Sized> QueryExecutorExt for E {}
+
+/// The isolation level of a transaction; see `TxOptions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsolationLevel {
+    /// Reads may see changes other transactions have not committed.
+    ReadUncommitted,
+    /// Each statement sees the changes committed before it started.
+    ReadCommitted,
+    /// Every read sees the changes committed before the transaction's first
+    /// statement.
+    RepeatableRead,
+    /// The transaction behaves as if no other ran at the same time; one that
+    /// cannot be ordered with a concurrent transaction fails to commit (on
+    /// PostgreSQL) or waits for its locks (on MySQL).
+    Serializable,
+}
+
+impl IsolationLevel {
+    /// The level as SQL names it.
+    pub(crate) fn sql(&self) -> &'static str {
+        match self {
+            IsolationLevel::ReadUncommitted => "READ UNCOMMITTED",
+            IsolationLevel::ReadCommitted => "READ COMMITTED",
+            IsolationLevel::RepeatableRead => "REPEATABLE READ",
+            IsolationLevel::Serializable => "SERIALIZABLE",
+        }
+    }
+}
+
+/// Settings for a transaction started with `Connection::transaction_with`
+/// or `Connection::begin_with`.
+///
+/// ```ignore
+/// let options = TxOptions { isolation: Some(IsolationLevel::Serializable), ..TxOptions::default() };
+/// conn.transaction_with(options, |tx| {
+///     tx.execute("UPDATE accounts SET balance = balance - $1 WHERE id = $2", &[&amount, &from])?;
+///     tx.execute("UPDATE accounts SET balance = balance + $1 WHERE id = $2", &[&amount, &to])
+/// }).await?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TxOptions {
+    /// The isolation level, or `None` for the server's default. SQLite
+    /// transactions are always serializable, which satisfies every level.
+    pub isolation: Option<IsolationLevel>,
+    /// Whether the transaction may only read, so a statement that writes
+    /// fails instead. Read-only transactions take turns on the connection's
+    /// replicas if it has any (see `Connection::with_replica`); on SQLite
+    /// `PRAGMA query_only` is set on the connection while one runs.
+    pub read_only: bool,
+    /// How long the transaction may take. A statement that runs past it is
+    /// cancelled (`statement_timeout` on PostgreSQL) or gives up waiting for
+    /// a lock (`innodb_lock_wait_timeout`, in whole seconds, on MySQL), and a
+    /// transaction that is still open once it has passed is rolled back
+    /// instead of committed, failing with `RusticxError::Timeout`.
+    pub timeout: Option<Duration>,
+    /// Whether to take the transaction's snapshot as it starts rather than
+    /// at its first read (`START TRANSACTION WITH CONSISTENT SNAPSHOT` on
+    /// MySQL). Only matters under `RepeatableRead` or the server's default,
+    /// which is repeatable read; PostgreSQL and SQLite ignore it.
+    pub consistent_snapshot: bool,
+    /// Whether to take SQLite's write lock as the transaction starts
+    /// (`BEGIN IMMEDIATE`), so rows it reads cannot change before it writes
+    /// them, as with `SQLModel::find_by_id_for_update`. Other writers wait
+    /// for it, or fail once their `busy_timeout` passes. PostgreSQL and
+    /// MySQL ignore it; their `FOR UPDATE` locks the rows read.
+    pub immediate: bool,
+}
+
+impl TxOptions {
+    /// The statements that start a transaction with these options.
+    pub(crate) fn begin_sql(&self, db_type: &DatabaseType) -> String {
+        let begin = self.start_sql(db_type);
+        match (db_type, self.timeout_sql(db_type)) {
+            (DatabaseType::PostgreSQL, Some(timeout)) => format!("{}; {}", begin, timeout),
+            (DatabaseType::MySQL, Some(timeout)) => format!("{}; {}", timeout, begin),
+            _ => begin,
+        }
+    }
+
+    /// The statements that start a transaction with these options, leaving
+    /// out the timeout.
+    fn start_sql(&self, db_type: &DatabaseType) -> String {
+        let read_only = if self.read_only { " READ ONLY" } else { "" };
+        match (db_type, self.isolation) {
+            (DatabaseType::PostgreSQL, Some(level)) => format!("BEGIN ISOLATION LEVEL {}{}", level.sql(), read_only),
+            (DatabaseType::PostgreSQL, None) => format!("BEGIN{}", read_only),
+            (DatabaseType::MySQL, Some(level)) => {
+                format!("SET TRANSACTION ISOLATION LEVEL {}; {}", level.sql(), self.mysql_start())
+            }
+            (DatabaseType::MySQL, None) => self.mysql_start(),
+            (DatabaseType::SQLite, _) => {
+                let begin = if self.immediate { "BEGIN IMMEDIATE" } else { "BEGIN" };
+                if self.read_only {
+                    format!("PRAGMA query_only = ON; {}", begin)
+                } else {
+                    begin.to_string()
+                }
+            }
+        }
+    }
+
+    /// MySQL's `START TRANSACTION` with its comma-separated characteristics.
+    fn mysql_start(&self) -> String {
+        let mut characteristics = Vec::new();
+        if self.consistent_snapshot {
+            characteristics.push("WITH CONSISTENT SNAPSHOT");
+        }
+        if self.read_only {
+            characteristics.push("READ ONLY");
+        }
+        if characteristics.is_empty() {
+            "START TRANSACTION".to_string()
+        } else {
+            format!("START TRANSACTION {}", characteristics.join(", "))
+        }
+    }
+
+    /// The statement that bounds the transaction's statements by `timeout`.
+    /// PostgreSQL's `SET LOCAL` ends with the transaction; MySQL's setting
+    /// is reset by `end_sql`.
+    pub(crate) fn timeout_sql(&self, db_type: &DatabaseType) -> Option<String> {
+        let timeout = self.timeout?;
+        match db_type {
+            DatabaseType::PostgreSQL => Some(format!("SET LOCAL statement_timeout = {}", timeout.as_millis().max(1))),
+            DatabaseType::MySQL => {
+                Some(format!("SET SESSION innodb_lock_wait_timeout = {}", timeout.as_millis().div_ceil(1000).max(1)))
+            }
+            DatabaseType::SQLite => None,
+        }
+    }
+
+    /// The statement that undoes `begin_sql`'s changes to the session once
+    /// the transaction has ended, if there are any.
+    pub(crate) fn end_sql(&self, db_type: &DatabaseType) -> Option<&'static str> {
+        match db_type {
+            DatabaseType::MySQL if self.timeout.is_some() => Some("SET SESSION innodb_lock_wait_timeout = DEFAULT"),
+            DatabaseType::SQLite if self.read_only => Some("PRAGMA query_only = OFF"),
+            _ => None,
+        }
+    }
+
+    /// When a transaction starting now runs out of time, if it has a timeout.
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.timeout.map(|timeout| Instant::now() + timeout)
+    }
+}
+
+/// Fails with `RusticxError::Timeout` once `deadline` has passed, so the
+/// transaction is rolled back rather than committed.
+fn check_deadline(deadline: Option<Instant>) -> Result<(), RusticxError> {
+    match deadline {
+        Some(deadline) if Instant::now() > deadline => Err(RusticxError::Timeout(
+            "The transaction ran longer than its timeout and was rolled back".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// A transaction opened with `Connection::begin`.
+///
+/// Derefs to the `Connection` the transaction runs on, so it can be passed
+/// to model methods and queries like any other connection, and can be held
+/// across `.await`s. The transaction is rolled back if the handle is dropped
+/// without `commit`.
+pub struct Transaction {
+    conn: Connection,
+    end_sql: Option<&'static str>,
+    deadline: Option<Instant>,
+    hooks: Mutex<TxHooks>,
+    finished: bool,
+    // Dropped after `conn`, so the session is idle when it is returned
+    _lease: Option<SessionLease>,
+}
+
+impl Transaction {
+    pub(crate) fn new(
+        conn: Connection,
+        end_sql: Option<&'static str>,
+        deadline: Option<Instant>,
+        lease: Option<SessionLease>,
+    ) -> Self {
+        Transaction { conn, end_sql, deadline, hooks: Mutex::new(TxHooks::default()), finished: false, _lease: lease }
+    }
+
+    /// Runs `hook` once the transaction has committed, and not at all if it
+    /// is rolled back.
+    ///
+    /// ```ignore
+    /// let tx = conn.begin()?;
+    /// order.insert(&tx)?;
+    /// tx.after_commit(move || cache.invalidate("orders"));
+    /// tx.commit()?;
+    /// ```
+    pub fn after_commit<F: FnOnce() + Send + 'static>(&self, hook: F) {
+        self.hooks.lock().unwrap_or_else(|e| e.into_inner()).after_commit.push(Box::new(hook));
+    }
+
+    /// Runs `hook` once the transaction has been rolled back, by `rollback`,
+    /// a failed commit or dropping the handle.
+    pub fn after_rollback<F: FnOnce() + Send + 'static>(&self, hook: F) {
+        self.hooks.lock().unwrap_or_else(|e| e.into_inner()).after_rollback.push(Box::new(hook));
+    }
+
+    /// Defers checking the named constraints until the transaction commits,
+    /// so rows that reference each other can be inserted one at a time:
+    ///
+    /// ```ignore
+    /// let tx = conn.begin()?;
+    /// tx.set_constraints_deferred(&["employees_manager_fk", "teams_lead_fk"])?;
+    /// lead.insert(&tx)?;
+    /// team.insert(&tx)?;
+    /// tx.commit()?;
+    /// ```
+    ///
+    /// Only constraints declared `DEFERRABLE` can be deferred.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError` if a constraint cannot be deferred, or a
+    /// `RusticxError::QueryError` outside PostgreSQL.
+    pub fn set_constraints_deferred(&self, names: &[&str]) -> Result<(), RusticxError> {
+        let sql = defer_constraints_sql(self.conn.get_db_type(), Some(names))?;
+        self.conn.execute_batch(&sql).map(|_| ())
+    }
+
+    /// Defers checking every deferrable constraint until the transaction
+    /// commits (on SQLite, every foreign key).
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError` if the statement fails, or a
+    /// `RusticxError::QueryError` on MySQL.
+    pub fn set_all_constraints_deferred(&self) -> Result<(), RusticxError> {
+        let sql = defer_constraints_sql(self.conn.get_db_type(), None)?;
+        self.conn.execute_batch(&sql).map(|_| ())
+    }
+
+    /// Commits the transaction.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::TransactionError` if the commit fails, in
+    /// which case the transaction's changes are not applied (this includes a
+    /// PostgreSQL transaction that an earlier statement error aborted), or a
+    /// `RusticxError::Timeout` if the transaction outlived its
+    /// `TxOptions::timeout`, in which case it is rolled back.
+    pub fn commit(mut self) -> Result<(), RusticxError> {
+        if let Err(e) = check_deadline(self.deadline) {
+            self.finish("ROLLBACK")?;
+            return Err(e);
+        }
+        self.finish("COMMIT")
+    }
+
+    /// Rolls back the transaction.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::TransactionError` if the rollback fails.
+    pub fn rollback(mut self) -> Result<(), RusticxError> {
+        self.finish("ROLLBACK")
+    }
+
+    fn finish(&mut self, sql: &str) -> Result<(), RusticxError> {
+        self.finished = true;
+        let committing = sql == "COMMIT";
+        let result = if committing { self.check_not_aborted() } else { Ok(()) }.and_then(|_| {
+            self.conn
+                .execute_batch(sql)
+                .map(|_| ())
+                .map_err(|e| transaction_error(&sql.to_lowercase(), e))
+        });
+        if committing && result.is_err() {
+            // A commit that failed can leave the transaction open on the session
+            if let Err(e) = self.conn.execute_batch("ROLLBACK") {
+                log::warn!("Failed to roll back after a failed commit: {}", e);
+            }
+        }
+        let hooks = std::mem::take(self.hooks.get_mut().unwrap_or_else(|e| e.into_inner()));
+        hooks.finish(committing && result.is_ok());
+        match self.end_sql {
+            Some(end_sql) => result.and(self.conn.execute_batch(end_sql).map(|_| ())),
+            None => result,
+        }
+    }
+
+    /// Fails if a statement error aborted the transaction.
+    ///
+    /// PostgreSQL answers `COMMIT` of an aborted transaction by rolling it
+    /// back, with a `ROLLBACK` command tag rather than an error, and
+    /// tokio-postgres does not expose command tags. Any statement fails in
+    /// an aborted transaction, so one is run before committing instead.
+    fn check_not_aborted(&self) -> Result<(), RusticxError> {
+        if !matches!(self.conn.get_db_type(), DatabaseType::PostgreSQL) {
+            return Ok(());
+        }
+        self.conn.execute("SELECT 1", &[]).map(|_| ()).map_err(|e| match e {
+            RusticxError::QueryError(message) => RusticxError::TransactionError(format!(
+                "Failed to commit transaction: it was aborted by an earlier error and has been rolled back ({})",
+                message
+            )),
+            other => other,
+        })
+    }
+}
+
+/// The statement deferring `constraints`, or all of them when `None`, to
+/// the end of the transaction.
+pub(crate) fn defer_constraints_sql(db_type: &DatabaseType, constraints: Option<&[&str]>) -> Result<String, RusticxError> {
+    match (db_type, constraints) {
+        (DatabaseType::PostgreSQL, None) => Ok("SET CONSTRAINTS ALL DEFERRED".to_string()),
+        (DatabaseType::PostgreSQL, Some([])) => {
+            Err(RusticxError::ValidationError("No constraints were named to defer".to_string()))
+        }
+        (DatabaseType::PostgreSQL, Some(names)) => {
+            let names: Vec<String> = names.iter().map(|name| db_type.quote_identifier(name)).collect();
+            Ok(format!("SET CONSTRAINTS {} DEFERRED", names.join(", ")))
+        }
+        (DatabaseType::SQLite, None) => Ok("PRAGMA defer_foreign_keys = ON".to_string()),
+        (DatabaseType::SQLite, Some(_)) => Err(RusticxError::QueryError(
+            "SQLite can only defer all foreign keys; use set_all_constraints_deferred".to_string(),
+        )),
+        (DatabaseType::MySQL, _) => {
+            Err(RusticxError::QueryError("Deferred constraints are not supported on MySQL".to_string()))
+        }
+    }
+}
+
+/// Reports a failure to `action` (commit, roll back) a transaction as a
+/// `TransactionError`, keeping errors of a more specific kind, such as a
+/// `SerializationFailure`, as they are.
+fn transaction_error(action: &str, err: RusticxError) -> RusticxError {
+    match err {
+        RusticxError::QueryError(message) => {
+            RusticxError::TransactionError(format!("Failed to {} transaction: {}", action, message))
+        }
+        other => other,
+    }
+}
+
+impl Deref for Transaction {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.finished {
+            if let Err(rollback_err) = self.finish("ROLLBACK") {
+                log::error!("Error during transaction rollback: {}", rollback_err);
+            }
+        }
+    }
+}
+
+/// The statements a `Connection` and a transaction both run, so a function
+/// bounded by it works inside and outside transactions alike:
+///
+/// ```ignore
+/// fn seed(db: &impl ConnectionLike) -> Result<(), RusticxError> {
+///     db.create_table::<Product>()?;
+///     db.execute("INSERT INTO products (name) VALUES ($1)", &[&"Widget"])?;
+///     Ok(())
+/// }
+///
+/// seed(&conn)?;
+/// conn.transaction(|tx| seed(&TxConnection::new(tx))).await?;
+/// ```
+///
+/// It is implemented for `Connection`, for the `Transaction` handle from
+/// `Connection::begin`, and for `TxConnection`, which wraps the executor a
+/// transaction closure receives.
+pub trait ConnectionLike {
+    /// The type of database the statements run on.
+    fn get_db_type(&self) -> &DatabaseType;
+
+    /// Executes an SQL statement with parameters, as `Connection::execute`.
+    fn execute(&self, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<u64, RusticxError>;
+
+    /// Runs a query and deserializes its rows, as `Connection::query_raw`.
+    fn query_raw<T>(&self, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<Vec<T>, RusticxError>
+    where
+        T: for<'de> serde::Deserialize<'de> + Debug;
+
+    /// Creates the table of the model `T`, as `Connection::create_table`.
+    fn create_table<T: SQLModel>(&self) -> Result<(), RusticxError> {
+        self.execute(&T::create_table_sql(self.get_db_type()), &[])?;
+        for index in T::create_index_sql(self.get_db_type()) {
+            self.execute(&index, &[])?;
+        }
+        Ok(())
+    }
+}
+
+impl ConnectionLike for Connection {
+    fn get_db_type(&self) -> &DatabaseType {
+        Connection::get_db_type(self)
+    }
+
+    fn execute(&self, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<u64, RusticxError> {
+        Connection::execute(self, sql, params)
+    }
+
+    fn query_raw<T>(&self, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<Vec<T>, RusticxError>
+    where
+        T: for<'de> serde::Deserialize<'de> + Debug,
+    {
+        Connection::query_raw(self, sql, params)
+    }
+
+    fn create_table<T: SQLModel>(&self) -> Result<(), RusticxError> {
+        Connection::create_table::<T>(self)
+    }
+}
+
+impl ConnectionLike for Transaction {
+    fn get_db_type(&self) -> &DatabaseType {
+        self.conn.get_db_type()
+    }
+
+    fn execute(&self, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<u64, RusticxError> {
+        self.conn.execute(sql, params)
+    }
+
+    fn query_raw<T>(&self, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<Vec<T>, RusticxError>
+    where
+        T: for<'de> serde::Deserialize<'de> + Debug,
+    {
+        self.conn.query_raw(sql, params)
+    }
+
+    fn create_table<T: SQLModel>(&self) -> Result<(), RusticxError> {
+        self.conn.create_table::<T>()
+    }
+}
+
+/// The executor of a transaction closure behind the `ConnectionLike` API,
+/// so functions written against a connection can run inside the
+/// transaction.
+///
+/// Model methods take a `Connection`; to call them inside a transaction use
+/// the handle from `Connection::begin` instead.
+pub struct TxConnection<'a> {
+    tx: RefCell<&'a mut dyn TransactionExecutor>,
+    db_type: DatabaseType,
+}
+
+impl<'a> TxConnection<'a> {
+    /// Wraps the executor a transaction closure receives.
+    pub fn new(tx: &'a mut dyn TransactionExecutor) -> Self {
+        let db_type = tx.get_db_type().clone();
+        TxConnection { tx: RefCell::new(tx), db_type }
+    }
+}
+
+impl ConnectionLike for TxConnection<'_> {
+    fn get_db_type(&self) -> &DatabaseType {
+        &self.db_type
+    }
+
+    fn execute(&self, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<u64, RusticxError> {
+        self.tx.borrow_mut().execute(sql, params)
+    }
+
+    fn query_raw<T>(&self, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<Vec<T>, RusticxError>
     where
-        T: for<'de> serde::Deserialize<'de>;
+        T: for<'de> serde::Deserialize<'de> + Debug,
+    {
+        QueryExecutorExt::query_raw(&mut **self.tx.borrow_mut(), sql, params)
+    }
+}
+
+/// Where a `DistributedTx` is in the two-phase commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Active,
+    Prepared,
+    Finished,
+}
+
+/// A transaction taking part in a two-phase commit, opened with
+/// `Connection::begin_distributed`.
+///
+/// Like `Transaction` it derefs to the `Connection` it runs on. Once every
+/// participant's work is done, `prepare` makes the transaction durable
+/// without committing it (`PREPARE TRANSACTION` on PostgreSQL, `XA PREPARE`
+/// on MySQL); it can then only end with `commit` or `rollback`, on this
+/// handle or, after a crash, through `Connection::commit_prepared` and
+/// `Connection::rollback_prepared`.
+///
+/// ```ignore
+/// let orders = orders_db.begin_distributed("transfer-42")?;
+/// let ledger = ledger_db.begin_distributed("transfer-42")?;
+/// order.insert(&orders)?;
+/// entry.insert(&ledger)?;
+/// orders.prepare()?;
+/// ledger.prepare()?;
+/// orders.commit()?;
+/// ledger.commit()?;
+/// ```
+///
+/// Dropping the handle rolls back a transaction that is not yet prepared,
+/// and leaves a prepared one for the coordinator to resolve.
+pub struct DistributedTx {
+    conn: Connection,
+    gid: String,
+    phase: Phase,
+}
+
+impl DistributedTx {
+    pub(crate) fn new(conn: Connection, gid: String) -> Self {
+        DistributedTx { conn, gid, phase: Phase::Active }
+    }
+
+    /// The global transaction identifier the transaction was begun with.
+    pub fn gid(&self) -> &str {
+        &self.gid
+    }
+
+    /// Whether `prepare` has succeeded.
+    pub fn is_prepared(&self) -> bool {
+        self.phase == Phase::Prepared
+    }
+
+    /// Ends the first phase: the transaction's changes are stored durably
+    /// under its identifier, holding their locks, until it is committed or
+    /// rolled back.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::TransactionError` if the transaction is
+    /// already prepared or the server refuses to prepare it, in which case
+    /// it has been rolled back. PostgreSQL refuses unless
+    /// `max_prepared_transactions` is above zero.
+    pub fn prepare(&mut self) -> Result<(), RusticxError> {
+        if self.phase != Phase::Active {
+            return Err(RusticxError::TransactionError(format!(
+                "Transaction '{}' is already prepared",
+                self.gid
+            )));
+        }
+        let sql = match self.conn.get_db_type() {
+            DatabaseType::MySQL => format!("XA END '{0}'; XA PREPARE '{0}'", self.gid),
+            _ => format!("PREPARE TRANSACTION '{}'", self.gid),
+        };
+        match self.conn.execute_batch(&sql) {
+            Ok(_) => {
+                self.phase = Phase::Prepared;
+                Ok(())
+            }
+            Err(e) => {
+                // A failed PREPARE TRANSACTION has already rolled back; XA needs telling
+                if let DatabaseType::MySQL = self.conn.get_db_type() {
+                    self.finish_active();
+                }
+                self.phase = Phase::Finished;
+                Err(transaction_error("prepare", e))
+            }
+        }
+    }
+
+    /// Ends the second phase by committing the prepared transaction.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::TransactionError` if the transaction has not
+    /// been prepared, which rolls it back, or if the commit fails, which
+    /// leaves it prepared.
+    pub fn commit(mut self) -> Result<(), RusticxError> {
+        if self.phase != Phase::Prepared {
+            return Err(RusticxError::TransactionError(format!(
+                "Transaction '{}' must be prepared before it is committed",
+                self.gid
+            )));
+        }
+        self.phase = Phase::Finished;
+        prepared_transaction(&self.conn, "COMMIT", &self.gid)
+    }
+
+    /// Rolls back the transaction, prepared or not.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RusticxError::TransactionError` if the rollback fails.
+    pub fn rollback(mut self) -> Result<(), RusticxError> {
+        match std::mem::replace(&mut self.phase, Phase::Finished) {
+            Phase::Prepared => prepared_transaction(&self.conn, "ROLLBACK", &self.gid),
+            _ => self
+                .conn
+                .execute_batch(&self.rollback_sql())
+                .map(|_| ())
+                .map_err(|e| transaction_error("roll back", e)),
+        }
+    }
+
+    /// The statements that roll back the transaction before it is prepared.
+    fn rollback_sql(&self) -> String {
+        match self.conn.get_db_type() {
+            DatabaseType::MySQL => format!("XA END '{0}'; XA ROLLBACK '{0}'", self.gid),
+            _ => "ROLLBACK".to_string(),
+        }
+    }
+
+    /// Rolls back an active transaction, logging a failure.
+    fn finish_active(&mut self) {
+        if let Err(rollback_err) = self.conn.execute_batch(&self.rollback_sql()) {
+            log::error!("Error during transaction rollback: {}", rollback_err);
+        }
+    }
+}
+
+impl Deref for DistributedTx {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+impl Drop for DistributedTx {
+    fn drop(&mut self) {
+        match self.phase {
+            Phase::Active => self.finish_active(),
+            Phase::Prepared => {
+                log::warn!("Prepared transaction '{}' dropped without being committed or rolled back", self.gid)
+            }
+            Phase::Finished => {}
+        }
+    }
+}
+
+/// Checks that `gid` can name a prepared transaction on every backend: 1 to
+/// 64 printable ASCII characters, without quotes or backslashes.
+pub(crate) fn validate_gid(gid: &str) -> Result<(), RusticxError> {
+    let valid = !gid.is_empty()
+        && gid.len() <= 64
+        && gid.bytes().all(|b| b.is_ascii_graphic() || b == b' ')
+        && !gid.contains(['\'', '"', '\\']);
+    if valid {
+        Ok(())
+    } else {
+        Err(RusticxError::ValidationError(format!(
+            "Invalid transaction identifier '{}': use 1 to 64 printable ASCII characters without quotes or backslashes",
+            gid
+        )))
+    }
+}
+
+/// Runs `action` (`COMMIT` or `ROLLBACK`) on the prepared transaction `gid`.
+pub(crate) fn prepared_transaction(conn: &Connection, action: &str, gid: &str) -> Result<(), RusticxError> {
+    let sql = match conn.get_db_type() {
+        DatabaseType::PostgreSQL => format!("{} PREPARED '{}'", action, gid),
+        DatabaseType::MySQL => format!("XA {} '{}'", action, gid),
+        DatabaseType::SQLite => {
+            return Err(RusticxError::TransactionError(
+                "Prepared transactions are not supported on SQLite".to_string(),
+            ))
+        }
+    };
+    let verb = if action == "COMMIT" { "commit" } else { "roll back" };
+    conn.execute_batch(&sql).map(|_| ()).map_err(|e| transaction_error(verb, e))
 }
 
 // PostgreSQL transaction executor implementation
 #[cfg(feature = "postgres")]
 pub struct PostgresTransactionExecutor<'a> {
     pub(crate) tx: &'a tokio_postgres::Transaction<'a>,
+    /// The runtime the connection's session runs on.
+    pub(crate) rt: tokio::runtime::Handle,
+    pub(crate) hooks: TxHooks,
 }
 
 #[cfg(feature = "postgres")]
 impl<'a> TransactionExecutor for PostgresTransactionExecutor<'a> {
-    fn execute(&mut self, sql: &str, _params: &[&dyn Debug]) -> Result<u64, RusticxError> {
-        // Create a runtime for executing the query
-        let rt = tokio::runtime::Runtime::new().map_err(|e| {
-            RusticxError::QueryError(format!("Failed to create runtime: {}", e))
-        })?;
-
-        // Execute the SQL statement (placeholder for parameters)
-        let result = rt
-            .block_on(async { self.tx.execute(sql, &[]).await }) // Using &[] as placeholder
-            .map_err(|e| RusticxError::QueryError(e.to_string()))?;
+    fn execute(&mut self, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<u64, RusticxError> {
+        let params: Vec<&(dyn ToSql + Sync)> = params.iter().map(|p| *p as _).collect();
+        let result = self
+            .rt
+            .block_on(async { self.tx.execute(sql, &params).await })
+            .map_err(RusticxError::from)?;
 
         Ok(result)
     }
+
+    fn execute_many(
+        &mut self,
+        sql: &str,
+        param_sets: &[&[&(dyn ToSql + Sync + 'static)]],
+    ) -> Result<u64, RusticxError> {
+        let tx = self.tx;
+        self.rt.block_on(async {
+            let statement = tx.prepare(sql).await?;
+            let mut affected = 0;
+            for params in param_sets {
+                let params: Vec<&(dyn ToSql + Sync)> = params.iter().map(|p| *p as _).collect();
+                affected += tx.execute(&statement, &params).await?;
+            }
+            Ok(affected)
+        })
+    }
+
+    fn add_after_commit(&mut self, hook: Box<dyn FnOnce() + Send>) {
+        self.hooks.after_commit.push(hook);
+    }
+
+    fn add_after_rollback(&mut self, hook: Box<dyn FnOnce() + Send>) {
+        self.hooks.after_rollback.push(hook);
+    }
+
+    fn set_constraints_deferred(&mut self, names: &[&str]) -> Result<(), RusticxError> {
+        self.execute(&defer_constraints_sql(&DatabaseType::PostgreSQL, Some(names))?, &[]).map(|_| ())
+    }
+
+    fn set_all_constraints_deferred(&mut self) -> Result<(), RusticxError> {
+        self.execute(&defer_constraints_sql(&DatabaseType::PostgreSQL, None)?, &[]).map(|_| ())
+    }
+
+    fn get_db_type(&self) -> &DatabaseType {
+        &DatabaseType::PostgreSQL
+    }
 }
 
 #[cfg(feature = "postgres")]
 impl<'a> QueryExecutor for PostgresTransactionExecutor<'a> {
-    fn query_raw<T>(&mut self, sql: &str, _params: &[&dyn Debug]) -> Result<Vec<T>, RusticxError>
-    where
-        T: for<'de> serde::Deserialize<'de>,
-    {
-        // Create a runtime for executing the query
-        let rt = tokio::runtime::Runtime::new().map_err(|e| {
-            RusticxError::QueryError(format!("Failed to create runtime: {}", e))
-        })?;
-
-        // Execute the query (placeholder for parameters)
-        let rows = rt
-            .block_on(async { self.tx.query(sql, &[]).await }) // Using &[] as placeholder
-            .map_err(|e| RusticxError::QueryError(e.to_string()))?;
+    fn query_json(
+        &mut self,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync + 'static)],
+    ) -> Result<Vec<serde_json::Value>, RusticxError> {
+        let params: Vec<&(dyn ToSql + Sync)> = params.iter().map(|p| *p as _).collect();
+        let rows = self
+            .rt
+            .block_on(async { self.tx.query(sql, &params).await })
+            .map_err(RusticxError::from)?;
 
-        let mut models = Vec::with_capacity(rows.len());
-        for row in rows {
-            let mut json_obj = serde_json::Map::new();
-
-            for column in row.columns() {
-                let name = column.name();
-                // Use the helper function to extract and convert the value
-                let value = pg_row_value_to_json(&row, column).unwrap_or(serde_json::Value::Null);
-                json_obj.insert(name.to_string(), value);
-            }
+        pg_rows_to_models(rows)
+    }
+}
 
-            let model = serde_json::from_value(serde_json::Value::Object(json_obj))
-                .map_err(|e| RusticxError::SerializationError(e.to_string()))?;
+/// Deserializes Postgres rows into `T` by way of a JSON object per row.
+///
+/// Shared by every Postgres read path (`query_raw`, `execute_returning` and the
+/// transaction executor) so row handling lives in one place.
+#[cfg(feature = "postgres")]
+pub(crate) fn pg_rows_to_models<T>(rows: Vec<tokio_postgres::Row>) -> Result<Vec<T>, RusticxError>
+where
+    T: for<'de> serde::Deserialize<'de>,
+{
+    rows.iter().map(pg_row_to_model).collect()
+}
 
-            models.push(model);
-        }
+/// Deserializes a single Postgres row into `T` via an intermediate JSON object.
+#[cfg(feature = "postgres")]
+pub(crate) fn pg_row_to_model<T>(row: &tokio_postgres::Row) -> Result<T, RusticxError>
+where
+    T: for<'de> serde::Deserialize<'de>,
+{
+    let mut json_obj = serde_json::Map::new();
 
-        Ok(models)
+    for column in row.columns() {
+        let name = column.name();
+        // Use the helper function to extract and convert the value
+        let value = pg_row_value_to_json(row, column).unwrap_or(serde_json::Value::Null);
+        json_obj.insert(name.to_string(), value);
     }
+
+    serde_json::from_value(serde_json::Value::Object(json_obj))
+        .map_err(|e| RusticxError::SerializationError(e.to_string()))
+}
+
+/// Converts a Postgres row into its column values, in select-list order.
+#[cfg(feature = "postgres")]
+pub(crate) fn pg_row_to_values(row: &tokio_postgres::Row) -> Vec<serde_json::Value> {
+    row.columns()
+        .iter()
+        .map(|column| pg_row_value_to_json(row, column).unwrap_or(serde_json::Value::Null))
+        .collect()
 }
 
 // Helper function to extract value from Postgres row and convert to serde_json::Value
@@ -114,6 +969,17 @@ pub fn pg_row_value_to_json(
                 Ok(serde_json::Value::Null)
             }
         }
+        // int2
+        21 => row.try_get::<_, i16>(name).map(|val| serde_json::Value::Number(val.into())),
+        // float4/float8; NaN and infinities have no JSON number and read as null
+        700 | 701 => {
+            let val = if type_oid == 700 {
+                row.try_get::<_, f32>(name).map(f64::from)?
+            } else {
+                row.try_get::<_, f64>(name)?
+            };
+            Ok(serde_json::Number::from_f64(val).map_or(serde_json::Value::Null, serde_json::Value::Number))
+        }
         // text/varchar
         25 | 1043 => row.try_get::<_, String>(name).map(serde_json::Value::String),
         // bool
@@ -132,6 +998,10 @@ pub fn pg_row_value_to_json(
         }
         // jsonb/json
         114 | 3802 => row.try_get::<_, serde_json::Value>(name),
+        // bytea, carried as base64 like SQLite blobs
+        17 => row
+            .try_get::<_, Vec<u8>>(name)
+            .map(|bytes| serde_json::Value::String(base64::engine::general_purpose::STANDARD.encode(bytes))),
         // Other types - attempt to convert to string
         _ => {
             if let Ok(s) = row.try_get::<_, String>(name) {
@@ -147,30 +1017,65 @@ pub fn pg_row_value_to_json(
 #[cfg(feature = "mysql")]
 pub struct MySQLTransactionExecutor<'a> {
     pub(crate) conn: &'a mut mysql::PooledConn,
+    pub(crate) hooks: TxHooks,
 }
 
 #[cfg(feature = "mysql")]
 impl<'a> TransactionExecutor for MySQLTransactionExecutor<'a> {
-    fn execute(&mut self, sql: &str, params: &[&dyn Debug]) -> Result<u64, RusticxError> {
-        // Execute the SQL statement (placeholder for parameters)
-        self.conn
-            .exec_drop(sql, ()) // Using () as placeholder parameters
-            .map_err(|e| RusticxError::QueryError(e.to_string()))?;
+    fn execute(&mut self, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<u64, RusticxError> {
+        let result = self
+            .conn
+            .exec_iter(sql, mysql_params(params)?)
+            .map_err(RusticxError::from)?;
 
-        // MySQL exec_drop doesn't reliably return affected rows for all statements.
-        // Returning 1 as a placeholder; a more robust approach might be needed.
-        Ok(1)
+        Ok(result.affected_rows())
+    }
+
+    fn execute_many(
+        &mut self,
+        sql: &str,
+        param_sets: &[&[&(dyn ToSql + Sync + 'static)]],
+    ) -> Result<u64, RusticxError> {
+        let statement = self.conn.prep(sql).map_err(RusticxError::from)?;
+        let mut affected = 0;
+        for params in param_sets {
+            self.conn
+                .exec_drop(&statement, mysql_params(params)?)
+                .map_err(RusticxError::from)?;
+            affected += self.conn.affected_rows();
+        }
+        Ok(affected)
+    }
+
+    fn add_after_commit(&mut self, hook: Box<dyn FnOnce() + Send>) {
+        self.hooks.after_commit.push(hook);
+    }
+
+    fn add_after_rollback(&mut self, hook: Box<dyn FnOnce() + Send>) {
+        self.hooks.after_rollback.push(hook);
+    }
+
+    fn set_constraints_deferred(&mut self, names: &[&str]) -> Result<(), RusticxError> {
+        self.execute(&defer_constraints_sql(&DatabaseType::MySQL, Some(names))?, &[]).map(|_| ())
+    }
+
+    fn set_all_constraints_deferred(&mut self) -> Result<(), RusticxError> {
+        self.execute(&defer_constraints_sql(&DatabaseType::MySQL, None)?, &[]).map(|_| ())
+    }
+
+    fn get_db_type(&self) -> &DatabaseType {
+        &DatabaseType::MySQL
     }
 }
 
 #[cfg(feature = "mysql")]
 impl<'a> QueryExecutor for MySQLTransactionExecutor<'a> {
-    fn query_raw<T>(&mut self, sql: &str, _params: &[&dyn Debug]) -> Result<Vec<T>, RusticxError>
-    where
-        T: for<'de> serde::Deserialize<'de>,
-    {
-        // Execute the query (placeholder for parameters)
-        let rows: Vec<Result<T, mysql::Error>> = self.conn.query_map(sql, |row: mysql::Row| {
+    fn query_json(
+        &mut self,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync + 'static)],
+    ) -> Result<Vec<serde_json::Value>, RusticxError> {
+        let rows = self.conn.exec_map(sql, mysql_params(params)?, |row: mysql::Row| {
             let mut json_obj = serde_json::Map::new();
             let columns = row.columns_ref();
 
@@ -181,16 +1086,10 @@ impl<'a> QueryExecutor for MySQLTransactionExecutor<'a> {
                 json_obj.insert(name, value);
             }
 
-            serde_json::from_value(serde_json::Value::Object(json_obj))
-                .map_err(|e| mysql::Error::from(std::io::Error::new(std::io::ErrorKind::Other, e.to_string())))
-        }).map_err(|e| RusticxError::QueryError(e.to_string()))?;
-
-        let result: Vec<T> = rows
-            .into_iter()
-            .collect::<Result<_, _>>()
-            .map_err(|e| RusticxError::QueryError(e.to_string()))?;
+            serde_json::Value::Object(json_obj)
+        }).map_err(RusticxError::from)?;
 
-        Ok(result)
+        Ok(rows)
     }
 }
 
@@ -201,6 +1100,18 @@ pub fn mysql_row_value_to_json(
     index: usize,
     column_type: mysql::consts::ColumnType,
 ) -> Result<serde_json::Value, mysql::Error> {
+    // Parameterized queries use the binary protocol, which sends dates as values rather than text
+    if let Some(mysql::Value::Date(year, month, day, hour, minute, second, micros)) = row.as_ref(index) {
+        let text = if matches!(column_type, mysql::consts::ColumnType::MYSQL_TYPE_DATE) {
+            format!("{:04}-{:02}-{:02}", year, month, day)
+        } else if *micros == 0 {
+            format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, hour, minute, second)
+        } else {
+            format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06}", year, month, day, hour, minute, second, micros)
+        };
+        return Ok(serde_json::Value::String(text));
+    }
+
     match column_type {
         mysql::consts::ColumnType::MYSQL_TYPE_TINY
         | mysql::consts::ColumnType::MYSQL_TYPE_SHORT
@@ -210,10 +1121,7 @@ pub fn mysql_row_value_to_json(
                 .transpose()? // Transpose Option<Result<T, E>> to Result<Option<T>, E>
                 .map(|v| serde_json::Value::Number(v.into()))
                 .ok_or_else(|| {
-                    mysql::Error::from(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Failed to get INT or INT24 value at index {}", index),
-                    ))
+                    mysql::Error::from(std::io::Error::other(format!("Failed to get INT or INT24 value at index {}", index)))
                 })
         }
         mysql::consts::ColumnType::MYSQL_TYPE_LONGLONG => {
@@ -221,10 +1129,7 @@ pub fn mysql_row_value_to_json(
                 .transpose()?
                 .map(|v| serde_json::Value::Number(serde_json::Number::from(v)))
                 .ok_or_else(|| {
-                    mysql::Error::from(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Failed to get LONGLONG value at index {}", index),
-                    ))
+                    mysql::Error::from(std::io::Error::other(format!("Failed to get LONGLONG value at index {}", index)))
                 })
         }
         mysql::consts::ColumnType::MYSQL_TYPE_FLOAT | mysql::consts::ColumnType::MYSQL_TYPE_DOUBLE => {
@@ -236,10 +1141,7 @@ pub fn mysql_row_value_to_json(
                         .unwrap_or(serde_json::Value::Null) // Handle potential f64 to Number conversion failure
                 })
                 .ok_or_else(|| {
-                    mysql::Error::from(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Failed to get FLOAT or DOUBLE value at index {}", index),
-                    ))
+                    mysql::Error::from(std::io::Error::other(format!("Failed to get FLOAT or DOUBLE value at index {}", index)))
                 })
         }
         mysql::consts::ColumnType::MYSQL_TYPE_STRING
@@ -256,10 +1158,7 @@ pub fn mysql_row_value_to_json(
                 .transpose()?
                 .map(serde_json::Value::String)
                 .ok_or_else(|| {
-                    mysql::Error::from(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Failed to get STRING or related value at index {}", index),
-                    ))
+                    mysql::Error::from(std::io::Error::other(format!("Failed to get STRING or related value at index {}", index)))
                 })
         }
         _ => {
@@ -268,10 +1167,7 @@ pub fn mysql_row_value_to_json(
                 .transpose()?
                 .map(serde_json::Value::String)
                 .ok_or_else(|| {
-                    mysql::Error::from(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Failed to get value as String for unknown type at index {}", index),
-                    ))
+                    mysql::Error::from(std::io::Error::other(format!("Failed to get value as String for unknown type at index {}", index)))
                 })
         }
     }
@@ -281,27 +1177,68 @@ pub fn mysql_row_value_to_json(
 #[cfg(feature = "rusqlite")]
 pub struct SQLiteTransactionExecutor<'a> {
     pub(crate) tx: &'a rusqlite::Transaction<'a>,
+    pub(crate) hooks: TxHooks,
 }
 
 #[cfg(feature = "rusqlite")]
 impl<'a> TransactionExecutor for SQLiteTransactionExecutor<'a> {
-    fn execute(&mut self, sql: &str, params: &[&dyn Debug]) -> Result<u64, RusticxError> {
-        // Execute the SQL statement (placeholder for parameters)
+    fn execute(&mut self, sql: &str, params: &[&(dyn ToSql + Sync + 'static)]) -> Result<u64, RusticxError> {
+        let values = sqlite_params(params)?;
         let result = self
             .tx
-            .execute(sql, []) // Using [] as placeholder parameters
+            .execute(sql, rusqlite::params_from_iter(values))
             .map_err(|e| RusticxError::QueryError(e.to_string()))?;
 
         Ok(result as u64)
     }
+
+    fn execute_many(
+        &mut self,
+        sql: &str,
+        param_sets: &[&[&(dyn ToSql + Sync + 'static)]],
+    ) -> Result<u64, RusticxError> {
+        let mut statement = self
+            .tx
+            .prepare(sql)
+            .map_err(|e| RusticxError::QueryError(e.to_string()))?;
+        let mut affected = 0;
+        for params in param_sets {
+            affected += statement
+                .execute(rusqlite::params_from_iter(sqlite_params(params)?))
+                .map_err(|e| RusticxError::QueryError(e.to_string()))? as u64;
+        }
+        Ok(affected)
+    }
+
+    fn add_after_commit(&mut self, hook: Box<dyn FnOnce() + Send>) {
+        self.hooks.after_commit.push(hook);
+    }
+
+    fn add_after_rollback(&mut self, hook: Box<dyn FnOnce() + Send>) {
+        self.hooks.after_rollback.push(hook);
+    }
+
+    fn set_constraints_deferred(&mut self, names: &[&str]) -> Result<(), RusticxError> {
+        self.execute(&defer_constraints_sql(&DatabaseType::SQLite, Some(names))?, &[]).map(|_| ())
+    }
+
+    fn set_all_constraints_deferred(&mut self) -> Result<(), RusticxError> {
+        self.execute(&defer_constraints_sql(&DatabaseType::SQLite, None)?, &[]).map(|_| ())
+    }
+
+    fn get_db_type(&self) -> &DatabaseType {
+        &DatabaseType::SQLite
+    }
 }
 
 #[cfg(feature = "rusqlite")]
 impl<'a> QueryExecutor for SQLiteTransactionExecutor<'a> {
-    fn query_raw<T>(&mut self, sql: &str, _params: &[&dyn Debug]) -> Result<Vec<T>, RusticxError>
-    where
-        T: for<'de> serde::Deserialize<'de>,
-    {
+    fn query_json(
+        &mut self,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync + 'static)],
+    ) -> Result<Vec<serde_json::Value>, RusticxError> {
+        let values = sqlite_params(params)?;
         let mut stmt = self
             .tx
             .prepare(sql)
@@ -313,8 +1250,8 @@ impl<'a> QueryExecutor for SQLiteTransactionExecutor<'a> {
             .map(|name| name.to_string())
             .collect();
 
-        let models = stmt
-            .query_map([], |row| {
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(values), |row| {
                 let mut json_obj = serde_json::Map::new();
 
                 for (i, name) in column_names.iter().enumerate() {
@@ -324,21 +1261,13 @@ impl<'a> QueryExecutor for SQLiteTransactionExecutor<'a> {
                     json_obj.insert(name.clone(), value); // Clone name as it's a reference
                 }
 
-                let model = serde_json::from_value(serde_json::Value::Object(json_obj)).map_err(
-                    |e| rusqlite::Error::FromSqlConversionFailure(
-                        0,
-                        rusqlite::types::Type::Text,
-                        Box::new(e),
-                    ),
-                )?;
-
-                Ok(model)
+                Ok(serde_json::Value::Object(json_obj))
             })
             .map_err(|e| RusticxError::QueryError(e.to_string()))?
             .collect::<Result<Vec<_>, _>>()
             .map_err(|e| RusticxError::QueryError(e.to_string()))?;
 
-        Ok(models)
+        Ok(rows)
     }
 }
 
@@ -361,7 +1290,7 @@ pub fn sqlite_row_value_to_json(
                     rusqlite::Error::FromSqlConversionFailure(
                         index,
                         rusqlite::types::Type::Real,
-                        Box::new(std::io::Error::new(std::io::ErrorKind::Other, "Failed to convert f64 to serde_json::Number")),
+                        Box::new(std::io::Error::other("Failed to convert f64 to serde_json::Number")),
                     )
                 })
         }
@@ -376,56 +1305,250 @@ pub fn sqlite_row_value_to_json(
     }
 }
 
+/// A query parameter in a form drivers other than PostgreSQL can bind.
+#[cfg(any(feature = "mysql", feature = "rusqlite", not(feature = "postgres")))]
+pub enum ParamValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+/// Decodes query parameters for binding on MySQL or SQLite.
+///
+/// Parameters are `postgres` `ToSql` values, so each one is encoded in the
+/// PostgreSQL binary format of the first type it accepts and decoded from
+/// there: integers and booleans become integers, floats reals, byte strings
+/// blobs, and strings, dates, times, UUIDs and JSON text in the form serde
+/// writes them.
+#[cfg(all(feature = "postgres", any(feature = "mysql", feature = "rusqlite")))]
+pub(crate) fn decode_params(
+    params: &[&(dyn crate::model::ToSql + Sync + 'static)],
+) -> Result<Vec<ParamValue>, RusticxError> {
+    use postgres::types::{IsNull, Type};
+
+    const TYPES: [Type; 13] = [
+        Type::BOOL,
+        Type::INT2,
+        Type::INT4,
+        Type::INT8,
+        Type::FLOAT4,
+        Type::FLOAT8,
+        Type::TEXT,
+        Type::BYTEA,
+        Type::TIMESTAMP,
+        Type::TIMESTAMPTZ,
+        Type::DATE,
+        Type::UUID,
+        Type::JSONB,
+    ];
+
+    let mut buf = bytes::BytesMut::new();
+    params
+        .iter()
+        .enumerate()
+        .map(|(index, param)| {
+            for ty in &TYPES {
+                buf.clear();
+                match param.to_sql_checked(ty, &mut buf) {
+                    Ok(IsNull::Yes) => return Ok(ParamValue::Null),
+                    Ok(IsNull::No) => return decode_param(ty, &buf),
+                    Err(_) => continue,
+                }
+            }
+            Err(RusticxError::QueryError(format!(
+                "Parameter {} has a type that can only be bound on PostgreSQL",
+                index + 1
+            )))
+        })
+        .collect()
+}
+
+/// Decodes a value in the PostgreSQL binary format of `ty`.
+#[cfg(all(feature = "postgres", any(feature = "mysql", feature = "rusqlite")))]
+fn decode_param(ty: &tokio_postgres::types::Type, raw: &[u8]) -> Result<ParamValue, RusticxError> {
+    use chrono::{DateTime, Duration, NaiveDate};
+    use postgres::types::Type;
+
+    let invalid = || RusticxError::QueryError(format!("Invalid {} parameter", ty));
+    let exact = |len: usize| raw.get(..len).filter(|_| raw.len() == len).ok_or_else(invalid);
+    // Timestamps and dates count from 2000-01-01
+    let epoch = NaiveDate::from_ymd_opt(2000, 1, 1).and_then(|d| d.and_hms_opt(0, 0, 0)).ok_or_else(invalid)?;
+
+    Ok(match *ty {
+        Type::BOOL => ParamValue::Integer(i64::from(exact(1)?[0] != 0)),
+        Type::INT2 => ParamValue::Integer(i16::from_be_bytes(exact(2)?.try_into().map_err(|_| invalid())?).into()),
+        Type::INT4 => ParamValue::Integer(i32::from_be_bytes(exact(4)?.try_into().map_err(|_| invalid())?).into()),
+        Type::INT8 => ParamValue::Integer(i64::from_be_bytes(exact(8)?.try_into().map_err(|_| invalid())?)),
+        Type::FLOAT4 => ParamValue::Real(f32::from_be_bytes(exact(4)?.try_into().map_err(|_| invalid())?).into()),
+        Type::FLOAT8 => ParamValue::Real(f64::from_be_bytes(exact(8)?.try_into().map_err(|_| invalid())?)),
+        Type::TEXT => ParamValue::Text(String::from_utf8(raw.to_vec()).map_err(|_| invalid())?),
+        Type::BYTEA => ParamValue::Blob(raw.to_vec()),
+        Type::TIMESTAMP | Type::TIMESTAMPTZ => {
+            let micros = i64::from_be_bytes(exact(8)?.try_into().map_err(|_| invalid())?);
+            let at = epoch + Duration::microseconds(micros);
+            if *ty == Type::TIMESTAMP {
+                ParamValue::Text(at.format("%Y-%m-%dT%H:%M:%S%.f").to_string())
+            } else {
+                ParamValue::Text(DateTime::<chrono::Utc>::from_naive_utc_and_offset(at, chrono::Utc).to_rfc3339())
+            }
+        }
+        Type::DATE => {
+            let days = i32::from_be_bytes(exact(4)?.try_into().map_err(|_| invalid())?);
+            ParamValue::Text((epoch.date() + Duration::days(days.into())).format("%Y-%m-%d").to_string())
+        }
+        Type::UUID => {
+            let hex: String = exact(16)?.iter().map(|b| format!("{:02x}", b)).collect();
+            ParamValue::Text(format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..]))
+        }
+        // JSONB is a version byte followed by the JSON text
+        Type::JSONB => ParamValue::Text(String::from_utf8(raw.get(1..).ok_or_else(invalid)?.to_vec()).map_err(|_| invalid())?),
+        _ => return Err(invalid()),
+    })
+}
+
+/// Without the `postgres` feature each parameter gives its value directly.
+#[cfg(all(not(feature = "postgres"), any(feature = "mysql", feature = "rusqlite")))]
+pub(crate) fn decode_params(
+    params: &[&(dyn crate::model::ToSql + Sync + 'static)],
+) -> Result<Vec<ParamValue>, RusticxError> {
+    Ok(params.iter().map(|param| param.to_param()).collect())
+}
+
+/// Converts query parameters to SQLite values.
+#[cfg(feature = "rusqlite")]
+pub(crate) fn sqlite_params(
+    params: &[&(dyn crate::model::ToSql + Sync + 'static)],
+) -> Result<Vec<rusqlite::types::Value>, RusticxError> {
+    use rusqlite::types::Value;
+
+    Ok(decode_params(params)?
+        .into_iter()
+        .map(|value| match value {
+            ParamValue::Null => Value::Null,
+            ParamValue::Integer(v) => Value::Integer(v),
+            ParamValue::Real(v) => Value::Real(v),
+            ParamValue::Text(v) => Value::Text(v),
+            ParamValue::Blob(v) => Value::Blob(v),
+        })
+        .collect())
+}
+
+/// Converts query parameters to positional MySQL parameters.
+#[cfg(feature = "mysql")]
+pub(crate) fn mysql_params(
+    params: &[&(dyn crate::model::ToSql + Sync + 'static)],
+) -> Result<mysql::Params, RusticxError> {
+    use mysql::Value;
+
+    let values: Vec<Value> = decode_params(params)?
+        .into_iter()
+        .map(|value| match value {
+            ParamValue::Null => Value::NULL,
+            ParamValue::Integer(v) => Value::Int(v),
+            ParamValue::Real(v) => Value::Double(v),
+            ParamValue::Text(v) => Value::Bytes(v.into_bytes()),
+            ParamValue::Blob(v) => Value::Bytes(v),
+        })
+        .collect();
+    Ok(if values.is_empty() { mysql::Params::Empty } else { mysql::Params::Positional(values) })
+}
+
 /// Helper function to run a transaction with PostgreSQL
+///
+/// The transaction runs on a blocking thread of the session's runtime `rt`,
+/// where the closure's statements can wait for their results without
+/// blocking, or panicking inside, the runtime the caller awaits on.
 #[cfg(feature = "postgres")]
 pub(crate) async fn run_postgres_transaction<F, R>(
-    client: &Arc<Mutex<tokio_postgres::Client>>, // Use &Client instead of &mut
+    client: &Arc<Mutex<tokio_postgres::Client>>,
+    rt: &tokio::runtime::Handle,
+    options: &TxOptions,
     transaction_fn: F,
 ) -> Result<R, RusticxError>
 where
-    F: FnOnce(&dyn TransactionExecutor) -> Result<R, RusticxError>,
+    F: FnOnce(&mut dyn TransactionExecutor) -> Result<R, RusticxError> + Send + 'static,
+    R: Send + 'static,
+{
+    let (client, handle, options) = (Arc::clone(client), rt.clone(), options.clone());
+    let task = rt.spawn_blocking(move || postgres_transaction(&client, &handle, &options, transaction_fn));
+    match task.await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(RusticxError::TransactionError(format!("Transaction was cancelled: {}", e))),
+    }
+}
+
+#[cfg(feature = "postgres")]
+fn postgres_transaction<F, R>(
+    client: &Mutex<tokio_postgres::Client>,
+    rt: &tokio::runtime::Handle,
+    options: &TxOptions,
+    transaction_fn: F,
+) -> Result<R, RusticxError>
+where
+    F: FnOnce(&mut dyn TransactionExecutor) -> Result<R, RusticxError>,
 {
     // Create a transaction
     let mut guard = client.lock().map_err(|e| {
         RusticxError::TransactionError(format!("Failed to acquire lock on connection: {}", e))
     })?;
-    let tx = guard
-        .transaction()
-        .await
+    let mut builder = guard.build_transaction();
+    if let Some(level) = options.isolation {
+        builder = builder.isolation_level(match level {
+            IsolationLevel::ReadUncommitted => tokio_postgres::IsolationLevel::ReadUncommitted,
+            IsolationLevel::ReadCommitted => tokio_postgres::IsolationLevel::ReadCommitted,
+            IsolationLevel::RepeatableRead => tokio_postgres::IsolationLevel::RepeatableRead,
+            IsolationLevel::Serializable => tokio_postgres::IsolationLevel::Serializable,
+        });
+    }
+    let deadline = options.deadline();
+    let tx = builder.read_only(options.read_only);
+    let tx = rt
+        .block_on(tx.start())
         .map_err(|e| RusticxError::TransactionError(format!("Failed to start transaction: {}", e)))?;
+    if let Some(timeout_sql) = options.timeout_sql(&DatabaseType::PostgreSQL) {
+        rt.block_on(tx.batch_execute(&timeout_sql))
+            .map_err(|e| RusticxError::TransactionError(format!("Failed to start transaction: {}", e)))?;
+    }
 
     // Create a transaction executor
-    let mut tx_executor = PostgresTransactionExecutor { tx: &tx };
+    let mut tx_executor = PostgresTransactionExecutor { tx: &tx, rt: rt.clone(), hooks: TxHooks::default() };
 
     // Execute the user's function within the transaction
-    let result = transaction_fn(&mut tx_executor); // Pass mutable reference
+    let result = transaction_fn(&mut tx_executor).and_then(|value| check_deadline(deadline).map(|_| value));
+    let hooks = tx_executor.hooks;
 
     // Commit or rollback based on the result
-    match result {
-        Ok(value) => {
-            tx.commit()
-                .await
-                .map_err(|e| RusticxError::TransactionError(format!("Failed to commit transaction: {}", e)))?;
-            Ok(value)
-        }
+    let result = match result {
+        Ok(value) => rt
+            .block_on(tx.commit())
+            .map(|_| value)
+            .map_err(|e| transaction_error("commit", RusticxError::from(e))),
         Err(e) => {
             // Explicit rollback for clarity, though it happens automatically when tx is dropped
-            if let Err(rollback_err) = tx.rollback().await {
+            if let Err(rollback_err) = rt.block_on(tx.rollback()) {
                 eprintln!("Error during transaction rollback: {}", rollback_err);
             }
             Err(e)
         }
-    }
+    };
+    // Release the connection first, so hooks can use it
+    drop(guard);
+    hooks.finish(result.is_ok());
+    result
 }
 
 /// Helper function to run a transaction with MySQL
 #[cfg(feature = "mysql")]
 pub(crate) fn run_mysql_transaction<F, R>(
     pool: &Arc<mysql::Pool>,
+    options: &TxOptions,
     transaction_fn: F,
 ) -> Result<R, RusticxError>
 where
-    F: FnOnce(&dyn TransactionExecutor) -> Result<R, RusticxError>,
+    F: FnOnce(&mut dyn TransactionExecutor) -> Result<R, RusticxError>,
 {
     // Get a connection from the pool
     let mut conn = pool
@@ -433,53 +1556,99 @@ where
         .map_err(|e| RusticxError::TransactionError(format!("Failed to get MySQL connection: {}", e)))?;
 
     // Start a transaction
-    conn.exec_drop("START TRANSACTION", ())
+    let deadline = options.deadline();
+    conn.query_drop(options.begin_sql(&DatabaseType::MySQL))
         .map_err(|e| RusticxError::TransactionError(format!("Failed to start transaction: {}", e)))?;
 
     // Create a transaction executor
-    let mut tx_executor = MySQLTransactionExecutor { conn: &mut conn };
+    let mut tx_executor = MySQLTransactionExecutor { conn: &mut conn, hooks: TxHooks::default() };
 
     // Execute the user's function
-    let result = transaction_fn(&mut tx_executor); // Pass mutable reference
+    let result = transaction_fn(&mut tx_executor).and_then(|value| check_deadline(deadline).map(|_| value));
+    let hooks = tx_executor.hooks;
 
     // Commit or rollback
-    match result {
-        Ok(value) => {
-            conn.exec_drop("COMMIT", ())
-                .map_err(|e| RusticxError::TransactionError(format!("Failed to commit transaction: {}", e)))?;
-            Ok(value)
-        }
+    let result = match result {
+        Ok(value) => conn
+            .exec_drop("COMMIT", ())
+            .map(|_| value)
+            .map_err(|e| transaction_error("commit", RusticxError::from(e))),
         Err(e) => {
             if let Err(rollback_err) = conn.exec_drop("ROLLBACK", ()) {
                 eprintln!("Error during transaction rollback: {}", rollback_err);
             }
             Err(e)
         }
-    }
+    };
+    // The pooled session outlives the transaction, so undo its settings
+    let ended = match options.end_sql(&DatabaseType::MySQL) {
+        Some(end_sql) => conn
+            .query_drop(end_sql)
+            .map_err(|e| RusticxError::TransactionError(format!("Failed to end transaction: {}", e))),
+        None => Ok(()),
+    };
+    drop(conn);
+    hooks.finish(result.is_ok());
+    ended?;
+    result
 }
 
 /// Helper function to run a transaction with SQLite
 #[cfg(feature = "rusqlite")]
 pub(crate) fn run_sqlite_transaction<F, R>(
     conn: &Arc<Mutex<rusqlite::Connection>>,
+    options: &TxOptions,
     transaction_fn: F,
 ) -> Result<R, RusticxError>
 where
-    F: FnOnce(&dyn TransactionExecutor) -> Result<R, RusticxError>,
+    F: FnOnce(&mut dyn TransactionExecutor) -> Result<R, RusticxError>,
 {
     let mut guard = conn.lock().map_err(|e| {
         RusticxError::TransactionError(format!("Failed to acquire lock on connection: {}", e))
     })?;
+    // Refuse writes for the duration of a read-only transaction
+    if options.read_only {
+        guard
+            .execute_batch("PRAGMA query_only = ON")
+            .map_err(|e| RusticxError::TransactionError(format!("Failed to start transaction: {}", e)))?;
+    }
+
+    let mut hooks = TxHooks::default();
+    let result = sqlite_transaction(&mut guard, options.deadline(), &mut hooks, transaction_fn);
+    let ended = match options.end_sql(&DatabaseType::SQLite) {
+        Some(end_sql) => guard
+            .execute_batch(end_sql)
+            .map_err(|e| RusticxError::TransactionError(format!("Failed to end transaction: {}", e))),
+        None => Ok(()),
+    };
+    // Release the connection first, so hooks can use it
+    drop(guard);
+    hooks.finish(result.is_ok());
+    ended?;
+    result
+}
+
+#[cfg(feature = "rusqlite")]
+fn sqlite_transaction<F, R>(
+    conn: &mut rusqlite::Connection,
+    deadline: Option<Instant>,
+    hooks: &mut TxHooks,
+    transaction_fn: F,
+) -> Result<R, RusticxError>
+where
+    F: FnOnce(&mut dyn TransactionExecutor) -> Result<R, RusticxError>,
+{
     // Start a transaction
-    let tx = guard
+    let tx = conn
         .transaction()
         .map_err(|e| RusticxError::TransactionError(format!("Failed to start transaction: {}", e)))?;
 
     // Create a transaction executor
-    let mut tx_executor = SQLiteTransactionExecutor { tx: &tx };
+    let mut tx_executor = SQLiteTransactionExecutor { tx: &tx, hooks: TxHooks::default() };
 
     // Execute the user's function
-    let result = transaction_fn(&mut tx_executor); // Pass mutable reference
+    let result = transaction_fn(&mut tx_executor).and_then(|value| check_deadline(deadline).map(|_| value));
+    *hooks = tx_executor.hooks;
 
     // Commit or rollback
     match result {
@@ -0,0 +1,87 @@
+use crate::connection::DatabaseType;
+use crate::error::RusticxError;
+use crate::model::SQLModel;
+
+/// A window function call, such as `row_number() OVER (PARTITION BY region
+/// ORDER BY amount DESC)`, selected with `QueryBuilder::select_window`.
+///
+/// ```ignore
+/// let leaderboard: Vec<RankedScore> = QueryBuilder::new()
+///     .select(&["player", "points"])
+///     .select_window("place", Window::rank().partition_by(&["league"]).order_by("points", false))
+///     .find_all_as::<Score, RankedScore>(&conn)?;
+/// ```
+///
+/// Partition and order columns are checked against the model's
+/// `field_names` and quoted when the query is rendered.
+#[derive(Debug, Clone)]
+pub struct Window {
+    function: String,
+    partition_by: Vec<String>,
+    order_by: Vec<(String, bool)>,
+}
+
+impl Window {
+    /// A window over the SQL function call `function`, inserted verbatim
+    /// (e.g. `SUM(amount)` or `lag(price, 1)`).
+    pub fn function(function: &str) -> Self {
+        Window { function: function.to_string(), partition_by: Vec::new(), order_by: Vec::new() }
+    }
+
+    /// `row_number()`: 1, 2, 3, ... within each partition.
+    pub fn row_number() -> Self {
+        Self::function("row_number()")
+    }
+
+    /// `rank()`: ties share a rank and leave gaps after them.
+    pub fn rank() -> Self {
+        Self::function("rank()")
+    }
+
+    /// `dense_rank()`: ties share a rank without gaps.
+    pub fn dense_rank() -> Self {
+        Self::function("dense_rank()")
+    }
+
+    /// Restarts the window for each distinct value of `columns`.
+    pub fn partition_by(mut self, columns: &[&str]) -> Self {
+        self.partition_by = columns.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    /// Orders the rows within each partition by `column`, ascending if
+    /// `asc`. Repeated calls add further sort keys.
+    pub fn order_by(mut self, column: &str, asc: bool) -> Self {
+        self.order_by.push((column.to_string(), asc));
+        self
+    }
+
+    /// Renders `function OVER (...)` for model `T`.
+    pub(crate) fn to_sql<T: SQLModel>(&self, db_type: &DatabaseType) -> Result<String, RusticxError> {
+        let check = |column: &str| -> Result<String, RusticxError> {
+            if !T::field_names().contains(&column) {
+                return Err(RusticxError::InvalidColumn(format!(
+                    "Cannot use {} in a window: not a column of {}",
+                    column,
+                    T::table_name()
+                )));
+            }
+            Ok(db_type.quote_identifier(column))
+        };
+
+        let mut clauses = Vec::new();
+        if !self.partition_by.is_empty() {
+            let columns = self.partition_by.iter().map(|c| check(c)).collect::<Result<Vec<_>, _>>()?;
+            clauses.push(format!("PARTITION BY {}", columns.join(", ")));
+        }
+        if !self.order_by.is_empty() {
+            let keys = self
+                .order_by
+                .iter()
+                .map(|(c, asc)| Ok(format!("{} {}", check(c)?, if *asc { "ASC" } else { "DESC" })))
+                .collect::<Result<Vec<_>, RusticxError>>()?;
+            clauses.push(format!("ORDER BY {}", keys.join(", ")));
+        }
+        Ok(format!("{} OVER ({})", self.function, clauses.join(" ")))
+    }
+}
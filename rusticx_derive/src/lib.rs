@@ -16,7 +16,7 @@ use syn::{
 /// attributes on the struct itself and on individual fields to configure the model
 /// mapping and behavior.
 ///
-/// ```rust
+/// ```rust,ignore
 /// use rusticx_derive::Model; // Assuming the macro is in a crate named rusticx_derive
 /// use uuid::Uuid; // Assuming you use the 'uuid' crate
 /// use chrono::NaiveDateTime; // Assuming you use the 'chrono' crate
@@ -57,32 +57,68 @@ use syn::{
 /// # Struct Attributes (`#[model(...)]` on the struct)
 ///
 /// * `#[model(table = "custom_name")]`: Specifies the database table name for this model.
-///     Defaults to the struct name (e.g., `User` -> `User`).
+///   Defaults to the struct name in lowercase (e.g., `BlogPost` -> `blogpost`).
+/// * `#[model(database = "reporting")]`: Routes the model to a named database registered
+///   on a `ConnectionManager`. Models without it use the default connection.
+/// * `#[model(default_scope = "published = TRUE")]`: A SQL condition added to every
+///   generated `SELECT` of the model. `SQLModel::unscoped` runs code without it.
 ///
 /// # Field Attributes (`#[model(...)]` on fields)
 ///
 /// * `#[model(primary_key)]`: Designates this field as the primary key for the table.
-///     Exactly one field should be marked as the primary key.
+///   Exactly one field should be marked as the primary key.
 /// * `#[model(column = "custom_name")]`: Specifies the database column name for this field.
-///     Defaults to the field name converted to lowercase.
+///   Defaults to the field name converted to lowercase.
 /// * `#[model(default = "SQL_DEFAULT_VALUE")]`: Sets a SQL default value for the column.
-///     The value is inserted directly into the SQL `CREATE TABLE` statement. Use
-///     appropriate quoting for string literals (e.g., `"'active'"`).
+///   The value is inserted directly into the SQL `CREATE TABLE` statement. Use
+///   appropriate quoting for string literals (e.g., `"'active'"`).
 /// * `#[model(nullable)]`: Explicitly marks the column as nullable (`NULL` in SQL).
-///     Fields with `Option<T>` type are automatically treated as nullable. This attribute
-///     is useful for non-Option types that should still allow `NULL`.
+///   Fields with `Option<T>` type are automatically treated as nullable. This attribute
+///   is useful for non-Option types that should still allow `NULL`.
 /// * `#[model(sql_type = "SQL_TYPE_STRING")]`: Specifies a custom SQL data type for the column.
-///     This overrides the default type mapping based on the Rust type.
+///   This overrides the default type mapping based on the Rust type.
 /// * `#[model(skip)]`: Excludes this field from the generated SQL model definition (CREATE TABLE,
-///     INSERT, UPDATE) and from deserialization (`from_row`).
+///   INSERT, UPDATE) and from deserialization (`from_row`).
 /// * `#[model(auto_increment)]`: Applicable only to `primary_key` fields. Adds the
-///     database-specific syntax for auto-incrementing integer primary keys (`SERIAL` or
-///     `GENERATED ALWAYS AS IDENTITY` for PostgreSQL, `AUTO_INCREMENT` for MySQL,
-///     `AUTOINCREMENT` for SQLite). The field type *must* be an integer type, usually `Option<i32>`.
+///   database-specific syntax for auto-incrementing integer primary keys (`SERIAL` or
+///   `GENERATED ALWAYS AS IDENTITY` for PostgreSQL, `AUTO_INCREMENT` for MySQL,
+///   `AUTOINCREMENT` for SQLite). The field type *must* be an integer type, usually `Option<i32>`.
 /// * `#[model(uuid)]`: Applicable only to `primary_key` fields. Adds database-specific
-///     default value generation for UUID primary keys (`gen_random_uuid()` for PostgreSQL,
-///     `UUID()` for MySQL, and a standard UUID generation expression for SQLite). The field
-///     type *must* be `uuid::Uuid` or `Option<uuid::Uuid>`.
+///   default value generation for UUID primary keys (`gen_random_uuid()` for PostgreSQL,
+///   `UUID()` for MySQL, and a standard UUID generation expression for SQLite). The field
+///   type *must* be `uuid::Uuid` or `Option<uuid::Uuid>`.
+/// * `#[model(updated_at)]`: Marks the timestamp column that `touch` sets to the current
+///   time. Use it on a `NaiveDateTime` (or similar) field.
+/// * `#[model(expires_at)]`: Marks a timestamp column after which the row is considered
+///   expired. Finders skip expired rows (a `NULL` expiry never expires) and
+///   `Connection::purge_expired` deletes them.
+/// * `#[model(compressed = "zstd")]`: Compresses a `String` or `Vec<u8>` field (or any
+///   `rusticx::Compress` type) before binding and decompresses it when loading, storing
+///   it as `BYTEA`/`BLOB`. Requires the `compression` feature of `rusticx`.
+/// * `#[model(soft_delete)]`: Marks a nullable timestamp column (e.g.
+///   `deleted_at: Option<NaiveDateTime>`). Deleting sets it instead of removing the row,
+///   finders skip rows where it is set, and `restore()` clears it.
+/// * `#[model(version)]`: Marks an integer column used for optimistic locking. `update()`
+///   only writes the row while the stored version matches and increments it, in the row
+///   and in the instance, returning `RusticxError::Conflict` otherwise.
+/// * `#[model(index)]`: Adds a secondary index on the column, named `idx_<table>_<column>`,
+///   which `Connection::create_table` creates along with the table.
+/// * `#[model(mask = "email")]` / `#[model(mask = "hash")]`: Anonymizes the column in
+///   `export_masked` output, replacing it with a fake address or a keyed HMAC-SHA256 digest.
+/// * `#[model(has_many)]` / `#[model(has_one)]`: Declares an association loaded by
+///   `Relations::find_all_with` instead of a column. Use it on a `Vec<Child>` (`has_many`)
+///   or `Option<Child>` (`has_one`) field, add `#[serde(default)]` to it, and name the
+///   child's column referencing this model with `foreign_key = "..."` (defaults to the
+///   snake-cased struct name followed by `_id`). The derive generates a `<Struct>Include`
+///   enum with one variant per relation (`posts` becomes `Posts`).
+/// * `#[model(store_as = "seconds")]`: Stores a duration-like field (`std::time::Duration`,
+///   `chrono::Duration` or any `rusticx::StoreAs` type) as a `BIGINT` number of
+///   `"seconds"`, `"millis"` or `"micros"`, converting when binding and in `from_row`.
+///
+/// The derive also generates a `<Struct>Column` enum with one variant per column
+/// (`created_at` becomes `CreatedAt`). Its `eq`, `gt`, `is_in` and similar methods
+/// build `rusticx::Predicate`s, so `UserColumn::Email.eq("a@b.c")` replaces
+/// `col("email").eq(...)` with a name the compiler checks.
 ///
 /// # Generated SQL Types Mapping
 ///
@@ -116,9 +152,29 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
     let name = &input.ident;
 
     // Extract the table name from the struct attributes. If not found,
-    // default to the struct name as is (without pluralizing or lowercasing).
+    // default to the struct name in lowercase (without pluralizing).
     let table_name = extract_table_name(&input.attrs)
-        .unwrap_or_else(|| name.to_string());
+        .unwrap_or_else(|| name.to_string().to_lowercase());
+
+    // Extract the database this model is routed to, if any (`#[model(database = "...")]`)
+    let database_name_fn = extract_struct_attr(&input.attrs, "database").map(|database| {
+        quote! {
+            /// Returns the name of the database this model is routed to.
+            fn database_name() -> Option<&'static str> {
+                Some(#database)
+            }
+        }
+    });
+
+    // Extract the filter every generated SELECT applies, if any (`#[model(default_scope = "...")]`)
+    let default_scope_fn = extract_struct_attr(&input.attrs, "default_scope").map(|scope| {
+        quote! {
+            /// Returns the condition applied to every generated `SELECT`.
+            fn default_scope() -> Option<&'static str> {
+                Some(#scope)
+            }
+        }
+    });
 
     // Ensure the derived item is a struct with named fields.
     // Panic otherwise with a descriptive error message.
@@ -139,8 +195,18 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
     let mut field_names = Vec::new(); // Collect database column names
     let mut field_to_sql_values = Vec::new(); // Collect code snippets for extracting field values for SQL binding
     let mut field_from_row = Vec::new(); // Collect code snippets for deserializing fields from a row (JSON value)
+    let mut field_from_values = Vec::new(); // Collect code snippets for deserializing fields from a row's values by position
     let mut field_idents = Vec::new(); // Collect original field idents
     let mut field_str_names = Vec::new(); // Collect original field names as strings
+    let mut field_sql_types = Vec::new(); // Collect the SqlType expression of each column
+    let mut updated_at_column: Option<String> = None; // Column marked #[model(updated_at)]
+    let mut expires_at_column: Option<String> = None; // Column marked #[model(expires_at)]
+    let mut version_column: Option<(String, Ident, bool)> = None; // (column, field, is Option) marked #[model(version)]
+    let mut soft_delete_column: Option<String> = None; // Column marked #[model(soft_delete)]
+    let mut masked_columns = Vec::new(); // (column, rusticx::Mask) pairs from #[model(mask = "...")]
+    let mut indexed_columns = Vec::new(); // Columns marked #[model(index)]
+    let mut defaulted_fields = Vec::new(); // Non-column fields (skip and relations), defaulted in `from_row`
+    let mut relations = Vec::new(); // Fields marked #[model(has_many)] or #[model(has_one)]
 
     // Iterate over each field in the struct
     for field in fields {
@@ -155,6 +221,16 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
         let mut skip = false; // #[model(skip)]
         let mut auto_increment = false; // #[model(auto_increment)]
         let mut uuid_pk = false; // #[model(uuid)] for primary key
+        let mut store_as = None; // #[model(store_as = "seconds" | "millis" | "micros")]
+        let mut compressed = None; // #[model(compressed = "zstd")]
+        let mut field_mask = None; // #[model(mask = "email" | "hash")]
+        let mut is_updated_at = false; // #[model(updated_at)]
+        let mut is_expires_at = false; // #[model(expires_at)]
+        let mut is_version = false; // #[model(version)]
+        let mut is_soft_delete = false; // #[model(soft_delete)]
+        let mut is_index = false; // #[model(index)]
+        let mut relation_kind = None; // #[model(has_many)] or #[model(has_one)]
+        let mut foreign_key = None; // #[model(foreign_key = "...")] for relations
 
         // Process attributes on the current field
         for attr in &field.attrs {
@@ -188,6 +264,20 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
                             } else if path.is_ident("uuid") {
                                 uuid_pk = true;
                                 pk_is_uuid = true; // Mark PK as UUID globally
+                            } else if path.is_ident("updated_at") {
+                                is_updated_at = true;
+                            } else if path.is_ident("expires_at") {
+                                is_expires_at = true;
+                            } else if path.is_ident("version") {
+                                is_version = true;
+                            } else if path.is_ident("soft_delete") {
+                                is_soft_delete = true;
+                            } else if path.is_ident("index") {
+                                is_index = true;
+                            } else if path.is_ident("has_many") {
+                                relation_kind = Some(RelationKind::HasMany);
+                            } else if path.is_ident("has_one") {
+                                relation_kind = Some(RelationKind::HasOne);
                             }
                         }
                         // Handle name-value attributes like `column = "..."` or `default = "..."`
@@ -211,6 +301,66 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
                                         custom_type = Some(lit_str.value()); // Set custom SQL type string
                                     }
                                 }
+                            } else if path.is_ident("store_as") {
+                                if let Expr::Lit(expr_lit) = value {
+                                    if let syn::Lit::Str(lit_str) = expr_lit.lit {
+                                        // Map the unit name to the corresponding rusticx::StoreUnit variant
+                                        let unit = match lit_str.value().as_str() {
+                                            "seconds" => quote! { rusticx::StoreUnit::Seconds },
+                                            "millis" => quote! { rusticx::StoreUnit::Millis },
+                                            "micros" => quote! { rusticx::StoreUnit::Micros },
+                                            other => {
+                                                let err = syn::Error::new(
+                                                    lit_str.span(),
+                                                    format!("unsupported store_as unit `{}`; expected \"seconds\", \"millis\" or \"micros\"", other),
+                                                );
+                                                return TokenStream::from(err.to_compile_error());
+                                            }
+                                        };
+                                        store_as = Some(unit);
+                                    }
+                                }
+                            } else if path.is_ident("compressed") {
+                                if let Expr::Lit(expr_lit) = value {
+                                    if let syn::Lit::Str(lit_str) = expr_lit.lit {
+                                        // Map the codec name to the corresponding rusticx::Compression variant
+                                        let codec = match lit_str.value().as_str() {
+                                            "zstd" => quote! { rusticx::Compression::Zstd },
+                                            other => {
+                                                let err = syn::Error::new(
+                                                    lit_str.span(),
+                                                    format!("unsupported compressed codec `{}`; expected \"zstd\"", other),
+                                                );
+                                                return TokenStream::from(err.to_compile_error());
+                                            }
+                                        };
+                                        compressed = Some(codec);
+                                    }
+                                }
+                            } else if path.is_ident("foreign_key") {
+                                if let Expr::Lit(expr_lit) = value {
+                                    if let syn::Lit::Str(lit_str) = expr_lit.lit {
+                                        foreign_key = Some(lit_str.value()); // Set the child's foreign key column
+                                    }
+                                }
+                            } else if path.is_ident("mask") {
+                                if let Expr::Lit(expr_lit) = value {
+                                    if let syn::Lit::Str(lit_str) = expr_lit.lit {
+                                        // Map the mask name to the corresponding rusticx::Mask variant
+                                        let mask = match lit_str.value().as_str() {
+                                            "email" => quote! { rusticx::Mask::Email },
+                                            "hash" => quote! { rusticx::Mask::Hash },
+                                            other => {
+                                                let err = syn::Error::new(
+                                                    lit_str.span(),
+                                                    format!("unsupported mask `{}`; expected \"email\" or \"hash\"", other),
+                                                );
+                                                return TokenStream::from(err.to_compile_error());
+                                            }
+                                        };
+                                        field_mask = Some(mask);
+                                    }
+                                }
                             }
                         }
                         _ => {
@@ -225,8 +375,21 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
             }
         }
 
+        // Relation fields are filled by `load_includes`, not stored in a column
+        if let Some(kind) = relation_kind {
+            let child_type = match relation_child_type(&field.ty, kind) {
+                Ok(child_type) => child_type,
+                Err(err) => return TokenStream::from(err.to_compile_error()),
+            };
+            let foreign_key = foreign_key.unwrap_or_else(|| format!("{}_id", to_snake_case(&name.to_string())));
+            relations.push((field_ident.clone(), kind, child_type, foreign_key));
+            defaulted_fields.push(field_ident);
+            continue;
+        }
+
         // If the field is marked to be skipped, continue to the next field
         if skip {
+            defaulted_fields.push(field_ident);
             continue;
         }
 
@@ -234,39 +397,92 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
         field_idents.push(field_ident.clone());
         field_str_names.push(field_name.clone());
         field_names.push(column_name.clone());
+        if is_updated_at {
+            updated_at_column = Some(column_name.clone());
+        }
+        if is_expires_at {
+            expires_at_column = Some(column_name.clone());
+        }
+        if is_version {
+            version_column = Some((column_name.clone(), field_ident.clone(), is_option_type(&field.ty)));
+        }
+        if is_soft_delete {
+            soft_delete_column = Some(column_name.clone());
+        }
+        if let Some(mask) = field_mask {
+            masked_columns.push(quote! { (#column_name, #mask) });
+        }
+        if is_index {
+            indexed_columns.push(column_name.clone());
+        }
 
         // Generate code snippet to extract the field's value.
         // Assumes the field type implements `Clone` and can be converted to `Box<dyn rusticx::ToSqlConvert>`.
         // The `rusticx::ToSqlConvert` trait would need to handle the actual type-specific conversion.
-        let field_to_sql_value = quote! {
-             // Clone the field value and box it as a trait object.
-             // The `rusticx::ToSqlConvert` trait should provide a method
-             // to convert the underlying type to database-specific parameters.
-            Box::new(self.#field_ident.clone()) as Box<dyn rusticx::ToSqlConvert>
+        // Determine if the field is semantically optional (either Option<T> or explicitly nullable)
+        let is_option = is_nullable || is_option_type(&field.ty);
+
+        let field_to_sql_value = match (&store_as, &compressed, is_option_type(&field.ty)) {
+            // store_as fields are bound as the integer number of units
+            (Some(unit), _, true) => quote! {
+                Box::new(self.#field_ident.as_ref().map(|v| rusticx::StoreAs::to_stored(v, #unit))) as Box<dyn rusticx::ToSqlConvert>
+            },
+            (Some(unit), _, false) => quote! {
+                Box::new(rusticx::StoreAs::to_stored(&self.#field_ident, #unit)) as Box<dyn rusticx::ToSqlConvert>
+            },
+            // compressed fields are bound as the compressed bytes, or the
+            // error compressing them, which the ORM reports before binding
+            (None, Some(codec), true) => quote! {
+                Box::new(self.#field_ident.as_ref().map(|v| rusticx::Compress::compress(v, #codec)).transpose()) as Box<dyn rusticx::ToSqlConvert>
+            },
+            (None, Some(codec), false) => quote! {
+                Box::new(rusticx::Compress::compress(&self.#field_ident, #codec)) as Box<dyn rusticx::ToSqlConvert>
+            },
+            (None, None, _) => quote! {
+                 // Clone the field value and box it as a trait object.
+                 // The `rusticx::ToSqlConvert` trait should provide a method
+                 // to convert the underlying type to database-specific parameters.
+                Box::new(self.#field_ident.clone()) as Box<dyn rusticx::ToSqlConvert>
+            },
         };
         field_to_sql_values.push(field_to_sql_value);
 
-        // Determine if the field is semantically optional (either Option<T> or explicitly nullable)
-        let is_option = is_nullable || is_option_type(&field.ty);
-        // Generate code snippet to deserialize the field from a JSON value (representing a database row)
-        let field_from_json = generate_from_json(&field_ident, &column_name, &field.ty, is_option);
-        field_from_row.push(field_from_json);
+        // Generate code snippets to deserialize the field from a database row, looked
+        // up by column name in a JSON object and by position in a list of values
+        let position = field_names.len() - 1;
+        for (lookup, snippets) in [
+            (quote! { obj.get(#column_name) }, &mut field_from_row),
+            (quote! { values.get(#position) }, &mut field_from_values),
+        ] {
+            snippets.push(match (&store_as, &compressed) {
+                (Some(unit), _) => generate_store_as_from_json(&field_ident, &column_name, &lookup, unit, is_option),
+                (None, Some(codec)) => generate_compressed_from_json(&field_ident, &column_name, &lookup, codec, is_option),
+                (None, None) => generate_from_json(&field_ident, &column_name, &lookup, &field.ty, is_option),
+            });
+        }
 
         // Determine the SQL type definition based on custom type or Rust type mapping
         let sql_type = if let Some(custom) = custom_type {
             // If a custom SQL type is specified, use it
             quote! { rusticx::SqlType::Custom(#custom.to_string()) }
+        } else if store_as.is_some() {
+            // Converted fields are stored as an integer number of units
+            quote! { rusticx::SqlType::BigInt }
+        } else if compressed.is_some() {
+            // Compressed fields are stored as binary data
+            quote! { rusticx::SqlType::Blob }
         } else {
             // Otherwise, map the Rust type to a generic SqlType enum variant
             let rust_type = &field.ty;
             generate_sql_type(rust_type) // Calls helper function for mapping
         };
+        field_sql_types.push(sql_type.clone());
 
         // Generate the SQL column definition string part (e.g., "name TEXT NOT NULL")
         let sql_def = quote! {
             {
                 // Start with column name and its determined SQL type based on DB type
-                let mut part = format!("\"{}\" {}", #column_name, match db_type {
+                let mut part = format!("{} {}", db_type.quote_identifier(#column_name), match db_type {
                     rusticx::DatabaseType::PostgreSQL => #sql_type.pg_type().to_string(),
                     rusticx::DatabaseType::MySQL => #sql_type.mysql_type().to_string(),
                     rusticx::DatabaseType::SQLite => #sql_type.sqlite_type().to_string(),
@@ -319,6 +535,241 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
     // Defaults to an identifier "id" if no field was marked as primary key (though this should ideally be a user error).
     let pk_ident = primary_key_field.unwrap_or_else(|| Ident::new("id", name.span()));
 
+    // Override `updated_at_field` only when a field is marked #[model(updated_at)]
+    let updated_at_fn = updated_at_column.map(|column| {
+        quote! {
+            /// Returns the column bumped by `touch`.
+            fn updated_at_field() -> Option<&'static str> {
+                Some(#column)
+            }
+        }
+    });
+
+    // Override `expires_at_field` only when a field is marked #[model(expires_at)]
+    let expires_at_fn = expires_at_column.map(|column| {
+        quote! {
+            /// Returns the column holding the record's expiry time.
+            fn expires_at_field() -> Option<&'static str> {
+                Some(#column)
+            }
+        }
+    });
+
+    // Override `version_field` only when a field is marked #[model(version)]
+    let version_fn = version_column.map(|(column, field, optional)| {
+        let increment = if optional {
+            quote! { if let Some(version) = self.#field.as_mut() { *version += 1; } }
+        } else {
+            quote! { self.#field += 1; }
+        };
+        quote! {
+            /// Returns the column used for optimistic locking.
+            fn version_field() -> Option<&'static str> {
+                Some(#column)
+            }
+
+            /// Advances the in-memory version after a successful `update`.
+            fn increment_version(&mut self) {
+                #increment
+            }
+        }
+    });
+
+    // Override `soft_delete_field` only when a field is marked #[model(soft_delete)]
+    let soft_delete_fn = soft_delete_column.map(|column| {
+        quote! {
+            /// Returns the column recording when the record was soft-deleted.
+            fn soft_delete_field() -> Option<&'static str> {
+                Some(#column)
+            }
+        }
+    });
+
+    // Override `masked_fields` only when some field is marked #[model(mask = "...")]
+    let masked_fields_fn = (!masked_columns.is_empty()).then(|| {
+        quote! {
+            /// Returns the columns anonymized by `export_masked`.
+            fn masked_fields() -> Vec<(&'static str, rusticx::Mask)> {
+                vec![#(#masked_columns),*]
+            }
+        }
+    });
+
+    // Override `indexed_fields` only when some field is marked #[model(index)]
+    let indexed_fields_fn = (!indexed_columns.is_empty()).then(|| {
+        quote! {
+            /// Returns the columns given a secondary index.
+            fn indexed_fields() -> Vec<&'static str> {
+                vec![#(#indexed_columns),*]
+            }
+        }
+    });
+
+    // Implement `rusticx::Relations` with a `<Name>Include` enum when the model declares relations
+    let relations_impl = (!relations.is_empty()).then(|| {
+        let vis = &input.vis;
+        let include_ident = Ident::new(&format!("{}Include", name), name.span());
+        let include_doc = format!("The relations of `{}` that `find_all_with` can eager-load.", name);
+        let variants: Vec<Ident> = relations
+            .iter()
+            .map(|(field_ident, ..)| Ident::new(&to_camel_case(&field_ident.to_string()), field_ident.span()))
+            .collect();
+        let loaders = relations.iter().zip(&variants).map(|((field_ident, kind, child_type, foreign_key), variant)| {
+            let attach = match kind {
+                RelationKind::HasMany => quote! { children.remove(&id).unwrap_or_default() },
+                RelationKind::HasOne => quote! { children.remove(&id).and_then(|found| found.into_iter().next()) },
+            };
+            quote! {
+                #include_ident::#variant => {
+                    let mut children = <Self as rusticx::SQLModel>::load_children::<#child_type>(conn, models, #foreign_key)?;
+                    for model in models.iter_mut() {
+                        if let Some(id) = rusticx::SQLModel::primary_key_value(model) {
+                            model.#field_ident = #attach;
+                        }
+                    }
+                }
+            }
+        });
+
+        quote! {
+            #[doc = #include_doc]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            #vis enum #include_ident {
+                #(#variants),*
+            }
+
+            impl rusticx::Relations for #name {
+                type Include = #include_ident;
+
+                /// Loads each requested relation with one batched query and
+                /// attaches the results to `models`.
+                fn load_includes(
+                    conn: &rusticx::Connection,
+                    models: &mut [Self],
+                    includes: &[Self::Include],
+                ) -> Result<(), rusticx::RusticxError> {
+                    for include in includes {
+                        match include {
+                            #(#loaders)*
+                        }
+                    }
+                    Ok(())
+                }
+            }
+        }
+    });
+
+    // Generate a `<Name>Column` enum naming each column, for typed predicates
+    let columns_enum = {
+        let vis = &input.vis;
+        let column_ident = Ident::new(&format!("{}Column", name), name.span());
+        let column_enum_doc = format!(
+            "The columns of `{}`, for building predicates without spelling column names as strings.",
+            name
+        );
+        let variants: Vec<Ident> = field_idents
+            .iter()
+            .map(|field_ident| Ident::new(&to_camel_case(&field_ident.to_string()), field_ident.span()))
+            .collect();
+        let variant_docs = field_names.iter().map(|column| format!("The `{}` column.", column));
+        let column_count = variants.len();
+
+        quote! {
+            #[doc = #column_enum_doc]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+            #vis enum #column_ident {
+                #(#[doc = #variant_docs] #variants),*
+            }
+
+            impl #column_ident {
+                /// Every column, in `field_names` order.
+                pub const ALL: [Self; #column_count] = [#(Self::#variants),*];
+
+                /// The column's name in the database.
+                pub fn name(self) -> &'static str {
+                    match self {
+                        #(Self::#variants => #field_names,)*
+                    }
+                }
+
+                /// Starts a predicate on the column.
+                pub fn col(self) -> rusticx::Col {
+                    rusticx::col(self.name())
+                }
+
+                /// `column = value`, or `column IS NULL` when `value` is `None`.
+                pub fn eq<V: rusticx::ToSqlConvert + 'static>(self, value: V) -> rusticx::Predicate {
+                    self.col().eq(value)
+                }
+
+                /// `column <> value`, or `column IS NOT NULL` when `value` is `None`.
+                pub fn ne<V: rusticx::ToSqlConvert + 'static>(self, value: V) -> rusticx::Predicate {
+                    self.col().ne(value)
+                }
+
+                /// `column > value`.
+                pub fn gt<V: rusticx::ToSqlConvert + 'static>(self, value: V) -> rusticx::Predicate {
+                    self.col().gt(value)
+                }
+
+                /// `column >= value`.
+                pub fn ge<V: rusticx::ToSqlConvert + 'static>(self, value: V) -> rusticx::Predicate {
+                    self.col().ge(value)
+                }
+
+                /// `column < value`.
+                pub fn lt<V: rusticx::ToSqlConvert + 'static>(self, value: V) -> rusticx::Predicate {
+                    self.col().lt(value)
+                }
+
+                /// `column <= value`.
+                pub fn le<V: rusticx::ToSqlConvert + 'static>(self, value: V) -> rusticx::Predicate {
+                    self.col().le(value)
+                }
+
+                /// `column LIKE pattern`.
+                pub fn like(self, pattern: &str) -> rusticx::Predicate {
+                    self.col().like(pattern)
+                }
+
+                /// Case-insensitive `column LIKE pattern`.
+                pub fn ilike(self, pattern: &str) -> rusticx::Predicate {
+                    self.col().ilike(pattern)
+                }
+
+                /// Case-insensitive `column = value`.
+                pub fn eq_ignore_case(self, value: &str) -> rusticx::Predicate {
+                    self.col().eq_ignore_case(value)
+                }
+
+                /// `column BETWEEN low AND high`, bounds included.
+                pub fn between<V: rusticx::ToSqlConvert + 'static>(self, low: V, high: V) -> rusticx::Predicate {
+                    self.col().between(low, high)
+                }
+
+                /// `column IS NULL`.
+                pub fn is_null(self) -> rusticx::Predicate {
+                    self.col().is_null()
+                }
+
+                /// `column IS NOT NULL`.
+                pub fn is_not_null(self) -> rusticx::Predicate {
+                    self.col().is_not_null()
+                }
+
+                /// `column IN (...)`. An empty list matches no rows.
+                pub fn is_in<V: rusticx::ToSqlConvert + 'static>(self, values: impl IntoIterator<Item = V>) -> rusticx::Predicate {
+                    self.col().is_in(values)
+                }
+
+                /// `column NOT IN (...)`. An empty list matches every row.
+                pub fn not_in<V: rusticx::ToSqlConvert + 'static>(self, values: impl IntoIterator<Item = V>) -> rusticx::Predicate {
+                    self.col().not_in(values)
+                }
+            }
+        }
+    };
+
     // Collect column names as string literals for the `field_names` method
     let field_name_literals: Vec<_> = field_names.iter().map(|name| quote! { #name }).collect();
 
@@ -392,6 +843,21 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
                 #table_name.to_string()
             }
 
+            #database_name_fn
+
+            #default_scope_fn
+
+            #updated_at_fn
+
+            #expires_at_fn
+
+            #version_fn
+
+            #soft_delete_fn
+
+            #masked_fields_fn
+            #indexed_fields_fn
+
             /// Returns the database column name of the primary key field.
             ///
             /// This is the field marked with `#[model(primary_key)]`.
@@ -448,9 +914,19 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
             /// A string containing the `CREATE TABLE` SQL statement.
             fn create_table_sql(db_type: &rusticx::DatabaseType) -> String {
                 // Start the CREATE TABLE statement
-                let mut sql = format!("CREATE TABLE IF NOT EXISTS {} (", Self::table_name());
+                let mut sql = format!("CREATE TABLE IF NOT EXISTS {} (", db_type.quote_identifier(&Self::table_name()));
                 // Collect the generated SQL definitions for each field
-                let fields = vec![#(#field_sql_defs),*];
+                let mut fields = vec![#(#field_sql_defs),*];
+                // MySQL has no `CREATE INDEX IF NOT EXISTS`, so its indexes are declared inline
+                if *db_type == rusticx::DatabaseType::MySQL {
+                    for column in Self::indexed_fields() {
+                        fields.push(format!(
+                            "INDEX {} ({})",
+                            db_type.quote_identifier(&format!("idx_{}_{}", Self::table_name(), column)),
+                            db_type.quote_identifier(column)
+                        ));
+                    }
+                }
                 // Join field definitions with commas and close the statement
                 sql.push_str(&fields.join(", "));
                 sql.push(')');
@@ -466,6 +942,12 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
                 vec![#(#field_name_literals),*]
             }
 
+            /// Returns the declared `SqlType` of each column, in the same order
+            /// as `field_names`.
+            fn field_sql_types() -> Vec<rusticx::SqlType> {
+                vec![#(#field_sql_types),*]
+            }
+
             /// Returns a vector of boxed trait objects (`ToSqlConvert`) representing
             /// the values of all non-skipped fields in the model.
             ///
@@ -499,16 +981,93 @@ pub fn derive_model(input: TokenStream) -> TokenStream {
                 // Get a reference to the JSON object
                 let obj = row.as_object().unwrap(); // Safe to unwrap because we checked is_object()
 
-                // Construct the struct instance by deserializing each field
+                // Construct the struct instance by deserializing each field;
+                // fields without a column start out empty
                 Ok(Self {
-                    #(#field_from_row),* // Execute the generated code snippets for each field
+                    #(#field_from_row,)* // Execute the generated code snippets for each field
+                    #(#defaulted_fields: Default::default(),)*
+                })
+            }
+
+            /// Deserializes a model instance from a row's column values, given in
+            /// `field_names` order.
+            fn from_row_positional(values: &[serde_json::Value]) -> Result<Self, rusticx::RusticxError> {
+                Ok(Self {
+                    #(#field_from_values,)*
+                    #(#defaulted_fields: Default::default(),)*
                 })
             }
         }
     };
 
     // Return the generated code as a TokenStream
-    TokenStream::from(expanded)
+    TokenStream::from(quote! {
+        #expanded
+
+        #columns_enum
+
+        #relations_impl
+    })
+}
+
+/// The kind of association declared by a relation field.
+#[derive(Clone, Copy)]
+enum RelationKind {
+    /// `#[model(has_many)]` on a `Vec<Child>` field.
+    HasMany,
+    /// `#[model(has_one)]` on an `Option<Child>` field.
+    HasOne,
+}
+
+/// Returns the child model type of a relation field: `Child` in `Vec<Child>`
+/// (`has_many`) or `Option<Child>` (`has_one`).
+fn relation_child_type(ty: &Type, kind: RelationKind) -> Result<Type, syn::Error> {
+    let (wrapper, attr) = match kind {
+        RelationKind::HasMany => ("Vec", "has_many"),
+        RelationKind::HasOne => ("Option", "has_one"),
+    };
+    if let Type::Path(TypePath { path, .. }) = ty {
+        if let Some(segment) = path.segments.last() {
+            if segment.ident == wrapper {
+                if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
+                    if let Some(syn::GenericArgument::Type(child)) = args.args.first() {
+                        return Ok(child.clone());
+                    }
+                }
+            }
+        }
+    }
+    Err(syn::Error::new_spanned(ty, format!("#[model({})] fields must be of type `{}<Model>`", attr, wrapper)))
+}
+
+/// Converts a field name like `blog_posts` to a variant name like `BlogPosts`.
+fn to_camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Converts a struct name like `BlogPost` to `blog_post`.
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
 }
 
 /// Helper function to check if a given Rust type is an `Option<T>`.
@@ -533,20 +1092,21 @@ fn is_option_type(ty: &Type) -> bool {
 ///
 /// * `field_ident`: The identifier of the struct field.
 /// * `column_name`: The database column name corresponding to the field.
+/// * `lookup`: An expression yielding the field's value as an `Option<&serde_json::Value>`.
 /// * `_field_type`: The Rust type of the field (used implicitly by `serde_json::from_value`).
 /// * `is_optional`: Boolean indicating if the field is `Option<T>` or marked nullable.
 ///
 /// # Returns
 ///
 /// A `proc_macro2::TokenStream` containing the code to deserialize the field.
-fn generate_from_json(field_ident: &Ident, column_name: &str, _field_type: &Type, is_optional: bool) -> proc_macro2::TokenStream {
-    // Use the column name as the key to look up the value in the JSON object
+fn generate_from_json(field_ident: &Ident, column_name: &str, lookup: &proc_macro2::TokenStream, _field_type: &Type, is_optional: bool) -> proc_macro2::TokenStream {
+    // The column name is used in error messages
     let column_literal = column_name;
 
     if is_optional {
         // Code for optional fields (Option<T> or #[model(nullable)])
         quote! {
-            #field_ident: if let Some(val) = obj.get(#column_literal) {
+            #field_ident: if let Some(val) = #lookup {
                 // If the key exists, check if the value is null
                 if val.is_null() {
                     None // If null, set field to None
@@ -568,7 +1128,7 @@ fn generate_from_json(field_ident: &Ident, column_name: &str, _field_type: &Type
     } else {
         // Code for required fields (non-Option and not #[model(nullable)])
         quote! {
-            #field_ident: if let Some(val) = obj.get(#column_literal) {
+            #field_ident: if let Some(val) = #lookup {
                 // If the key exists, attempt to deserialize the value
                  match serde_json::from_value(val.clone()) {
                     Ok(v) => v, // If successful, use the value
@@ -586,6 +1146,59 @@ fn generate_from_json(field_ident: &Ident, column_name: &str, _field_type: &Type
     }
 }
 
+/// Helper function to generate code that rebuilds a `compressed` field from its
+/// stored bytes via `rusticx::Compress::from_column`.
+fn generate_compressed_from_json(field_ident: &Ident, column_name: &str, lookup: &proc_macro2::TokenStream, codec: &proc_macro2::TokenStream, is_optional: bool) -> proc_macro2::TokenStream {
+    if is_optional {
+        quote! {
+            #field_ident: match #lookup {
+                Some(val) if !val.is_null() => Some(rusticx::Compress::from_column(val, #codec)?),
+                _ => None,
+            }
+        }
+    } else {
+        quote! {
+            #field_ident: match #lookup {
+                Some(val) => rusticx::Compress::from_column(val, #codec)?,
+                None => return Err(rusticx::RusticxError::DeserializationError(
+                    format!("Missing required field: `{}`", #column_name)
+                )),
+            }
+        }
+    }
+}
+
+/// Helper function to generate code that rebuilds a `store_as` field from its
+/// stored integer value via `rusticx::StoreAs::from_stored`.
+fn generate_store_as_from_json(field_ident: &Ident, column_name: &str, lookup: &proc_macro2::TokenStream, unit: &proc_macro2::TokenStream, is_optional: bool) -> proc_macro2::TokenStream {
+    let convert = quote! {
+        match val.as_i64() {
+            Some(stored) => rusticx::StoreAs::from_stored(stored, #unit)?,
+            None => return Err(rusticx::RusticxError::DeserializationError(
+                format!("Failed to deserialize field `{}`: expected an integer, got {}", #column_name, val)
+            )),
+        }
+    };
+
+    if is_optional {
+        quote! {
+            #field_ident: match #lookup {
+                Some(val) if !val.is_null() => Some(#convert),
+                _ => None,
+            }
+        }
+    } else {
+        quote! {
+            #field_ident: match #lookup {
+                Some(val) => #convert,
+                None => return Err(rusticx::RusticxError::DeserializationError(
+                    format!("Missing required field: `{}`", #column_name)
+                )),
+            }
+        }
+    }
+}
+
 /// Helper function to map a Rust type to a generic `SqlType` enum variant.
 ///
 /// This mapping is used to determine the database column type in the `CREATE TABLE` statement,
@@ -614,11 +1227,9 @@ fn generate_sql_type(rust_type: &Type) -> proc_macro2::TokenStream {
             // Handle Option<T> recursively: get the inner type's mapping
             if type_name == "Option" {
                 if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
-                    if let Some(arg) = args.args.first() {
-                        if let syn::GenericArgument::Type(inner_type) = arg {
-                            // Recursively call for the inner type
-                            return generate_sql_type(inner_type);
-                        }
+                    if let Some(syn::GenericArgument::Type(inner_type)) = args.args.first() {
+                        // Recursively call for the inner type
+                        return generate_sql_type(inner_type);
                     }
                 }
                 // Panic if Option type has invalid arguments
@@ -642,12 +1253,10 @@ fn generate_sql_type(rust_type: &Type) -> proc_macro2::TokenStream {
                 // Map Vec<u8> to Blob
                 "Vec" => {
                     if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
-                        if let Some(arg) = args.args.first() {
-                            if let syn::GenericArgument::Type(Type::Path(TypePath { path, .. })) = arg {
-                                if let Some(seg) = path.segments.last() {
-                                    if seg.ident == "u8" {
-                                        return quote! { rusticx::SqlType::Blob };
-                                    }
+                        if let Some(syn::GenericArgument::Type(Type::Path(TypePath { path, .. }))) = args.args.first() {
+                            if let Some(seg) = path.segments.last() {
+                                if seg.ident == "u8" {
+                                    return quote! { rusticx::SqlType::Blob };
                                 }
                             }
                         }
@@ -674,6 +1283,20 @@ fn generate_sql_type(rust_type: &Type) -> proc_macro2::TokenStream {
 ///
 /// An `Option<String>` containing the custom table name if found, otherwise `None`.
 fn extract_table_name(attrs: &[Attribute]) -> Option<String> {
+    extract_struct_attr(attrs, "table").map(|table| table.to_lowercase())
+}
+
+/// Helper function to extract a string value from a struct-level `#[model(key = "...")]` attribute.
+///
+/// # Arguments
+///
+/// * `attrs`: A slice of `syn::Attribute` applied to the struct.
+/// * `key`: The attribute key to look for (e.g., `"table"` or `"database"`).
+///
+/// # Returns
+///
+/// An `Option<String>` containing the attribute value if found, otherwise `None`.
+fn extract_struct_attr(attrs: &[Attribute], key: &str) -> Option<String> {
     // Iterate through all attributes on the struct
     for attr in attrs {
         // Check if the attribute is our custom #[model(...)] attribute
@@ -689,13 +1312,13 @@ fn extract_table_name(attrs: &[Attribute]) -> Option<String> {
         // Process the parsed meta items
         if let Ok(items) = parsed {
             for meta in items {
-                // Check for the `table = "..."` name-value pair
+                // Check for the `key = "..."` name-value pair
                 if let Meta::NameValue(MetaNameValue { path, value, .. }) = meta {
-                    if path.is_ident("table") {
+                    if path.is_ident(key) {
                         // If found, extract the string literal value
                         if let Expr::Lit(expr_lit) = value {
                             if let syn::Lit::Str(lit_str) = expr_lit.lit {
-                                return Some(lit_str.value().to_lowercase()); // Return the extracted table name
+                                return Some(lit_str.value()); // Return the extracted value
                             }
                         }
                     }
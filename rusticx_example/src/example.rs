@@ -47,7 +47,7 @@ fn main() -> Result<(), rusticx::RusticxError> {
     // This part is often run separately or conditionally to set up the database.
     // Uncomment and run once if you need to create the table.
     println!("\n--- Demonstrating create_table_sql ---");
-    let create_sql = User::create_table_sql(&conn.get_db_type());
+    let create_sql = User::create_table_sql(conn.get_db_type());
     println!("Generated CREATE TABLE SQL:\n{}", create_sql);
     // Execute the create table SQL (use with caution - it will error if table exists)
     let create_result = conn.execute(&create_sql, &[]);